@@ -0,0 +1,205 @@
+//! Offset samples for the SOCK reference clock of chrony
+//!
+//! Instead of steering the system clock, we can leave it to chrony and send
+//! it the offset of the system clock to our time, with `refclock SOCK <path>`
+//! in chrony.conf. Chrony creates the socket and combines the samples with its
+//! other sources.
+
+use std::{
+    mem::size_of,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+};
+
+use statime::{
+    config::LeapIndicator,
+    time::{Duration, Time},
+};
+
+use crate::clock::LinuxClock;
+
+// The sample as defined in refclock_sock.c of chrony
+const SOCK_MAGIC: libc::c_int = 0x534f434b;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SockSample {
+    tv: libc::timeval,
+    offset: f64,
+    pulse: libc::c_int,
+    leap: libc::c_int,
+    _pad: libc::c_int,
+    magic: libc::c_int,
+}
+
+/// A single offset sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChronySample {
+    /// The time of the sample on the system clock, which we keep on TAI
+    pub time: Time,
+    /// The offset of our time to the system clock, positive when the system
+    /// clock is behind
+    pub offset: Duration,
+    /// The sample is from a pulse, so chrony only uses its offset within a
+    /// second
+    pub pulse: bool,
+    /// The leap second announced for the end of the current UTC day
+    pub leap: LeapIndicator,
+}
+
+impl ChronySample {
+    /// The sample of the system clock reading `system_time` at our `time`.
+    /// Chrony adds the offset to the time of the sample to get the time of the
+    /// reference, so the offset is our time minus that of the system clock.
+    pub fn new(system_time: Time, time: Time, pulse: bool, leap: LeapIndicator) -> Self {
+        Self {
+            time: system_time,
+            offset: time - system_time,
+            pulse,
+            leap,
+        }
+    }
+
+    fn to_sock_sample(self, tai_offset: i32) -> SockSample {
+        SockSample {
+            // Chrony expects the time on the realtime clock, which is on UTC
+            tv: libc::timeval {
+                tv_sec: (self.time.secs() as i64 - tai_offset as i64) as _,
+                tv_usec: (self.time.subsec_nanos() / 1_000) as _,
+            },
+            offset: self.offset.seconds(),
+            pulse: self.pulse as libc::c_int,
+            leap: match self.leap {
+                LeapIndicator::NoLeap => 0,
+                LeapIndicator::Leap61 => 1,
+                LeapIndicator::Leap59 => 2,
+            },
+            _pad: 0,
+            magic: SOCK_MAGIC,
+        }
+    }
+}
+
+/// The socket of a SOCK reference clock of chrony
+#[derive(Debug)]
+pub struct ChronySocket {
+    socket: UnixDatagram,
+    path: PathBuf,
+    failing: bool,
+}
+
+impl ChronySocket {
+    /// Send the samples to the socket at `path`, on which chrony listens
+    pub fn new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        // A sample that cannot be sent right away is outdated by the next one
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            path: path.as_ref().to_owned(),
+            failing: false,
+        })
+    }
+
+    /// Send a sample. Chrony not running is not an error, it just does not
+    /// get the sample.
+    pub fn send(&mut self, sample: ChronySample) -> std::io::Result<()> {
+        let tai_offset = LinuxClock::CLOCK_TAI.get_tai_offset()?;
+        let sample = sample.to_sock_sample(tai_offset);
+
+        // Safety: the sample is plain old data without padding between fields,
+        // and the slice lives as long as the sample
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &sample as *const SockSample as *const u8,
+                size_of::<SockSample>(),
+            )
+        };
+
+        match self.socket.send_to(bytes, &self.path) {
+            Ok(_) => {
+                if self.failing {
+                    log::info!("Sending samples to chrony at {}", self.path.display());
+                }
+                self.failing = false;
+            }
+            Err(error) => {
+                if !self.failing {
+                    log::warn!(
+                        "Could not send sample to chrony at {}: {}",
+                        self.path.display(),
+                        error
+                    );
+                }
+                self.failing = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sock_sample() {
+        let sample = ChronySample {
+            time: Time::from_nanos(1_700_000_037_123_456_789),
+            offset: Duration::from_micros(-15),
+            pulse: false,
+            leap: LeapIndicator::Leap61,
+        }
+        .to_sock_sample(37);
+
+        assert_eq!(sample.tv.tv_sec, 1_700_000_000);
+        assert_eq!(sample.tv.tv_usec, 123_456);
+        assert_eq!(sample.offset, -15e-6);
+        assert_eq!(sample.pulse, 0);
+        assert_eq!(sample.leap, 1);
+        assert_eq!(sample.magic, SOCK_MAGIC);
+
+        if size_of::<libc::time_t>() == 8 {
+            assert_eq!(size_of::<SockSample>(), 40);
+        }
+    }
+
+    #[test]
+    fn sample_offset() {
+        // The system clock is 1.5us behind
+        let system_time = Time::from_nanos(1_700_000_037_000_000_000);
+        let time = system_time + Duration::from_nanos(1_500);
+        let sample = ChronySample::new(system_time, time, false, LeapIndicator::NoLeap);
+
+        assert_eq!(sample.time, system_time);
+        assert_eq!(sample.offset, Duration::from_nanos(1_500));
+        assert_eq!(sample.to_sock_sample(37).offset, 1.5e-6);
+        assert_eq!(sample.time + sample.offset, time);
+    }
+
+    #[test]
+    fn send_to_socket() {
+        let path = std::env::temp_dir().join(format!("statime-chrony-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let chrony = UnixDatagram::bind(&path).unwrap();
+
+        let mut socket = ChronySocket::new(&path).unwrap();
+        socket
+            .send(ChronySample {
+                time: Time::from_secs(1_700_000_000),
+                offset: Duration::from_nanos(500),
+                pulse: true,
+                leap: LeapIndicator::NoLeap,
+            })
+            .unwrap();
+
+        let mut buffer = [0; 64];
+        let size = chrony.recv(&mut buffer).unwrap();
+        assert_eq!(size, size_of::<SockSample>());
+        assert_eq!(&buffer[size - 4..size], &SOCK_MAGIC.to_ne_bytes(),);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
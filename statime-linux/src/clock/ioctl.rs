@@ -0,0 +1,94 @@
+//! Kernel interfaces of hardware clocks and their pins, from linux/ptp_clock.h
+
+use std::{fs::File, mem::size_of, os::fd::AsRawFd};
+
+// The generic ioctl request encoding of asm-generic/ioctl.h
+pub(crate) const IOC_WRITE: u32 = 1;
+pub(crate) const IOC_READ: u32 = 2;
+
+pub(crate) const fn ioc(dir: u32, kind: u8, nr: u8, size: usize) -> libc::Ioctl {
+    ((dir << 30) | ((size as u32) << 16) | ((kind as u32) << 8) | nr as u32) as libc::Ioctl
+}
+
+/// Perform an ioctl that reads and writes a single `T`
+pub(crate) fn ioctl<T>(
+    device: &File,
+    request: libc::Ioctl,
+    argument: &mut T,
+) -> std::io::Result<()> {
+    // Safety: callers only pass requests for which the kernel reads and writes
+    // a value of type T
+    match unsafe { libc::ioctl(device.as_raw_fd(), request, argument as *mut T) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) const PTP_EXTTS_REQUEST: libc::Ioctl =
+    ioc(IOC_WRITE, b'=', 2, size_of::<PtpExttsRequest>());
+pub(crate) const PTP_PIN_SETFUNC: libc::Ioctl = ioc(IOC_WRITE, b'=', 7, size_of::<PtpPinDesc>());
+pub(crate) const PTP_PEROUT_REQUEST2: libc::Ioctl =
+    ioc(IOC_WRITE, b'=', 12, size_of::<PtpPeroutRequest>());
+
+pub(crate) const PTP_ENABLE_FEATURE: libc::c_uint = 1 << 0;
+pub(crate) const PTP_RISING_EDGE: libc::c_uint = 1 << 1;
+pub(crate) const PTP_PEROUT_DUTY_CYCLE: libc::c_uint = 1 << 1;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PtpClockTime {
+    pub(crate) sec: i64,
+    pub(crate) nsec: u32,
+    pub(crate) reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PtpExttsRequest {
+    pub(crate) index: libc::c_uint,
+    pub(crate) flags: libc::c_uint,
+    pub(crate) rsv: [libc::c_uint; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PtpExttsEvent {
+    pub(crate) t: PtpClockTime,
+    pub(crate) index: libc::c_uint,
+    pub(crate) flags: libc::c_uint,
+    pub(crate) rsv: [libc::c_uint; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PtpPeroutRequest {
+    /// The absolute start time of the output
+    pub(crate) start: PtpClockTime,
+    pub(crate) period: PtpClockTime,
+    pub(crate) index: libc::c_uint,
+    pub(crate) flags: libc::c_uint,
+    /// The pulse width with [`PTP_PEROUT_DUTY_CYCLE`]
+    pub(crate) on: PtpClockTime,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct PtpPinDesc {
+    pub(crate) name: [libc::c_char; 64],
+    pub(crate) index: libc::c_uint,
+    pub(crate) func: libc::c_uint,
+    pub(crate) chan: libc::c_uint,
+    pub(crate) rsv: [libc::c_uint; 5],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ioctl_requests() {
+        assert_eq!(PTP_EXTTS_REQUEST as u32, 0x40103d02);
+        assert_eq!(PTP_PIN_SETFUNC as u32, 0x40603d07);
+        assert_eq!(PTP_PEROUT_REQUEST2 as u32, 0x40383d0c);
+    }
+}
@@ -1,6 +1,10 @@
 //! Implementation of the abstract clock for the linux platform
 
-use std::path::Path;
+pub mod chrony;
+pub(crate) mod ioctl;
+pub mod shm;
+
+use std::{fs::File, path::Path, sync::Arc};
 
 use clock_steering::{unix::UnixClock, TimeOffset};
 use statime::{
@@ -9,27 +13,141 @@ use statime::{
     Clock,
 };
 
+use self::ioctl::{
+    ioctl, PtpClockTime, PtpPeroutRequest, PtpPinDesc, PTP_PEROUT_DUTY_CYCLE, PTP_PEROUT_REQUEST2,
+    PTP_PIN_SETFUNC,
+};
+
 #[derive(Debug, Clone)]
 pub struct LinuxClock {
     clock: clock_steering::unix::UnixClock,
     is_tai: bool,
+    // The hardware clock device, for the ioctls the clock crate does not do
+    device: Option<Arc<File>>,
+}
+
+/// The function of a pin of a hardware clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinFunction {
+    None,
+    ExternalTimestamp,
+    PeriodicOutput,
+}
+
+/// A periodic output signal of a hardware clock, like a pulse per second
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodicOutput {
+    /// Time between the starts of two pulses
+    pub period: Duration,
+    /// Offset of the pulses from the start of a second
+    pub phase: Duration,
+    /// Length of a pulse, `None` for the default of the hardware
+    pub pulse_width: Option<Duration>,
+}
+
+impl PeriodicOutput {
+    /// The start time of an output enabled at `now`, a few seconds out so we
+    /// can be sure it is still in the future when the hardware gets it
+    fn start(&self, now: Time) -> Time {
+        Time::from_secs(now.secs() + 2) + self.phase
+    }
+}
+
+fn duration_to_clock_time(duration: Duration) -> PtpClockTime {
+    let nanos = duration.nanos_rounded();
+    PtpClockTime {
+        sec: nanos.div_euclid(1_000_000_000) as i64,
+        nsec: nanos.rem_euclid(1_000_000_000) as u32,
+        reserved: 0,
+    }
+}
+
+fn not_a_hardware_clock() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the system clock has no pins",
+    )
 }
 
 impl LinuxClock {
     pub const CLOCK_TAI: Self = Self {
         clock: UnixClock::CLOCK_TAI,
         is_tai: true,
+        device: None,
     };
 
     pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
-        let clock = UnixClock::open(path)?;
+        let clock = UnixClock::open(path.as_ref())?;
+        let device = File::options().read(true).write(true).open(path)?;
 
         Ok(Self {
             clock,
             is_tai: false,
+            device: Some(Arc::new(device)),
         })
     }
 
+    /// Assign a function to a pin of a hardware clock, with the channel of that
+    /// function it is used for
+    pub fn set_pin_function(
+        &self,
+        pin: u32,
+        function: PinFunction,
+        channel: u32,
+    ) -> std::io::Result<()> {
+        let device = self.device.as_ref().ok_or_else(not_a_hardware_clock)?;
+
+        let mut desc = PtpPinDesc {
+            name: [0; 64],
+            index: pin,
+            func: match function {
+                PinFunction::None => 0,
+                PinFunction::ExternalTimestamp => 1,
+                PinFunction::PeriodicOutput => 2,
+            },
+            chan: channel,
+            rsv: [0; 5],
+        };
+        ioctl(device, PTP_PIN_SETFUNC, &mut desc)
+    }
+
+    /// Enable a periodic output channel of a hardware clock. It runs on the
+    /// time of the clock, so the pulses stay aligned when it is steered.
+    pub fn enable_periodic_output(
+        &self,
+        channel: u32,
+        output: PeriodicOutput,
+    ) -> std::io::Result<()> {
+        let device = self.device.as_ref().ok_or_else(not_a_hardware_clock)?;
+
+        let start = output.start(statime::Clock::now(self));
+        let mut request = PtpPeroutRequest {
+            start: duration_to_clock_time(start - Time::from_secs(0)),
+            period: duration_to_clock_time(output.period),
+            index: channel,
+            flags: 0,
+            on: PtpClockTime::default(),
+        };
+        if let Some(pulse_width) = output.pulse_width {
+            request.flags |= PTP_PEROUT_DUTY_CYCLE;
+            request.on = duration_to_clock_time(pulse_width);
+        }
+
+        ioctl(device, PTP_PEROUT_REQUEST2, &mut request)
+    }
+
+    /// Disable a periodic output channel of a hardware clock
+    pub fn disable_periodic_output(&self, channel: u32) -> std::io::Result<()> {
+        let device = self.device.as_ref().ok_or_else(not_a_hardware_clock)?;
+
+        // A zero period disables the output
+        let mut request = PtpPeroutRequest {
+            index: channel,
+            ..Default::default()
+        };
+        ioctl(device, PTP_PEROUT_REQUEST2, &mut request)
+    }
+
     /// Return three timestamps t1 t2 and t3 minted in that order.
     /// T1 and T3 are minted using the system TAI clock and T2 by the hardware
     /// clock
@@ -98,6 +216,15 @@ impl Clock for LinuxClock {
         Ok(time_from_timestamp(timestamp, statime::Clock::now(self)))
     }
 
+    fn get_frequency(&self) -> Result<f64, Self::Error> {
+        use clock_steering::Clock;
+        if self.is_tai {
+            UnixClock::CLOCK_REALTIME.get_frequency()
+        } else {
+            self.clock.get_frequency()
+        }
+    }
+
     fn step_clock(&mut self, time_offset: Duration) -> Result<Time, Self::Error> {
         use clock_steering::Clock;
 
@@ -151,3 +278,28 @@ impl Clock for LinuxClock {
 pub fn libc_timespec_into_instant(spec: libc::timespec) -> Time {
     Time::from_fixed_nanos(spec.tv_sec as i128 * 1_000_000_000i128 + spec.tv_nsec as i128)
 }
+
+#[cfg(test)]
+mod tests {
+    use statime::time::{Duration, Time};
+
+    use super::{duration_to_clock_time, PeriodicOutput};
+
+    #[test]
+    fn periodic_output_start() {
+        let output = PeriodicOutput {
+            period: Duration::from_secs(1),
+            phase: Duration::from_millis(250),
+            pulse_width: None,
+        };
+
+        // Aligned to the start of a second, with the phase
+        assert_eq!(
+            output.start(Time::from_nanos(100_999_999_999)),
+            Time::from_millis(102_250)
+        );
+
+        let time = duration_to_clock_time(Time::from_millis(102_250) - Time::from_secs(0));
+        assert_eq!((time.sec, time.nsec), (102, 250_000_000));
+    }
+}
@@ -0,0 +1,212 @@
+//! The shared memory reference clock of ntpd and chrony
+//!
+//! Unit N is the System V shared memory segment with key `0x4e545030 + N`
+//! ("NTP0" and up), which holds the time of a reference clock together with
+//! the time of the system clock at which it was read. It is configured in
+//! ntpd with `server 127.127.28.N`, and in chrony with `refclock SHM N`.
+
+use std::{
+    mem::size_of,
+    ptr::{addr_of, addr_of_mut, read_volatile, write_volatile},
+    sync::atomic::{fence, Ordering},
+};
+
+use statime::{config::LeapIndicator, time::Time};
+
+use crate::clock::LinuxClock;
+
+const SHM_KEY_BASE: libc::key_t = 0x4e545030;
+
+// The precision of our samples as a power of two in seconds, about the
+// microsecond of reading a hardware clock against the system clock
+const PRECISION: libc::c_int = -20;
+
+// The segment as defined in refclock_shm.c of ntpd
+#[repr(C)]
+struct ShmTime {
+    mode: libc::c_int,
+    count: libc::c_int,
+    clock_time_stamp_sec: libc::time_t,
+    clock_time_stamp_usec: libc::c_int,
+    receive_time_stamp_sec: libc::time_t,
+    receive_time_stamp_usec: libc::c_int,
+    leap: libc::c_int,
+    precision: libc::c_int,
+    nsamples: libc::c_int,
+    valid: libc::c_int,
+    clock_time_stamp_nsec: libc::c_uint,
+    receive_time_stamp_nsec: libc::c_uint,
+    dummy: [libc::c_int; 8],
+}
+
+/// A single pair of timestamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShmSample {
+    /// The time on the system clock, which we keep on TAI
+    pub receive_time: Time,
+    /// Our time at that moment, on TAI as well
+    pub clock_time: Time,
+    /// The leap second announced for the end of the current UTC day
+    pub leap: LeapIndicator,
+}
+
+/// An attached NTP shared memory segment
+#[derive(Debug)]
+pub struct NtpShm {
+    segment: *mut ShmTime,
+}
+
+// Safety: the segment is only written through `&mut self`, the readers are
+// other processes that synchronize through its count and valid fields
+unsafe impl Send for NtpShm {}
+
+impl NtpShm {
+    /// Attach the segment of `unit`, creating it when the NTP daemon did not
+    /// yet. Like ntpd, units 0 and 1 are only accessible by root, the others
+    /// by anyone.
+    pub fn open(unit: u32) -> std::io::Result<Self> {
+        let key = SHM_KEY_BASE + unit as libc::key_t;
+        let mode = if unit < 2 { 0o600 } else { 0o666 };
+
+        // Safety: shmget has no memory safety requirements
+        let id = unsafe { libc::shmget(key, size_of::<ShmTime>(), libc::IPC_CREAT | mode) };
+        if id == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        // Safety: the segment is at least as large as ShmTime, and no memory
+        // of ours is mapped over
+        let segment = unsafe { libc::shmat(id, std::ptr::null(), 0) };
+        if segment as isize == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            segment: segment as *mut ShmTime,
+        })
+    }
+
+    /// Publish a sample, replacing the previous one
+    pub fn publish(&mut self, sample: ShmSample) -> std::io::Result<()> {
+        let tai_offset = LinuxClock::CLOCK_TAI.get_tai_offset()?;
+        self.write(sample, tai_offset);
+        Ok(())
+    }
+
+    fn write(&mut self, sample: ShmSample, tai_offset: i32) {
+        let shm = self.segment;
+        let utc_secs = |time: Time| (time.secs() as i64 - tai_offset as i64) as libc::time_t;
+
+        // Safety: the segment stays attached while we exist, and all fields
+        // are plain integers. The writes are volatile, as readers may look at
+        // the segment at any time. In mode 1 they only use a sample when the
+        // count did not change while they read it.
+        unsafe {
+            write_volatile(addr_of_mut!((*shm).valid), 0);
+            write_volatile(addr_of_mut!((*shm).mode), 1);
+            let count = read_volatile(addr_of!((*shm).count));
+            write_volatile(addr_of_mut!((*shm).count), count.wrapping_add(1));
+            fence(Ordering::SeqCst);
+
+            write_volatile(
+                addr_of_mut!((*shm).clock_time_stamp_sec),
+                utc_secs(sample.clock_time),
+            );
+            write_volatile(
+                addr_of_mut!((*shm).clock_time_stamp_usec),
+                (sample.clock_time.subsec_nanos() / 1_000) as libc::c_int,
+            );
+            write_volatile(
+                addr_of_mut!((*shm).clock_time_stamp_nsec),
+                sample.clock_time.subsec_nanos(),
+            );
+            write_volatile(
+                addr_of_mut!((*shm).receive_time_stamp_sec),
+                utc_secs(sample.receive_time),
+            );
+            write_volatile(
+                addr_of_mut!((*shm).receive_time_stamp_usec),
+                (sample.receive_time.subsec_nanos() / 1_000) as libc::c_int,
+            );
+            write_volatile(
+                addr_of_mut!((*shm).receive_time_stamp_nsec),
+                sample.receive_time.subsec_nanos(),
+            );
+            write_volatile(
+                addr_of_mut!((*shm).leap),
+                match sample.leap {
+                    LeapIndicator::NoLeap => 0,
+                    LeapIndicator::Leap61 => 1,
+                    LeapIndicator::Leap59 => 2,
+                },
+            );
+            write_volatile(addr_of_mut!((*shm).precision), PRECISION);
+
+            fence(Ordering::SeqCst);
+            write_volatile(addr_of_mut!((*shm).count), count.wrapping_add(2));
+            write_volatile(addr_of_mut!((*shm).valid), 1);
+        }
+    }
+}
+
+impl Drop for NtpShm {
+    fn drop(&mut self) {
+        // Safety: the segment was attached by us. The segment itself stays, as
+        // the NTP daemon may still use it.
+        unsafe { libc::shmdt(self.segment as *const libc::c_void) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shm_layout() {
+        if size_of::<libc::time_t>() == 8 {
+            assert_eq!(size_of::<ShmTime>(), 96);
+        }
+    }
+
+    #[test]
+    fn write_sample() {
+        // A unit far beyond those used in practice, so the test does not
+        // disturb a running NTP daemon
+        let unit = 0x10000 + (std::process::id() & 0xffff);
+        let mut shm = match NtpShm::open(unit) {
+            Ok(shm) => shm,
+            // Some sandboxes do not allow System V shared memory
+            Err(error) if error.raw_os_error() == Some(libc::ENOSYS) => return,
+            Err(error) => panic!("could not open segment: {error}"),
+        };
+
+        shm.write(
+            ShmSample {
+                receive_time: Time::from_nanos(1_700_000_037_000_001_500),
+                clock_time: Time::from_nanos(1_700_000_037_000_000_250),
+                leap: LeapIndicator::Leap59,
+            },
+            37,
+        );
+
+        // Safety: the segment is attached and only used by this test
+        let segment = unsafe { &*shm.segment };
+        assert_eq!(segment.mode, 1);
+        assert_eq!(segment.count % 2, 0);
+        assert_eq!(segment.valid, 1);
+        assert_eq!(segment.clock_time_stamp_sec, 1_700_000_000);
+        assert_eq!(segment.clock_time_stamp_usec, 0);
+        assert_eq!(segment.clock_time_stamp_nsec, 250);
+        assert_eq!(segment.receive_time_stamp_sec, 1_700_000_000);
+        assert_eq!(segment.receive_time_stamp_usec, 1);
+        assert_eq!(segment.receive_time_stamp_nsec, 1_500);
+        assert_eq!(segment.leap, 2);
+        assert_eq!(segment.precision, PRECISION);
+
+        // Safety: the key refers to the segment of this test only
+        unsafe {
+            let id = libc::shmget(SHM_KEY_BASE + unit as libc::key_t, 0, 0);
+            libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut());
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     fs::read_to_string,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     str::FromStr,
@@ -9,12 +10,21 @@ use std::{
 use log::warn;
 use serde::{Deserialize, Deserializer};
 use statime::{
-    config::{ClockIdentity, DelayMechanism},
+    config::{
+        telecom, ClockAccuracy, ClockIdentity, ClockQuality, DelayMechanism, HoldoverDegradation,
+        LeapIndicator, MasterLockingStatus, PortAddress, Profile, SynchronizationMetadata,
+        TimePropertiesDS, TimeSource, TransparentClockConfigError, UnicastNegotiationConfig,
+        MAX_UNICAST_MASTERS,
+    },
+    filters::{AnyFilterConfiguration, KalmanConfiguration, LinregConfiguration, PiConfiguration},
     time::{Duration, Interval},
+    transparent_clock::MAX_TRANSPARENT_CLOCK_PORTS,
 };
 use timestamped_socket::interface::InterfaceName;
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+use crate::clock::PeriodicOutput;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Config {
     #[serde(
@@ -24,21 +34,55 @@ pub struct Config {
     pub loglevel: log::LevelFilter,
     #[serde(default = "default_sdo_id")]
     pub sdo_id: u16,
-    #[serde(default = "default_domain")]
-    pub domain: u8,
+    #[serde(default)]
+    pub domain: Option<u8>,
     #[serde(default, deserialize_with = "deserialize_clock_identity")]
     pub identity: Option<ClockIdentity>,
     #[serde(default = "default_priority1")]
     pub priority1: u8,
     #[serde(default = "default_priority2")]
     pub priority2: u8,
+    #[serde(default)]
+    pub transparent_clock: Option<TransparentClockMode>,
+    #[serde(default)]
+    pub profile: ProfileType,
+    #[serde(default = "default_neighbor_prop_delay_threshold")]
+    pub neighbor_prop_delay_threshold: u64,
+    #[serde(default = "default_local_priority")]
+    pub local_priority: u8,
+    #[serde(default = "default_grandmaster_id")]
+    pub grandmaster_id: u16,
+    #[serde(default)]
+    pub time_inaccuracy: u32,
+    #[serde(default)]
+    pub clock_quality: Option<ClockQualityConfig>,
+    #[serde(default)]
+    pub holdover: Option<HoldoverConfig>,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    #[serde(default)]
+    pub clock_filter: FilterConfig,
+    #[serde(default)]
+    pub time_properties: TimePropertiesConfig,
+    #[serde(default)]
+    pub smpte: SmpteConfig,
+    #[serde(default)]
+    pub pps: Option<PpsConfig>,
+    #[serde(default)]
+    pub nmea: Option<NmeaConfig>,
+    #[serde(default)]
+    pub chrony_socket: Option<PathBuf>,
+    #[serde(default)]
+    pub ntp_shm_unit: Option<u32>,
     #[serde(rename = "port")]
     pub ports: Vec<PortConfig>,
+    #[serde(default, rename = "periodic-output")]
+    pub periodic_outputs: Vec<PeriodicOutputConfig>,
     #[serde(default)]
     pub observability: ObservabilityConfig,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct PortConfig {
     pub interface: InterfaceName,
@@ -48,20 +92,78 @@ pub struct PortConfig {
     pub hardware_clock: Option<PathBuf>,
     #[serde(default)]
     pub network_mode: NetworkMode,
-    #[serde(default = "default_announce_interval")]
-    pub announce_interval: i8,
-    #[serde(default = "default_sync_interval")]
-    pub sync_interval: i8,
+    #[serde(default)]
+    pub announce_interval: Option<i8>,
+    #[serde(default)]
+    pub sync_interval: Option<i8>,
     #[serde(default = "default_announce_receipt_timeout")]
     pub announce_receipt_timeout: u8,
     #[serde(default)]
     pub master_only: bool,
+    #[serde(default = "default_local_priority")]
+    pub local_priority: u8,
     #[serde(default = "default_delay_asymmetry")]
     pub delay_asymmetry: i64,
     #[serde(default)]
+    pub one_step: bool,
+    #[serde(default)]
+    pub hybrid_e2e: bool,
+    #[serde(default)]
     pub delay_mechanism: DelayType,
-    #[serde(default = "default_delay_interval")]
-    pub delay_interval: i8,
+    #[serde(default)]
+    pub delay_interval: Option<i8>,
+    #[serde(default, deserialize_with = "deserialize_unicast_master_table")]
+    pub unicast_master_table: Vec<PortAddress>,
+    #[serde(default)]
+    pub unicast_negotiation: bool,
+    #[serde(default = "default_grant_duration")]
+    pub grant_duration: u32,
+    #[serde(default)]
+    pub filter: Option<FilterConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PeriodicOutputConfig {
+    pub hardware_clock: PathBuf,
+    pub channel: u32,
+    #[serde(default)]
+    pub pin: Option<u32>,
+    #[serde(default = "default_period")]
+    pub period: u64,
+    #[serde(default)]
+    pub phase: u64,
+    #[serde(default)]
+    pub pulse_width: Option<u64>,
+}
+
+impl TryFrom<&PeriodicOutputConfig> for PeriodicOutput {
+    type Error = ConfigError;
+
+    fn try_from(config: &PeriodicOutputConfig) -> Result<Self, ConfigError> {
+        let nanos = |nanos: u64| {
+            i64::try_from(nanos).map(Duration::from_nanos).map_err(|_| {
+                ConfigError::PeriodicOutputOutOfRange(config.hardware_clock.clone(), config.channel)
+            })
+        };
+
+        Ok(PeriodicOutput {
+            period: nanos(config.period)?,
+            phase: nanos(config.phase)?,
+            pulse_width: config.pulse_width.map(nanos).transpose()?,
+        })
+    }
+}
+
+impl PortConfig {
+    fn accepts_address(&self, address: &PortAddress) -> bool {
+        matches!(
+            (self.network_mode, address),
+            (NetworkMode::Ipv4, PortAddress::Ipv4(_))
+                | (NetworkMode::Ipv6, PortAddress::Ipv6(_))
+                | (NetworkMode::Ethernet, PortAddress::Ethernet(_))
+        )
+    }
 }
 
 fn deserialize_loglevel<'de, D>(deserializer: D) -> Result<log::LevelFilter, D::Error>
@@ -95,6 +197,32 @@ where
     Ok(Some(result))
 }
 
+fn deserialize_unicast_master_table<'de, D>(deserializer: D) -> Result<Vec<PortAddress>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use hex::FromHex;
+    use serde::de::Error;
+
+    let raw: Vec<String> = Deserialize::deserialize(deserializer)?;
+    let mut result = Vec::with_capacity(raw.len());
+
+    for address in raw {
+        if let Ok(ip) = IpAddr::from_str(&address) {
+            result.push(PortAddress::from(ip));
+        } else {
+            let mac = <[u8; 6]>::from_hex(address.replace(':', "")).map_err(|_| {
+                D::Error::custom(format!(
+                    "Invalid unicast master address: {address}, expected an IP or MAC address"
+                ))
+            })?;
+            result.push(PortAddress::Ethernet(mac));
+        }
+    }
+
+    Ok(result)
+}
+
 fn deserialize_clock_identity<'de, D>(deserializer: D) -> Result<Option<ClockIdentity>, D::Error>
 where
     D: Deserializer<'de>,
@@ -107,28 +235,74 @@ where
     )?)))
 }
 
-impl From<PortConfig> for statime::config::PortConfig<Option<Vec<ClockIdentity>>> {
-    fn from(pc: PortConfig) -> Self {
-        Self {
-            acceptable_master_list: pc.acceptable_master_list,
-            announce_interval: Interval::from_log_2(pc.announce_interval),
-            sync_interval: Interval::from_log_2(pc.sync_interval),
-            announce_receipt_timeout: pc.announce_receipt_timeout,
-            master_only: pc.master_only,
-            delay_asymmetry: Duration::from_nanos(pc.delay_asymmetry),
-            delay_mechanism: match pc.delay_mechanism {
-                DelayType::E2E => DelayMechanism::E2E {
-                    interval: Interval::from_log_2(pc.delay_interval),
-                },
-                DelayType::P2P => DelayMechanism::P2P {
-                    interval: Interval::from_log_2(pc.delay_interval),
-                },
+impl PortConfig {
+    /// The announce interval of the port, defaulting to the one of the profile
+    pub fn announce_interval(&self, profile: ProfileType) -> Interval {
+        match (self.announce_interval, profile) {
+            (Some(log_2), _) => Interval::from_log_2(log_2),
+            (None, ProfileType::TelecomPartialTiming) => telecom::PARTIAL_TIMING_ANNOUNCE_INTERVAL,
+            (None, _) => Interval::from_log_2(default_announce_interval()),
+        }
+    }
+
+    /// The sync interval of the port, defaulting to the one of the profile
+    pub fn sync_interval(&self, profile: ProfileType) -> Interval {
+        match (self.sync_interval, profile) {
+            (Some(log_2), _) => Interval::from_log_2(log_2),
+            (None, ProfileType::TelecomPartialTiming) => telecom::PARTIAL_TIMING_SYNC_INTERVAL,
+            (None, _) => Interval::from_log_2(default_sync_interval()),
+        }
+    }
+
+    /// The delay request interval of the port, defaulting to the one of the
+    /// profile
+    pub fn delay_interval(&self, profile: ProfileType) -> Interval {
+        match (self.delay_interval, profile) {
+            (Some(log_2), _) => Interval::from_log_2(log_2),
+            (None, ProfileType::TelecomPartialTiming) => telecom::PARTIAL_TIMING_DELAY_INTERVAL,
+            (None, _) => Interval::from_log_2(default_delay_interval()),
+        }
+    }
+
+    /// The configuration of the port in an instance running the given profile
+    pub fn instance_port_config(
+        self,
+        profile: ProfileType,
+    ) -> statime::config::PortConfig<Option<Vec<ClockIdentity>>> {
+        let unicast_master_table = self
+            .unicast_master_table
+            .iter()
+            .filter(|address| self.accepts_address(address))
+            .take(MAX_UNICAST_MASTERS)
+            .copied()
+            .collect();
+        let interval = self.delay_interval(profile);
+
+        statime::config::PortConfig {
+            announce_interval: self.announce_interval(profile),
+            sync_interval: self.sync_interval(profile),
+            acceptable_master_list: self.acceptable_master_list,
+            announce_receipt_timeout: self.announce_receipt_timeout,
+            master_only: self.master_only,
+            local_priority: self.local_priority,
+            delay_asymmetry: Duration::from_nanos(self.delay_asymmetry),
+            one_step: self.one_step,
+            hybrid_e2e: self.hybrid_e2e,
+            delay_mechanism: match self.delay_mechanism {
+                DelayType::E2E => DelayMechanism::E2E { interval },
+                DelayType::P2P => DelayMechanism::P2P { interval },
             },
+            unicast_master_table,
+            unicast_negotiation: self
+                .unicast_negotiation
+                .then_some(UnicastNegotiationConfig {
+                    grant_duration: self.grant_duration,
+                }),
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum NetworkMode {
     #[default]
@@ -137,6 +311,29 @@ pub enum NetworkMode {
     Ethernet,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TransparentClockMode {
+    E2E,
+    P2P,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileType {
+    #[default]
+    Default,
+    Gptp,
+    #[serde(rename = "g8275.1")]
+    TelecomFullTiming,
+    #[serde(rename = "g8275.2")]
+    TelecomPartialTiming,
+    #[serde(rename = "c37.238")]
+    Power,
+    #[serde(rename = "st2059-2")]
+    Smpte2059,
+}
+
 #[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum DelayType {
@@ -157,19 +354,311 @@ impl Config {
 
         let contents = read_to_string(file).map_err(ConfigError::Io)?;
         let config: Config = toml::de::from_str(&contents).map_err(ConfigError::Toml)?;
+        config.validate()?;
         config.warn_when_unreasonable();
         Ok(config)
     }
 
+    /// Reject configurations that the daemon cannot run
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.transparent_clock.is_some() && self.ports.len() > MAX_TRANSPARENT_CLOCK_PORTS {
+            return Err(ConfigError::TransparentClock(
+                TransparentClockConfigError::TooManyPorts,
+            ));
+        }
+
+        if let Some(port) = self
+            .ports
+            .iter()
+            .find(|port| port.one_step && port.hardware_clock.is_none())
+        {
+            return Err(ConfigError::OneStepWithoutHardwareClock(port.interface));
+        }
+
+        if self.profile == ProfileType::Power
+            && !POWER_GRANDMASTER_IDS.contains(&self.grandmaster_id)
+        {
+            return Err(ConfigError::InvalidGrandmasterId(self.grandmaster_id));
+        }
+
+        for output in &self.periodic_outputs {
+            PeriodicOutput::try_from(output)?;
+        }
+
+        Ok(())
+    }
+
+    /// The domain of the instance, defaulting to the one of the profile
+    pub fn domain(&self) -> u8 {
+        match (self.domain, self.profile) {
+            (Some(domain), _) => domain,
+            (None, ProfileType::TelecomFullTiming) => telecom::FULL_TIMING_DEFAULT_DOMAIN,
+            (None, ProfileType::TelecomPartialTiming) => telecom::PARTIAL_TIMING_DEFAULT_DOMAIN,
+            (None, _) => default_domain(),
+        }
+    }
+
+    /// The PTP profile the instance runs
+    pub fn profile(&self) -> Profile {
+        match self.profile {
+            ProfileType::Default => Profile::Default,
+            ProfileType::Gptp => Profile::Gptp {
+                neighbor_prop_delay_threshold: Duration::from_nanos(
+                    self.neighbor_prop_delay_threshold as i64,
+                ),
+            },
+            ProfileType::TelecomFullTiming => Profile::TelecomFullTiming {
+                local_priority: self.local_priority,
+            },
+            ProfileType::TelecomPartialTiming => Profile::TelecomPartialTiming {
+                local_priority: self.local_priority,
+            },
+            ProfileType::Power => Profile::Power {
+                grandmaster_id: self.grandmaster_id,
+                time_inaccuracy: self.time_inaccuracy,
+            },
+            ProfileType::Smpte2059 => Profile::Smpte2059 {
+                metadata: self.smpte.metadata(),
+            },
+        }
+    }
+
+    /// The clock quality announced as grandmaster, `None` for the default of
+    /// the profile
+    pub fn clock_quality(&self) -> Option<ClockQuality> {
+        self.clock_quality
+            .map(ClockQuality::from)
+            .or_else(|| self.pps.as_ref().map(PpsConfig::clock_quality))
+    }
+
+    /// The time properties announced as grandmaster, see
+    /// [`TimePropertiesConfig::time_properties_ds`]
+    pub fn time_properties_ds(
+        &self,
+        kernel_utc_offset: impl FnOnce() -> Option<i16>,
+    ) -> TimePropertiesDS {
+        let default_time_source = match self.pps {
+            Some(_) => TimeSource::Gnss,
+            None => TimeSource::InternalOscillator,
+        };
+
+        self.time_properties
+            .time_properties_ds(default_time_source, kernel_utc_offset)
+    }
+
     /// Warns about unreasonable config values
     pub fn warn_when_unreasonable(&self) {
         if self.ports.is_empty() {
             warn!("No ports configured.");
         }
 
-        if self.ports.len() > 16 {
+        if self.ports.len() > MAX_TRANSPARENT_CLOCK_PORTS {
             warn!("Too many ports are configured.");
         }
+
+        if self.transparent_clock.is_some() && self.ports.len() < 2 {
+            warn!("A transparent clock needs at least two ports to forward messages between.");
+        }
+
+        if self.time_properties.timescale == Timescale::Arb
+            && self.time_properties.current_utc_offset.is_some()
+        {
+            warn!("The current UTC offset is ignored with the ARB timescale.");
+        }
+
+        if self.time_properties.timescale == Timescale::Arb
+            && self.time_properties.leap_seconds_file.is_some()
+        {
+            warn!("The leap seconds file is ignored with the ARB timescale.");
+        }
+
+        if let Some(pps) = &self.pps {
+            if pps.extts_pin.is_some() && pps.extts_channel.is_none() {
+                warn!("The PPS extts-pin is ignored without an extts-channel.");
+            }
+        }
+
+        for port in self.ports.iter().filter(|port| port.one_step) {
+            warn!(
+                "Port {} sends one-step messages with the time of its hardware clock when they \
+                 were created, which is off by the delay until they are sent.",
+                port.interface
+            );
+        }
+
+        for output in &self.periodic_outputs {
+            if output.period == 0 {
+                warn!(
+                    "Periodic output {} of {} has no period and stays disabled.",
+                    output.channel,
+                    output.hardware_clock.display()
+                );
+            }
+
+            if output
+                .pulse_width
+                .map_or(false, |pulse_width| pulse_width >= output.period)
+            {
+                warn!(
+                    "The pulse width of periodic output {} of {} should be shorter than its \
+                     period.",
+                    output.channel,
+                    output.hardware_clock.display()
+                );
+            }
+        }
+
+        if self.nmea.is_some() && self.pps.is_none() {
+            warn!("NMEA sentences are ignored without a PPS reference.");
+        }
+
+        let filters = [&self.filter, &self.clock_filter]
+            .into_iter()
+            .chain(self.ports.iter().filter_map(|port| port.filter.as_ref()));
+        for filter in filters {
+            if let FilterConfig::Kalman(kalman) = filter {
+                if kalman.precision_hysteresis > 127 {
+                    warn!("The precision-hysteresis of a kalman filter should be at most 127.");
+                }
+
+                if kalman.difference_estimation_boundary > 32
+                    || kalman.statistical_estimation_boundary > 32
+                {
+                    warn!("The estimation boundaries of a kalman filter should be at most 32.");
+                }
+            }
+        }
+
+        if (self.chrony_socket.is_some() || self.ntp_shm_unit.is_some())
+            && self.ports.iter().any(|port| port.hardware_clock.is_none())
+        {
+            warn!(
+                "Ports without a hardware clock steer the system clock, so it is not handed to \
+                 chrony or ntpd."
+            );
+        }
+
+        let telecom_domains = match self.profile {
+            ProfileType::Default
+            | ProfileType::Gptp
+            | ProfileType::Power
+            | ProfileType::Smpte2059 => None,
+            ProfileType::TelecomFullTiming => Some(telecom::FULL_TIMING_DOMAINS),
+            ProfileType::TelecomPartialTiming => Some(telecom::PARTIAL_TIMING_DOMAINS),
+        };
+        if let Some(domains) = telecom_domains {
+            if !domains.contains(&self.domain()) {
+                warn!(
+                    "The telecom profile requires a domain between {} and {}.",
+                    domains.start(),
+                    domains.end()
+                );
+            }
+        }
+
+        for port in &self.ports {
+            if self.profile == ProfileType::Gptp {
+                if port.delay_mechanism != DelayType::P2P {
+                    warn!(
+                        "gPTP requires the peer to peer delay mechanism, port {} will not work.",
+                        port.interface
+                    );
+                }
+
+                if port.network_mode != NetworkMode::Ethernet {
+                    warn!(
+                        "gPTP is only defined for ethernet, port {} will not interoperate.",
+                        port.interface
+                    );
+                }
+            }
+
+            if self.profile == ProfileType::Power {
+                if port.delay_mechanism != DelayType::P2P {
+                    warn!(
+                        "The power profile requires the peer to peer delay mechanism, port {} \
+                         will not work.",
+                        port.interface
+                    );
+                }
+
+                if port.network_mode != NetworkMode::Ethernet {
+                    warn!(
+                        "The power profile is only defined for ethernet, port {} will not \
+                         interoperate.",
+                        port.interface
+                    );
+                }
+            }
+
+            if self.profile == ProfileType::TelecomPartialTiming {
+                if port.network_mode == NetworkMode::Ethernet {
+                    warn!(
+                        "The partial timing telecom profile runs over IPv4 or IPv6, port {} will \
+                         not interoperate.",
+                        port.interface
+                    );
+                }
+
+                if port.unicast_master_table.is_empty() || !port.unicast_negotiation {
+                    warn!(
+                        "The partial timing telecom profile requires unicast negotiation with a \
+                         unicast master table, port {} will not find a master.",
+                        port.interface
+                    );
+                }
+
+                if !(-3..=0).contains(&port.announce_interval(self.profile).as_log_2())
+                    || !(-7..=0).contains(&port.sync_interval(self.profile).as_log_2())
+                    || !(-7..=0).contains(&port.delay_interval(self.profile).as_log_2())
+                {
+                    warn!(
+                        "Message rates of port {} are outside of the range allowed by the partial \
+                         timing telecom profile.",
+                        port.interface
+                    );
+                }
+            }
+
+            let grant_durations = match self.profile {
+                ProfileType::TelecomPartialTiming => telecom::PARTIAL_TIMING_GRANT_DURATIONS,
+                _ => 10..=1000,
+            };
+            if port.unicast_negotiation && !grant_durations.contains(&port.grant_duration) {
+                warn!(
+                    "Grant duration of {} should be between {} and {} seconds.",
+                    port.interface,
+                    grant_durations.start(),
+                    grant_durations.end()
+                );
+            }
+
+            if port
+                .unicast_master_table
+                .iter()
+                .any(|address| !port.accepts_address(address))
+            {
+                warn!(
+                    "Unicast master table of {} contains addresses that do not match its \
+                     network mode, these are ignored.",
+                    port.interface
+                );
+            }
+
+            if port.hybrid_e2e && port.delay_mechanism != DelayType::E2E {
+                warn!(
+                    "Hybrid mode of {} only applies to the end to end delay mechanism.",
+                    port.interface
+                );
+            }
+
+            if port.unicast_master_table.len() > MAX_UNICAST_MASTERS {
+                warn!(
+                    "Unicast master table of {} is too long, only the first {} entries are used.",
+                    port.interface, MAX_UNICAST_MASTERS
+                );
+            }
+        }
     }
 }
 
@@ -177,6 +666,10 @@ impl Config {
 pub enum ConfigError {
     Io(std::io::Error),
     Toml(toml::de::Error),
+    TransparentClock(TransparentClockConfigError),
+    OneStepWithoutHardwareClock(InterfaceName),
+    InvalidGrandmasterId(u16),
+    PeriodicOutputOutOfRange(PathBuf, u32),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -184,6 +677,23 @@ impl std::fmt::Display for ConfigError {
         match self {
             ConfigError::Io(e) => writeln!(f, "io error while reading config: {e}"),
             ConfigError::Toml(e) => writeln!(f, "config toml parsing error: {e}"),
+            ConfigError::TransparentClock(e) => writeln!(f, "invalid transparent clock: {e}"),
+            ConfigError::OneStepWithoutHardwareClock(interface) => writeln!(
+                f,
+                "port {interface} can only send one-step messages with a hardware clock"
+            ),
+            ConfigError::InvalidGrandmasterId(id) => writeln!(
+                f,
+                "grandmaster-id {id} is outside of the range {} to {} of the power profile",
+                POWER_GRANDMASTER_IDS.start(),
+                POWER_GRANDMASTER_IDS.end()
+            ),
+            ConfigError::PeriodicOutputOutOfRange(hardware_clock, channel) => writeln!(
+                f,
+                "periodic output {channel} of {} has a period, phase or pulse width that is out \
+                 of range",
+                hardware_clock.display()
+            ),
         }
     }
 }
@@ -202,6 +712,25 @@ fn default_sdo_id() -> u16 {
     0x000
 }
 
+fn default_local_priority() -> u8 {
+    telecom::DEFAULT_LOCAL_PRIORITY
+}
+
+/// The grandmasterIDs allowed by IEEE C37.238-2017
+const POWER_GRANDMASTER_IDS: RangeInclusive<u16> = 3..=254;
+
+fn default_grandmaster_id() -> u16 {
+    *POWER_GRANDMASTER_IDS.end()
+}
+
+fn default_neighbor_prop_delay_threshold() -> u64 {
+    800
+}
+
+fn default_grant_duration() -> u32 {
+    telecom::PARTIAL_TIMING_GRANT_DURATION
+}
+
 fn default_announce_interval() -> i8 {
     1
 }
@@ -230,6 +759,10 @@ fn default_delay_interval() -> i8 {
     0
 }
 
+fn default_period() -> u64 {
+    1_000_000_000
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct ObservabilityConfig {
@@ -257,6 +790,472 @@ impl Default for ObservabilityConfig {
     }
 }
 
+/// The clock quality announced as grandmaster
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ClockQualityConfig {
+    pub clock_class: u8,
+    #[serde(default = "default_clock_accuracy")]
+    pub clock_accuracy: u8,
+    #[serde(default = "default_offset_scaled_log_variance")]
+    pub offset_scaled_log_variance: u16,
+}
+
+impl From<ClockQualityConfig> for ClockQuality {
+    fn from(config: ClockQualityConfig) -> Self {
+        ClockQuality {
+            clock_class: config.clock_class,
+            clock_accuracy: ClockAccuracy::from_primitive(config.clock_accuracy),
+            offset_scaled_log_variance: config.offset_scaled_log_variance,
+        }
+    }
+}
+
+/// The holdover once no port is a slave anymore
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HoldoverConfig {
+    #[serde(default = "default_time_error_budget")]
+    pub time_error_budget: u64,
+    #[serde(default = "default_max_holdover_duration")]
+    pub max_duration: u64,
+    #[serde(default)]
+    pub degradation: DegradationType,
+}
+
+impl From<HoldoverConfig> for statime::config::HoldoverConfig {
+    fn from(config: HoldoverConfig) -> Self {
+        statime::config::HoldoverConfig {
+            time_error_budget: Duration::from_nanos(config.time_error_budget as i64),
+            max_duration: Duration::from_secs(config.max_duration as i64),
+            degradation: match config.degradation {
+                DegradationType::AlternativeA => HoldoverDegradation::AlternativeA,
+                DegradationType::AlternativeB => HoldoverDegradation::AlternativeB,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DegradationType {
+    AlternativeA,
+    #[default]
+    AlternativeB,
+}
+
+fn default_time_error_budget() -> u64 {
+    1_000
+}
+
+fn default_max_holdover_duration() -> u64 {
+    24 * 60 * 60
+}
+
+/// The filter that synchronizes a clock, and its tuning
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum FilterConfig {
+    Kalman(KalmanFilterConfig),
+    Basic(BasicFilterConfig),
+    Pi(PiFilterConfig),
+    Linreg(LinregFilterConfig),
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig::Kalman(KalmanFilterConfig::default())
+    }
+}
+
+impl From<FilterConfig> for AnyFilterConfiguration {
+    fn from(config: FilterConfig) -> Self {
+        match config {
+            FilterConfig::Kalman(config) => AnyFilterConfiguration::Kalman(config.into()),
+            FilterConfig::Basic(config) => AnyFilterConfiguration::Basic(config.gain),
+            FilterConfig::Pi(config) => AnyFilterConfiguration::Pi(config.into()),
+            FilterConfig::Linreg(config) => AnyFilterConfiguration::Linreg(config.into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct KalmanFilterConfig {
+    pub step_threshold: u64,
+    pub deadzone: f64,
+    pub steer_time: f64,
+    pub max_steer: f64,
+    pub max_freq_offset: f64,
+    pub initial_frequency_uncertainty: f64,
+    pub initial_wander: f64,
+    pub delay_wander: f64,
+    pub precision_low_probability: f64,
+    pub precision_high_probability: f64,
+    pub precision_hysteresis: u8,
+    pub estimate_threshold: u64,
+    pub difference_estimation_boundary: usize,
+    pub statistical_estimation_boundary: usize,
+    pub peer_delay_factor: f64,
+}
+
+impl Default for KalmanFilterConfig {
+    fn default() -> Self {
+        let config = KalmanConfiguration::default();
+        KalmanFilterConfig {
+            step_threshold: config.step_threshold.nanos_rounded() as u64,
+            deadzone: config.deadzone,
+            steer_time: config.steer_time.seconds(),
+            max_steer: config.max_steer,
+            max_freq_offset: config.max_freq_offset,
+            initial_frequency_uncertainty: config.initial_frequency_uncertainty,
+            initial_wander: config.initial_wander,
+            delay_wander: config.delay_wander,
+            precision_low_probability: config.precision_low_probability,
+            precision_high_probability: config.precision_high_probability,
+            precision_hysteresis: config.precision_hysteresis,
+            estimate_threshold: config.estimate_threshold.nanos_rounded() as u64,
+            difference_estimation_boundary: config.difference_estimation_boundary,
+            statistical_estimation_boundary: config.statistical_estimation_boundary,
+            peer_delay_factor: config.peer_delay_factor,
+        }
+    }
+}
+
+impl From<KalmanFilterConfig> for KalmanConfiguration {
+    fn from(config: KalmanFilterConfig) -> Self {
+        KalmanConfiguration {
+            step_threshold: Duration::from_nanos(config.step_threshold as i64),
+            deadzone: config.deadzone,
+            steer_time: Duration::from_seconds(config.steer_time),
+            max_steer: config.max_steer,
+            max_freq_offset: config.max_freq_offset,
+            initial_frequency_uncertainty: config.initial_frequency_uncertainty,
+            initial_wander: config.initial_wander,
+            delay_wander: config.delay_wander,
+            precision_low_probability: config.precision_low_probability,
+            precision_high_probability: config.precision_high_probability,
+            precision_hysteresis: config.precision_hysteresis,
+            estimate_threshold: Duration::from_nanos(config.estimate_threshold as i64),
+            difference_estimation_boundary: config.difference_estimation_boundary,
+            statistical_estimation_boundary: config.statistical_estimation_boundary,
+            peer_delay_factor: config.peer_delay_factor,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BasicFilterConfig {
+    #[serde(default = "default_basic_gain")]
+    pub gain: f64,
+}
+
+fn default_basic_gain() -> f64 {
+    0.1
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct PiFilterConfig {
+    pub kp: Option<f64>,
+    pub kp_scale: f64,
+    pub kp_exponent: f64,
+    pub kp_norm_max: f64,
+    pub ki: Option<f64>,
+    pub ki_scale: f64,
+    pub ki_exponent: f64,
+    pub ki_norm_max: f64,
+    pub first_step_threshold: u64,
+    pub step_threshold: u64,
+    pub max_frequency: f64,
+}
+
+impl Default for PiFilterConfig {
+    fn default() -> Self {
+        let config = PiConfiguration::default();
+        PiFilterConfig {
+            kp: config.kp,
+            kp_scale: config.kp_scale,
+            kp_exponent: config.kp_exponent,
+            kp_norm_max: config.kp_norm_max,
+            ki: config.ki,
+            ki_scale: config.ki_scale,
+            ki_exponent: config.ki_exponent,
+            ki_norm_max: config.ki_norm_max,
+            first_step_threshold: step_threshold_nanos(config.first_step_threshold),
+            step_threshold: step_threshold_nanos(config.step_threshold),
+            max_frequency: config.max_frequency,
+        }
+    }
+}
+
+impl From<PiFilterConfig> for PiConfiguration {
+    fn from(config: PiFilterConfig) -> Self {
+        PiConfiguration {
+            kp: config.kp,
+            kp_scale: config.kp_scale,
+            kp_exponent: config.kp_exponent,
+            kp_norm_max: config.kp_norm_max,
+            ki: config.ki,
+            ki_scale: config.ki_scale,
+            ki_exponent: config.ki_exponent,
+            ki_norm_max: config.ki_norm_max,
+            first_step_threshold: step_threshold(config.first_step_threshold),
+            step_threshold: step_threshold(config.step_threshold),
+            max_frequency: config.max_frequency,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct LinregFilterConfig {
+    pub first_step_threshold: u64,
+    pub step_threshold: u64,
+    pub max_frequency: f64,
+}
+
+impl Default for LinregFilterConfig {
+    fn default() -> Self {
+        let config = LinregConfiguration::default();
+        LinregFilterConfig {
+            first_step_threshold: step_threshold_nanos(config.first_step_threshold),
+            step_threshold: step_threshold_nanos(config.step_threshold),
+            max_frequency: config.max_frequency,
+        }
+    }
+}
+
+impl From<LinregFilterConfig> for LinregConfiguration {
+    fn from(config: LinregFilterConfig) -> Self {
+        LinregConfiguration {
+            first_step_threshold: step_threshold(config.first_step_threshold),
+            step_threshold: step_threshold(config.step_threshold),
+            max_frequency: config.max_frequency,
+        }
+    }
+}
+
+// Like linuxptp, a step threshold of 0 disables stepping
+fn step_threshold(nanos: u64) -> Option<Duration> {
+    (nanos != 0).then(|| Duration::from_nanos(nanos as i64))
+}
+
+fn step_threshold_nanos(threshold: Option<Duration>) -> u64 {
+    threshold.map_or(0, |threshold| threshold.nanos_rounded() as u64)
+}
+
+/// The time properties announced as grandmaster
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TimePropertiesConfig {
+    #[serde(default)]
+    pub timescale: Timescale,
+    #[serde(default)]
+    pub current_utc_offset: Option<i16>,
+    #[serde(default)]
+    pub time_source: Option<TimeSourceType>,
+    #[serde(default)]
+    pub time_traceable: bool,
+    #[serde(default)]
+    pub frequency_traceable: bool,
+    #[serde(default)]
+    pub leap_seconds_file: Option<PathBuf>,
+}
+
+impl TimePropertiesConfig {
+    /// The time properties dataset of the instance. With the PTP timescale and
+    /// no configured UTC offset, the offset is taken from `kernel_utc_offset`.
+    /// Without a configured time source, `default_time_source` is used.
+    pub fn time_properties_ds(
+        &self,
+        default_time_source: TimeSource,
+        kernel_utc_offset: impl FnOnce() -> Option<i16>,
+    ) -> TimePropertiesDS {
+        let time_source = self.time_source.map_or(default_time_source, Into::into);
+
+        match self.timescale {
+            Timescale::Ptp => TimePropertiesDS::new_ptp_time(
+                self.current_utc_offset.or_else(kernel_utc_offset),
+                LeapIndicator::NoLeap,
+                self.time_traceable,
+                self.frequency_traceable,
+                time_source,
+            ),
+            Timescale::Arb => TimePropertiesDS::new_arbitrary_time(
+                self.time_traceable,
+                self.frequency_traceable,
+                time_source,
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Timescale {
+    Ptp,
+    #[default]
+    Arb,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeSourceType {
+    AtomicClock,
+    Gnss,
+    TerrestrialRadio,
+    SerialTimeCode,
+    Ptp,
+    Ntp,
+    HandSet,
+    Other,
+    InternalOscillator,
+}
+
+impl From<TimeSourceType> for TimeSource {
+    fn from(time_source: TimeSourceType) -> Self {
+        match time_source {
+            TimeSourceType::AtomicClock => TimeSource::AtomicClock,
+            TimeSourceType::Gnss => TimeSource::Gnss,
+            TimeSourceType::TerrestrialRadio => TimeSource::TerrestrialRadio,
+            TimeSourceType::SerialTimeCode => TimeSource::SerialTimeCode,
+            TimeSourceType::Ptp => TimeSource::Ptp,
+            TimeSourceType::Ntp => TimeSource::Ntp,
+            TimeSourceType::HandSet => TimeSource::HandSet,
+            TimeSourceType::Other => TimeSource::Other,
+            TimeSourceType::InternalOscillator => TimeSource::InternalOscillator,
+        }
+    }
+}
+
+fn default_clock_accuracy() -> u8 {
+    ClockAccuracy::Unknown.to_primitive()
+}
+
+fn default_offset_scaled_log_variance() -> u16 {
+    0xffff
+}
+
+/// A pulse per second reference that disciplines the clock as grandmaster
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PpsConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub extts_channel: Option<u32>,
+    #[serde(default)]
+    pub extts_pin: Option<u32>,
+}
+
+impl PpsConfig {
+    /// The clock quality of a grandmaster locked to the pulses. Hardware
+    /// timestamps of external timestamp channels are more accurate than the
+    /// interrupt timestamps of kernel PPS devices.
+    pub fn clock_quality(&self) -> ClockQuality {
+        ClockQuality {
+            clock_class: 6,
+            clock_accuracy: match self.extts_channel {
+                Some(_) => ClockAccuracy::NS100,
+                None => ClockAccuracy::US1,
+            },
+            offset_scaled_log_variance: default_offset_scaled_log_variance(),
+        }
+    }
+}
+
+/// A GNSS receiver that sends the time of day of its PPS reference in NMEA
+/// sentences
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NmeaConfig {
+    pub path: PathBuf,
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+/// The SMPTE ST 2059-2 synchronization metadata sent as grandmaster
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SmpteConfig {
+    #[serde(default = "default_frame_rate_numerator")]
+    pub frame_rate_numerator: u32,
+    #[serde(default = "default_frame_rate_denominator")]
+    pub frame_rate_denominator: u32,
+    #[serde(default)]
+    pub master_locking_status: LockingStatus,
+    #[serde(default)]
+    pub drop_frame: bool,
+    #[serde(default)]
+    pub color_frame_identification: bool,
+    #[serde(default)]
+    pub local_offset: i32,
+    #[serde(default)]
+    pub daylight_saving: bool,
+}
+
+impl Default for SmpteConfig {
+    fn default() -> Self {
+        Self {
+            frame_rate_numerator: default_frame_rate_numerator(),
+            frame_rate_denominator: default_frame_rate_denominator(),
+            master_locking_status: Default::default(),
+            drop_frame: false,
+            color_frame_identification: false,
+            local_offset: 0,
+            daylight_saving: false,
+        }
+    }
+}
+
+impl SmpteConfig {
+    fn metadata(&self) -> SynchronizationMetadata {
+        SynchronizationMetadata {
+            frame_rate_numerator: self.frame_rate_numerator,
+            frame_rate_denominator: self.frame_rate_denominator,
+            master_locking_status: match self.master_locking_status {
+                LockingStatus::NotInUse => MasterLockingStatus::NotInUse,
+                LockingStatus::FreeRun => MasterLockingStatus::FreeRun,
+                LockingStatus::ColdLocking => MasterLockingStatus::ColdLocking,
+                LockingStatus::WarmLocking => MasterLockingStatus::WarmLocking,
+                LockingStatus::Locked => MasterLockingStatus::Locked,
+            },
+            drop_frame: self.drop_frame,
+            color_frame_identification: self.color_frame_identification,
+            current_local_offset: self.local_offset,
+            previous_jam_local_offset: self.local_offset,
+            daylight_saving: self.daylight_saving,
+            daylight_saving_at_next_jump: self.daylight_saving,
+            daylight_saving_at_previous_jam: self.daylight_saving,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockingStatus {
+    #[default]
+    NotInUse,
+    FreeRun,
+    ColdLocking,
+    WarmLocking,
+    Locked,
+}
+
+fn default_frame_rate_numerator() -> u32 {
+    30000
+}
+
+fn default_frame_rate_denominator() -> u32 {
+    1001
+}
+
 const fn default_observability_loglevel() -> log::LevelFilter {
     log::LevelFilter::Info
 }
@@ -271,11 +1270,26 @@ fn default_metrics_exporter_listen() -> SocketAddr {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{path::PathBuf, str::FromStr};
 
+    use statime::{
+        config::{
+            telecom, ClockAccuracy, ClockQuality, DelayMechanism, HoldoverDegradation,
+            LeapIndicator, MasterLockingStatus, PortAddress, Profile, SynchronizationMetadata,
+            TimePropertiesDS, TimeSource, UnicastNegotiationConfig,
+        },
+        filters::{AnyFilterConfiguration, PiConfiguration},
+        time::Duration,
+    };
     use timestamped_socket::interface::InterfaceName;
 
-    use crate::config::ObservabilityConfig;
+    use crate::{
+        clock::PeriodicOutput,
+        config::{
+            FilterConfig, KalmanFilterConfig, NmeaConfig, ObservabilityConfig, PiFilterConfig,
+            PpsConfig, SmpteConfig,
+        },
+    };
 
     // Minimal amount of config results in default values
     #[test]
@@ -290,23 +1304,47 @@ interface = "enp0s31f6"
             acceptable_master_list: None,
             hardware_clock: None,
             network_mode: crate::config::NetworkMode::Ipv4,
-            announce_interval: 1,
-            sync_interval: 0,
+            announce_interval: None,
+            sync_interval: None,
             announce_receipt_timeout: 3,
             master_only: false,
+            local_priority: 128,
             delay_asymmetry: 0,
+            one_step: false,
+            hybrid_e2e: false,
             delay_mechanism: crate::config::DelayType::E2E,
-            delay_interval: 0,
+            delay_interval: None,
+            unicast_master_table: vec![],
+            unicast_negotiation: false,
+            grant_duration: 300,
+            filter: None,
         };
 
         let expected = crate::config::Config {
             loglevel: log::LevelFilter::Info,
             sdo_id: 0x000,
-            domain: 0,
+            domain: None,
             identity: None,
             priority1: 128,
             priority2: 128,
+            transparent_clock: None,
+            profile: crate::config::ProfileType::Default,
+            neighbor_prop_delay_threshold: 800,
+            local_priority: 128,
+            grandmaster_id: 254,
+            time_inaccuracy: 0,
+            clock_quality: None,
+            holdover: None,
+            filter: FilterConfig::default(),
+            clock_filter: FilterConfig::default(),
+            time_properties: Default::default(),
+            smpte: SmpteConfig::default(),
+            pps: None,
+            nmea: None,
+            chrony_socket: None,
+            ntp_shm_unit: None,
             ports: vec![expected_port],
+            periodic_outputs: vec![],
             observability: ObservabilityConfig::default(),
         };
 
@@ -314,4 +1352,535 @@ interface = "enp0s31f6"
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn unicast_master_table() {
+        const UNICAST_CONFIG: &str = r#"
+[[port]]
+interface = "enp0s31f6"
+unicast-master-table = ["192.168.1.1", "fe80::1", "00:1b:19:aa:bb:cc", "10.0.0.1"]
+"#;
+
+        let config: crate::config::Config = toml::from_str(UNICAST_CONFIG).unwrap();
+        assert_eq!(
+            config.ports[0].unicast_master_table,
+            vec![
+                PortAddress::Ipv4([192, 168, 1, 1]),
+                PortAddress::Ipv6([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
+                PortAddress::Ethernet([0x00, 0x1b, 0x19, 0xaa, 0xbb, 0xcc]),
+                PortAddress::Ipv4([10, 0, 0, 1]),
+            ]
+        );
+
+        // Only the addresses matching the network mode are used
+        let port_config: statime::config::PortConfig<_> =
+            config.ports[0].clone().instance_port_config(config.profile);
+        assert_eq!(
+            port_config.unicast_master_table.as_slice(),
+            &[
+                PortAddress::Ipv4([192, 168, 1, 1]),
+                PortAddress::Ipv4([10, 0, 0, 1]),
+            ]
+        );
+
+        const INVALID_CONFIG: &str = r#"
+[[port]]
+interface = "enp0s31f6"
+unicast-master-table = ["ptp.example.com"]
+"#;
+
+        assert!(toml::from_str::<crate::config::Config>(INVALID_CONFIG).is_err());
+    }
+
+    #[test]
+    fn one_step_requires_hardware_clock() {
+        const ONE_STEP_CONFIG: &str = r#"
+[[port]]
+interface = "enp0s31f6"
+one-step = true
+"#;
+
+        let config: crate::config::Config = toml::from_str(ONE_STEP_CONFIG).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(crate::config::ConfigError::OneStepWithoutHardwareClock(_))
+        ));
+
+        let config: crate::config::Config =
+            toml::from_str(&(ONE_STEP_CONFIG.to_owned() + "hardware-clock = \"/dev/ptp0\"\n"))
+                .unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn transparent_clock() {
+        const TRANSPARENT_CONFIG: &str = r#"
+transparent-clock = "E2E"
+
+[[port]]
+interface = "enp0s31f6"
+
+[[port]]
+interface = "enp0s31f7"
+"#;
+
+        let config: crate::config::Config = toml::from_str(TRANSPARENT_CONFIG).unwrap();
+        assert_eq!(
+            config.transparent_clock,
+            Some(crate::config::TransparentClockMode::E2E)
+        );
+        assert_eq!(config.ports.len(), 2);
+
+        let config: crate::config::Config =
+            toml::from_str(&TRANSPARENT_CONFIG.replace("E2E", "P2P")).unwrap();
+        assert_eq!(
+            config.transparent_clock,
+            Some(crate::config::TransparentClockMode::P2P)
+        );
+        assert!(config.validate().is_ok());
+
+        let too_many_ports =
+            TRANSPARENT_CONFIG.to_owned() + &"[[port]]\ninterface = \"enp0s31f6\"\n".repeat(15);
+        let config: crate::config::Config = toml::from_str(&too_many_ports).unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(crate::config::ConfigError::TransparentClock(_))
+        ));
+    }
+
+    #[test]
+    fn gptp_profile() {
+        const GPTP_CONFIG: &str = r#"
+profile = "gptp"
+neighbor-prop-delay-threshold = 1000
+
+[[port]]
+interface = "enp0s31f6"
+network-mode = "ethernet"
+delay-mechanism = "P2P"
+"#;
+
+        let config: crate::config::Config = toml::from_str(GPTP_CONFIG).unwrap();
+        assert_eq!(
+            config.profile(),
+            Profile::Gptp {
+                neighbor_prop_delay_threshold: Duration::from_micros(1),
+            }
+        );
+
+        const DEFAULT_CONFIG: &str = r#"
+[[port]]
+interface = "enp0s31f6"
+"#;
+
+        let config: crate::config::Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(config.profile(), Profile::Default);
+    }
+
+    #[test]
+    fn telecom_profile() {
+        const TELECOM_CONFIG: &str = r#"
+profile = "g8275.1"
+local-priority = 100
+
+[[port]]
+interface = "enp0s31f6"
+network-mode = "ethernet"
+local-priority = 50
+master-only = true
+"#;
+
+        let config: crate::config::Config = toml::from_str(TELECOM_CONFIG).unwrap();
+        assert_eq!(
+            config.profile(),
+            Profile::TelecomFullTiming {
+                local_priority: 100
+            }
+        );
+        assert_eq!(config.domain(), telecom::FULL_TIMING_DEFAULT_DOMAIN);
+
+        let port_config: statime::config::PortConfig<_> =
+            config.ports[0].clone().instance_port_config(config.profile);
+        assert_eq!(port_config.local_priority, 50);
+        assert!(port_config.master_only);
+    }
+
+    #[test]
+    fn telecom_partial_timing_profile() {
+        const TELECOM_CONFIG: &str = r#"
+profile = "g8275.2"
+
+[[port]]
+interface = "enp0s31f6"
+unicast-master-table = ["192.168.1.1"]
+unicast-negotiation = true
+grant-duration = 60
+"#;
+
+        let config: crate::config::Config = toml::from_str(TELECOM_CONFIG).unwrap();
+        assert_eq!(
+            config.profile(),
+            Profile::TelecomPartialTiming {
+                local_priority: 128
+            }
+        );
+        assert_eq!(config.domain(), telecom::PARTIAL_TIMING_DEFAULT_DOMAIN);
+
+        let port_config: statime::config::PortConfig<_> =
+            config.ports[0].clone().instance_port_config(config.profile);
+        assert_eq!(
+            port_config.unicast_negotiation,
+            Some(UnicastNegotiationConfig { grant_duration: 60 })
+        );
+        assert_eq!(
+            port_config.announce_interval,
+            telecom::PARTIAL_TIMING_ANNOUNCE_INTERVAL
+        );
+        assert_eq!(
+            port_config.sync_interval,
+            telecom::PARTIAL_TIMING_SYNC_INTERVAL
+        );
+        assert_eq!(
+            port_config.delay_mechanism,
+            DelayMechanism::E2E {
+                interval: telecom::PARTIAL_TIMING_DELAY_INTERVAL
+            }
+        );
+    }
+
+    #[test]
+    fn power_profile() {
+        const POWER_CONFIG: &str = r#"
+profile = "c37.238"
+domain = 254
+grandmaster-id = 3
+time-inaccuracy = 50
+
+[[port]]
+interface = "enp0s31f6.100"
+network-mode = "ethernet"
+delay-mechanism = "P2P"
+"#;
+
+        let config: crate::config::Config = toml::from_str(POWER_CONFIG).unwrap();
+        assert_eq!(
+            config.profile(),
+            Profile::Power {
+                grandmaster_id: 3,
+                time_inaccuracy: 50,
+            }
+        );
+        assert!(config.validate().is_ok());
+
+        let config: crate::config::Config =
+            toml::from_str(&POWER_CONFIG.replace("grandmaster-id = 3", "grandmaster-id = 2"))
+                .unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(crate::config::ConfigError::InvalidGrandmasterId(2))
+        ));
+    }
+
+    #[test]
+    fn smpte_profile() {
+        const SMPTE_CONFIG: &str = r#"
+profile = "st2059-2"
+domain = 127
+
+[smpte]
+frame-rate-numerator = 25
+frame-rate-denominator = 1
+master-locking-status = "locked"
+local-offset = 3600
+
+[[port]]
+interface = "enp0s31f6"
+"#;
+
+        let config: crate::config::Config = toml::from_str(SMPTE_CONFIG).unwrap();
+        assert_eq!(
+            config.profile(),
+            Profile::Smpte2059 {
+                metadata: SynchronizationMetadata {
+                    frame_rate_numerator: 25,
+                    frame_rate_denominator: 1,
+                    master_locking_status: MasterLockingStatus::Locked,
+                    current_local_offset: 3600,
+                    previous_jam_local_offset: 3600,
+                    ..Default::default()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn grandmaster_time_properties() {
+        const GRANDMASTER_CONFIG: &str = r#"
+[clock-quality]
+clock-class = 6
+clock-accuracy = 0x21
+
+[time-properties]
+timescale = "ptp"
+time-source = "gnss"
+time-traceable = true
+frequency-traceable = true
+
+[[port]]
+interface = "enp0s31f6"
+"#;
+
+        let config: crate::config::Config = toml::from_str(GRANDMASTER_CONFIG).unwrap();
+        assert_eq!(
+            config.clock_quality.map(ClockQuality::from),
+            Some(ClockQuality {
+                clock_class: 6,
+                clock_accuracy: ClockAccuracy::NS100,
+                offset_scaled_log_variance: 0xffff,
+            })
+        );
+        assert_eq!(
+            config.time_properties_ds(|| Some(37)),
+            TimePropertiesDS::new_ptp_time(
+                Some(37),
+                LeapIndicator::NoLeap,
+                true,
+                true,
+                TimeSource::Gnss
+            )
+        );
+
+        // A configured UTC offset takes precedence over that of the kernel
+        let config = crate::config::TimePropertiesConfig {
+            current_utc_offset: Some(36),
+            ..config.time_properties
+        };
+        assert_eq!(
+            config
+                .time_properties_ds(TimeSource::InternalOscillator, || Some(37))
+                .current_utc_offset,
+            Some(36)
+        );
+
+        // The arbitrary timescale is the default
+        let config: crate::config::Config = toml::from_str(
+            r#"
+[[port]]
+interface = "enp0s31f6"
+"#,
+        )
+        .unwrap();
+        assert!(!config.time_properties_ds(|| Some(37)).is_ptp());
+    }
+
+    #[test]
+    fn periodic_outputs() {
+        const PEROUT_CONFIG: &str = r#"
+[[port]]
+interface = "enp0s31f6"
+hardware-clock = "/dev/ptp0"
+
+[[periodic-output]]
+hardware-clock = "/dev/ptp0"
+channel = 0
+pin = 1
+phase = 500_000_000
+pulse-width = 100_000_000
+
+[[periodic-output]]
+hardware-clock = "/dev/ptp1"
+channel = 1
+period = 100_000_000
+"#;
+
+        let mut config: crate::config::Config = toml::from_str(PEROUT_CONFIG).unwrap();
+        assert_eq!(config.periodic_outputs.len(), 2);
+        assert_eq!(config.periodic_outputs[0].pin, Some(1));
+        assert_eq!(
+            PeriodicOutput::try_from(&config.periodic_outputs[0]).unwrap(),
+            PeriodicOutput {
+                period: Duration::from_secs(1),
+                phase: Duration::from_millis(500),
+                pulse_width: Some(Duration::from_millis(100)),
+            }
+        );
+        assert_eq!(config.periodic_outputs[1].pin, None);
+        assert_eq!(
+            PeriodicOutput::try_from(&config.periodic_outputs[1]).unwrap(),
+            PeriodicOutput {
+                period: Duration::from_millis(100),
+                phase: Duration::ZERO,
+                pulse_width: None,
+            }
+        );
+        assert!(config.validate().is_ok());
+
+        // The nanoseconds must fit in a duration
+        config.periodic_outputs[1].pulse_width = Some(u64::MAX);
+        assert!(matches!(
+            config.validate(),
+            Err(crate::config::ConfigError::PeriodicOutputOutOfRange(_, 1))
+        ));
+    }
+
+    #[test]
+    fn holdover() {
+        const HOLDOVER_CONFIG: &str = r#"
+[holdover]
+time-error-budget = 1_500
+degradation = "alternative-a"
+
+[[port]]
+interface = "enp0s31f6"
+"#;
+
+        let config: crate::config::Config = toml::from_str(HOLDOVER_CONFIG).unwrap();
+        assert_eq!(
+            config.holdover.map(statime::config::HoldoverConfig::from),
+            Some(statime::config::HoldoverConfig {
+                time_error_budget: Duration::from_nanos(1_500),
+                max_duration: Duration::from_secs(86400),
+                degradation: HoldoverDegradation::AlternativeA,
+            })
+        );
+    }
+
+    #[test]
+    fn filters() {
+        const FILTER_CONFIG: &str = r#"
+[filter]
+type = "kalman"
+step-threshold = 500_000
+steer-time = 2.0
+
+[clock-filter]
+type = "basic"
+
+[[port]]
+interface = "enp0s31f6"
+
+[[port]]
+interface = "enp0s31f7"
+filter = { type = "pi", ki = 0.1, step-threshold = 1_000 }
+"#;
+
+        let config: crate::config::Config = toml::from_str(FILTER_CONFIG).unwrap();
+        assert_eq!(
+            config.filter,
+            FilterConfig::Kalman(KalmanFilterConfig {
+                step_threshold: 500_000,
+                steer_time: 2.0,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            AnyFilterConfiguration::from(config.clock_filter),
+            AnyFilterConfiguration::Basic(0.1)
+        );
+        assert_eq!(config.ports[0].filter, None);
+
+        let Some(FilterConfig::Pi(pi)) = config.ports[1].filter else {
+            panic!("the second port should have a pi filter");
+        };
+        assert_eq!(
+            pi,
+            PiFilterConfig {
+                ki: Some(0.1),
+                step_threshold: 1_000,
+                ..Default::default()
+            }
+        );
+        let pi = PiConfiguration::from(pi);
+        assert_eq!(pi.step_threshold, Some(Duration::from_micros(1)));
+        assert_eq!(pi.first_step_threshold, Some(Duration::from_micros(20)));
+
+        // Unknown filters and options are rejected
+        assert!(toml::from_str::<crate::config::Config>(
+            &FILTER_CONFIG.replace("\"kalman\"", "\"other\"")
+        )
+        .is_err());
+        assert!(toml::from_str::<crate::config::Config>(
+            &FILTER_CONFIG.replace("ki = 0.1", "gain = 0.1")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn system_clock_output() {
+        const CHRONY_CONFIG: &str = r#"
+chrony-socket = "/run/chrony/statime.sock"
+ntp-shm-unit = 2
+
+[[port]]
+interface = "enp0s31f6"
+hardware-clock = "/dev/ptp0"
+"#;
+
+        let config: crate::config::Config = toml::from_str(CHRONY_CONFIG).unwrap();
+        assert_eq!(
+            config.chrony_socket,
+            Some(PathBuf::from("/run/chrony/statime.sock"))
+        );
+        assert_eq!(config.ntp_shm_unit, Some(2));
+    }
+
+    #[test]
+    fn pps_reference() {
+        const PPS_CONFIG: &str = r#"
+[pps]
+path = "/dev/ptp0"
+extts-channel = 1
+
+[[port]]
+interface = "enp0s31f6"
+hardware-clock = "/dev/ptp0"
+"#;
+
+        let config: crate::config::Config = toml::from_str(PPS_CONFIG).unwrap();
+        assert_eq!(
+            config.pps,
+            Some(PpsConfig {
+                path: "/dev/ptp0".into(),
+                extts_channel: Some(1),
+                extts_pin: None,
+            })
+        );
+
+        // A PPS reference makes a GNSS locked grandmaster by default
+        assert_eq!(
+            config.clock_quality(),
+            Some(ClockQuality {
+                clock_class: 6,
+                clock_accuracy: ClockAccuracy::NS100,
+                offset_scaled_log_variance: 0xffff,
+            })
+        );
+        assert_eq!(
+            config.time_properties_ds(|| None).time_source,
+            TimeSource::Gnss
+        );
+
+        // Unless configured otherwise
+        let config: crate::config::Config = toml::from_str(&format!(
+            "[clock-quality]\nclock-class = 7\n[time-properties]\ntime-source = \"other\"\n{}",
+            PPS_CONFIG
+        ))
+        .unwrap();
+        assert_eq!(config.clock_quality().unwrap().clock_class, 7);
+        assert_eq!(
+            config.time_properties_ds(|| None).time_source,
+            TimeSource::Other
+        );
+
+        // Optionally with the time of day from a GNSS receiver
+        let config: crate::config::Config =
+            toml::from_str(&format!("[nmea]\npath = \"/dev/ttyS0\"\n{}", PPS_CONFIG)).unwrap();
+        assert_eq!(
+            config.nmea,
+            Some(NmeaConfig {
+                path: "/dev/ttyS0".into(),
+                baud_rate: 9600,
+            })
+        );
+    }
 }
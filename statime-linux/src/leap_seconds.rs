@@ -0,0 +1,340 @@
+//! Leap second information from an IETF/NIST `leap-seconds.list` file
+//!
+//! A grandmaster with the PTP timescale uses this to announce the current UTC
+//! offset and upcoming leap seconds.
+
+use std::{
+    fmt::Display,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sha1::{Digest, Sha1};
+use statime::config::{LeapIndicator, TimePropertiesDS};
+
+/// Seconds between the NTP epoch (1900) used in the file and the unix epoch
+const NTP_TO_UNIX: i64 = 2_208_988_800;
+
+/// The leap indicator is raised during the UTC day before the leap second
+const LEAP_WINDOW: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeapSecondsError {
+    /// A line of the file could not be parsed
+    Malformed,
+    /// The file has no `#h` hash line
+    MissingHash,
+    /// The hash of the file does not match its contents
+    HashMismatch,
+    /// The file has no entries, or no `#@` expiration line
+    Incomplete,
+    /// The expiration date of the file has passed
+    Expired,
+}
+
+impl Display for LeapSecondsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed line"),
+            Self::MissingHash => f.write_str("missing hash"),
+            Self::HashMismatch => f.write_str("hash does not match the contents"),
+            Self::Incomplete => f.write_str("missing expiration date or entries"),
+            Self::Expired => f.write_str("file has expired"),
+        }
+    }
+}
+
+impl std::error::Error for LeapSecondsError {}
+
+/// The contents of a `leap-seconds.list` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeapSecondsList {
+    /// Unix time after which the file should no longer be used
+    expires: i64,
+    /// Unix times from which a TAI-UTC offset applies, in order
+    entries: Vec<(i64, i16)>,
+}
+
+impl LeapSecondsList {
+    /// Parse the file and check its hash
+    pub fn parse(contents: &str) -> Result<Self, LeapSecondsError> {
+        // The hash covers the update time, the expiration time and the data
+        // lines, without whitespace and comments
+        let mut hashed = String::new();
+        let mut hash = None;
+        let mut expires = None;
+        let mut entries: Vec<(i64, i16)> = vec![];
+
+        for line in contents.lines() {
+            if let Some(update) = line.strip_prefix("#$") {
+                hashed.extend(update.chars().filter(|c| !c.is_whitespace()));
+            } else if let Some(expiration) = line.strip_prefix("#@") {
+                hashed.extend(expiration.chars().filter(|c| !c.is_whitespace()));
+                let expiration: i64 = expiration
+                    .trim()
+                    .parse()
+                    .map_err(|_| LeapSecondsError::Malformed)?;
+                expires = Some(expiration - NTP_TO_UNIX);
+            } else if let Some(words) = line.strip_prefix("#h") {
+                // Some versions of the file drop the leading zeros of the words
+                let mut digest = [0; 20];
+                let mut words = words.split_whitespace();
+                for chunk in digest.chunks_mut(4) {
+                    let word = words.next().ok_or(LeapSecondsError::Malformed)?;
+                    let word =
+                        u32::from_str_radix(word, 16).map_err(|_| LeapSecondsError::Malformed)?;
+                    chunk.copy_from_slice(&word.to_be_bytes());
+                }
+                hash = Some(digest);
+            } else if !line.starts_with('#') {
+                let data = line.split('#').next().unwrap_or_default();
+                hashed.extend(data.chars().filter(|c| !c.is_whitespace()));
+
+                let mut fields = data.split_whitespace();
+                let Some(time) = fields.next() else {
+                    // Empty line
+                    continue;
+                };
+                let time = time
+                    .parse::<i64>()
+                    .map_err(|_| LeapSecondsError::Malformed)?
+                    - NTP_TO_UNIX;
+                let offset = fields
+                    .next()
+                    .and_then(|offset| offset.parse().ok())
+                    .ok_or(LeapSecondsError::Malformed)?;
+                if matches!(entries.last(), Some(&(last, _)) if last >= time) {
+                    return Err(LeapSecondsError::Malformed);
+                }
+                entries.push((time, offset));
+            }
+        }
+
+        if Sha1::digest(hashed.as_bytes())[..] != hash.ok_or(LeapSecondsError::MissingHash)?[..] {
+            return Err(LeapSecondsError::HashMismatch);
+        }
+
+        match expires {
+            Some(expires) if !entries.is_empty() => Ok(Self { expires, entries }),
+            _ => Err(LeapSecondsError::Incomplete),
+        }
+    }
+
+    /// The TAI-UTC offset and leap indicator at the given unix time
+    pub fn leap_info(&self, now: i64) -> Result<(i16, LeapIndicator), LeapSecondsError> {
+        if now >= self.expires {
+            return Err(LeapSecondsError::Expired);
+        }
+
+        let next = self.entries.partition_point(|&(time, _)| time <= now);
+        // Before the first entry there was no leap second yet
+        let offset = match next.checked_sub(1) {
+            Some(current) => self.entries[current].1,
+            None => self.entries[0].1,
+        };
+
+        let leap_indicator = match self.entries.get(next) {
+            Some(&(time, next_offset)) if time - now <= LEAP_WINDOW && next > 0 => {
+                if next_offset > offset {
+                    LeapIndicator::Leap61
+                } else if next_offset < offset {
+                    LeapIndicator::Leap59
+                } else {
+                    LeapIndicator::NoLeap
+                }
+            }
+            _ => LeapIndicator::NoLeap,
+        };
+
+        Ok((offset, leap_indicator))
+    }
+}
+
+/// A `leap-seconds.list` file on disk, reloaded when it changes, applied to
+/// the configured time properties of the instance
+#[derive(Debug)]
+pub struct LeapSecondsFile {
+    time_properties_ds: TimePropertiesDS,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    missing: bool,
+    list: Option<LeapSecondsList>,
+}
+
+impl LeapSecondsFile {
+    pub fn new(path: PathBuf, time_properties_ds: TimePropertiesDS) -> Self {
+        let mut file = Self {
+            time_properties_ds,
+            path,
+            modified: None,
+            missing: false,
+            list: None,
+        };
+        file.reload_if_changed();
+        file
+    }
+
+    /// Reload the file when its modification time changed since the last load
+    fn reload_if_changed(&mut self) {
+        let modified = match std::fs::metadata(&self.path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(error) => {
+                // Only report once until the file reappears
+                if !self.missing {
+                    log::error!(
+                        "Could not read leap seconds file {}: {}",
+                        self.path.display(),
+                        error
+                    );
+                }
+                self.missing = true;
+                self.modified = None;
+                self.list = None;
+                return;
+            }
+        };
+        self.missing = false;
+
+        if self.modified == Some(modified) {
+            return;
+        }
+        self.modified = Some(modified);
+
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                log::error!(
+                    "Could not read leap seconds file {}: {}",
+                    self.path.display(),
+                    error
+                );
+                self.list = None;
+                return;
+            }
+        };
+
+        match LeapSecondsList::parse(&contents) {
+            Ok(list) => {
+                log::info!("Loaded leap seconds file {}", self.path.display());
+                self.list = Some(list);
+            }
+            Err(error) => {
+                log::error!(
+                    "Rejected leap seconds file {}: {}",
+                    self.path.display(),
+                    error
+                );
+                self.list = None;
+            }
+        }
+    }
+
+    /// The configured time properties with the current UTC offset and leap
+    /// indicator of the file, when they use the PTP timescale. Without a valid
+    /// file, the configured time properties are returned unchanged.
+    pub fn time_properties_ds(&mut self) -> TimePropertiesDS {
+        self.reload_if_changed();
+
+        let time_properties_ds = self.time_properties_ds;
+        if !time_properties_ds.is_ptp() {
+            return time_properties_ds;
+        }
+
+        let Some(list) = &self.list else {
+            return time_properties_ds;
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        match list.leap_info(now) {
+            Ok((offset, leap_indicator)) => TimePropertiesDS {
+                current_utc_offset: Some(offset),
+                leap_indicator,
+                ..time_properties_ds
+            },
+            Err(error) => {
+                log::error!(
+                    "Rejected leap seconds file {}: {}",
+                    self.path.display(),
+                    error
+                );
+                self.list = None;
+                time_properties_ds
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A shortened leap-seconds.list, with the hash of its contents
+    const LEAP_SECONDS: &str = "\
+#	Updated through IERS Bulletin C
+#$	 3676924800
+#@	 3928521600
+#
+2272060800	10	# 1 Jan 1972
+3644697600	36	# 1 Jul 2015
+3692217600	37	# 1 Jan 2017
+#h	74eb352c c04ca8f1 f82cccfc a746c602 1f3154f5
+";
+
+    fn unix(ntp: i64) -> i64 {
+        ntp - NTP_TO_UNIX
+    }
+
+    #[test]
+    fn leap_seconds_list() {
+        let list = LeapSecondsList::parse(LEAP_SECONDS).unwrap();
+
+        // Well before the leap second of 2017
+        assert_eq!(
+            list.leap_info(unix(3692217600) - 2 * LEAP_WINDOW),
+            Ok((36, LeapIndicator::NoLeap))
+        );
+        // On the last day before it
+        assert_eq!(
+            list.leap_info(unix(3692217600) - 3600),
+            Ok((36, LeapIndicator::Leap61))
+        );
+        // And after it
+        assert_eq!(
+            list.leap_info(unix(3692217600)),
+            Ok((37, LeapIndicator::NoLeap))
+        );
+        // Until the file expires
+        assert_eq!(
+            list.leap_info(unix(3928521600)),
+            Err(LeapSecondsError::Expired)
+        );
+    }
+
+    #[test]
+    fn leap_seconds_list_rejected() {
+        // Changed contents no longer match the hash
+        let corrupted = LEAP_SECONDS.replace("\t37\t", "\t38\t");
+        assert_eq!(
+            LeapSecondsList::parse(&corrupted),
+            Err(LeapSecondsError::HashMismatch)
+        );
+
+        let unhashed: String = LEAP_SECONDS
+            .lines()
+            .filter(|line| !line.starts_with("#h"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        assert_eq!(
+            LeapSecondsList::parse(&unhashed),
+            Err(LeapSecondsError::MissingHash)
+        );
+
+        let malformed = LEAP_SECONDS.replace("\t37\t", "\tx\t");
+        assert_eq!(
+            LeapSecondsList::parse(&malformed),
+            Err(LeapSecondsError::Malformed)
+        );
+    }
+}
@@ -3,23 +3,41 @@ use std::{
     future::Future,
     path::PathBuf,
     pin::{pin, Pin},
+    sync::{Arc, Mutex},
 };
 
 use clap::Parser;
 use rand::{rngs::StdRng, SeedableRng};
 use statime::{
-    config::{ClockIdentity, InstanceConfig, SdoId, TimePropertiesDS, TimeSource},
-    filters::{Filter, KalmanConfiguration, KalmanFilter},
+    config::{
+        ClockIdentity, ClockQuality, DelayMechanism, InstanceConfig, SdoId, TimePropertiesDS,
+        TransparentClockConfig,
+    },
+    filters::{AnyFilter, AnyFilterConfiguration, Filter, HoldoverEstimate},
     observability::ObservableInstanceState,
     port::{
         InBmca, Measurement, Port, PortAction, PortActionIterator, TimestampContext, MAX_DATA_LEN,
     },
-    time::Time,
+    time::{Duration, Interval, Time},
+    transparent_clock::{
+        TransparentClock, TransparentClockAction, TransparentClockActionIterator,
+        TransparentClockTimestampContext,
+    },
     PtpInstance,
 };
 use statime_linux::{
-    clock::LinuxClock,
-    config::Config,
+    clock::{
+        chrony::{ChronySample, ChronySocket},
+        shm::{NtpShm, ShmSample},
+        LinuxClock, PinFunction,
+    },
+    config::{Config, TransparentClockMode},
+    leap_seconds::LeapSecondsFile,
+    refclock::{
+        nmea::{NmeaReader, NmeaTime},
+        pps::PpsSource,
+        pulse_measurement, reference_measurement, ReferenceStatus,
+    },
     socket::{
         open_ethernet_socket, open_ipv4_event_socket, open_ipv4_general_socket,
         open_ipv6_event_socket, open_ipv6_general_socket, timestamp_to_time, PtpTargetAddress,
@@ -29,7 +47,7 @@ use statime_linux::{
 use timestamped_socket::{
     interface::interfaces,
     networkaddress::{EthernetAddress, NetworkAddress},
-    socket::{InterfaceTimestampMode, Open, Socket},
+    socket::{InterfaceTimestampMode, Open, Socket, Timestamp},
 };
 use tokio::{
     sync::mpsc::{Receiver, Sender},
@@ -135,24 +153,83 @@ enum ClockSyncMode {
     ToSystem,
 }
 
-fn start_clock_task(clock: LinuxClock) -> tokio::sync::watch::Sender<ClockSyncMode> {
+/// Another daemon disciplines the system clock, so instead of steering it,
+/// the time of the system clock is handed to that daemon
+#[derive(Clone)]
+struct SystemClockOutput {
+    chrony_socket: Option<PathBuf>,
+    ntp_shm_unit: Option<u32>,
+    time_properties_receiver: tokio::sync::watch::Receiver<TimePropertiesDS>,
+}
+
+struct SystemClockSinks {
+    chrony: Option<ChronySocket>,
+    ntp_shm: Option<NtpShm>,
+}
+
+impl SystemClockOutput {
+    fn open(&self) -> SystemClockSinks {
+        SystemClockSinks {
+            chrony: self
+                .chrony_socket
+                .as_ref()
+                .map(|path| ChronySocket::new(path).expect("Could not create chrony socket")),
+            ntp_shm: self
+                .ntp_shm_unit
+                .map(|unit| NtpShm::open(unit).expect("Could not attach NTP shared memory")),
+        }
+    }
+
+    /// Hand over that the system clock read `system_time` at our `time`
+    fn send(&self, sinks: &mut SystemClockSinks, system_time: Time, time: Time, pulse: bool) {
+        let leap = self.time_properties_receiver.borrow().leap_indicator();
+
+        if let Some(socket) = &mut sinks.chrony {
+            if let Err(error) = socket.send(ChronySample::new(system_time, time, pulse, leap)) {
+                log::error!("Unable to get tai offset, skipping chrony sample: {error}");
+            }
+        }
+
+        if let Some(shm) = &mut sinks.ntp_shm {
+            if let Err(error) = shm.publish(ShmSample {
+                receive_time: system_time,
+                clock_time: time,
+                leap,
+            }) {
+                log::error!("Unable to get tai offset, skipping shared memory sample: {error}");
+            }
+        }
+    }
+}
+
+fn start_clock_task(
+    clock: LinuxClock,
+    filter_config: AnyFilterConfiguration,
+    output: Option<SystemClockOutput>,
+) -> tokio::sync::watch::Sender<ClockSyncMode> {
     let (mode_sender, mode_receiver) = tokio::sync::watch::channel(ClockSyncMode::FromSystem);
 
-    tokio::spawn(clock_task(clock, mode_receiver));
+    tokio::spawn(clock_task(clock, filter_config, mode_receiver, output));
 
     mode_sender
 }
 
 async fn clock_task(
     clock: LinuxClock,
+    filter_config: AnyFilterConfiguration,
     mut mode_receiver: tokio::sync::watch::Receiver<ClockSyncMode>,
+    output: Option<SystemClockOutput>,
 ) {
     let mut measurement_timer = pin!(Timer::new());
     let mut update_timer = pin!(Timer::new());
 
     measurement_timer.as_mut().reset(std::time::Duration::ZERO);
 
-    let mut filter = KalmanFilter::new(KalmanConfiguration::default());
+    let mut filter = AnyFilter::new(filter_config);
+    let mut output = output.map(|output| {
+        let sinks = output.open();
+        (output, sinks)
+    });
 
     let mut current_mode = *mode_receiver.borrow_and_update();
     let mut filter_clock = match current_mode {
@@ -160,6 +237,10 @@ async fn clock_task(
         ClockSyncMode::ToSystem => LinuxClock::CLOCK_TAI,
     };
     loop {
+        // With another daemon disciplining it, the system clock is not ours
+        // to steer
+        let to_output = current_mode == ClockSyncMode::ToSystem && output.is_some();
+
         tokio::select! {
             () = &mut measurement_timer => {
                 let (t1, t2, t3) = clock.system_offset().expect("Unable to determine offset from system clock");
@@ -170,33 +251,37 @@ async fn clock_task(
                 let offset_a = t2 - t1;
                 let offset_b = t3 - t2;
 
-                let m = match current_mode {
-                    ClockSyncMode::FromSystem => Measurement {
-                        event_time: t2,
-                        offset: Some(offset_a - delay),
-                        delay: Some(delay),
-                        peer_delay: None,
-                        raw_sync_offset: Some(offset_a),
-                        raw_delay_offset: Some(-offset_b),
-                    },
-                    ClockSyncMode::ToSystem => Measurement {
-                        event_time: t1+delay,
-                        offset: Some(offset_b - delay),
-                        delay: Some(delay),
-                        peer_delay: None,
-                        raw_sync_offset: Some(offset_b),
-                        raw_delay_offset: Some(-offset_a),
-                    },
-                };
+                if let (true, Some((output, sinks))) = (to_output, &mut output) {
+                    output.send(sinks, t1+delay, t2, false);
+                } else {
+                    let m = match current_mode {
+                        ClockSyncMode::FromSystem => Measurement {
+                            event_time: t2,
+                            offset: Some(offset_a - delay),
+                            delay: Some(delay),
+                            peer_delay: None,
+                            raw_sync_offset: Some(offset_a),
+                            raw_delay_offset: Some(-offset_b),
+                        },
+                        ClockSyncMode::ToSystem => Measurement {
+                            event_time: t1+delay,
+                            offset: Some(offset_b - delay),
+                            delay: Some(delay),
+                            peer_delay: None,
+                            raw_sync_offset: Some(offset_b),
+                            raw_delay_offset: Some(-offset_a),
+                        },
+                    };
 
-                let update = filter.measurement(m, &mut filter_clock);
-                if let Some(timeout) = update.next_update {
-                    update_timer.as_mut().reset(timeout);
+                    let update = filter.measurement(m, &mut filter_clock);
+                    if let Some(timeout) = update.next_update {
+                        update_timer.as_mut().reset(timeout);
+                    }
                 }
 
                 measurement_timer.as_mut().reset(std::time::Duration::from_millis(250));
             }
-            () = &mut update_timer => {
+            () = &mut update_timer, if !to_output => {
                 let update = filter.update(&mut filter_clock);
                 if let Some(timeout) = update.next_update {
                     update_timer.as_mut().reset(timeout);
@@ -205,7 +290,7 @@ async fn clock_task(
             _ = mode_receiver.changed() => {
                 let new_mode = *mode_receiver.borrow_and_update();
                 if new_mode != current_mode {
-                    let mut new_filter = KalmanFilter::new(KalmanConfiguration::default());
+                    let mut new_filter = AnyFilter::new(filter_config);
                     std::mem::swap(&mut filter, &mut new_filter);
                     new_filter.demobilize(&mut filter_clock);
                     match new_mode {
@@ -219,11 +304,292 @@ async fn clock_task(
     }
 }
 
+/// The last usable pulse of a PPS reference
+#[derive(Debug, Clone, Copy)]
+struct LastPulse {
+    received: tokio::time::Instant,
+    /// How the time of the clock drifts off without the reference, when it
+    /// disciplines the clock
+    holdover_estimate: Option<HoldoverEstimate>,
+}
+
+/// A pulse per second reference, which disciplines its clock while no port
+/// is steering
+struct PpsReference {
+    active_sender: tokio::sync::watch::Sender<bool>,
+    last_pulse_receiver: tokio::sync::watch::Receiver<Option<LastPulse>>,
+    /// The clock quality while locked to the reference
+    clock_quality: ClockQuality,
+    status: ReferenceStatus,
+    /// The clock task of the disciplined hardware clock, `None` for the system
+    /// clock
+    clock_id: Option<usize>,
+}
+
+impl PpsReference {
+    fn start(
+        source: PpsSource,
+        nmea: Option<NmeaReader>,
+        clock_quality: ClockQuality,
+        clock_id: Option<usize>,
+        filter_config: AnyFilterConfiguration,
+        time_properties_receiver: tokio::sync::watch::Receiver<TimePropertiesDS>,
+        output: Option<SystemClockOutput>,
+    ) -> Self {
+        let (active_sender, active_receiver) = tokio::sync::watch::channel(true);
+        let (last_pulse_sender, last_pulse_receiver) = tokio::sync::watch::channel(None);
+        let (pulse_sender, pulse_receiver) = tokio::sync::mpsc::channel(1);
+
+        let clock = source.clock().clone();
+        std::thread::spawn(move || pps_thread(source, pulse_sender));
+
+        let nmea_receiver = nmea.map(|reader| {
+            let (nmea_sender, nmea_receiver) = tokio::sync::mpsc::channel(1);
+            let clock = clock.clone();
+            std::thread::spawn(move || nmea_thread(reader, clock, nmea_sender));
+            nmea_receiver
+        });
+
+        tokio::spawn(pps_task(
+            clock,
+            pulse_receiver,
+            nmea_receiver,
+            active_receiver,
+            last_pulse_sender,
+            filter_config,
+            time_properties_receiver,
+            output,
+        ));
+
+        Self {
+            active_sender,
+            last_pulse_receiver,
+            clock_quality,
+            status: ReferenceStatus::FreeRunning,
+            clock_id,
+        }
+    }
+
+    /// Update the clock quality of the instance given how recently the
+    /// reference was usable, and start its holdover when the reference is lost
+    fn update_instance<F: Filter>(&mut self, instance: &PtpInstance<F>) {
+        let last_pulse = *self.last_pulse_receiver.borrow();
+        let status =
+            ReferenceStatus::from_last_pulse(last_pulse.map(|pulse| pulse.received.elapsed()));
+
+        instance.set_clock_quality(status.clock_quality(self.clock_quality));
+        match status {
+            ReferenceStatus::Holdover if self.status != ReferenceStatus::Holdover => {
+                log::warn!("The PPS reference was lost, the clock is in holdover");
+                instance
+                    .start_reference_holdover(last_pulse.and_then(|pulse| pulse.holdover_estimate));
+            }
+            ReferenceStatus::Holdover => {}
+            ReferenceStatus::Locked | ReferenceStatus::FreeRunning => {
+                instance.end_reference_holdover()
+            }
+        }
+        self.status = status;
+    }
+}
+
+// Waiting for pulses blocks, so it gets a thread of its own
+fn pps_thread(mut source: PpsSource, pulse_sender: Sender<Time>) {
+    loop {
+        match source.next_pulse(std::time::Duration::from_secs(2)) {
+            Ok(Some(timestamp)) => {
+                if pulse_sender.blocking_send(timestamp).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => log::warn!("No pulse received from the PPS reference"),
+            Err(error) => {
+                log::error!("Could not read the PPS reference: {}", error);
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+// Reading sentences blocks as well
+fn nmea_thread(mut reader: NmeaReader, clock: LinuxClock, nmea_sender: Sender<(NmeaTime, Time)>) {
+    loop {
+        match reader.next_time() {
+            Ok(time) => {
+                let received = statime::Clock::now(&clock);
+                if nmea_sender.blocking_send((time, received)).is_err() {
+                    return;
+                }
+            }
+            Err(error) => {
+                log::error!("Could not read NMEA sentences: {}", error);
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+async fn recv_nmea(
+    nmea_receiver: &mut Option<Receiver<(NmeaTime, Time)>>,
+) -> Option<(NmeaTime, Time)> {
+    match nmea_receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn pps_task(
+    mut clock: LinuxClock,
+    mut pulse_receiver: Receiver<Time>,
+    mut nmea_receiver: Option<Receiver<(NmeaTime, Time)>>,
+    mut active_receiver: tokio::sync::watch::Receiver<bool>,
+    last_pulse_sender: tokio::sync::watch::Sender<Option<LastPulse>>,
+    filter_config: AnyFilterConfiguration,
+    time_properties_receiver: tokio::sync::watch::Receiver<TimePropertiesDS>,
+    output: Option<SystemClockOutput>,
+) {
+    let mut update_timer = pin!(Timer::new());
+
+    let mut filter = AnyFilter::new(filter_config);
+    let mut output = output.map(|output| {
+        let sinks = output.open();
+        (output, sinks)
+    });
+
+    // With NMEA sentences, a pulse is only used once the sentence with its
+    // time of day arrives, and while the receiver has a fix
+    let mut pending_pulse = None;
+    let mut fix = true;
+    let mut utc_offset_known = true;
+
+    let mut active = *active_receiver.borrow_and_update();
+    loop {
+        let m = tokio::select! {
+            Some(timestamp) = pulse_receiver.recv() => {
+                if nmea_receiver.is_some() {
+                    pending_pulse = Some(timestamp);
+                    None
+                } else {
+                    Some(pulse_measurement(timestamp))
+                }
+            }
+            Some((time, received)) = recv_nmea(&mut nmea_receiver) => {
+                if let Some(time_fix) = time.fix {
+                    if time_fix != fix {
+                        log::info!("GNSS receiver {} its fix", if time_fix { "has" } else { "lost" });
+                    }
+                    fix = time_fix;
+                }
+
+                match pending_pulse.take() {
+                    Some(pulse) if fix && received >= pulse && received - pulse < Duration::from_secs(1) => {
+                        // The offset of TAI to UTC of the time properties in use,
+                        // which come from the configuration, the leap seconds file
+                        // or the kernel. The ARB timescale has none, so then we
+                        // use that of the kernel directly.
+                        let utc_offset = time_properties_receiver
+                            .borrow()
+                            .utc_offset()
+                            .or_else(read_kernel_utc_offset);
+                        if utc_offset.is_none() && utc_offset_known {
+                            log::warn!(
+                                "The UTC offset is unknown, ignoring the NMEA time of day until the TAI offset of the kernel is set"
+                            );
+                        }
+                        utc_offset_known = utc_offset.is_some();
+
+                        utc_offset.map(|utc_offset| {
+                            let reference = Time::from_secs(time.utc_seconds) + Duration::from_secs(utc_offset.into());
+                            reference_measurement(pulse, reference)
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            () = &mut update_timer => {
+                if active {
+                    let update = filter.update(&mut clock);
+                    if let Some(timeout) = update.next_update {
+                        update_timer.as_mut().reset(timeout);
+                    }
+                }
+                None
+            }
+            _ = active_receiver.changed() => {
+                let new_active = *active_receiver.borrow_and_update();
+                if new_active != active {
+                    if active {
+                        log::info!("A port steers the clock, PPS reference no longer in use");
+                        let mut new_filter = AnyFilter::new(filter_config);
+                        std::mem::swap(&mut filter, &mut new_filter);
+                        new_filter.demobilize(&mut clock);
+                    } else {
+                        log::info!("Disciplining the clock with the PPS reference");
+                    }
+                    active = new_active;
+                }
+                None
+            }
+        };
+
+        if let Some(m) = m {
+            let mut holdover_estimate = None;
+
+            if active {
+                log::debug!("PPS measurement: {:?}", m.offset);
+
+                if let Some((output, sinks)) = &mut output {
+                    // Without the time of day, chrony only takes the offset
+                    // within the second from the pulse
+                    let time = m.event_time - m.offset.unwrap_or(Duration::ZERO);
+                    output.send(sinks, m.event_time, time, nmea_receiver.is_none());
+                } else {
+                    let update = filter.measurement(m, &mut clock);
+                    if let Some(timeout) = update.next_update {
+                        update_timer.as_mut().reset(timeout);
+                    }
+                    holdover_estimate = filter.holdover_estimate();
+                }
+            }
+
+            // The reference is usable, even when a port steers the clock
+            let _ = last_pulse_sender.send(Some(LastPulse {
+                received: tokio::time::Instant::now(),
+                holdover_estimate,
+            }));
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     actual_main().await;
 }
 
+/// The offset of TAI to UTC that was configured in the kernel, usually by an
+/// NTP daemon
+fn kernel_utc_offset() -> Option<i16> {
+    let utc_offset = read_kernel_utc_offset();
+    if utc_offset.is_none() {
+        log::warn!("The kernel TAI offset is not set, announcing no UTC offset");
+    }
+    utc_offset
+}
+
+/// Like [`kernel_utc_offset`], without warning when the offset is unknown
+fn read_kernel_utc_offset() -> Option<i16> {
+    match LinuxClock::CLOCK_TAI.get_tai_offset() {
+        // The kernel reports an offset of 0 when it was never set
+        Ok(0) => None,
+        Ok(offset) => offset.try_into().ok(),
+        Err(error) => {
+            log::debug!("Could not read the kernel TAI offset: {:?}", error);
+            None
+        }
+    }
+}
+
 async fn actual_main() {
     let args = Args::parse();
 
@@ -236,6 +602,10 @@ async fn actual_main() {
 
     statime_linux::setup_logger(config.loglevel).expect("could not setup logging");
 
+    if let Some(mode) = config.transparent_clock {
+        run_transparent_clock(mode, config).await;
+    }
+
     let clock_identity = config.identity.unwrap_or(ClockIdentity(
         get_clock_id().expect("could not get clock identity"),
     ));
@@ -246,17 +616,28 @@ async fn actual_main() {
         clock_identity,
         priority_1: config.priority1,
         priority_2: config.priority2,
-        domain_number: config.domain,
+        domain_number: config.domain(),
         slave_only: false,
         sdo_id: SdoId::try_from(config.sdo_id).expect("sdo-id should be between 0 and 4095"),
+        profile: config.profile(),
+        clock_quality: config.clock_quality(),
+        holdover: config.holdover.map(Into::into),
     };
 
-    let time_properties_ds =
-        TimePropertiesDS::new_arbitrary_time(false, false, TimeSource::InternalOscillator);
+    let time_properties_ds = config.time_properties_ds(kernel_utc_offset);
+    let mut leap_seconds = config
+        .time_properties
+        .leap_seconds_file
+        .clone()
+        .map(|path| LeapSecondsFile::new(path, time_properties_ds));
+    let time_properties_ds = leap_seconds
+        .as_mut()
+        .map_or(time_properties_ds, LeapSecondsFile::time_properties_ds);
 
     // Leak to get a static reference, the ptp instance will be around for the rest
-    // of the program anyway
-    let instance = Box::leak(Box::new(PtpInstance::new(
+    // of the program anyway. The filter of each port is selected by the
+    // configuration, so they can differ between ports.
+    let instance = Box::leak(Box::new(PtpInstance::<AnyFilter>::new(
         instance_config,
         time_properties_ds,
     )));
@@ -268,9 +649,27 @@ async fn actual_main() {
             current_ds: instance.current_ds(),
             parent_ds: instance.parent_ds(),
             time_properties_ds: instance.time_properties_ds(),
+            smpte_metadata: instance.smpte_metadata(),
         });
     statime_linux::observer::spawn(&config, instance_state_receiver).await;
 
+    // The leap indicator handed to chrony or ntpd, and the UTC offset of NMEA
+    // sentences follow the time properties in use
+    let (time_properties_sender, time_properties_receiver) =
+        tokio::sync::watch::channel(instance.time_properties_ds());
+    // Ports without a hardware clock steer the system clock themselves, so then
+    // there is nothing to hand over
+    let hand_over_system_clock = (config.chrony_socket.is_some() || config.ntp_shm_unit.is_some())
+        && config
+            .ports
+            .iter()
+            .all(|port| port.hardware_clock.is_some());
+    let system_clock_output = hand_over_system_clock.then(|| SystemClockOutput {
+        chrony_socket: config.chrony_socket.clone(),
+        ntp_shm_unit: config.ntp_shm_unit,
+        time_properties_receiver: time_properties_receiver.clone(),
+    });
+
     let (bmca_notify_sender, bmca_notify_receiver) = tokio::sync::watch::channel(false);
 
     let mut main_task_senders = Vec::with_capacity(config.ports.len());
@@ -285,6 +684,12 @@ async fn actual_main() {
 
     let tlv_forwarder = TlvForwarder::new();
 
+    // Ports without a filter of their own use the global one. A filter of a
+    // port replaces it completely, its options are not merged with it.
+    let port_filter = config.filter;
+    let clock_filter = AnyFilterConfiguration::from(config.clock_filter);
+
+    let profile = config.profile;
     for port_config in config.ports {
         let interface = port_config.interface;
         let network_mode = port_config.network_mode;
@@ -297,7 +702,11 @@ async fn actual_main() {
                     let id = internal_sync_senders.len();
                     clock_port_map.push(Some(id));
                     clock_name_map.insert(path.clone(), id);
-                    internal_sync_senders.push(start_clock_task(clock.clone()));
+                    internal_sync_senders.push(start_clock_task(
+                        clock.clone(),
+                        clock_filter,
+                        system_clock_output.clone(),
+                    ));
                 }
                 (clock, InterfaceTimestampMode::HardwarePTPAll)
             }
@@ -307,10 +716,11 @@ async fn actual_main() {
             }
         };
 
+        let filter_config = port_config.filter.unwrap_or(port_filter);
         let rng = StdRng::from_entropy();
         let port = instance.add_port(
-            port_config.into(),
-            KalmanConfiguration::default(),
+            port_config.instance_port_config(profile),
+            filter_config.into(),
             port_clock.clone(),
             rng,
         );
@@ -380,6 +790,82 @@ async fn actual_main() {
     // Drop the forwarder so we don't keep an unneeded subscriber.
     drop(tlv_forwarder);
 
+    // The clocks are kept so their outputs stay enabled
+    let mut periodic_output_clocks = Vec::with_capacity(config.periodic_outputs.len());
+    for output in &config.periodic_outputs {
+        let clock = LinuxClock::open(&output.hardware_clock).expect("Unable to open clock");
+        // Hardware clocks that no port uses are kept synchronized as well
+        clock_name_map
+            .entry(output.hardware_clock.clone())
+            .or_insert_with(|| {
+                internal_sync_senders.push(start_clock_task(
+                    clock.clone(),
+                    clock_filter,
+                    system_clock_output.clone(),
+                ));
+                internal_sync_senders.len() - 1
+            });
+
+        if let Some(pin) = output.pin {
+            clock
+                .set_pin_function(pin, PinFunction::PeriodicOutput, output.channel)
+                .expect("Could not assign pin to periodic output");
+        }
+        clock
+            .enable_periodic_output(
+                output.channel,
+                output
+                    .try_into()
+                    .expect("periodic outputs are checked when loading the config"),
+            )
+            .expect("Could not enable periodic output");
+
+        periodic_output_clocks.push(clock);
+    }
+
+    let nmea = config.nmea;
+    let pps_reference = config.pps.map(|pps| {
+        let (source, clock_id) = match pps.extts_channel {
+            Some(channel) => {
+                let source = PpsSource::open_extts(&pps.path, channel, pps.extts_pin)
+                    .expect("Could not open PPS reference");
+                // The hardware clock disciplined by the reference gets a clock
+                // task too, even when no port uses it
+                let clock_id = *clock_name_map.entry(pps.path.clone()).or_insert_with(|| {
+                    internal_sync_senders.push(start_clock_task(
+                        source.clock().clone(),
+                        clock_filter,
+                        system_clock_output.clone(),
+                    ));
+                    internal_sync_senders.len() - 1
+                });
+                (source, Some(clock_id))
+            }
+            None => (
+                PpsSource::open_kernel(&pps.path).expect("Could not open PPS reference"),
+                None,
+            ),
+        };
+
+        let nmea = nmea.map(|nmea| {
+            NmeaReader::open(&nmea.path, nmea.baud_rate).expect("Could not open NMEA device")
+        });
+
+        PpsReference::start(
+            source,
+            nmea,
+            instance_config
+                .clock_quality
+                .unwrap_or_else(|| pps.clock_quality()),
+            clock_id,
+            clock_filter,
+            time_properties_receiver,
+            // A hardware clock is synchronized to the system clock by its
+            // clock task, which hands over the system clock itself
+            system_clock_output.clone().filter(|_| clock_id.is_none()),
+        )
+    });
+
     // All ports created, so we can start running them.
     for (i, port) in ports.into_iter().enumerate() {
         main_task_senders[i]
@@ -392,22 +878,29 @@ async fn actual_main() {
         instance,
         bmca_notify_sender,
         instance_state_sender,
+        time_properties_sender,
         main_task_receivers,
         main_task_senders,
         internal_sync_senders,
         clock_port_map,
+        leap_seconds,
+        pps_reference,
     )
     .await
 }
 
-async fn run(
-    instance: &'static PtpInstance<KalmanFilter>,
+#[allow(clippy::too_many_arguments)]
+async fn run<F: Filter>(
+    instance: &'static PtpInstance<F>,
     bmca_notify_sender: tokio::sync::watch::Sender<bool>,
     instance_state_sender: tokio::sync::watch::Sender<ObservableInstanceState>,
-    mut main_task_receivers: Vec<Receiver<BmcaPort>>,
-    main_task_senders: Vec<Sender<BmcaPort>>,
+    time_properties_sender: tokio::sync::watch::Sender<TimePropertiesDS>,
+    mut main_task_receivers: Vec<Receiver<BmcaPort<F>>>,
+    main_task_senders: Vec<Sender<BmcaPort<F>>>,
     internal_sync_senders: Vec<tokio::sync::watch::Sender<ClockSyncMode>>,
     clock_port_map: Vec<Option<usize>>,
+    mut leap_seconds: Option<LeapSecondsFile>,
+    mut pps_reference: Option<PpsReference>,
 ) -> ! {
     // run bmca over all of the ports at the same time. The ports don't perform
     // their normal actions at this time: bmca is stop-the-world!
@@ -441,6 +934,14 @@ async fn run(
             mut_bmca_ports.push(mut_bmca_port);
         }
 
+        if let Some(leap_seconds) = &mut leap_seconds {
+            instance.set_local_time_properties(leap_seconds.time_properties_ds());
+        }
+
+        if let Some(pps_reference) = &mut pps_reference {
+            pps_reference.update_instance(instance);
+        }
+
         instance.bmca(&mut mut_bmca_ports);
 
         // Update instance state for observability
@@ -450,7 +951,9 @@ async fn run(
             current_ds: instance.current_ds(),
             parent_ds: instance.parent_ds(),
             time_properties_ds: instance.time_properties_ds(),
+            smpte_metadata: instance.smpte_metadata(),
         });
+        let _ = time_properties_sender.send(instance.time_properties_ds());
 
         let mut clock_states = vec![ClockSyncMode::FromSystem; internal_sync_senders.len()];
         for (idx, port) in mut_bmca_ports.iter().enumerate() {
@@ -460,6 +963,18 @@ async fn run(
                 }
             }
         }
+        if let Some(pps_reference) = &pps_reference {
+            let active = !mut_bmca_ports.iter().any(|port| port.is_steering());
+            if active {
+                if let Some(id) = pps_reference.clock_id {
+                    clock_states[id] = ClockSyncMode::ToSystem;
+                }
+            }
+            pps_reference
+                .active_sender
+                .send(active)
+                .expect("PPS reference state change failed");
+        }
         for (mode, sender) in clock_states.into_iter().zip(internal_sync_senders.iter()) {
             sender.send(mode).expect("Clock mode change failed");
         }
@@ -472,7 +987,24 @@ async fn run(
     }
 }
 
-type BmcaPort = Port<InBmca<'static>, Option<Vec<ClockIdentity>>, StdRng, LinuxClock, KalmanFilter>;
+type BmcaPort<F> = Port<InBmca<'static>, Option<Vec<ClockIdentity>>, StdRng, LinuxClock, F>;
+
+/// The time of a timestamp taken by the socket of a port with `clock`, `None`
+/// when the TAI offset of the system clock cannot be read
+fn port_timestamp(clock: &LinuxClock, mut timestamp: Timestamp) -> Option<Time> {
+    // get_tai gives zero if this is a hardware clock, and the needed
+    // correction when this port uses software timestamping
+    match clock.get_tai_offset() {
+        Ok(tai_offset) => {
+            timestamp.seconds += tai_offset as libc::time_t;
+            Some(timestamp_to_time(timestamp))
+        }
+        Err(error) => {
+            log::error!("Unable to get tai offset, ignoring timestamp: {error}");
+            None
+        }
+    }
+}
 
 // the Port task
 //
@@ -480,9 +1012,9 @@ type BmcaPort = Port<InBmca<'static>, Option<Vec<ClockIdentity>>, StdRng, LinuxC
 // It will then move the port into the running state, and process actions. When
 // the task is notified of a BMCA, it will stop running, move the port into the
 // bmca state, and send it on its Sender
-async fn port_task<A: NetworkAddress + PtpTargetAddress>(
-    mut port_task_receiver: Receiver<BmcaPort>,
-    port_task_sender: Sender<BmcaPort>,
+async fn port_task<A: NetworkAddress + PtpTargetAddress, F: Filter>(
+    mut port_task_receiver: Receiver<BmcaPort<F>>,
+    port_task_sender: Sender<BmcaPort<F>>,
     mut event_socket: Socket<A, Open>,
     mut general_socket: Socket<A, Open>,
     mut bmca_notify: tokio::sync::watch::Receiver<bool>,
@@ -495,6 +1027,7 @@ async fn port_task<A: NetworkAddress + PtpTargetAddress>(
         port_announce_timeout_timer: pin!(Timer::new()),
         delay_request_timer: pin!(Timer::new()),
         filter_update_timer: pin!(Timer::new()),
+        unicast_negotiation_timer: pin!(Timer::new()),
     };
 
     loop {
@@ -532,12 +1065,12 @@ async fn port_task<A: NetworkAddress + PtpTargetAddress>(
             let mut actions = tokio::select! {
                 result = event_socket.recv(&mut event_buffer) => match result {
                     Ok(packet) => {
-                        if let Some(mut timestamp) = packet.timestamp {
-                            // get_tai gives zero if this is a hardware clock, and the needed
-                            // correction when this port uses software timestamping
-                            timestamp.seconds += clock.get_tai_offset().expect("Unable to get tai offset") as i64;
+                        if let Some(timestamp) = packet.timestamp {
                             log::trace!("Recv timestamp: {:?}", packet.timestamp);
-                            port.handle_event_receive(&event_buffer[..packet.bytes_read], timestamp_to_time(timestamp))
+                            match port_timestamp(&clock, timestamp) {
+                                Some(timestamp) => port.handle_event_receive_from(&event_buffer[..packet.bytes_read], timestamp, packet.remote_addr.port_address()),
+                                None => PortActionIterator::empty(),
+                            }
                         } else {
                             log::error!("Missing recv timestamp");
                             PortActionIterator::empty()
@@ -546,7 +1079,7 @@ async fn port_task<A: NetworkAddress + PtpTargetAddress>(
                     Err(error) => panic!("Error receiving: {error:?}"),
                 },
                 result = general_socket.recv(&mut general_buffer) => match result {
-                    Ok(packet) => port.handle_general_receive(&general_buffer[..packet.bytes_read]),
+                    Ok(packet) => port.handle_general_receive_from(&general_buffer[..packet.bytes_read], packet.remote_addr.port_address()),
                     Err(error) => panic!("Error receiving: {error:?}"),
                 },
                 () = &mut timers.port_announce_timer => {
@@ -564,6 +1097,9 @@ async fn port_task<A: NetworkAddress + PtpTargetAddress>(
                 () = &mut timers.filter_update_timer => {
                     port.handle_filter_update_timer()
                 },
+                () = &mut timers.unicast_negotiation_timer => {
+                    port.handle_unicast_negotiation_timer()
+                },
                 result = bmca_notify.wait_for(|v| *v) => match result {
                     Ok(_) => break,
                     Err(error) => panic!("Error on bmca notify: {error:?}"),
@@ -600,9 +1136,9 @@ async fn port_task<A: NetworkAddress + PtpTargetAddress>(
 // It will then move the port into the running state, and process actions. When
 // the task is notified of a BMCA, it will stop running, move the port into the
 // bmca state, and send it on its Sender
-async fn ethernet_port_task(
-    mut port_task_receiver: Receiver<BmcaPort>,
-    port_task_sender: Sender<BmcaPort>,
+async fn ethernet_port_task<F: Filter>(
+    mut port_task_receiver: Receiver<BmcaPort<F>>,
+    port_task_sender: Sender<BmcaPort<F>>,
     interface: libc::c_int,
     mut socket: Socket<EthernetAddress, Open>,
     mut bmca_notify: tokio::sync::watch::Receiver<bool>,
@@ -615,6 +1151,7 @@ async fn ethernet_port_task(
         port_announce_timeout_timer: pin!(Timer::new()),
         delay_request_timer: pin!(Timer::new()),
         filter_update_timer: pin!(Timer::new()),
+        unicast_negotiation_timer: pin!(Timer::new()),
     };
 
     loop {
@@ -657,14 +1194,14 @@ async fn ethernet_port_task(
             let mut actions = tokio::select! {
                 result = socket.recv(&mut event_buffer) => match result {
                     Ok(packet) => {
-                        if let Some(mut timestamp) = packet.timestamp {
-                            // get_tai gives zero if this is a hardware clock, and the needed
-                            // correction when this port uses software timestamping
-                            timestamp.seconds += clock.get_tai_offset().expect("Unable to get tai offset") as i64;
+                        if let Some(timestamp) = packet.timestamp {
                             log::trace!("Recv timestamp: {:?}", packet.timestamp);
-                            port.handle_event_receive(&event_buffer[..packet.bytes_read], timestamp_to_time(timestamp))
+                            match port_timestamp(&clock, timestamp) {
+                                Some(timestamp) => port.handle_event_receive_from(&event_buffer[..packet.bytes_read], timestamp, packet.remote_addr.port_address()),
+                                None => PortActionIterator::empty(),
+                            }
                         } else {
-                            port.handle_general_receive(&event_buffer[..packet.bytes_read])
+                            port.handle_general_receive_from(&event_buffer[..packet.bytes_read], packet.remote_addr.port_address())
                         }
                     }
                     Err(error) => panic!("Error receiving: {error:?}"),
@@ -684,6 +1221,9 @@ async fn ethernet_port_task(
                 () = &mut timers.filter_update_timer => {
                     port.handle_filter_update_timer()
                 },
+                () = &mut timers.unicast_negotiation_timer => {
+                    port.handle_unicast_negotiation_timer()
+                },
                 result = bmca_notify.wait_for(|v| *v) => match result {
                     Ok(_) => break,
                     Err(error) => panic!("Error on bmca notify: {error:?}"),
@@ -720,6 +1260,7 @@ struct Timers<'a> {
     port_announce_timeout_timer: Pin<&'a mut Timer>,
     delay_request_timer: Pin<&'a mut Timer>,
     filter_update_timer: Pin<&'a mut Timer>,
+    unicast_negotiation_timer: Pin<&'a mut Timer>,
 }
 
 async fn handle_actions<A: NetworkAddress + PtpTargetAddress>(
@@ -738,42 +1279,47 @@ async fn handle_actions<A: NetworkAddress + PtpTargetAddress>(
                 context,
                 data,
                 link_local,
+                destination,
             } => {
+                let address = match destination {
+                    Some(peer) => {
+                        A::unicast_event(peer).expect("Unicast peer of wrong address family")
+                    }
+                    None if link_local => A::PDELAY_EVENT,
+                    None => A::PRIMARY_EVENT,
+                };
+
                 // send timestamp of the send
                 let time = event_socket
-                    .send_to(
-                        data,
-                        if link_local {
-                            A::PDELAY_EVENT
-                        } else {
-                            A::PRIMARY_EVENT
-                        },
-                    )
+                    .send_to(data, address)
                     .await
                     .expect("Failed to send event message");
 
                 // anything we send later will have a later pending (send) timestamp
-                if let Some(mut time) = time {
-                    // get_tai gives zero if this is a hardware clock, and the needed
-                    // correction when this port uses software timestamping
-                    time.seconds +=
-                        clock.get_tai_offset().expect("Unable to get tai offset") as i64;
+                if let Some(time) = time {
                     log::trace!("Send timestamp {:?}", time);
-                    pending_timestamp = Some((context, timestamp_to_time(time)));
+                    if let Some(time) = port_timestamp(&clock, time) {
+                        pending_timestamp = Some((context, time));
+                    }
                 } else {
                     log::error!("Missing send timestamp");
                 }
             }
-            PortAction::SendGeneral { data, link_local } => {
+            PortAction::SendGeneral {
+                data,
+                link_local,
+                destination,
+            } => {
+                let address = match destination {
+                    Some(peer) => {
+                        A::unicast_general(peer).expect("Unicast peer of wrong address family")
+                    }
+                    None if link_local => A::PDELAY_GENERAL,
+                    None => A::PRIMARY_GENERAL,
+                };
+
                 general_socket
-                    .send_to(
-                        data,
-                        if link_local {
-                            A::PDELAY_GENERAL
-                        } else {
-                            A::PRIMARY_GENERAL
-                        },
-                    )
+                    .send_to(data, address)
                     .await
                     .expect("Failed to send general message");
             }
@@ -792,6 +1338,9 @@ async fn handle_actions<A: NetworkAddress + PtpTargetAddress>(
             PortAction::ResetFilterUpdateTimer { duration } => {
                 timers.filter_update_timer.as_mut().reset(duration);
             }
+            PortAction::ResetUnicastNegotiationTimer { duration } => {
+                timers.unicast_negotiation_timer.as_mut().reset(duration);
+            }
             PortAction::ForwardTLV { tlv } => {
                 tlv_forwarder.forward(tlv.into_owned());
             }
@@ -817,57 +1366,50 @@ async fn handle_actions_ethernet(
                 context,
                 data,
                 link_local,
+                destination,
             } => {
+                let address = match destination {
+                    Some(peer) => EthernetAddress::unicast_event(peer)
+                        .expect("Unicast peer of wrong address family"),
+                    None if link_local => EthernetAddress::PDELAY_EVENT,
+                    None => EthernetAddress::PRIMARY_EVENT,
+                };
+
                 // send timestamp of the send
                 let time = socket
                     .send_to(
                         data,
-                        EthernetAddress::new(
-                            if link_local {
-                                EthernetAddress::PDELAY_EVENT.protocol()
-                            } else {
-                                EthernetAddress::PRIMARY_EVENT.protocol()
-                            },
-                            if link_local {
-                                EthernetAddress::PDELAY_EVENT.mac()
-                            } else {
-                                EthernetAddress::PRIMARY_EVENT.mac()
-                            },
-                            interface,
-                        ),
+                        EthernetAddress::new(address.protocol(), address.mac(), interface),
                     )
                     .await
                     .expect("Failed to send event message");
 
                 // anything we send later will have a later pending (send) timestamp
-                if let Some(mut time) = time {
-                    // get_tai gives zero if this is a hardware clock, and the needed
-                    // correction when this port uses software timestamping
-                    time.seconds +=
-                        clock.get_tai_offset().expect("Unable to get tai offset") as libc::time_t;
+                if let Some(time) = time {
                     log::trace!("Send timestamp {:?}", time);
-                    pending_timestamp = Some((context, timestamp_to_time(time)));
+                    if let Some(time) = port_timestamp(&clock, time) {
+                        pending_timestamp = Some((context, time));
+                    }
                 } else {
                     log::error!("Missing send timestamp");
                 }
             }
-            PortAction::SendGeneral { data, link_local } => {
+            PortAction::SendGeneral {
+                data,
+                link_local,
+                destination,
+            } => {
+                let address = match destination {
+                    Some(peer) => EthernetAddress::unicast_general(peer)
+                        .expect("Unicast peer of wrong address family"),
+                    None if link_local => EthernetAddress::PDELAY_GENERAL,
+                    None => EthernetAddress::PRIMARY_GENERAL,
+                };
+
                 socket
                     .send_to(
                         data,
-                        EthernetAddress::new(
-                            if link_local {
-                                EthernetAddress::PDELAY_GENERAL.protocol()
-                            } else {
-                                EthernetAddress::PRIMARY_GENERAL.protocol()
-                            },
-                            if link_local {
-                                EthernetAddress::PDELAY_GENERAL.mac()
-                            } else {
-                                EthernetAddress::PRIMARY_GENERAL.mac()
-                            },
-                            interface,
-                        ),
+                        EthernetAddress::new(address.protocol(), address.mac(), interface),
                     )
                     .await
                     .expect("Failed to send general message");
@@ -887,6 +1429,9 @@ async fn handle_actions_ethernet(
             PortAction::ResetFilterUpdateTimer { duration } => {
                 timers.filter_update_timer.as_mut().reset(duration);
             }
+            PortAction::ResetUnicastNegotiationTimer { duration } => {
+                timers.unicast_negotiation_timer.as_mut().reset(duration);
+            }
             PortAction::ForwardTLV { tlv } => tlv_forwarder.forward(tlv.into_owned()),
         }
     }
@@ -894,6 +1439,343 @@ async fn handle_actions_ethernet(
     pending_timestamp
 }
 
+// A packet that the transparent clock forwards to another port
+struct ForwardedPacket {
+    data: Vec<u8>,
+    context: Option<TransparentClockTimestampContext>,
+    link_local: bool,
+}
+
+async fn run_transparent_clock(mode: TransparentClockMode, config: Config) -> ! {
+    let clock_identity = config.identity.unwrap_or(ClockIdentity(
+        get_clock_id().expect("could not get clock identity"),
+    ));
+
+    log::info!("Clock identity: {}", hex::encode(clock_identity.0));
+
+    // All ports measure their link delay at the same interval, use the shortest
+    // configured one
+    let interval = Interval::from_log_2(
+        config
+            .ports
+            .iter()
+            .map(|port| port.delay_interval(config.profile).as_log_2())
+            .min()
+            .unwrap_or(0),
+    );
+
+    let transparent_clock = Arc::new(Mutex::new(
+        TransparentClock::new(TransparentClockConfig {
+            clock_identity,
+            number_ports: config.ports.len(),
+            domain_number: config.domain(),
+            sdo_id: SdoId::try_from(config.sdo_id).expect("sdo-id should be between 0 and 4095"),
+            delay_mechanism: match mode {
+                TransparentClockMode::E2E => DelayMechanism::E2E { interval },
+                TransparentClockMode::P2P => DelayMechanism::P2P { interval },
+            },
+        })
+        .expect("the number of ports is checked when loading the config"),
+    ));
+
+    let (forward_senders, forward_receivers): (Vec<_>, Vec<_>) = config
+        .ports
+        .iter()
+        .map(|_| tokio::sync::mpsc::channel(16))
+        .unzip();
+
+    // Residence times are measured using the clocks of two different ports, so
+    // hardware clocks are kept synchronized to the system clock.
+    let mut clock_name_map = HashMap::new();
+    let mut internal_sync_senders = vec![];
+    let clock_filter = AnyFilterConfiguration::from(config.clock_filter);
+
+    for (index, (port_config, forward_receiver)) in
+        config.ports.into_iter().zip(forward_receivers).enumerate()
+    {
+        let interface = port_config.interface;
+        let (port_clock, timestamping) = match &port_config.hardware_clock {
+            Some(path) => {
+                let clock = LinuxClock::open(path).expect("Unable to open clock");
+                if !clock_name_map.contains_key(path) {
+                    clock_name_map.insert(path.clone(), internal_sync_senders.len());
+                    internal_sync_senders.push(start_clock_task(clock.clone(), clock_filter, None));
+                }
+                (clock, InterfaceTimestampMode::HardwarePTPAll)
+            }
+            None => (LinuxClock::CLOCK_TAI, InterfaceTimestampMode::SoftwareAll),
+        };
+
+        let port = TransparentPort {
+            index,
+            forward_receiver,
+            forward_senders: forward_senders.clone(),
+            transparent_clock: transparent_clock.clone(),
+            clock: port_clock,
+        };
+
+        match port_config.network_mode {
+            statime_linux::config::NetworkMode::Ipv4 => {
+                let event_socket = open_ipv4_event_socket(interface, timestamping)
+                    .expect("Could not open event socket");
+                let general_socket =
+                    open_ipv4_general_socket(interface).expect("Could not open general socket");
+
+                tokio::spawn(transparent_port_task(port, event_socket, general_socket));
+            }
+            statime_linux::config::NetworkMode::Ipv6 => {
+                let event_socket = open_ipv6_event_socket(interface, timestamping)
+                    .expect("Could not open event socket");
+                let general_socket =
+                    open_ipv6_general_socket(interface).expect("Could not open general socket");
+
+                tokio::spawn(transparent_port_task(port, event_socket, general_socket));
+            }
+            statime_linux::config::NetworkMode::Ethernet => {
+                let socket =
+                    open_ethernet_socket(interface, timestamping).expect("Could not open socket");
+
+                tokio::spawn(ethernet_transparent_port_task(
+                    port,
+                    interface
+                        .get_index()
+                        .expect("Unable to get network interface index") as _,
+                    socket,
+                ));
+            }
+        }
+    }
+
+    // The ports do all the work from here on, we only keep the senders of the
+    // clock tasks alive
+    loop {
+        std::future::pending::<()>().await;
+    }
+}
+
+// Everything a port task of a transparent clock shares with the other ports
+struct TransparentPort {
+    index: usize,
+    forward_receiver: Receiver<ForwardedPacket>,
+    forward_senders: Vec<Sender<ForwardedPacket>>,
+    transparent_clock: Arc<Mutex<TransparentClock>>,
+    clock: LinuxClock,
+}
+
+impl TransparentPort {
+    fn handle_event_receive(&self, data: &[u8], timestamp: Time) {
+        let mut transparent_clock = self.transparent_clock.lock().unwrap();
+        self.forward(transparent_clock.handle_event_receive(self.index, data, timestamp));
+    }
+
+    fn handle_general_receive(&self, data: &[u8]) {
+        let mut transparent_clock = self.transparent_clock.lock().unwrap();
+        self.forward(transparent_clock.handle_general_receive(self.index, data));
+    }
+
+    fn handle_send_timestamp(&self, context: TransparentClockTimestampContext, timestamp: Time) {
+        let mut transparent_clock = self.transparent_clock.lock().unwrap();
+        self.forward(transparent_clock.handle_send_timestamp(context, timestamp));
+    }
+
+    // Returns the duration after which the delay request timer should expire
+    // again, if it should be restarted
+    fn handle_delay_request_timer(&self) -> Option<std::time::Duration> {
+        let mut transparent_clock = self.transparent_clock.lock().unwrap();
+        self.forward(transparent_clock.handle_delay_request_timer(self.index))
+    }
+
+    // Hand the packets to the tasks of the ports they should be sent on, returns
+    // the requested reset of the delay request timer of this port
+    fn forward(&self, actions: TransparentClockActionIterator<'_>) -> Option<std::time::Duration> {
+        let mut timer_reset = None;
+
+        for action in actions {
+            let (port, packet) = match action {
+                TransparentClockAction::SendEvent {
+                    port,
+                    context,
+                    data,
+                    link_local,
+                } => (
+                    port,
+                    ForwardedPacket {
+                        data: data.to_vec(),
+                        context: Some(context),
+                        link_local,
+                    },
+                ),
+                TransparentClockAction::SendGeneral {
+                    port,
+                    data,
+                    link_local,
+                } => (
+                    port,
+                    ForwardedPacket {
+                        data: data.to_vec(),
+                        context: None,
+                        link_local,
+                    },
+                ),
+                TransparentClockAction::ResetDelayRequestTimer { port, duration } => {
+                    debug_assert_eq!(port, self.index);
+                    timer_reset = Some(duration);
+                    continue;
+                }
+            };
+
+            if self.forward_senders[port].try_send(packet).is_err() {
+                log::warn!("Forwarding queue of port {port} is full, dropping packet");
+            }
+        }
+
+        timer_reset
+    }
+}
+
+// The port task of a transparent clock
+//
+// This task forwards the packets received on its sockets to the other ports,
+// and sends the packets the other ports forward to it.
+async fn transparent_port_task<A: NetworkAddress + PtpTargetAddress>(
+    mut port: TransparentPort,
+    mut event_socket: Socket<A, Open>,
+    mut general_socket: Socket<A, Open>,
+) {
+    let mut event_buffer = [0; MAX_DATA_LEN];
+    let mut general_buffer = [0; 2048];
+
+    // Only a peer-to-peer transparent clock keeps this timer running
+    let mut delay_request_timer = pin!(Timer::new());
+    delay_request_timer
+        .as_mut()
+        .reset(std::time::Duration::ZERO);
+
+    loop {
+        tokio::select! {
+            result = event_socket.recv(&mut event_buffer) => match result {
+                Ok(packet) => {
+                    if let Some(timestamp) = packet.timestamp {
+                        if let Some(timestamp) = port_timestamp(&port.clock, timestamp) {
+                            port.handle_event_receive(&event_buffer[..packet.bytes_read], timestamp);
+                        }
+                    } else {
+                        log::error!("Missing recv timestamp");
+                    }
+                }
+                Err(error) => panic!("Error receiving: {error:?}"),
+            },
+            result = general_socket.recv(&mut general_buffer) => match result {
+                Ok(packet) => port.handle_general_receive(&general_buffer[..packet.bytes_read]),
+                Err(error) => panic!("Error receiving: {error:?}"),
+            },
+            () = &mut delay_request_timer => {
+                if let Some(duration) = port.handle_delay_request_timer() {
+                    delay_request_timer.as_mut().reset(duration);
+                }
+            },
+            Some(packet) = port.forward_receiver.recv() => match packet.context {
+                Some(context) => {
+                    let address = if packet.link_local {
+                        A::PDELAY_EVENT
+                    } else {
+                        A::PRIMARY_EVENT
+                    };
+                    let time = event_socket
+                        .send_to(&packet.data, address)
+                        .await
+                        .expect("Failed to send event message");
+
+                    if let Some(time) = time {
+                        if let Some(timestamp) = port_timestamp(&port.clock, time) {
+                            port.handle_send_timestamp(context, timestamp);
+                        }
+                    } else {
+                        log::error!("Missing send timestamp");
+                    }
+                }
+                None => {
+                    let address = if packet.link_local {
+                        A::PDELAY_GENERAL
+                    } else {
+                        A::PRIMARY_GENERAL
+                    };
+                    general_socket
+                        .send_to(&packet.data, address)
+                        .await
+                        .expect("Failed to send general message");
+                }
+            },
+        }
+    }
+}
+
+// The port task of a transparent clock for ethernet transport
+//
+// This task forwards the packets received on its socket to the other ports,
+// and sends the packets the other ports forward to it.
+async fn ethernet_transparent_port_task(
+    mut port: TransparentPort,
+    interface: libc::c_int,
+    mut socket: Socket<EthernetAddress, Open>,
+) {
+    let mut event_buffer = [0; MAX_DATA_LEN];
+
+    // Only a peer-to-peer transparent clock keeps this timer running
+    let mut delay_request_timer = pin!(Timer::new());
+    delay_request_timer
+        .as_mut()
+        .reset(std::time::Duration::ZERO);
+
+    loop {
+        tokio::select! {
+            result = socket.recv(&mut event_buffer) => match result {
+                Ok(packet) => {
+                    // General messages passed to handle_event_receive are
+                    // treated as such by the transparent clock
+                    if let Some(timestamp) = packet.timestamp {
+                        if let Some(timestamp) = port_timestamp(&port.clock, timestamp) {
+                            port.handle_event_receive(&event_buffer[..packet.bytes_read], timestamp);
+                        }
+                    } else {
+                        port.handle_general_receive(&event_buffer[..packet.bytes_read]);
+                    }
+                }
+                Err(error) => panic!("Error receiving: {error:?}"),
+            },
+            () = &mut delay_request_timer => {
+                if let Some(duration) = port.handle_delay_request_timer() {
+                    delay_request_timer.as_mut().reset(duration);
+                }
+            },
+            Some(packet) = port.forward_receiver.recv() => {
+                let address = if packet.link_local {
+                    EthernetAddress::PDELAY_EVENT
+                } else {
+                    EthernetAddress::PRIMARY_EVENT
+                };
+                let time = socket
+                    .send_to(
+                        &packet.data,
+                        EthernetAddress::new(address.protocol(), address.mac(), interface),
+                    )
+                    .await
+                    .expect("Failed to send message");
+
+                if let Some(context) = packet.context {
+                    if let Some(time) = time {
+                        if let Some(timestamp) = port_timestamp(&port.clock, time) {
+                            port.handle_send_timestamp(context, timestamp);
+                        }
+                    } else {
+                        log::error!("Missing send timestamp");
+                    }
+                }
+            },
+        }
+    }
+}
+
 fn get_clock_id() -> Option<[u8; 8]> {
     let candidates = interfaces()
         .unwrap()
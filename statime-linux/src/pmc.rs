@@ -0,0 +1,398 @@
+//! A management client, similar to `pmc` from linuxptp
+//!
+//! Sends management requests built with [`statime::management`] and prints
+//! the replies of every PTP instance that answers.
+
+use std::{
+    net::{IpAddr, SocketAddrV4, SocketAddrV6},
+    str::FromStr,
+    time::Duration,
+};
+
+use clap::Parser;
+use statime::{
+    config::{ClockIdentity, PortIdentity, SdoId},
+    management::{
+        ManagementAction, ManagementData, ManagementId, ManagementRequest, ManagementRequestBody,
+        ManagementResponse, ManagementResponseBody,
+    },
+    observability::port::DelayMechanism,
+};
+use timestamped_socket::{
+    interface::{interfaces, InterfaceName},
+    networkaddress::{EthernetAddress, NetworkAddress},
+    socket::{InterfaceTimestampMode, Open, Socket},
+};
+
+use crate::{
+    config::NetworkMode,
+    socket::{
+        open_ethernet_socket, open_ipv4_general_socket, open_ipv6_general_socket, PtpTargetAddress,
+    },
+};
+
+const GENERAL_PORT: u16 = 320;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub(crate) struct Args {
+    /// Network interface to send the requests on
+    #[clap(long = "interface", short = 'i', value_parser = parse_interface_name)]
+    interface: InterfaceName,
+
+    /// Transport to use
+    #[clap(long = "network-mode", short = 'n', value_enum, default_value = "ipv4")]
+    network_mode: NetworkMode,
+
+    /// Send the requests to this address instead of the PTP multicast address
+    /// (ipv4 and ipv6 only)
+    #[clap(long = "address", short = 'a')]
+    address: Option<IpAddr>,
+
+    /// Domain number of the targeted instances
+    #[clap(long = "domain", short = 'd', default_value = "0")]
+    domain: u8,
+
+    /// Sdo id of the targeted instances
+    #[clap(long = "sdo-id", default_value = "0")]
+    sdo_id: u16,
+
+    /// Port identity to target, as `<clock identity in hex>-<port number>`.
+    /// Targets all ports of all instances when omitted
+    #[clap(long = "target", short = 't', value_parser = parse_port_identity)]
+    target: Option<PortIdentity>,
+
+    /// Number of boundary clocks the requests may pass through
+    #[clap(long = "boundary-hops", short = 'b', default_value = "1")]
+    boundary_hops: u8,
+
+    /// Time in milliseconds to wait for replies to each request
+    #[clap(long = "timeout", default_value = "1000")]
+    timeout: u64,
+
+    /// Requests to send, e.g. "GET PARENT_DATA_SET" or "SET PRIORITY1 100"
+    #[clap(required = true)]
+    commands: Vec<String>,
+}
+
+pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut requests = Vec::with_capacity(args.commands.len());
+    for command in &args.commands {
+        match parse_command(command) {
+            Ok(body) => requests.push(body),
+            Err(e) => {
+                eprintln!("invalid command \"{command}\": {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Ok(sdo_id) = SdoId::try_from(args.sdo_id) else {
+        eprintln!("sdo id {} is out of range", args.sdo_id);
+        std::process::exit(1);
+    };
+
+    let mac = interfaces()?
+        .get(&args.interface)
+        .and_then(|data| data.mac())
+        .unwrap_or_default();
+    let source_port_identity = PortIdentity {
+        clock_identity: ClockIdentity(std::array::from_fn(|i| {
+            mac.get(i).copied().unwrap_or_default()
+        })),
+        // Distinguishes concurrently running clients on the same machine
+        port_number: std::process::id() as u16,
+    };
+
+    let client = Client {
+        source_port_identity,
+        target_port_identity: args.target.unwrap_or(PortIdentity {
+            clock_identity: ClockIdentity([0xff; 8]),
+            port_number: 0xffff,
+        }),
+        domain_number: args.domain,
+        sdo_id,
+        boundary_hops: args.boundary_hops,
+        timeout: Duration::from_millis(args.timeout),
+    };
+
+    match (args.network_mode, args.address) {
+        (NetworkMode::Ipv4, address) => {
+            let address = match address {
+                Some(IpAddr::V4(ip)) => SocketAddrV4::new(ip, GENERAL_PORT),
+                Some(IpAddr::V6(_)) => {
+                    eprintln!("an ipv4 address is required for the ipv4 network mode");
+                    std::process::exit(1);
+                }
+                None => SocketAddrV4::PRIMARY_GENERAL,
+            };
+            let socket = open_ipv4_general_socket(args.interface)?;
+            client.run(socket, address, &requests).await
+        }
+        (NetworkMode::Ipv6, address) => {
+            let address = match address {
+                Some(IpAddr::V6(ip)) => SocketAddrV6::new(ip, GENERAL_PORT, 0, 0),
+                Some(IpAddr::V4(_)) => {
+                    eprintln!("an ipv6 address is required for the ipv6 network mode");
+                    std::process::exit(1);
+                }
+                None => SocketAddrV6::PRIMARY_GENERAL,
+            };
+            let socket = open_ipv6_general_socket(args.interface)?;
+            client.run(socket, address, &requests).await
+        }
+        (NetworkMode::Ethernet, Some(_)) => {
+            eprintln!("an address can not be used with the ethernet network mode");
+            std::process::exit(1);
+        }
+        (NetworkMode::Ethernet, None) => {
+            let address = EthernetAddress::new(
+                EthernetAddress::PRIMARY_GENERAL.protocol(),
+                EthernetAddress::PRIMARY_GENERAL.mac(),
+                args.interface
+                    .get_index()
+                    .ok_or("Unable to get network interface index")? as _,
+            );
+            let socket = open_ethernet_socket(args.interface, InterfaceTimestampMode::None)?;
+            client.run(socket, address, &requests).await
+        }
+    }
+}
+
+struct Client {
+    source_port_identity: PortIdentity,
+    target_port_identity: PortIdentity,
+    domain_number: u8,
+    sdo_id: SdoId,
+    boundary_hops: u8,
+    timeout: Duration,
+}
+
+impl Client {
+    async fn run<A: NetworkAddress + Copy>(
+        &self,
+        mut socket: Socket<A, Open>,
+        address: A,
+        requests: &[ManagementRequestBody],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut send_buffer = [0; statime::port::MAX_DATA_LEN];
+        let mut recv_buffer = [0; 2048];
+
+        for (sequence_id, body) in requests.iter().enumerate() {
+            let request = ManagementRequest {
+                source_port_identity: self.source_port_identity,
+                target_port_identity: self.target_port_identity,
+                domain_number: self.domain_number,
+                sdo_id: self.sdo_id,
+                sequence_id: sequence_id as u16,
+                boundary_hops: self.boundary_hops,
+                body: *body,
+            };
+
+            let length = request.serialize(&mut send_buffer)?;
+            socket.send_to(&send_buffer[..length], address).await?;
+
+            // Several instances can reply to a single request, so keep listening
+            // until the timeout expires
+            let deadline = tokio::time::Instant::now() + self.timeout;
+            while let Ok(result) =
+                tokio::time::timeout_at(deadline, socket.recv(&mut recv_buffer)).await
+            {
+                let data = &recv_buffer[..result?.bytes_read];
+
+                // Everything that is not a reply to our request is ignored
+                let Ok(response) = ManagementResponse::deserialize(data) else {
+                    continue;
+                };
+                if response.target_port_identity != self.source_port_identity
+                    || response.sequence_id != request.sequence_id
+                    || !matches!(
+                        response.action,
+                        ManagementAction::RESPONSE | ManagementAction::ACKNOWLEDGE
+                    )
+                {
+                    continue;
+                }
+
+                print_response(&response);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn print_response(response: &ManagementResponse) {
+    let source = response.source_port_identity;
+    print!(
+        "{}-{} seq {} {} ",
+        source.clock_identity, source.port_number, response.sequence_id, response.action
+    );
+
+    match response.body {
+        ManagementResponseBody::Data(data) => {
+            println!("MANAGEMENT {}", data.management_id());
+            println!("{data:#?}");
+        }
+        ManagementResponseBody::Error(status) => {
+            println!("MANAGEMENT_ERROR_STATUS {}", status.management_id);
+            println!("{}", status.error_id);
+        }
+        ManagementResponseBody::Undecoded(management_id) => {
+            println!("MANAGEMENT {management_id}");
+            println!("(data not supported by this client)");
+        }
+    }
+}
+
+fn parse_interface_name(value: &str) -> Result<InterfaceName, String> {
+    InterfaceName::from_str(value).map_err(|_| format!("invalid interface name {value}"))
+}
+
+fn parse_port_identity(value: &str) -> Result<PortIdentity, String> {
+    let (clock_identity, port_number) = value
+        .split_once('-')
+        .ok_or("expected <clock identity>-<port number>")?;
+
+    let mut clock_identity_bytes = [0; 8];
+    hex::decode_to_slice(clock_identity.replace('.', ""), &mut clock_identity_bytes)
+        .map_err(|e| format!("invalid clock identity: {e}"))?;
+
+    Ok(PortIdentity {
+        clock_identity: ClockIdentity(clock_identity_bytes),
+        port_number: port_number
+            .parse()
+            .map_err(|e| format!("invalid port number: {e}"))?,
+    })
+}
+
+fn parse_command(command: &str) -> Result<ManagementRequestBody, String> {
+    let mut words = command.split_whitespace();
+
+    let action = words.next().ok_or("missing action")?;
+    let action =
+        ManagementAction::from_str(action).map_err(|_| format!("unknown action {action}"))?;
+
+    let id = words.next().ok_or("missing management id")?;
+    let id = ManagementId::from_str(id).map_err(|_| format!("unknown management id {id}"))?;
+
+    let value = words.next();
+    if words.next().is_some() {
+        return Err("too many arguments".into());
+    }
+
+    match action {
+        ManagementAction::GET => match value {
+            None => Ok(ManagementRequestBody::Get(id)),
+            Some(_) => Err("GET does not take a value".into()),
+        },
+        ManagementAction::SET => {
+            let value = value.ok_or("missing value")?;
+            Ok(ManagementRequestBody::Set(parse_value(id, value)?))
+        }
+        ManagementAction::COMMAND => {
+            let data = match (id, value) {
+                (ManagementId::NullPtpManagement, None) => ManagementData::NullPtpManagement,
+                (ManagementId::EnablePort, None) => ManagementData::EnablePort,
+                (ManagementId::DisablePort, None) => ManagementData::DisablePort,
+                (ManagementId::Initialize, key) => ManagementData::Initialize {
+                    key: key.map(parse_number).transpose()?.unwrap_or(0),
+                },
+                (_, None) => return Err(format!("{id} is not a supported command")),
+                (_, Some(_)) => return Err(format!("{id} does not take a value")),
+            };
+            Ok(ManagementRequestBody::Command(data))
+        }
+        _ => Err(format!("{action} can not be sent by a management node")),
+    }
+}
+
+fn parse_value(id: ManagementId, value: &str) -> Result<ManagementData, String> {
+    Ok(match id {
+        ManagementId::Priority1 => ManagementData::Priority1(parse_number(value)?),
+        ManagementId::Priority2 => ManagementData::Priority2(parse_number(value)?),
+        ManagementId::Domain => ManagementData::Domain(parse_number(value)?),
+        ManagementId::SlaveOnly => ManagementData::SlaveOnly(match value {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => return Err(format!("invalid boolean {value}")),
+        }),
+        ManagementId::LogAnnounceInterval => {
+            ManagementData::LogAnnounceInterval(parse_number(value)?)
+        }
+        ManagementId::AnnounceReceiptTimeout => {
+            ManagementData::AnnounceReceiptTimeout(parse_number(value)?)
+        }
+        ManagementId::LogSyncInterval => ManagementData::LogSyncInterval(parse_number(value)?),
+        ManagementId::LogMinPdelayReqInterval => {
+            ManagementData::LogMinPdelayReqInterval(parse_number(value)?)
+        }
+        ManagementId::DelayMechanism => {
+            ManagementData::DelayMechanism(match value.to_ascii_uppercase().as_str() {
+                "E2E" => DelayMechanism::E2E,
+                "P2P" => DelayMechanism::P2P,
+                "COMMON_P2P" => DelayMechanism::CommonP2P,
+                "SPECIAL" => DelayMechanism::Special,
+                "NO_MECHANISM" => DelayMechanism::NoMechanism,
+                _ => return Err(format!("invalid delay mechanism {value}")),
+            })
+        }
+        _ => return Err(format!("setting {id} is not supported by this client")),
+    })
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value {value}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            parse_command("GET PARENT_DATA_SET"),
+            Ok(ManagementRequestBody::Get(ManagementId::ParentDataSet))
+        );
+        assert_eq!(
+            parse_command("set priority1 100"),
+            Ok(ManagementRequestBody::Set(ManagementData::Priority1(100)))
+        );
+        assert_eq!(
+            parse_command("COMMAND DISABLE_PORT"),
+            Ok(ManagementRequestBody::Command(ManagementData::DisablePort))
+        );
+        assert_eq!(
+            parse_command("COMMAND INITIALIZE"),
+            Ok(ManagementRequestBody::Command(ManagementData::Initialize {
+                key: 0
+            }))
+        );
+
+        assert!(parse_command("GET").is_err());
+        assert!(parse_command("SET PRIORITY1").is_err());
+        assert!(parse_command("SET PRIORITY1 300").is_err());
+        assert!(parse_command("GET PRIORITY1 3").is_err());
+        assert!(parse_command("RESPONSE PRIORITY1").is_err());
+    }
+
+    #[test]
+    fn port_identity() {
+        assert_eq!(
+            parse_port_identity("0011223344556677-2"),
+            Ok(PortIdentity {
+                clock_identity: ClockIdentity([0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]),
+                port_number: 2,
+            })
+        );
+        assert!(parse_port_identity("0011223344556677").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Reference clocks that discipline the local clock of a grandmaster
+
+pub mod nmea;
+pub mod pps;
+
+#[cfg(doc)]
+use statime::PtpInstance;
+use statime::{
+    config::{ClockAccuracy, ClockQuality},
+    port::Measurement,
+    time::{Duration, Time},
+};
+
+/// The measurement of a pulse that marks the start of a second, taken at
+/// `timestamp` on the disciplined clock
+///
+/// A pulse only gives the phase of the reference, so this assumes the clock is
+/// already within half a second of it.
+pub fn pulse_measurement(timestamp: Time) -> Measurement {
+    let second = Time::from_secs(timestamp.secs());
+    if timestamp - second > Duration::from_millis(500) {
+        reference_measurement(timestamp, second + Duration::from_secs(1))
+    } else {
+        reference_measurement(timestamp, second)
+    }
+}
+
+/// The measurement of a pulse taken at `timestamp` on the disciplined clock,
+/// that marks the start of second `reference` of the reference
+pub fn reference_measurement(timestamp: Time, reference: Time) -> Measurement {
+    let offset = timestamp - reference;
+
+    // The pulse has no propagation delay, so both the sync and delay offsets
+    // are the offset itself.
+    Measurement {
+        event_time: timestamp,
+        offset: Some(offset),
+        delay: Some(Duration::ZERO),
+        peer_delay: None,
+        raw_sync_offset: Some(offset),
+        raw_delay_offset: Some(offset),
+    }
+}
+
+/// How well the clock is locked to its reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceStatus {
+    /// The reference disciplines the clock
+    Locked,
+    /// The reference was lost, the clock keeps running at the frequency it
+    /// was disciplined to
+    Holdover,
+    /// The reference never disciplined the clock
+    FreeRunning,
+}
+
+impl ReferenceStatus {
+    /// How long the clock stays locked after the last usable pulse, so a
+    /// few missed pulses do not change the clock quality
+    const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(4);
+
+    /// The status given the time since the last usable pulse of the reference
+    pub fn from_last_pulse(since_last_pulse: Option<std::time::Duration>) -> Self {
+        match since_last_pulse {
+            Some(since) if since <= Self::LOCK_TIMEOUT => Self::Locked,
+            Some(_) => Self::Holdover,
+            None => Self::FreeRunning,
+        }
+    }
+
+    /// The clock quality in this status, given that when locked. In holdover
+    /// the instance degrades the locked quality itself, see
+    /// [`PtpInstance::start_reference_holdover`].
+    pub fn clock_quality(self, locked: ClockQuality) -> ClockQuality {
+        match self {
+            Self::Locked | Self::Holdover => locked,
+            Self::FreeRunning => ClockQuality {
+                clock_class: 248,
+                clock_accuracy: ClockAccuracy::Unknown,
+                offset_scaled_log_variance: 0xffff,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use statime::{
+        config::{ClockAccuracy, ClockQuality},
+        time::{Duration, Time},
+    };
+
+    use super::{pulse_measurement, reference_measurement, ReferenceStatus};
+
+    #[test]
+    fn pulse_offset() {
+        // A clock that runs ahead
+        let m = pulse_measurement(Time::from_nanos(1_000_001_500));
+        assert_eq!(m.offset, Some(Duration::from_nanos(1_500)));
+        assert_eq!(m.raw_sync_offset, m.offset);
+        assert_eq!(m.raw_delay_offset, m.offset);
+        assert_eq!(m.delay, Some(Duration::ZERO));
+
+        // A clock that runs behind
+        let m = pulse_measurement(Time::from_nanos(1_999_998_000));
+        assert_eq!(m.offset, Some(Duration::from_nanos(-2_000)));
+
+        // Exactly on the second
+        let m = pulse_measurement(Time::from_secs(2));
+        assert_eq!(m.offset, Some(Duration::ZERO));
+
+        // Half a second is taken as ahead
+        let m = pulse_measurement(Time::from_millis(2_500));
+        assert_eq!(m.offset, Some(Duration::from_millis(500)));
+        let m = pulse_measurement(Time::from_millis(2_501));
+        assert_eq!(m.offset, Some(Duration::from_millis(-499)));
+
+        // With the seconds of the reference, whole seconds are measured too
+        let m = reference_measurement(Time::from_nanos(3_000_000_100), Time::from_secs(1));
+        assert_eq!(m.offset, Some(Duration::from_nanos(2_000_000_100)));
+    }
+
+    #[test]
+    fn reference_status() {
+        assert_eq!(
+            ReferenceStatus::from_last_pulse(Some(std::time::Duration::from_secs(1))),
+            ReferenceStatus::Locked
+        );
+        assert_eq!(
+            ReferenceStatus::from_last_pulse(Some(std::time::Duration::from_secs(10))),
+            ReferenceStatus::Holdover
+        );
+        assert_eq!(
+            ReferenceStatus::from_last_pulse(None),
+            ReferenceStatus::FreeRunning
+        );
+
+        let locked = ClockQuality {
+            clock_class: 6,
+            clock_accuracy: ClockAccuracy::NS100,
+            offset_scaled_log_variance: 0x4e5d,
+        };
+        assert_eq!(ReferenceStatus::Locked.clock_quality(locked), locked);
+        assert_eq!(ReferenceStatus::Holdover.clock_quality(locked), locked);
+        assert_eq!(
+            ReferenceStatus::FreeRunning
+                .clock_quality(locked)
+                .clock_class,
+            248
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Time of day from the NMEA 0183 sentences of a GNSS receiver
+//!
+//! The RMC and ZDA sentences carry the UTC time of the second they refer to,
+//! which for most receivers is the second that started at the preceding pulse
+//! of their PPS output. RMC also tells whether the receiver has a fix.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    os::fd::AsRawFd,
+    path::Path,
+};
+
+/// The time of day reported by a single sentence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NmeaTime {
+    /// The UTC second the sentence refers to, in seconds since the unix epoch
+    pub utc_seconds: u64,
+    /// Whether the receiver has a valid fix, `None` when the sentence does not
+    /// report it
+    pub fix: Option<bool>,
+}
+
+impl NmeaTime {
+    /// Parse a sentence. Returns `None` for sentences without a time of day,
+    /// and for those that are malformed or fail their checksum.
+    pub fn parse(sentence: &str) -> Option<Self> {
+        let sentence = sentence.trim().strip_prefix('$')?;
+        let (body, checksum) = sentence.split_once('*')?;
+        let checksum = u8::from_str_radix(checksum, 16).ok()?;
+        if body.bytes().fold(0, |acc, byte| acc ^ byte) != checksum {
+            return None;
+        }
+
+        let mut fields = body.split(',');
+        let address = fields.next()?;
+        // The address is a two letter talker, like GP or GN, and the type
+        let kind = address.get(2..)?;
+
+        match kind {
+            "RMC" => {
+                let time = fields.next()?;
+                let status = fields.next()?;
+                let date = fields.nth(6)?;
+
+                let day = parse_number(date.get(0..2)?)?;
+                let month = parse_number(date.get(2..4)?)?;
+                let year = match parse_number(date.get(4..6)?)? {
+                    year @ 0..=79 => 2000 + year,
+                    year => 1900 + year,
+                };
+
+                Some(Self {
+                    utc_seconds: unix_seconds(year, month, day, time)?,
+                    fix: Some(status == "A"),
+                })
+            }
+            "ZDA" => {
+                let time = fields.next()?;
+                let day = parse_number(fields.next()?)?;
+                let month = parse_number(fields.next()?)?;
+                let year = parse_number(fields.next()?)?;
+
+                Some(Self {
+                    utc_seconds: unix_seconds(year, month, day, time)?,
+                    fix: None,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_number(digits: &str) -> Option<u64> {
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    digits.parse().ok()
+}
+
+/// Seconds since the unix epoch of a date and a `hhmmss` time, ignoring any
+/// fraction of the second
+fn unix_seconds(year: u64, month: u64, day: u64, time: &str) -> Option<u64> {
+    let hours = parse_number(time.get(0..2)?)?;
+    let minutes = parse_number(time.get(2..4)?)?;
+    let seconds = parse_number(time.get(4..6)?)?;
+
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+
+    // Days since 1970-01-01 of the proleptic Gregorian calendar, using years
+    // that start in March so the leap day is at the end
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+/// The serial device or pseudo terminal on which a GNSS receiver sends its
+/// sentences
+#[derive(Debug)]
+pub struct NmeaReader {
+    reader: BufReader<File>,
+    line: Vec<u8>,
+}
+
+impl NmeaReader {
+    /// Open the device, setting its baud rate when it is a terminal
+    pub fn open(path: impl AsRef<Path>, baud_rate: u32) -> std::io::Result<Self> {
+        let device = File::open(path)?;
+        configure_terminal(&device, baud_rate)?;
+
+        Ok(Self {
+            reader: BufReader::new(device),
+            line: Vec::new(),
+        })
+    }
+
+    /// Wait for the next sentence with a time of day
+    pub fn next_time(&mut self) -> std::io::Result<NmeaTime> {
+        loop {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+
+            // Anything that is not a sentence, like the noise of a serial line
+            // that just opened, is skipped
+            if let Some(time) = std::str::from_utf8(&self.line)
+                .ok()
+                .and_then(NmeaTime::parse)
+            {
+                return Ok(time);
+            }
+        }
+    }
+}
+
+fn configure_terminal(device: &File, baud_rate: u32) -> std::io::Result<()> {
+    let speed = match baud_rate {
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        921600 => libc::B921600,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unsupported baud rate {baud_rate}"),
+            ))
+        }
+    };
+
+    // Safety: termios is plain old data, which tcgetattr fills in
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    // Safety: we pass a valid file descriptor and termios
+    if unsafe { libc::tcgetattr(device.as_raw_fd(), &mut termios) } == -1 {
+        let error = std::io::Error::last_os_error();
+        // Replaying sentences from a pipe or a file is fine too
+        return match error.raw_os_error() {
+            Some(libc::ENOTTY) => Ok(()),
+            _ => Err(error),
+        };
+    }
+
+    // Safety: we pass a valid termios, and a valid file descriptor
+    unsafe {
+        libc::cfmakeraw(&mut termios);
+        termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+        if libc::cfsetspeed(&mut termios, speed) == -1
+            || libc::tcsetattr(device.as_raw_fd(), libc::TCSANOW, &termios) == -1
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, io::Write, os::fd::FromRawFd};
+
+    use super::*;
+
+    #[test]
+    fn parse_sentences() {
+        assert_eq!(
+            NmeaTime::parse(
+                "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n"
+            ),
+            Some(NmeaTime {
+                utc_seconds: 764426119,
+                fix: Some(true),
+            })
+        );
+        assert_eq!(
+            NmeaTime::parse("$GNRMC,235959.00,V,,,,,,,311224,,,N*65"),
+            Some(NmeaTime {
+                utc_seconds: 1735689599,
+                fix: Some(false),
+            })
+        );
+        assert_eq!(
+            NmeaTime::parse("$GPZDA,201530.00,04,07,2002,00,00*60"),
+            Some(NmeaTime {
+                utc_seconds: 1025813730,
+                fix: None,
+            })
+        );
+
+        // Checksum mismatch
+        assert_eq!(
+            NmeaTime::parse("$GPZDA,201530.00,04,07,2002,00,00*61"),
+            None
+        );
+        // Without time of day
+        assert_eq!(NmeaTime::parse("$GPZDA,,,,,,*48"), None);
+        assert_eq!(
+            NmeaTime::parse("$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39"),
+            None
+        );
+        assert_eq!(NmeaTime::parse("garbage"), None);
+    }
+
+    #[test]
+    fn unix_seconds_of_dates() {
+        assert_eq!(unix_seconds(1970, 1, 1, "000000"), Some(0));
+        assert_eq!(unix_seconds(2000, 2, 29, "120000"), Some(951825600));
+        assert_eq!(unix_seconds(2016, 12, 31, "235960"), Some(1483228800));
+        assert_eq!(unix_seconds(2024, 13, 1, "000000"), None);
+        assert_eq!(unix_seconds(2024, 1, 1, "240000"), None);
+    }
+
+    #[test]
+    fn read_from_pseudo_terminal() {
+        // Safety: plain libc calls on the descriptor we just opened, ptsname is
+        // not used concurrently in this test binary
+        let (mut master, path) = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(fd >= 0);
+            assert_eq!(libc::grantpt(fd), 0);
+            assert_eq!(libc::unlockpt(fd), 0);
+            let path = CStr::from_ptr(libc::ptsname(fd))
+                .to_str()
+                .unwrap()
+                .to_owned();
+            (File::from_raw_fd(fd), path)
+        };
+
+        let mut reader = NmeaReader::open(path, 9600).unwrap();
+
+        master
+            .write_all(
+                b"\x00\xff$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39\r\n\
+                  $GPZDA,201530.00,04,07,2002,00,00*60\r\n\
+                  $GNRMC,235959.00,V,,,,,,,311224,,,N*65\r\n",
+            )
+            .unwrap();
+
+        assert_eq!(reader.next_time().unwrap().utc_seconds, 1025813730);
+        assert_eq!(
+            reader.next_time().unwrap(),
+            NmeaTime {
+                utc_seconds: 1735689599,
+                fix: Some(false),
+            }
+        );
+    }
+}
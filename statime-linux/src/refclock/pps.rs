@@ -0,0 +1,278 @@
+//! Pulse per second input from kernel PPS devices and hardware clocks
+//!
+//! Kernel PPS devices (`/dev/ppsN`, see `Documentation/driver-api/pps.rst`)
+//! timestamp the assert edges of a pulse, for example on the DCD line of a
+//! serial port, with the system clock. The external timestamp (EXTTS) channels
+//! of a hardware clock timestamp the rising edges on a pin of the network card
+//! with that hardware clock.
+
+use std::{
+    fs::File,
+    mem::size_of,
+    os::fd::AsRawFd,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use statime::time::Time;
+
+use crate::clock::{
+    ioctl::{
+        ioc, ioctl, PtpExttsEvent, PtpExttsRequest, IOC_READ, IOC_WRITE, PTP_ENABLE_FEATURE,
+        PTP_EXTTS_REQUEST, PTP_RISING_EDGE,
+    },
+    libc_timespec_into_instant, LinuxClock, PinFunction,
+};
+
+// From linux/pps.h. Note that the kernel encodes the size of a pointer to the
+// structures in these requests, not that of the structures themselves.
+const PPS_GETPARAMS: libc::Ioctl = ioc(IOC_READ, b'p', 0xa1, size_of::<*mut PpsKparams>());
+const PPS_SETPARAMS: libc::Ioctl = ioc(IOC_WRITE, b'p', 0xa2, size_of::<*mut PpsKparams>());
+const PPS_FETCH: libc::Ioctl = ioc(IOC_READ | IOC_WRITE, b'p', 0xa4, size_of::<*mut PpsFdata>());
+const PPS_CAPTUREASSERT: libc::c_int = 0x01;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsKtime {
+    sec: i64,
+    nsec: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsKinfo {
+    assert_sequence: u32,
+    clear_sequence: u32,
+    assert_tu: PpsKtime,
+    clear_tu: PpsKtime,
+    current_mode: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsFdata {
+    info: PpsKinfo,
+    timeout: PpsKtime,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct PpsKparams {
+    api_version: libc::c_int,
+    mode: libc::c_int,
+    assert_off_tu: PpsKtime,
+    clear_off_tu: PpsKtime,
+}
+
+/// A source of pulse per second timestamps
+#[derive(Debug)]
+pub struct PpsSource {
+    device: File,
+    kind: PpsKind,
+    clock: LinuxClock,
+}
+
+#[derive(Debug)]
+enum PpsKind {
+    Kernel { sequence: u32 },
+    Extts { channel: u32 },
+}
+
+impl PpsSource {
+    /// Open a kernel PPS device, which timestamps pulses with the system clock
+    pub fn open_kernel(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let device = File::options().read(true).write(true).open(path)?;
+
+        let mut params = PpsKparams::default();
+        ioctl(&device, PPS_GETPARAMS, &mut params)?;
+        if params.mode & PPS_CAPTUREASSERT == 0 {
+            params.mode |= PPS_CAPTUREASSERT;
+            ioctl(&device, PPS_SETPARAMS, &mut params)?;
+        }
+
+        // A zero timeout returns the last pulse without waiting, so we only
+        // report pulses after this one
+        let mut data = PpsFdata::default();
+        ioctl(&device, PPS_FETCH, &mut data)?;
+
+        Ok(Self {
+            device,
+            kind: PpsKind::Kernel {
+                sequence: data.info.assert_sequence,
+            },
+            clock: LinuxClock::CLOCK_TAI,
+        })
+    }
+
+    /// Open an external timestamp channel of a hardware clock, optionally
+    /// assigning it to a pin first
+    pub fn open_extts(
+        path: impl AsRef<Path>,
+        channel: u32,
+        pin: Option<u32>,
+    ) -> std::io::Result<Self> {
+        let clock = LinuxClock::open(path.as_ref())?;
+        let device = File::options().read(true).write(true).open(path)?;
+
+        if let Some(pin) = pin {
+            clock.set_pin_function(pin, PinFunction::ExternalTimestamp, channel)?;
+        }
+
+        let mut request = PtpExttsRequest {
+            index: channel,
+            flags: PTP_ENABLE_FEATURE | PTP_RISING_EDGE,
+            ..Default::default()
+        };
+        ioctl(&device, PTP_EXTTS_REQUEST, &mut request)?;
+
+        Ok(Self {
+            device,
+            kind: PpsKind::Extts { channel },
+            clock,
+        })
+    }
+
+    /// The clock with which the pulses are timestamped
+    pub fn clock(&self) -> &LinuxClock {
+        &self.clock
+    }
+
+    /// Wait for the next pulse and return its timestamp, or `None` when no
+    /// pulse arrives within `timeout`
+    pub fn next_pulse(&mut self, timeout: Duration) -> std::io::Result<Option<Time>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+
+            let result = match self.kind {
+                PpsKind::Kernel { ref mut sequence } => {
+                    Self::fetch_kernel(&self.device, sequence, remaining)
+                }
+                PpsKind::Extts { channel } => Self::fetch_extts(&self.device, channel, remaining),
+            };
+
+            match result {
+                Ok(Some(mut timestamp)) => {
+                    if matches!(self.kind, PpsKind::Kernel { .. }) {
+                        // Kernel PPS timestamps are UTC, while we keep the
+                        // system clock on TAI
+                        timestamp.tv_sec += self.clock.get_tai_offset()? as libc::time_t;
+                    }
+
+                    return Ok(Some(libc_timespec_into_instant(timestamp)));
+                }
+                Ok(None) => {}
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn fetch_kernel(
+        device: &File,
+        sequence: &mut u32,
+        timeout: Duration,
+    ) -> std::io::Result<Option<libc::timespec>> {
+        let mut data = PpsFdata {
+            timeout: PpsKtime {
+                sec: timeout.as_secs() as i64,
+                nsec: timeout.subsec_nanos() as i32,
+                flags: 0,
+            },
+            ..Default::default()
+        };
+
+        match ioctl(device, PPS_FETCH, &mut data) {
+            Err(error) if error.raw_os_error() == Some(libc::ETIMEDOUT) => return Ok(None),
+            result => result?,
+        }
+
+        // We are also woken for clear edges
+        if data.info.assert_sequence == *sequence {
+            return Ok(None);
+        }
+        *sequence = data.info.assert_sequence;
+
+        Ok(Some(libc::timespec {
+            tv_sec: data.info.assert_tu.sec as _,
+            tv_nsec: data.info.assert_tu.nsec as _,
+        }))
+    }
+
+    fn fetch_extts(
+        device: &File,
+        channel: u32,
+        timeout: Duration,
+    ) -> std::io::Result<Option<libc::timespec>> {
+        let mut poll_fd = libc::pollfd {
+            fd: device.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+
+        // Safety: we pass a single valid pollfd
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+            -1 => return Err(std::io::Error::last_os_error()),
+            0 => return Ok(None),
+            _ => {}
+        }
+
+        let mut event = PtpExttsEvent::default();
+        // Safety: the buffer is a single event, which is what the kernel writes
+        let read = unsafe {
+            libc::read(
+                device.as_raw_fd(),
+                &mut event as *mut PtpExttsEvent as *mut libc::c_void,
+                size_of::<PtpExttsEvent>(),
+            )
+        };
+        if read == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        // Events of channels enabled by others are delivered to us as well
+        if read as usize != size_of::<PtpExttsEvent>() || event.index != channel {
+            return Ok(None);
+        }
+
+        Ok(Some(libc::timespec {
+            tv_sec: event.t.sec as _,
+            tv_nsec: event.t.nsec as _,
+        }))
+    }
+}
+
+impl Drop for PpsSource {
+    fn drop(&mut self) {
+        if let PpsKind::Extts { channel } = self.kind {
+            let mut request = PtpExttsRequest {
+                index: channel,
+                ..Default::default()
+            };
+            if let Err(error) = ioctl(&self.device, PTP_EXTTS_REQUEST, &mut request) {
+                log::warn!("Could not disable external timestamps: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ioctl_requests() {
+        // The values of the C headers on 64 bit platforms
+        if size_of::<usize>() == 8 {
+            assert_eq!(PPS_GETPARAMS as u32, 0x800870a1);
+            assert_eq!(PPS_SETPARAMS as u32, 0x400870a2);
+            assert_eq!(PPS_FETCH as u32, 0xc00870a4);
+        }
+    }
+}
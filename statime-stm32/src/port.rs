@@ -164,6 +164,8 @@ impl Port {
                         .try_send((TimerName::FilterUpdate, duration))
                         .ok());
                 }
+                // Unicast negotiation is not configured, so this timer is never started
+                PortAction::ResetUnicastNegotiationTimer { .. } => {}
                 // Single port implementation, so no need to forward TLVs
                 PortAction::ForwardTLV { .. } => {}
             }
@@ -277,6 +279,9 @@ pub fn setup_statime(
         domain_number: 0,
         slave_only: false,
         sdo_id: SdoId::default(),
+        profile: Default::default(),
+        clock_quality: None,
+        holdover: None,
     };
     let time_properties_ds =
         TimePropertiesDS::new_arbitrary_time(false, false, TimeSource::InternalOscillator);
@@ -292,7 +297,12 @@ pub fn setup_statime(
         announce_receipt_timeout: 3,
         sync_interval: Interval::from_log_2(-6),
         master_only: false,
+        local_priority: 128,
         delay_asymmetry: Duration::ZERO,
+        one_step: false,
+        hybrid_e2e: false,
+        unicast_master_table: Default::default(),
+        unicast_negotiation: None,
     };
     let filter_config = 0.1;
 
@@ -70,6 +70,15 @@ impl statime::Clock for &PtpClock {
         Ok(self.now())
     }
 
+    fn get_frequency(&self) -> Result<f64, Self::Error> {
+        let addend = self.access(|clock| clock.addend());
+
+        let total_frequency = u32::MAX as f64 / addend as f64;
+        let freq = self.addend_starter_frequency / total_frequency;
+
+        Ok((freq - 1.0) * 1_000_000.0)
+    }
+
     fn set_properties(
         &mut self,
         _time_properties_ds: &statime::config::TimePropertiesDS,
@@ -5,13 +5,14 @@ use core::cmp::Ordering;
 use super::{
     acceptable_master::AcceptableMasterList,
     dataset_comparison::{ComparisonDataset, DatasetOrdering},
-    foreign_master::ForeignMasterList,
+    foreign_master::{ForeignMasterList, FOREIGN_MASTER_THRESHOLD},
 };
 use crate::{
+    config::Profile,
     datastructures::{
         common::{PortIdentity, TimeInterval},
         datasets::InternalDefaultDS,
-        messages::{AnnounceMessage, Header},
+        messages::{AnnounceMessage, Header, PowerProfileTlv},
     },
     port::state::PortState,
     time::Duration,
@@ -35,6 +36,8 @@ pub(crate) struct Bmca<A> {
     foreign_master_list: ForeignMasterList,
     acceptable_master_list: A,
     own_port_identity: PortIdentity,
+    own_port_local_priority: u8,
+    profile: Profile,
 }
 
 impl<A> Bmca<A> {
@@ -42,14 +45,30 @@ impl<A> Bmca<A> {
         acceptable_master_list: A,
         own_port_announce_interval: TimeInterval,
         own_port_identity: PortIdentity,
+        own_port_local_priority: u8,
+        profile: Profile,
     ) -> Self {
+        // gPTP considers announce messages qualified as soon as they are received
+        // (IEEE 802.1AS-2020 section 10.3.10)
+        let qualification_threshold = match profile {
+            Profile::Default
+            | Profile::TelecomFullTiming { .. }
+            | Profile::TelecomPartialTiming { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => FOREIGN_MASTER_THRESHOLD,
+            Profile::Gptp { .. } => 0,
+        };
+
         Self {
             foreign_master_list: ForeignMasterList::new(
                 own_port_announce_interval,
                 own_port_identity,
+                qualification_threshold,
             ),
             acceptable_master_list,
             own_port_identity,
+            own_port_local_priority,
+            profile,
         }
     }
 
@@ -62,23 +81,28 @@ impl<A> Bmca<A> {
     /// the announce message.
     pub(crate) fn find_best_announce_message(
         announce_messages: impl IntoIterator<Item = BestAnnounceMessage>,
+        profile: Profile,
     ) -> Option<BestAnnounceMessage> {
         announce_messages
             .into_iter()
-            .max_by(BestAnnounceMessage::compare)
+            .max_by(|a, b| a.compare(b, profile))
     }
 
     fn compare_d0_best(
         d0: &ComparisonDataset,
         opt_best: Option<BestAnnounceMessage>,
+        profile: Profile,
     ) -> MessageComparison {
         match opt_best {
             None => MessageComparison::Better,
             Some(best) => {
-                let dataset =
-                    ComparisonDataset::from_announce_message(&best.message, &best.identity);
+                let dataset = ComparisonDataset::from_announce_message(
+                    &best.message,
+                    &best.identity,
+                    best.local_priority,
+                );
 
-                match d0.compare(&dataset).as_ordering() {
+                match d0.compare(&dataset, profile).as_ordering() {
                     Ordering::Less => MessageComparison::Worse(best),
                     Ordering::Equal => MessageComparison::Same,
                     Ordering::Greater => MessageComparison::Better,
@@ -135,7 +159,7 @@ impl<A> Bmca<A> {
     ) -> RecommendedState {
         let d0 = ComparisonDataset::from_own_data(own_data);
 
-        match Self::compare_d0_best(&d0, best_port_announce_message) {
+        match Self::compare_d0_best(&d0, best_port_announce_message, own_data.profile) {
             MessageComparison::Better => RecommendedState::M1(*own_data),
             MessageComparison::Same => RecommendedState::M1(*own_data),
             MessageComparison::Worse(port) => RecommendedState::P1(port.message),
@@ -149,12 +173,14 @@ impl<A> Bmca<A> {
     ) -> RecommendedState {
         let d0 = ComparisonDataset::from_own_data(own_data);
 
-        match Self::compare_d0_best(&d0, best_global_announce_message) {
+        match Self::compare_d0_best(&d0, best_global_announce_message, own_data.profile) {
             MessageComparison::Better => RecommendedState::M2(*own_data),
             MessageComparison::Same => RecommendedState::M2(*own_data),
             MessageComparison::Worse(global_message) => match best_port_announce_message {
                 None => RecommendedState::M3(global_message.message),
-                Some(port_message) => Self::compare_global_and_port(global_message, port_message),
+                Some(port_message) => {
+                    Self::compare_global_and_port(global_message, port_message, own_data.profile)
+                }
             },
         }
     }
@@ -162,6 +188,7 @@ impl<A> Bmca<A> {
     fn compare_global_and_port(
         global_message: BestAnnounceMessage,
         port_message: BestAnnounceMessage,
+        profile: Profile,
     ) -> RecommendedState {
         if global_message == port_message {
             // effectively, E_best == E_rbest
@@ -170,15 +197,20 @@ impl<A> Bmca<A> {
             let ebest = ComparisonDataset::from_announce_message(
                 &global_message.message,
                 &global_message.identity,
+                global_message.local_priority,
             );
 
             let erbest = ComparisonDataset::from_announce_message(
                 &port_message.message,
                 &port_message.identity,
+                port_message.local_priority,
             );
 
             // E_best better by topology than E_rbest
-            if matches!(ebest.compare(&erbest), DatasetOrdering::BetterByTopology) {
+            if matches!(
+                ebest.compare(&erbest, profile),
+                DatasetOrdering::BetterByTopology
+            ) {
                 RecommendedState::P2(port_message.message)
             } else {
                 RecommendedState::M3(global_message.message)
@@ -188,11 +220,13 @@ impl<A> Bmca<A> {
 }
 
 impl<A: AcceptableMasterList> Bmca<A> {
-    /// Register a received announce message to the BMC algorithm
+    /// Register a received announce message to the BMC algorithm, together
+    /// with the IEEE_C37_238 TLV it carried
     pub(crate) fn register_announce_message(
         &mut self,
         header: &Header,
         announce_message: &AnnounceMessage,
+        power_profile: Option<PowerProfileTlv>,
     ) -> bool {
         // Ignore messages comming from the same port
         if announce_message.header.source_port_identity != self.own_port_identity
@@ -203,6 +237,7 @@ impl<A: AcceptableMasterList> Bmca<A> {
             self.foreign_master_list.register_announce_message(
                 header,
                 announce_message,
+                power_profile,
                 Duration::ZERO,
             );
             true
@@ -215,6 +250,7 @@ impl<A: AcceptableMasterList> Bmca<A> {
         &mut self,
         header: &Header,
         announce_message: &AnnounceMessage,
+        power_profile: Option<PowerProfileTlv>,
         age: Duration,
     ) {
         // Ignore messages comming from the same port
@@ -223,8 +259,12 @@ impl<A: AcceptableMasterList> Bmca<A> {
                 .acceptable_master_list
                 .is_acceptable(announce_message.header.source_port_identity.clock_identity)
         {
-            self.foreign_master_list
-                .register_announce_message(header, announce_message, age);
+            self.foreign_master_list.register_announce_message(
+                header,
+                announce_message,
+                power_profile,
+                age,
+            );
         }
     }
 
@@ -235,20 +275,28 @@ impl<A: AcceptableMasterList> Bmca<A> {
         let announce_messages = self.foreign_master_list.take_qualified_announce_messages();
 
         // The best of the foreign master messages is our erbest
-        let erbest = Self::find_best_announce_message(announce_messages.map(|message| {
-            BestAnnounceMessage {
+        let erbest = Self::find_best_announce_message(
+            announce_messages.map(|message| BestAnnounceMessage {
                 header: message.header,
                 message: message.message,
+                power_profile: message.power_profile,
                 age: message.age,
                 identity: self.own_port_identity,
-            }
-        }));
+                local_priority: self.own_port_local_priority,
+            }),
+            self.profile,
+        );
 
         if let Some(best) = &erbest {
             // All messages that were considered have been removed from the
             // foreignmasterlist. However, the one that has been selected as the
             // Erbest must not be removed, so let's just reregister it.
-            self.reregister_announce_message(&best.header, &best.message, best.age);
+            self.reregister_announce_message(
+                &best.header,
+                &best.message,
+                best.power_profile,
+                best.age,
+            );
         }
 
         erbest
@@ -266,22 +314,34 @@ enum MessageComparison {
 pub(crate) struct BestAnnounceMessage {
     header: Header,
     message: AnnounceMessage,
+    pub(crate) power_profile: Option<PowerProfileTlv>,
     age: Duration,
     identity: PortIdentity,
+    local_priority: u8,
 }
 
 impl BestAnnounceMessage {
-    fn compare(&self, other: &Self) -> Ordering {
+    fn compare(&self, other: &Self, profile: Profile) -> Ordering {
         // use the age as a tie-break if needed (prefer newer messages)
         let tie_break = other.age.cmp(&self.age);
-        self.compare_dataset(other).as_ordering().then(tie_break)
+        self.compare_dataset(other, profile)
+            .as_ordering()
+            .then(tie_break)
     }
 
-    fn compare_dataset(&self, other: &Self) -> DatasetOrdering {
-        let data1 = ComparisonDataset::from_announce_message(&self.message, &self.identity);
-        let data2 = ComparisonDataset::from_announce_message(&other.message, &other.identity);
+    fn compare_dataset(&self, other: &Self, profile: Profile) -> DatasetOrdering {
+        let data1 = ComparisonDataset::from_announce_message(
+            &self.message,
+            &self.identity,
+            self.local_priority,
+        );
+        let data2 = ComparisonDataset::from_announce_message(
+            &other.message,
+            &other.identity,
+            other.local_priority,
+        );
 
-        data1.compare(&data2)
+        data1.compare(&data2, profile)
     }
 }
 
@@ -355,8 +415,10 @@ mod tests {
         BestAnnounceMessage {
             header,
             message,
+            power_profile: None,
             age: Duration::ZERO,
             identity,
+            local_priority: 128,
         }
     }
 
@@ -366,31 +428,55 @@ mod tests {
             AcceptAnyMaster,
             TimeInterval(100.into()),
             PortIdentity::default(),
+            128,
+            Profile::Default,
         );
         let mut announce = default_announce_message();
         announce.header.source_port_identity.clock_identity.0 = [1, 2, 3, 4, 5, 6, 7, 8];
 
-        bmca.register_announce_message(&announce.header, &announce);
-        bmca.register_announce_message(&announce.header, &announce);
-        bmca.register_announce_message(&announce.header, &announce);
+        bmca.register_announce_message(&announce.header, &announce, None);
+        bmca.register_announce_message(&announce.header, &announce, None);
+        bmca.register_announce_message(&announce.header, &announce, None);
 
         assert!(bmca.take_best_port_announce_message().is_some());
         assert!(bmca.take_best_port_announce_message().is_some());
     }
 
+    #[test]
+    fn test_master_registration_gptp() {
+        let mut bmca = Bmca::new(
+            AcceptAnyMaster,
+            TimeInterval(100.into()),
+            PortIdentity::default(),
+            128,
+            Profile::Gptp {
+                neighbor_prop_delay_threshold: Duration::from_nanos(800),
+            },
+        );
+        let mut announce = default_announce_message();
+        announce.header.source_port_identity.clock_identity.0 = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        // A single announce message is enough to qualify the foreign master
+        bmca.register_announce_message(&announce.header, &announce, None);
+
+        assert!(bmca.take_best_port_announce_message().is_some());
+    }
+
     #[test]
     fn test_acceptable_master_filter() {
         let mut bmca = Bmca::new(
             std::vec![],
             TimeInterval(100.into()),
             PortIdentity::default(),
+            128,
+            Profile::Default,
         );
         let mut announce = default_announce_message();
         announce.header.source_port_identity.clock_identity.0 = [1, 2, 3, 4, 5, 6, 7, 8];
 
-        bmca.register_announce_message(&announce.header, &announce);
-        bmca.register_announce_message(&announce.header, &announce);
-        bmca.register_announce_message(&announce.header, &announce);
+        bmca.register_announce_message(&announce.header, &announce, None);
+        bmca.register_announce_message(&announce.header, &announce, None);
+        bmca.register_announce_message(&announce.header, &announce, None);
 
         assert!(bmca.take_best_port_announce_message().is_none());
     }
@@ -400,7 +486,9 @@ mod tests {
         let message1 = default_best_announce_message();
         let message2 = default_best_announce_message();
 
-        let ordering = message1.compare_dataset(&message2).as_ordering();
+        let ordering = message1
+            .compare_dataset(&message2, Profile::Default)
+            .as_ordering();
         assert_eq!(ordering, Ordering::Equal);
     }
 
@@ -418,11 +506,23 @@ mod tests {
         message2.message.grandmaster_priority_1 = 1;
 
         // hence we expect message1 to be better than message2
-        assert_eq!(message1.compare_dataset(&message2), DatasetOrdering::Better);
-        assert_eq!(message2.compare_dataset(&message1), DatasetOrdering::Worse);
+        assert_eq!(
+            message1.compare_dataset(&message2, Profile::Default),
+            DatasetOrdering::Better
+        );
+        assert_eq!(
+            message2.compare_dataset(&message1, Profile::Default),
+            DatasetOrdering::Worse
+        );
 
-        assert_eq!(message1.compare(&message2), Ordering::Greater);
-        assert_eq!(message2.compare(&message1), Ordering::Less);
+        assert_eq!(
+            message1.compare(&message2, Profile::Default),
+            Ordering::Greater
+        );
+        assert_eq!(
+            message2.compare(&message1, Profile::Default),
+            Ordering::Less
+        );
     }
 
     #[test]
@@ -436,11 +536,16 @@ mod tests {
         // the newest message should be preferred
         assert!(message2.age < message1.age);
 
-        let ordering = message1.compare_dataset(&message2).as_ordering();
+        let ordering = message1
+            .compare_dataset(&message2, Profile::Default)
+            .as_ordering();
         assert_eq!(ordering, Ordering::Equal);
 
         // so message1 is lower in the ordering than message2
-        assert_eq!(message1.compare(&message2), Ordering::Less)
+        assert_eq!(
+            message1.compare(&message2, Profile::Default),
+            Ordering::Less
+        )
     }
 
     fn default_own_data() -> InternalDefaultDS {
@@ -458,6 +563,9 @@ mod tests {
             domain_number,
             slave_only,
             sdo_id,
+            profile: Default::default(),
+            clock_quality: None,
+            holdover: None,
         })
     }
 
@@ -499,6 +607,9 @@ mod tests {
             domain_number,
             slave_only,
             sdo_id,
+            profile: Default::default(),
+            clock_quality: None,
+            holdover: None,
         });
 
         own_data.clock_quality.clock_class = 1;
@@ -510,7 +621,7 @@ mod tests {
         let port_message = default_best_announce_message();
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(port_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(port_message), Profile::Default),
             MessageComparison::Same
         ));
 
@@ -531,7 +642,7 @@ mod tests {
         port_message.identity.port_number = 1;
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(port_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(port_message), Profile::Default),
             MessageComparison::Better
         ));
 
@@ -559,7 +670,7 @@ mod tests {
         let d0 = ComparisonDataset::from_own_data(&own_data);
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(port_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(port_message), Profile::Default),
             MessageComparison::Worse(_)
         ));
 
@@ -587,7 +698,7 @@ mod tests {
         let global_message = default_best_announce_message();
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(global_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(global_message), Profile::Default),
             MessageComparison::Same
         ));
 
@@ -608,7 +719,7 @@ mod tests {
         global_message.identity.port_number = 1;
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(global_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(global_message), Profile::Default),
             MessageComparison::Better
         ));
 
@@ -636,7 +747,7 @@ mod tests {
         let d0 = ComparisonDataset::from_own_data(&own_data);
 
         assert!(matches!(
-            Bmca::<()>::compare_d0_best(&d0, Some(global_message)),
+            Bmca::<()>::compare_d0_best(&d0, Some(global_message), Profile::Default),
             MessageComparison::Worse(_)
         ));
 
@@ -673,13 +784,17 @@ mod tests {
         let ebest = ComparisonDataset::from_announce_message(
             &global_message.message,
             &global_message.identity,
+            128,
         );
 
-        let erbest =
-            ComparisonDataset::from_announce_message(&port_message.message, &port_message.identity);
+        let erbest = ComparisonDataset::from_announce_message(
+            &port_message.message,
+            &port_message.identity,
+            128,
+        );
 
         assert!(!matches!(
-            ebest.compare(&erbest),
+            ebest.compare(&erbest, Profile::Default),
             DatasetOrdering::BetterByTopology
         ));
 
@@ -716,13 +831,17 @@ mod tests {
         let ebest = ComparisonDataset::from_announce_message(
             &global_message.message,
             &global_message.identity,
+            128,
         );
 
-        let erbest =
-            ComparisonDataset::from_announce_message(&port_message.message, &port_message.identity);
+        let erbest = ComparisonDataset::from_announce_message(
+            &port_message.message,
+            &port_message.identity,
+            128,
+        );
 
         assert!(!matches!(
-            ebest.compare(&erbest),
+            ebest.compare(&erbest, Profile::Default),
             DatasetOrdering::BetterByTopology
         ));
 
@@ -1,11 +1,15 @@
-//! Implementation of chapter 9.3.4 Data set comparison algorithm
+//! Implementation of chapter 9.3.4 Data set comparison algorithm, and the
+//! variants of the profiles that change it
 
 use core::cmp::Ordering;
 
-use crate::datastructures::{
-    common::{ClockIdentity, ClockQuality, PortIdentity},
-    datasets::InternalDefaultDS,
-    messages::AnnounceMessage,
+use crate::{
+    config::{telecom, Profile},
+    datastructures::{
+        common::{ClockIdentity, ClockQuality, PortIdentity},
+        datasets::InternalDefaultDS,
+        messages::AnnounceMessage,
+    },
 };
 
 /// A collection of data that is gathered from other sources (mainly announce
@@ -21,15 +25,19 @@ pub(crate) struct ComparisonDataset {
     gm_priority_2: u8,
     steps_removed: u16,
     identity_of_senders: ClockIdentity,
+    port_number_of_senders: u16,
     identity_of_receiver: PortIdentity,
+    local_priority: u8,
 }
 
 impl ComparisonDataset {
     /// Create a ComparisonDataset from the data in an announce message and the
-    /// port identity of the port that received the announce message
+    /// port identity and local priority of the port that received the announce
+    /// message
     pub(crate) fn from_announce_message(
         message: &AnnounceMessage,
         port_receiver_identity: &PortIdentity,
+        port_local_priority: u8,
     ) -> Self {
         Self {
             gm_priority_1: message.grandmaster_priority_1,
@@ -38,7 +46,9 @@ impl ComparisonDataset {
             gm_priority_2: message.grandmaster_priority_2,
             steps_removed: message.steps_removed,
             identity_of_senders: message.header.source_port_identity.clock_identity,
+            port_number_of_senders: message.header.source_port_identity.port_number,
             identity_of_receiver: *port_receiver_identity,
+            local_priority: port_local_priority,
         }
     }
 
@@ -50,19 +60,44 @@ impl ComparisonDataset {
             gm_priority_2: data.priority_2,
             steps_removed: 0,
             identity_of_senders: data.clock_identity,
+            port_number_of_senders: 0,
             identity_of_receiver: PortIdentity {
                 clock_identity: data.clock_identity,
                 port_number: 0,
             },
+            local_priority: match data.profile {
+                Profile::TelecomFullTiming { local_priority }
+                | Profile::TelecomPartialTiming { local_priority } => local_priority,
+                Profile::Default
+                | Profile::Gptp { .. }
+                | Profile::Power { .. }
+                | Profile::Smpte2059 { .. } => telecom::DEFAULT_LOCAL_PRIORITY,
+            },
         }
     }
 
-    /// Returns the ordering of `self` in comparison to other.
-    pub(crate) fn compare(&self, other: &Self) -> DatasetOrdering {
-        if self.gm_identity == other.gm_identity {
-            Self::compare_same_identity(self, other)
+    /// Returns the ordering of `self` in comparison to other, using the
+    /// comparison rules of `profile`.
+    pub(crate) fn compare(&self, other: &Self, profile: Profile) -> DatasetOrdering {
+        if self.gm_identity != other.gm_identity {
+            match profile {
+                Profile::Default
+                | Profile::Gptp { .. }
+                | Profile::Power { .. }
+                | Profile::Smpte2059 { .. } => Self::compare_different_identity(self, other),
+                Profile::TelecomFullTiming { .. } | Profile::TelecomPartialTiming { .. } => {
+                    Self::compare_different_identity_telecom(self, other)
+                }
+            }
         } else {
-            Self::compare_different_identity(self, other)
+            match profile {
+                Profile::Default
+                | Profile::TelecomFullTiming { .. }
+                | Profile::TelecomPartialTiming { .. }
+                | Profile::Power { .. }
+                | Profile::Smpte2059 { .. } => Self::compare_same_identity(self, other),
+                Profile::Gptp { .. } => Self::compare_same_identity_gptp(self, other),
+            }
         }
     }
 
@@ -87,6 +122,53 @@ impl ComparisonDataset {
         }
     }
 
+    /// PTP grandmaster instances are different, compared with the alternate
+    /// algorithm of *ITU-T G.8275.1 section 6.3.7*
+    ///
+    /// Priority 1 is not used, and the local priority of the receiving ports
+    /// is compared after the quality of the grandmasters. Grandmasters that
+    /// are locked to a reference (clock class up to 127) are then compared by
+    /// topology as in figure 35, so the nearest one is preferred. Others are
+    /// compared by identity, so all clocks agree on a single one.
+    fn compare_different_identity_telecom(&self, other: &Self) -> DatasetOrdering {
+        let self_quality = self.gm_clock_quality;
+        let other_quality = other.gm_clock_quality;
+
+        let ordering = (self_quality.clock_class.cmp(&other_quality.clock_class))
+            .then_with(|| {
+                self_quality
+                    .clock_accuracy
+                    .cmp_numeric(&other_quality.clock_accuracy)
+            })
+            .then_with(|| {
+                self_quality
+                    .offset_scaled_log_variance
+                    .cmp(&other_quality.offset_scaled_log_variance)
+            })
+            .then_with(|| self.gm_priority_2.cmp(&other.gm_priority_2))
+            .then_with(|| self.local_priority.cmp(&other.local_priority));
+
+        match ordering {
+            Ordering::Greater => return DatasetOrdering::Worse,
+            Ordering::Less => return DatasetOrdering::Better,
+            Ordering::Equal => {}
+        }
+
+        if self_quality.clock_class <= 127 {
+            match self.compare_same_identity(other) {
+                // The topology can't tell them apart, fall back to the identity
+                DatasetOrdering::Error1 | DatasetOrdering::Error2 => {}
+                ordering => return ordering,
+            }
+        }
+
+        match self.gm_identity.cmp(&other.gm_identity) {
+            Ordering::Equal => unreachable!("gm_identity is guaranteed to be different"),
+            Ordering::Greater => DatasetOrdering::Worse,
+            Ordering::Less => DatasetOrdering::Better,
+        }
+    }
+
     /// Potentially the same PTP grandmaster instance
     fn compare_same_identity(&self, other: &Self) -> DatasetOrdering {
         let steps_removed_difference = self.steps_removed as i32 - other.steps_removed as i32;
@@ -126,6 +208,32 @@ impl ComparisonDataset {
             }
         }
     }
+
+    /// The same grandmaster, compared by the remaining elements of the
+    /// priority vector of *IEEE 802.1AS-2020 section 10.3.4*. There is no
+    /// special treatment of paths that differ by a single step.
+    fn compare_same_identity_gptp(&self, other: &Self) -> DatasetOrdering {
+        let ordering = self
+            .steps_removed
+            .cmp(&other.steps_removed)
+            .then_with(|| self.identity_of_senders.cmp(&other.identity_of_senders))
+            .then_with(|| {
+                self.port_number_of_senders
+                    .cmp(&other.port_number_of_senders)
+            })
+            .then_with(|| {
+                Ord::cmp(
+                    &self.identity_of_receiver.port_number,
+                    &other.identity_of_receiver.port_number,
+                )
+            });
+
+        match ordering {
+            Ordering::Less => DatasetOrdering::BetterByTopology,
+            Ordering::Equal => DatasetOrdering::Error2,
+            Ordering::Greater => DatasetOrdering::WorseByTopology,
+        }
+    }
 }
 
 /// The ordering result of the dataset comparison algorithm
@@ -184,84 +292,206 @@ mod tests {
         a.gm_identity = IDENTITY_A;
         b.gm_identity = IDENTITY_B;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Better);
-        assert_eq!(b.compare(&a), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Worse);
 
         a.gm_priority_2 = 1;
         b.gm_priority_2 = 0;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Worse);
-        assert_eq!(b.compare(&a), DatasetOrdering::Better);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Better);
 
         a.gm_clock_quality.offset_scaled_log_variance = 0;
         b.gm_clock_quality.offset_scaled_log_variance = 1;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Better);
-        assert_eq!(b.compare(&a), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Worse);
 
         a.gm_clock_quality.clock_accuracy = ClockAccuracy::US1;
         b.gm_clock_quality.clock_accuracy = ClockAccuracy::NS1;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Worse);
-        assert_eq!(b.compare(&a), DatasetOrdering::Better);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Better);
 
         a.gm_clock_quality.clock_class = 0;
         b.gm_clock_quality.clock_class = 1;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Better);
-        assert_eq!(b.compare(&a), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Worse);
 
         a.gm_priority_1 = 1;
         b.gm_priority_1 = 0;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Worse);
-        assert_eq!(b.compare(&a), DatasetOrdering::Better);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Better);
     }
 
     #[test]
     fn figure_35() {
         let (mut a, mut b) = get_default_test_pair();
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Error2);
-        assert_eq!(b.compare(&a), DatasetOrdering::Error2);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Error2);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Error2);
 
         a.identity_of_receiver.port_number = 1;
         b.identity_of_receiver.port_number = 0;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::WorseByTopology);
-        assert_eq!(b.compare(&a), DatasetOrdering::BetterByTopology);
+        assert_eq!(
+            a.compare(&b, Profile::Default),
+            DatasetOrdering::WorseByTopology
+        );
+        assert_eq!(
+            b.compare(&a, Profile::Default),
+            DatasetOrdering::BetterByTopology
+        );
 
         a.identity_of_senders = IDENTITY_A;
         b.identity_of_senders = IDENTITY_B;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::BetterByTopology);
-        assert_eq!(b.compare(&a), DatasetOrdering::WorseByTopology);
+        assert_eq!(
+            a.compare(&b, Profile::Default),
+            DatasetOrdering::BetterByTopology
+        );
+        assert_eq!(
+            b.compare(&a, Profile::Default),
+            DatasetOrdering::WorseByTopology
+        );
 
         a.steps_removed = 0;
         a.identity_of_receiver.clock_identity = IDENTITY_A;
         b.steps_removed = 1;
         b.identity_of_receiver.clock_identity = IDENTITY_B;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Error1);
-        assert_eq!(b.compare(&a), DatasetOrdering::Error1);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Error1);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Error1);
 
         a.identity_of_receiver.clock_identity = IDENTITY_B;
         b.identity_of_receiver.clock_identity = IDENTITY_C;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::BetterByTopology);
-        assert_eq!(b.compare(&a), DatasetOrdering::WorseByTopology);
+        assert_eq!(
+            a.compare(&b, Profile::Default),
+            DatasetOrdering::BetterByTopology
+        );
+        assert_eq!(
+            b.compare(&a, Profile::Default),
+            DatasetOrdering::WorseByTopology
+        );
 
         // the inverse of the identity_of_senders
         a.identity_of_receiver.clock_identity = IDENTITY_B;
         b.identity_of_receiver.clock_identity = IDENTITY_A;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Better);
-        assert_eq!(b.compare(&a), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Worse);
 
         a.steps_removed = 0;
         b.steps_removed = 2;
 
-        assert_eq!(a.compare(&b), DatasetOrdering::Better);
-        assert_eq!(b.compare(&a), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, Profile::Default), DatasetOrdering::Worse);
+    }
+
+    #[test]
+    fn gptp_priority_vector() {
+        let profile = Profile::Gptp {
+            neighbor_prop_delay_threshold: Default::default(),
+        };
+        let (mut a, mut b) = get_default_test_pair();
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Error2);
+
+        a.identity_of_receiver.port_number = 1;
+        b.identity_of_receiver.port_number = 0;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::WorseByTopology);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::BetterByTopology);
+
+        a.port_number_of_senders = 1;
+        b.port_number_of_senders = 2;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::BetterByTopology);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::WorseByTopology);
+
+        a.identity_of_senders = IDENTITY_B;
+        b.identity_of_senders = IDENTITY_A;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::WorseByTopology);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::BetterByTopology);
+
+        // A single step difference is decided by steps removed alone, unlike
+        // figure 35 of IEEE1588-2019
+        a.steps_removed = 1;
+        b.steps_removed = 2;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::BetterByTopology);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::WorseByTopology);
+
+        // Different grandmasters are compared as in the default profile
+        a.gm_identity = IDENTITY_B;
+        b.gm_identity = IDENTITY_A;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Better);
+    }
+
+    #[test]
+    fn telecom_alternate_bmca() {
+        let profile = Profile::TelecomFullTiming {
+            local_priority: 128,
+        };
+        let (mut a, mut b) = get_default_test_pair();
+        a.gm_identity = IDENTITY_A;
+        b.gm_identity = IDENTITY_B;
+        a.gm_clock_quality.clock_class = 248;
+        b.gm_clock_quality.clock_class = 248;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Worse);
+
+        // Unlocked grandmasters are compared by identity, whatever the topology
+        a.steps_removed = 3;
+        b.steps_removed = 1;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Worse);
+
+        // But the nearest locked grandmaster wins
+        a.gm_clock_quality.clock_class = 6;
+        b.gm_clock_quality.clock_class = 6;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Better);
+
+        // With equal steps removed, by the identity of the senders
+        a.steps_removed = 1;
+        a.identity_of_senders = IDENTITY_A;
+        b.identity_of_senders = IDENTITY_B;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::BetterByTopology);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::WorseByTopology);
+
+        a.local_priority = 2;
+        b.local_priority = 1;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Better);
+
+        a.gm_priority_2 = 0;
+        b.gm_priority_2 = 1;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Better);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Worse);
+
+        a.gm_clock_quality.clock_class = 7;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Worse);
+        assert_eq!(b.compare(&a, profile), DatasetOrdering::Better);
+
+        // Priority 1 is ignored
+        a.gm_priority_1 = 0;
+        b.gm_priority_1 = 255;
+
+        assert_eq!(a.compare(&b, profile), DatasetOrdering::Worse);
+        assert_eq!(a.compare(&b, Profile::Default), DatasetOrdering::Better);
     }
 }
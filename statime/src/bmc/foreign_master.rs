@@ -5,7 +5,7 @@ use arrayvec::ArrayVec;
 use crate::{
     datastructures::{
         common::{PortIdentity, TimeInterval},
-        messages::{AnnounceMessage, Header},
+        messages::{AnnounceMessage, Header, PowerProfileTlv},
     },
     time::Duration,
 };
@@ -16,7 +16,7 @@ const FOREIGN_MASTER_TIME_WINDOW: u16 = 4;
 
 /// This is the amount of announce messages that must have been received within
 /// the time window for a foreign master to be valid
-const FOREIGN_MASTER_THRESHOLD: usize = 2;
+pub(crate) const FOREIGN_MASTER_THRESHOLD: usize = 2;
 
 /// The maximum amount of announce message to store within the time window
 const MAX_ANNOUNCE_MESSAGES: usize = 8;
@@ -35,14 +35,20 @@ pub struct ForeignMaster {
 pub(crate) struct ForeignAnnounceMessage {
     pub(crate) header: Header,
     pub(crate) message: AnnounceMessage,
+    pub(crate) power_profile: Option<PowerProfileTlv>,
     pub(crate) age: Duration,
 }
 
 impl ForeignMaster {
-    fn new(header: Header, announce_message: AnnounceMessage) -> Self {
+    fn new(
+        header: Header,
+        announce_message: AnnounceMessage,
+        power_profile: Option<PowerProfileTlv>,
+    ) -> Self {
         let message = ForeignAnnounceMessage {
             header,
             message: announce_message,
+            power_profile,
             age: Duration::ZERO,
         };
 
@@ -74,6 +80,7 @@ impl ForeignMaster {
         &mut self,
         header: Header,
         announce_message: AnnounceMessage,
+        power_profile: Option<PowerProfileTlv>,
         announce_interval: TimeInterval,
         age: Duration,
     ) {
@@ -82,6 +89,7 @@ impl ForeignMaster {
         let new_message = ForeignAnnounceMessage {
             header,
             message: announce_message,
+            power_profile,
             age,
         };
 
@@ -107,20 +115,26 @@ pub(crate) struct ForeignMasterList {
     foreign_masters: ArrayVec<ForeignMaster, MAX_FOREIGN_MASTERS>,
     own_port_announce_interval: TimeInterval,
     own_port_identity: PortIdentity,
+    qualification_threshold: usize,
 }
 
 impl ForeignMasterList {
     /// - `port_announce_interval`: The time interval derived from the
     ///   PortDS.log_announce_interval
     /// - `port_identity`: The identity of the port for which this list is used
+    /// - `qualification_threshold`: The number of messages a foreign master
+    ///   needs to exceed in the time window to be qualified, normally
+    ///   [FOREIGN_MASTER_THRESHOLD]
     pub(crate) fn new(
         own_port_announce_interval: TimeInterval,
         own_port_identity: PortIdentity,
+        qualification_threshold: usize,
     ) -> Self {
         Self {
             foreign_masters: ArrayVec::<ForeignMaster, MAX_FOREIGN_MASTERS>::new(),
             own_port_announce_interval,
             own_port_identity,
+            qualification_threshold,
         }
     }
 
@@ -143,10 +157,10 @@ impl ForeignMasterList {
         let mut qualified_foreign_masters = ArrayVec::<_, MAX_FOREIGN_MASTERS>::new();
 
         for i in (0..self.foreign_masters.len()).rev() {
-            // A foreign master must have at least FOREIGN_MASTER_THRESHOLD messages in the
+            // A foreign master must have at least qualification_threshold messages in the
             // last FOREIGN_MASTER_TIME_WINDOW to be qualified, so we filter out
             // any that don't have that
-            if self.foreign_masters[i].announce_messages.len() > FOREIGN_MASTER_THRESHOLD {
+            if self.foreign_masters[i].announce_messages.len() > self.qualification_threshold {
                 // Only the most recent announce message is qualified, so we remove that one
                 // from the list
                 let last_index = self.foreign_masters[i].announce_messages.len() - 1;
@@ -163,6 +177,7 @@ impl ForeignMasterList {
         &mut self,
         header: &Header,
         announce_message: &AnnounceMessage,
+        power_profile: Option<PowerProfileTlv>,
         age: Duration,
     ) {
         if !self.is_announce_message_qualified(announce_message) {
@@ -180,14 +195,18 @@ impl ForeignMasterList {
             foreign_master.register_announce_message(
                 *header,
                 *announce_message,
+                power_profile,
                 port_announce_interval,
                 age,
             );
         } else {
             // No, insert a new foreign master, if there is room in the array
             if self.foreign_masters.len() < MAX_FOREIGN_MASTERS {
-                self.foreign_masters
-                    .push(ForeignMaster::new(*header, *announce_message));
+                self.foreign_masters.push(ForeignMaster::new(
+                    *header,
+                    *announce_message,
+                    power_profile,
+                ));
             }
         }
     }
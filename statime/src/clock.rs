@@ -42,6 +42,10 @@ pub trait Clock {
     /// applied
     fn set_frequency(&mut self, ppm: f64) -> Result<Time, Self::Error>;
 
+    /// Get the frequency of the clock set last, in ppm difference from the
+    /// clocks base frequency.
+    fn get_frequency(&self) -> Result<f64, Self::Error>;
+
     /// Adjust the timescale properties of the clock, including
     /// things like the leap indicator, to the extend supported by the
     /// system.
@@ -1,13 +1,21 @@
-use crate::config::{ClockIdentity, SdoId};
 #[cfg(doc)]
-use crate::PtpInstance;
+use crate::{
+    config::{telecom, DelayMechanism, PortConfig},
+    filters::Filter,
+    PtpInstance,
+};
+use crate::{
+    config::{ClockIdentity, ClockQuality, SdoId},
+    observability::smpte::SynchronizationMetadata,
+    time::Duration,
+};
 
 /// Configuration for a [`PtpInstance`]
 ///
 /// # Example
 /// A configuration with common default values:
 /// ```
-/// # use statime::config::{ClockIdentity, InstanceConfig, SdoId};
+/// # use statime::config::{ClockIdentity, InstanceConfig, Profile, SdoId};
 /// let config = InstanceConfig {
 ///     clock_identity: ClockIdentity::from_mac_address([1,2,3,4,5,6]),
 ///     priority_1: 128,
@@ -15,6 +23,9 @@ use crate::PtpInstance;
 ///     domain_number: 0,
 ///     sdo_id: SdoId::default(),
 ///     slave_only: false,
+///     profile: Profile::Default,
+///     clock_quality: None,
+///     holdover: None,
 /// };
 /// ```
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -41,8 +52,168 @@ pub struct InstanceConfig {
     pub domain_number: u8,
 
     /// See [`InstanceConfig::domain_number`].
+    ///
+    /// This is ignored for the [`Profile::Gptp`] profile, which always uses
+    /// an sdo id of 0x100.
     pub sdo_id: SdoId,
 
     /// Whether this node may never become a master in the network
     pub slave_only: bool,
+
+    /// The PTP profile this [`PtpInstance`] follows
+    pub profile: Profile,
+
+    /// The quality of the clock of this instance, announced when it is the
+    /// grandmaster. `None` uses the default of the [`InstanceConfig::profile`].
+    ///
+    /// A slave only instance with one of the telecom profiles always uses
+    /// [`telecom::QUALITY_SLAVE_ONLY`].
+    pub clock_quality: Option<ClockQuality>,
+
+    /// How the announced clock quality degrades once no port is a slave
+    /// anymore, while the clock keeps running at the frequency it was steered
+    /// to. `None` keeps announcing [`InstanceConfig::clock_quality`].
+    pub holdover: Option<HoldoverConfig>,
+}
+
+/// The holdover of a [`PtpInstance`] that lost its master
+///
+/// The clock class announced in holdover is that of *IEEE 1588-2019 table 4*,
+/// or *ITU-T G.8275.1 table 2* for the telecom profiles:
+///
+/// * Within the [`HoldoverConfig::time_error_budget`], 7, or 14 for the ARB
+///   timescale. [`telecom::CLASS_BC_HOLDOVER`] for the telecom profiles.
+/// * Beyond it, the class of the [`HoldoverConfig::degradation`], or
+///   [`telecom::CLASS_BC_HOLDOVER_OUT_OF_SPEC`] for the telecom profiles.
+/// * After the [`HoldoverConfig::max_duration`], 248.
+///
+/// These classes claim the clock was locked to a primary reference, so they
+/// are only used when the lost grandmaster had clock class 6, or 13 for the
+/// ARB timescale. With the telecom profiles, a grandmaster with class
+/// [`telecom::CLASS_LOCKED`], [`telecom::CLASS_GM_HOLDOVER`] or
+/// [`telecom::CLASS_BC_HOLDOVER`] counts as well. Otherwise the instance keeps
+/// announcing [`InstanceConfig::clock_quality`].
+///
+/// An instance that loses its own reference as grandmaster, see
+/// [`PtpInstance::start_reference_holdover`], degrades the same way from its
+/// own clock quality. With the telecom profiles it uses the T-GM classes
+/// [`telecom::CLASS_GM_HOLDOVER`] and
+/// [`telecom::CLASS_GM_HOLDOVER_OUT_OF_SPEC_3`] instead.
+///
+/// A clock class of 127 or below keeps an instance from becoming a slave, so
+/// with the default profile a returning master is only used once the time
+/// error budget is exceeded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HoldoverConfig {
+    /// The time error the clock may accumulate while still within its holdover
+    /// specification. The time error is estimated from the uncertainty of the
+    /// filter of the port that was the slave, see
+    /// [`Filter::holdover_estimate`].
+    pub time_error_budget: Duration,
+    /// How long the clock is in holdover before it is considered free running
+    pub max_duration: Duration,
+    /// The clock class once the time error budget is exceeded
+    pub degradation: HoldoverDegradation,
+}
+
+impl Default for HoldoverConfig {
+    fn default() -> Self {
+        Self {
+            time_error_budget: Duration::from_micros(1),
+            max_duration: Duration::from_secs(24 * 60 * 60),
+            degradation: HoldoverDegradation::default(),
+        }
+    }
+}
+
+/// The degradation alternatives of *IEEE 1588-2019 table 4* for a clock out of
+/// its holdover specification
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum HoldoverDegradation {
+    /// Clock class 52, or 58 for the ARB timescale, which keeps the instance
+    /// from becoming a slave
+    AlternativeA,
+    /// Clock class 187, or 193 for the ARB timescale, which allows the
+    /// instance to become a slave
+    #[default]
+    AlternativeB,
+}
+
+/// A PTP profile, a set of rules for how the instance behaves on the network
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Profile {
+    /// The default profiles of *IEEE1588-2019 annex I*
+    #[default]
+    Default,
+    /// The generalized PTP profile of *IEEE 802.1AS-2020*
+    ///
+    /// Ports of an instance with this profile should use the
+    /// [`DelayMechanism::P2P`] delay mechanism. They only exchange time
+    /// information with their neighbor when it is gPTP capable (asCapable),
+    /// send all messages to the link local address, and carry the Follow_Up
+    /// information TLV in Follow_Up messages. The best master clock algorithm
+    /// compares masters using the priority vectors of *IEEE 802.1AS-2020
+    /// section 10.3.4*, and announce messages are qualified immediately.
+    Gptp {
+        /// Links with a larger mean delay are not considered gPTP capable
+        /// (neighborPropDelayThresh). The standard recommends 800 nanoseconds
+        /// for copper links.
+        neighbor_prop_delay_threshold: Duration,
+    },
+    /// The telecom profile with full timing support of *ITU-T G.8275.1*
+    ///
+    /// The best master clock algorithm uses the alternate algorithm of the
+    /// profile, which ignores priority 1 and prefers masters received on ports
+    /// with a lower [`PortConfig::local_priority`]. Ports with
+    /// [`PortConfig::master_only`] set are never slave (notSlave). The domain
+    /// number should be in [`telecom::FULL_TIMING_DOMAINS`], and priority 1 is always
+    /// 128. The default clock quality is [`telecom::QUALITY_FREE_RUNNING`],
+    /// or [`telecom::QUALITY_SLAVE_ONLY`] for a slave only instance.
+    TelecomFullTiming {
+        /// The local priority of this instance itself, used when comparing it
+        /// to other masters. Usually [`telecom::DEFAULT_LOCAL_PRIORITY`].
+        local_priority: u8,
+    },
+    /// The telecom profile with partial timing support of *ITU-T G.8275.2*
+    ///
+    /// This profile runs over unicast IPv4 or IPv6, possibly through network
+    /// elements that are not PTP aware. Its ports should use a
+    /// [`PortConfig::unicast_master_table`] with
+    /// [`PortConfig::unicast_negotiation`], and only accept announce messages
+    /// that were sent by unicast from a master in that table. The best master
+    /// clock algorithm, priority 1 and clock quality are the same as those of
+    /// [`Profile::TelecomFullTiming`]. The domain number should be in
+    /// [`telecom::PARTIAL_TIMING_DOMAINS`].
+    TelecomPartialTiming {
+        /// The local priority of this instance itself, used when comparing it
+        /// to other masters. Usually [`telecom::DEFAULT_LOCAL_PRIORITY`].
+        local_priority: u8,
+    },
+    /// The power profile of *IEEE C37.238-2017*
+    ///
+    /// Ports of an instance with this profile should use the
+    /// [`DelayMechanism::P2P`] delay mechanism over ethernet, usually with
+    /// VLAN tags. Announce messages carry the IEEE_C37_238 TLV, which
+    /// identifies the grandmaster and accumulates the time inaccuracy of
+    /// every clock on the path from the grandmaster. Announce messages
+    /// without a valid TLV are ignored. The received values are available
+    /// in the [`ParentDS`](`crate::observability::parent::ParentDS`).
+    Power {
+        /// The grandmasterID this instance sends when it is the grandmaster
+        grandmaster_id: u16,
+        /// The worst case inaccuracy in nanoseconds of the time of this
+        /// instance as grandmaster, or that it adds as boundary clock
+        time_inaccuracy: u32,
+    },
+    /// The broadcast profile of *SMPTE ST 2059-2*
+    ///
+    /// As master, this instance periodically sends the synchronization
+    /// metadata TLV in a management message along with its announce messages.
+    /// As slave, it accepts that TLV from its parent, and makes the metadata
+    /// available through [`PtpInstance::smpte_metadata`]. The best master
+    /// clock algorithm is that of the default profile.
+    Smpte2059 {
+        /// The metadata this instance sends when it is the grandmaster
+        metadata: SynchronizationMetadata,
+    },
 }
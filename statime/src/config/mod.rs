@@ -7,19 +7,31 @@
 //! Configurations for a [`Port`](`crate::port::Port`):
 //! * [`PortConfig`]
 //!
-//! And types used within those configurations.
+//! Configuration for a
+//! [`TransparentClock`](`crate::transparent_clock::TransparentClock`):
+//! * [`TransparentClockConfig`]
+//!
+//! And types used within those configurations. Values specific to the
+//! telecom profile are found in [`telecom`].
 
 mod instance;
 mod port;
+pub mod telecom;
+mod transparent_clock;
 
-pub use instance::InstanceConfig;
-pub use port::{DelayMechanism, PortConfig};
+pub use instance::{HoldoverConfig, HoldoverDegradation, InstanceConfig, Profile};
+pub use port::{DelayMechanism, PortConfig, UnicastNegotiationConfig, MAX_UNICAST_MASTERS};
+pub use transparent_clock::{TransparentClockConfig, TransparentClockConfigError};
 
 pub use crate::{
     bmc::acceptable_master::{AcceptAnyMaster, AcceptableMasterList},
     datastructures::{
-        common::{ClockAccuracy, ClockIdentity, ClockQuality, LeapIndicator, TimeSource},
+        common::{
+            ClockAccuracy, ClockIdentity, ClockQuality, LeapIndicator, PortAddress, PortIdentity,
+            TimeSource,
+        },
         datasets::TimePropertiesDS,
         messages::SdoId,
     },
+    observability::smpte::{MasterLockingStatus, SynchronizationMetadata},
 };
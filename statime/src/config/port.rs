@@ -1,8 +1,15 @@
+use arrayvec::ArrayVec;
 use rand::Rng;
 
-use crate::time::{Duration, Interval};
 #[cfg(doc)]
-use crate::{config::AcceptableMasterList, port::Port};
+use crate::{
+    config::{telecom, AcceptableMasterList, Profile},
+    port::Port,
+};
+use crate::{
+    datastructures::common::PortAddress,
+    time::{Duration, Interval},
+};
 
 /// Which delay mechanism a port is using.
 ///
@@ -24,9 +31,31 @@ pub enum DelayMechanism {
     // No support for other delay mechanisms
 }
 
+/// Maximum number of entries in the unicast master table of a [`Port`]
+pub const MAX_UNICAST_MASTERS: usize = 8;
+
+/// Configuration of unicast negotiation on a [`Port`]
+///
+/// A port using unicast negotiation only sends announce, sync and delay
+/// response messages to ports that were granted them, and requests these
+/// messages from the ports in its
+/// [`unicast_master_table`](`PortConfig::unicast_master_table`).
+///
+/// For more details see *IEEE1588-2019 section 16.1*
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnicastNegotiationConfig {
+    /// The duration in seconds of the grants this [`Port`] requests, and the
+    /// maximum duration of the grants it gives out.
+    ///
+    /// The standard recommends a value between 10 and 1000 seconds, the
+    /// partial timing telecom profile allows
+    /// [`telecom::PARTIAL_TIMING_GRANT_DURATIONS`].
+    pub grant_duration: u32,
+}
+
 /// Configuration items of the PTP PortDS dataset. Dynamical fields are kept
 /// as part of [crate::port::Port].
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct PortConfig<A> {
     /// A list that contains all nodes that this [`Port`] will accept as a
     /// master.
@@ -48,10 +77,55 @@ pub struct PortConfig<A> {
     pub sync_interval: Interval,
 
     /// Never let this [`Port`] become a slave.
+    ///
+    /// This is called notSlave in the telecom profiles.
     pub master_only: bool,
 
+    /// The local priority of this [`Port`], only used by the best master
+    /// clock algorithm of [`Profile::TelecomFullTiming`].
+    ///
+    /// Masters received on ports with a lower value are preferred over those
+    /// of equal quality received on other ports. The default is
+    /// [`telecom::DEFAULT_LOCAL_PRIORITY`].
+    pub local_priority: u8,
+
     /// The estimated asymmetry in the link connected to this [`Port`]
     pub delay_asymmetry: Duration,
+
+    /// Send one-step Sync and Pdelay_Resp messages instead of following them
+    /// up with the precise transmit time.
+    ///
+    /// One-step messages carry the time of the clock of this [`Port`] when the
+    /// message is created, so the timestamps are only as accurate as the delay
+    /// between creating and sending the message. Network cards that insert
+    /// the transmit time themselves are not supported, as they expect
+    /// different contents of the messages.
+    pub one_step: bool,
+
+    /// Exchange delay requests and responses over unicast while the other
+    /// messages are multicast, as in the enterprise profile.
+    ///
+    /// A slave sends its delay requests to the address the sync messages of
+    /// its master come from, and a master responds to delay requests at the
+    /// address they came from. This requires the network stack to report
+    /// source addresses using [`Port::handle_event_receive_from`].
+    pub hybrid_e2e: bool,
+
+    /// The addresses of the ports this [`Port`] communicates with over
+    /// unicast instead of multicast, see *IEEE1588-2019 section 17.6*.
+    ///
+    /// Without unicast negotiation, the messages this [`Port`] would multicast
+    /// are sent to every port in the table instead, and delay responses are
+    /// only sent to ports in the table. With unicast negotiation, this
+    /// [`Port`] requests announce messages from every port in the table.
+    ///
+    /// An empty table with no unicast negotiation configured means this
+    /// [`Port`] uses multicast.
+    pub unicast_master_table: ArrayVec<PortAddress, MAX_UNICAST_MASTERS>,
+
+    /// Use unicast negotiation instead of multicast for announce, sync and
+    /// delay response messages
+    pub unicast_negotiation: Option<UnicastNegotiationConfig>,
     // Notes:
     // Fields specific for delay mechanism are kept as part of [DelayMechanism].
     // Version is always 2.1, so not stored (versionNumber, minorVersionNumber)
@@ -0,0 +1,102 @@
+//! Values defined by the ITU-T G.8275.1 and G.8275.2 telecom profiles
+//!
+//! These are meant for use with [`Profile::TelecomFullTiming`] and
+//! [`Profile::TelecomPartialTiming`]. The clock classes are those of *ITU-T
+//! G.8275.1 table 2*, the clock qualities those of *ITU-T G.8275.1 section
+//! 6.4*. G.8275.2 uses the same values.
+
+use core::ops::RangeInclusive;
+
+#[cfg(doc)]
+use crate::config::{InstanceConfig, PortConfig, Profile, UnicastNegotiationConfig};
+use crate::{
+    config::{ClockAccuracy, ClockQuality},
+    time::Interval,
+};
+
+/// The domain numbers allowed by the full timing profile
+pub const FULL_TIMING_DOMAINS: RangeInclusive<u8> = 24..=43;
+
+/// The default domain number of the full timing profile
+pub const FULL_TIMING_DEFAULT_DOMAIN: u8 = 24;
+
+/// The domain numbers allowed by the partial timing profile
+pub const PARTIAL_TIMING_DOMAINS: RangeInclusive<u8> = 44..=63;
+
+/// The default domain number of the partial timing profile
+pub const PARTIAL_TIMING_DEFAULT_DOMAIN: u8 = 44;
+
+/// The default announce interval of the partial timing profile, one message
+/// per second. The profile allows between 1 and 8 messages per second.
+pub const PARTIAL_TIMING_ANNOUNCE_INTERVAL: Interval = Interval::from_log_2(0);
+
+/// The default sync interval of the partial timing profile, 16 messages per
+/// second. The profile allows between 1 and 128 messages per second.
+pub const PARTIAL_TIMING_SYNC_INTERVAL: Interval = Interval::from_log_2(-4);
+
+/// The default delay request interval of the partial timing profile, 16
+/// messages per second. The profile allows between 1 and 128 messages per
+/// second.
+pub const PARTIAL_TIMING_DELAY_INTERVAL: Interval = Interval::from_log_2(-4);
+
+/// The [`UnicastNegotiationConfig::grant_duration`] values allowed by the
+/// partial timing profile, narrower than the 10 to 1000 seconds recommended
+/// by IEEE 1588
+pub const PARTIAL_TIMING_GRANT_DURATIONS: RangeInclusive<u32> = 60..=1000;
+
+/// The default [`UnicastNegotiationConfig::grant_duration`] of the partial
+/// timing profile
+pub const PARTIAL_TIMING_GRANT_DURATION: u32 = 300;
+
+/// The default value of [`PortConfig::local_priority`] and of the local
+/// priority of the telecom profiles
+pub const DEFAULT_LOCAL_PRIORITY: u8 = 128;
+
+/// A telecom grandmaster (T-GM) locked to its primary reference time clock
+pub const CLASS_LOCKED: u8 = 6;
+
+/// A T-GM in holdover, within its holdover specification
+pub const CLASS_GM_HOLDOVER: u8 = 7;
+
+/// A telecom boundary clock (T-BC) in holdover, within its holdover
+/// specification
+pub const CLASS_BC_HOLDOVER: u8 = 135;
+
+/// A T-GM in holdover, out of its holdover specification, category 1
+pub const CLASS_GM_HOLDOVER_OUT_OF_SPEC_1: u8 = 140;
+
+/// A T-GM in holdover, out of its holdover specification, category 2
+pub const CLASS_GM_HOLDOVER_OUT_OF_SPEC_2: u8 = 150;
+
+/// A T-GM in holdover, out of its holdover specification, category 3
+pub const CLASS_GM_HOLDOVER_OUT_OF_SPEC_3: u8 = 160;
+
+/// A T-BC in holdover, out of its holdover specification
+pub const CLASS_BC_HOLDOVER_OUT_OF_SPEC: u8 = 165;
+
+/// A T-GM or T-BC without time reference, in free run
+pub const CLASS_FREE_RUNNING: u8 = 248;
+
+/// A clock that can only be a slave, see [`InstanceConfig::slave_only`]
+pub const CLASS_SLAVE_ONLY: u8 = 255;
+
+/// The clock quality of a T-GM locked to its primary reference time clock
+pub const QUALITY_LOCKED: ClockQuality = ClockQuality {
+    clock_class: CLASS_LOCKED,
+    clock_accuracy: ClockAccuracy::NS100,
+    offset_scaled_log_variance: 0x4e5d,
+};
+
+/// The clock quality of a free running clock
+pub const QUALITY_FREE_RUNNING: ClockQuality = ClockQuality {
+    clock_class: CLASS_FREE_RUNNING,
+    clock_accuracy: ClockAccuracy::Unknown,
+    offset_scaled_log_variance: 0xffff,
+};
+
+/// The clock quality of a slave only clock
+pub const QUALITY_SLAVE_ONLY: ClockQuality = ClockQuality {
+    clock_class: CLASS_SLAVE_ONLY,
+    clock_accuracy: ClockAccuracy::Unknown,
+    offset_scaled_log_variance: 0xffff,
+};
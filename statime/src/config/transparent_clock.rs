@@ -0,0 +1,72 @@
+#[cfg(doc)]
+use crate::transparent_clock::TransparentClock;
+use crate::{
+    config::{ClockIdentity, DelayMechanism, SdoId},
+    transparent_clock::MAX_TRANSPARENT_CLOCK_PORTS,
+};
+
+/// Configuration for a [`TransparentClock`]
+///
+/// # Example
+/// A configuration for a peer-to-peer transparent clock with four ports:
+/// ```
+/// # use statime::config::{ClockIdentity, DelayMechanism, SdoId, TransparentClockConfig};
+/// # use statime::time::Interval;
+/// let config = TransparentClockConfig {
+///     clock_identity: ClockIdentity::from_mac_address([1,2,3,4,5,6]),
+///     number_ports: 4,
+///     domain_number: 0,
+///     sdo_id: SdoId::default(),
+///     delay_mechanism: DelayMechanism::P2P {
+///         interval: Interval::ONE_SECOND,
+///     },
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TransparentClockConfig {
+    /// The unique identifier for this device within the PTP network.
+    ///
+    /// The ports of the transparent clock use this identity, together with
+    /// their index plus one as port number, in the messages they originate.
+    pub clock_identity: ClockIdentity,
+
+    /// The number of ports of the transparent clock, at most
+    /// [`MAX_TRANSPARENT_CLOCK_PORTS`]
+    pub number_ports: usize,
+
+    /// The domain used in messages originated by the transparent clock
+    pub domain_number: u8,
+
+    /// See [`TransparentClockConfig::domain_number`].
+    pub sdo_id: SdoId,
+
+    /// The delay mechanism the transparent clock supports.
+    ///
+    /// An end-to-end transparent clock forwards delay requests and responses,
+    /// the interval is not used in that case. A peer-to-peer transparent clock
+    /// measures the delay of the link on each of its ports by sending peer
+    /// delay requests at the given interval.
+    pub delay_mechanism: DelayMechanism,
+}
+
+/// Error returned when a [`TransparentClock`] cannot be created from a
+/// [`TransparentClockConfig`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TransparentClockConfigError {
+    /// More than [`MAX_TRANSPARENT_CLOCK_PORTS`] ports are configured
+    TooManyPorts,
+}
+
+impl core::fmt::Display for TransparentClockConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TransparentClockConfigError::TooManyPorts => write!(
+                f,
+                "a transparent clock supports at most {MAX_TRANSPARENT_CLOCK_PORTS} ports"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransparentClockConfigError {}
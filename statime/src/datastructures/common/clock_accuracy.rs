@@ -1,6 +1,8 @@
 use core::cmp::Ordering;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// How accurate the underlying clock device is expected to be when not
 /// synchronized.
@@ -104,7 +106,8 @@ impl ClockAccuracy {
         }
     }
 
-    pub(crate) fn from_primitive(value: u8) -> Self {
+    /// Converts u8 literals to the enum
+    pub fn from_primitive(value: u8) -> Self {
         match value {
             0x00..=0x16 | 0x32..=0x7f | 0xff => Self::Reserved,
             0x17 => Self::PS1,
@@ -143,6 +146,49 @@ impl ClockAccuracy {
     pub(crate) fn cmp_numeric(&self, other: &Self) -> Ordering {
         self.to_primitive().cmp(&other.to_primitive())
     }
+
+    /// The best accuracy that still includes a time error of `error`
+    pub(crate) fn from_time_error(error: Duration) -> Self {
+        const BOUNDS: [(f64, ClockAccuracy); 24] = [
+            (1e-3, ClockAccuracy::PS1),
+            (2.5e-3, ClockAccuracy::PS2_5),
+            (10e-3, ClockAccuracy::PS10),
+            (25e-3, ClockAccuracy::PS25),
+            (100e-3, ClockAccuracy::PS100),
+            (250e-3, ClockAccuracy::PS250),
+            (1.0, ClockAccuracy::NS1),
+            (2.5, ClockAccuracy::NS2_5),
+            (10.0, ClockAccuracy::NS10),
+            (25.0, ClockAccuracy::NS25),
+            (100.0, ClockAccuracy::NS100),
+            (250.0, ClockAccuracy::NS250),
+            (1e3, ClockAccuracy::US1),
+            (2.5e3, ClockAccuracy::US2_5),
+            (10e3, ClockAccuracy::US10),
+            (25e3, ClockAccuracy::US25),
+            (100e3, ClockAccuracy::US100),
+            (250e3, ClockAccuracy::US250),
+            (1e6, ClockAccuracy::MS1),
+            (2.5e6, ClockAccuracy::MS2_5),
+            (10e6, ClockAccuracy::MS10),
+            (25e6, ClockAccuracy::MS25),
+            (100e6, ClockAccuracy::MS100),
+            (250e6, ClockAccuracy::MS250),
+        ];
+
+        let nanos = error.abs().nanos_lossy();
+        BOUNDS
+            .iter()
+            .find(|(bound, _)| nanos <= *bound)
+            .map(|(_, accuracy)| *accuracy)
+            .unwrap_or(if nanos <= 1e9 {
+                ClockAccuracy::S1
+            } else if nanos <= 10e9 {
+                ClockAccuracy::S10
+            } else {
+                ClockAccuracy::SGT10
+            })
+    }
 }
 
 impl Default for ClockAccuracy {
@@ -175,4 +221,20 @@ mod tests {
 
         assert_eq!(a.cmp_numeric(&b), Ordering::Less);
     }
+
+    #[test]
+    fn from_time_error() {
+        let accuracy = |nanos| ClockAccuracy::from_time_error(Duration::from_nanos(nanos));
+        assert_eq!(accuracy(0), ClockAccuracy::PS1);
+        assert_eq!(accuracy(1), ClockAccuracy::NS1);
+        assert_eq!(accuracy(2), ClockAccuracy::NS2_5);
+        assert_eq!(accuracy(100), ClockAccuracy::NS100);
+        assert_eq!(accuracy(-101), ClockAccuracy::NS250);
+        assert_eq!(accuracy(1_000_000_000), ClockAccuracy::S1);
+        assert_eq!(accuracy(10_000_000_001), ClockAccuracy::SGT10);
+        assert_eq!(
+            ClockAccuracy::from_time_error(Duration::from_fixed_nanos(0.002)),
+            ClockAccuracy::PS2_5
+        );
+    }
 }
@@ -1,8 +1,13 @@
 use super::clock_accuracy::ClockAccuracy;
-use crate::datastructures::{WireFormat, WireFormatError};
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
+use crate::{
+    datastructures::{WireFormat, WireFormatError},
+    time::Duration,
+};
 
 /// A description of the accuracy and type of a clock.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClockQuality {
     /// The PTP clock class.
@@ -37,6 +42,17 @@ impl Default for ClockQuality {
     }
 }
 
+impl ClockQuality {
+    /// The offset scaled log variance of a clock whose time has a standard
+    /// deviation of `deviation`, see *IEEE1588-2019 section 7.6.3.3*
+    pub(crate) fn scaled_log_variance(deviation: Duration) -> u16 {
+        // 0xffff is reserved for a variance that is too large or unknown
+        let log_variance = 2.0 * deviation.abs().seconds().log2();
+        // Rounded to the nearest integer
+        (log_variance * 256.0 + 32768.5).clamp(0.0, 65534.0) as u16
+    }
+}
+
 impl WireFormat for ClockQuality {
     fn wire_size(&self) -> usize {
         4
@@ -62,6 +78,27 @@ impl WireFormat for ClockQuality {
 mod tests {
     use super::*;
 
+    #[test]
+    fn scaled_log_variance() {
+        // The estimate of the default, a deviation of 2^-11.5 seconds
+        let deviation = Duration::from_seconds(2.0f64.powf(-11.5));
+        assert_eq!(
+            ClockQuality::scaled_log_variance(deviation),
+            ClockQuality::default().offset_scaled_log_variance
+        );
+
+        assert_eq!(
+            ClockQuality::scaled_log_variance(Duration::from_secs(1)),
+            0x8000
+        );
+        assert_eq!(ClockQuality::scaled_log_variance(Duration::ZERO), 0);
+        // log2(1e-6) * 256 is -5102.49
+        assert_eq!(
+            ClockQuality::scaled_log_variance(Duration::from_millis(1)),
+            0x8000 - 5102
+        );
+    }
+
     #[test]
     fn timestamp_wireformat() {
         let representations = [(
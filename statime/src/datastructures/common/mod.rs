@@ -4,6 +4,7 @@ mod clock_accuracy;
 mod clock_identity;
 mod clock_quality;
 mod leap_indicator;
+mod port_address;
 mod port_identity;
 mod time_interval;
 mod time_source;
@@ -14,7 +15,8 @@ pub use clock_accuracy::*;
 pub use clock_identity::*;
 pub use clock_quality::*;
 pub use leap_indicator::*;
-pub(crate) use port_identity::*;
+pub use port_address::*;
+pub use port_identity::*;
 pub(crate) use time_interval::*;
 pub use time_source::*;
 pub use timestamp::*;
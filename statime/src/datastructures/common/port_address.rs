@@ -0,0 +1,26 @@
+/// The network address of a PTP port
+///
+/// Used to direct messages to a single port instead of to the multicast
+/// address of the transport, for example when using unicast negotiation.
+///
+/// For more details see *IEEE1588-2019 section 5.3.6*
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortAddress {
+    /// An address of a port using the UDP/IPv4 transport (annex C)
+    Ipv4([u8; 4]),
+    /// An address of a port using the UDP/IPv6 transport (annex D)
+    Ipv6([u8; 16]),
+    /// An address of a port using the IEEE 802.3 transport (annex E)
+    Ethernet([u8; 6]),
+}
+
+#[cfg(feature = "std")]
+impl From<std::net::IpAddr> for PortAddress {
+    fn from(value: std::net::IpAddr) -> Self {
+        match value {
+            std::net::IpAddr::V4(address) => Self::Ipv4(address.octets()),
+            std::net::IpAddr::V6(address) => Self::Ipv6(address.octets()),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    config::InstanceConfig,
+    config::{telecom, InstanceConfig, Profile},
     datastructures::{
         common::{ClockIdentity, ClockQuality},
         messages::SdoId,
@@ -16,6 +16,7 @@ use crate::{
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub(crate) struct InternalDefaultDS {
     pub(crate) clock_identity: ClockIdentity,
+    pub(crate) two_step: bool,
     pub(crate) number_ports: u16,
     pub(crate) clock_quality: ClockQuality,
     pub(crate) priority_1: u8,
@@ -23,19 +24,51 @@ pub(crate) struct InternalDefaultDS {
     pub(crate) domain_number: u8,
     pub(crate) slave_only: bool,
     pub(crate) sdo_id: SdoId,
+    pub(crate) profile: Profile,
 }
 
 impl InternalDefaultDS {
     pub(crate) fn new(config: InstanceConfig) -> Self {
+        let (clock_quality, priority_1) = match config.profile {
+            Profile::Default
+            | Profile::Gptp { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => {
+                (config.clock_quality.unwrap_or_default(), config.priority_1)
+            }
+            Profile::TelecomFullTiming { .. } | Profile::TelecomPartialTiming { .. }
+                if config.slave_only =>
+            {
+                (telecom::QUALITY_SLAVE_ONLY, 128)
+            }
+            Profile::TelecomFullTiming { .. } | Profile::TelecomPartialTiming { .. } => (
+                config
+                    .clock_quality
+                    .unwrap_or(telecom::QUALITY_FREE_RUNNING),
+                128,
+            ),
+        };
+
         Self {
             clock_identity: config.clock_identity,
+            // Until ports are added that are all one-step
+            two_step: true,
             number_ports: 0,
-            clock_quality: Default::default(),
-            priority_1: config.priority_1,
+            clock_quality,
+            priority_1,
             priority_2: config.priority_2,
             domain_number: config.domain_number,
             slave_only: config.slave_only,
-            sdo_id: config.sdo_id,
+            sdo_id: match config.profile {
+                Profile::Default
+                | Profile::TelecomFullTiming { .. }
+                | Profile::TelecomPartialTiming { .. }
+                | Profile::Power { .. }
+                | Profile::Smpte2059 { .. } => config.sdo_id,
+                // The transportSpecific field of 802.1AS is the major sdo id
+                Profile::Gptp { .. } => SdoId::GPTP,
+            },
+            profile: config.profile,
         }
     }
 }
@@ -1,5 +1,11 @@
 use super::InternalDefaultDS;
-use crate::datastructures::common::{ClockIdentity, ClockQuality, PortIdentity};
+use crate::{
+    datastructures::{
+        common::{ClockIdentity, ClockQuality, PortIdentity},
+        messages::PowerProfileTlv,
+    },
+    observability::smpte::SynchronizationMetadata,
+};
 
 // TODO: Discuss moving this (and TimePropertiesDS, ...) to slave?
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -12,6 +18,11 @@ pub(crate) struct InternalParentDS {
     pub(crate) grandmaster_clock_quality: ClockQuality,
     pub(crate) grandmaster_priority_1: u8,
     pub(crate) grandmaster_priority_2: u8,
+    // The IEEE_C37_238 TLV of the parent, our own as grandmaster
+    pub(crate) power_profile: Option<PowerProfileTlv>,
+    // The SMPTE synchronization metadata of the grandmaster, our own as
+    // grandmaster
+    pub(crate) smpte_metadata: Option<SynchronizationMetadata>,
 }
 
 impl InternalParentDS {
@@ -28,6 +39,8 @@ impl InternalParentDS {
             grandmaster_clock_quality: default_ds.clock_quality,
             grandmaster_priority_1: default_ds.priority_1,
             grandmaster_priority_2: default_ds.priority_2,
+            power_profile: PowerProfileTlv::for_grandmaster(default_ds.profile),
+            smpte_metadata: SynchronizationMetadata::for_grandmaster(default_ds.profile),
         }
     }
 }
@@ -1,8 +1,13 @@
 use super::Header;
-use crate::datastructures::{
-    common::{ClockIdentity, ClockQuality, LeapIndicator, TimeSource, WireTimestamp},
-    datasets::TimePropertiesDS,
-    WireFormat, WireFormatError,
+use crate::{
+    config::Profile,
+    datastructures::{
+        common::{
+            ClockIdentity, ClockQuality, LeapIndicator, TimeSource, Tlv, TlvType, WireTimestamp,
+        },
+        datasets::TimePropertiesDS,
+        WireFormat, WireFormatError,
+    },
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -87,11 +92,124 @@ impl AnnounceMessage {
     }
 }
 
+/// The IEEE_C37_238 TLV of *IEEE C37.238-2017*
+///
+/// Carried in the announce messages of the power profile. Identifies the
+/// grandmaster and accumulates the worst case time inaccuracy of the
+/// grandmaster and every clock between it and the receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PowerProfileTlv {
+    pub(crate) grandmaster_id: u16,
+    /// In nanoseconds
+    pub(crate) total_time_inaccuracy: u32,
+}
+
+impl PowerProfileTlv {
+    const ORGANIZATION_ID: [u8; 3] = [0x1c, 0x12, 0x9d];
+    const ORGANIZATION_SUB_TYPE: [u8; 3] = [0x00, 0x00, 0x02];
+    pub(crate) const WIRE_SIZE: usize = 16;
+
+    /// The TLV a grandmaster with the given profile sends, `None` when the
+    /// profile is not the power profile
+    pub(crate) fn for_grandmaster(profile: Profile) -> Option<Self> {
+        match profile {
+            Profile::Power {
+                grandmaster_id,
+                time_inaccuracy,
+            } => Some(Self {
+                grandmaster_id,
+                total_time_inaccuracy: time_inaccuracy,
+            }),
+            _ => None,
+        }
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        if buffer.len() < Self::WIRE_SIZE {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
+        buffer[0..3].copy_from_slice(&Self::ORGANIZATION_ID);
+        buffer[3..6].copy_from_slice(&Self::ORGANIZATION_SUB_TYPE);
+        buffer[6..8].copy_from_slice(&self.grandmaster_id.to_be_bytes());
+        // reserved
+        buffer[8..12].fill(0);
+        buffer[12..16].copy_from_slice(&self.total_time_inaccuracy.to_be_bytes());
+
+        Ok(())
+    }
+
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..Self::WIRE_SIZE)
+            .ok_or(WireFormatError::BufferTooShort)?;
+        self.serialize(value)?;
+
+        Ok(Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&*value).into(),
+        })
+    }
+
+    /// Parse a TLV, returns `None` for TLVs that are not an IEEE_C37_238 TLV
+    /// or are malformed
+    pub(crate) fn from_tlv(tlv: &Tlv<'_>) -> Option<Self> {
+        let value = &tlv.value;
+        if tlv.tlv_type != TlvType::OrganizationExtension
+            || value.len() != Self::WIRE_SIZE
+            || value[0..3] != Self::ORGANIZATION_ID
+            || value[3..6] != Self::ORGANIZATION_SUB_TYPE
+        {
+            return None;
+        }
+
+        Some(Self {
+            grandmaster_id: u16::from_be_bytes(value[6..8].try_into().ok()?),
+            total_time_inaccuracy: u32::from_be_bytes(value[12..16].try_into().ok()?),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::datastructures::common::ClockAccuracy;
 
+    #[test]
+    fn power_profile_tlv_wireformat() {
+        let object = PowerProfileTlv {
+            grandmaster_id: 0x0102,
+            total_time_inaccuracy: 1000,
+        };
+        let bytes = [
+            0x1c, 0x12, 0x9d, 0x00, 0x00, 0x02, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x03, 0xe8,
+        ];
+
+        let mut buffer = [0; 16];
+        let tlv = object.to_tlv(&mut buffer).unwrap();
+        assert_eq!(tlv.tlv_type, TlvType::OrganizationExtension);
+        assert_eq!(&*tlv.value, &bytes[..]);
+        assert_eq!(PowerProfileTlv::from_tlv(&tlv), Some(object));
+
+        // The TLV of C37.238-2011 has a different sub type and length
+        let mut legacy = [0; 18];
+        legacy[..16].copy_from_slice(&bytes);
+        legacy[5] = 0x01;
+        let tlv = Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&legacy[..]).into(),
+        };
+        assert_eq!(PowerProfileTlv::from_tlv(&tlv), None);
+
+        // Truncated TLVs are malformed
+        let tlv = Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&bytes[..12]).into(),
+        };
+        assert_eq!(PowerProfileTlv::from_tlv(&tlv), None);
+    }
+
     #[test]
     fn announce_wireformat() {
         let representations = [(
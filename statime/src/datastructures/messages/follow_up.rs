@@ -1,4 +1,9 @@
-use crate::datastructures::{common::WireTimestamp, WireFormat, WireFormatError};
+use crate::datastructures::{
+    common::{Tlv, TlvType, WireTimestamp},
+    WireFormat, WireFormatError,
+};
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct FollowUpMessage {
@@ -27,10 +32,142 @@ impl FollowUpMessage {
     }
 }
 
+/// The Follow_Up information TLV of *IEEE 802.1AS-2020 section 11.4.4.3*
+///
+/// Carries the rate of the grandmaster relative to the clock that sent the
+/// Follow_Up, and information about changes of the grandmaster time base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct FollowUpInformationTlv {
+    /// The rate ratio to the grandmaster minus one, in units of 2^-41
+    pub(crate) cumulative_scaled_rate_offset: i32,
+    pub(crate) gm_time_base_indicator: u16,
+    /// A 96 bit value in units of 2^-16 nanoseconds
+    pub(crate) last_gm_phase_change: i128,
+    pub(crate) scaled_last_gm_freq_change: i32,
+}
+
+impl FollowUpInformationTlv {
+    const ORGANIZATION_ID: [u8; 3] = [0x00, 0x80, 0xc2];
+    const ORGANIZATION_SUB_TYPE: [u8; 3] = [0x00, 0x00, 0x01];
+    pub(crate) const WIRE_SIZE: usize = 28;
+
+    /// The unit of the cumulative scaled rate offset, 2^-41
+    const RATE_OFFSET_SCALE: f64 = (1u64 << 41) as f64;
+
+    /// The TLV of a clock whose rate ratio to the grandmaster is `rate_ratio`
+    pub(crate) fn from_rate_ratio(rate_ratio: f64) -> Self {
+        Self {
+            cumulative_scaled_rate_offset: ((rate_ratio - 1.0) * Self::RATE_OFFSET_SCALE).round()
+                as i32,
+            ..Default::default()
+        }
+    }
+
+    /// The ratio of the frequency of the grandmaster to that of the clock
+    /// that sent the Follow_Up
+    pub(crate) fn rate_ratio(&self) -> f64 {
+        1.0 + self.cumulative_scaled_rate_offset as f64 / Self::RATE_OFFSET_SCALE
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        if buffer.len() < Self::WIRE_SIZE {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
+        buffer[0..3].copy_from_slice(&Self::ORGANIZATION_ID);
+        buffer[3..6].copy_from_slice(&Self::ORGANIZATION_SUB_TYPE);
+        buffer[6..10].copy_from_slice(&self.cumulative_scaled_rate_offset.to_be_bytes());
+        buffer[10..12].copy_from_slice(&self.gm_time_base_indicator.to_be_bytes());
+        buffer[12..24].copy_from_slice(&self.last_gm_phase_change.to_be_bytes()[4..]);
+        buffer[24..28].copy_from_slice(&self.scaled_last_gm_freq_change.to_be_bytes());
+
+        Ok(())
+    }
+
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..Self::WIRE_SIZE)
+            .ok_or(WireFormatError::BufferTooShort)?;
+        self.serialize(value)?;
+
+        Ok(Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&*value).into(),
+        })
+    }
+
+    /// Parse a TLV, returns `None` for TLVs that are not a Follow_Up
+    /// information TLV or are malformed
+    pub(crate) fn from_tlv(tlv: &Tlv<'_>) -> Option<Self> {
+        let value = &tlv.value;
+        if tlv.tlv_type != TlvType::OrganizationExtension
+            || value.get(0..3)? != Self::ORGANIZATION_ID
+            || value.get(3..6)? != Self::ORGANIZATION_SUB_TYPE
+        {
+            return None;
+        }
+
+        // Sign extend the 96 bit phase change
+        let phase_change = value.get(12..24)?;
+        let mut phase_change_bytes = [if phase_change[0] & 0x80 != 0 { 0xff } else { 0 }; 16];
+        phase_change_bytes[4..].copy_from_slice(phase_change);
+
+        Some(Self {
+            cumulative_scaled_rate_offset: i32::from_be_bytes(value.get(6..10)?.try_into().ok()?),
+            gm_time_base_indicator: u16::from_be_bytes(value.get(10..12)?.try_into().ok()?),
+            last_gm_phase_change: i128::from_be_bytes(phase_change_bytes),
+            scaled_last_gm_freq_change: i32::from_be_bytes(value.get(24..28)?.try_into().ok()?),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn follow_up_information_tlv_wireformat() {
+        let object = FollowUpInformationTlv {
+            cumulative_scaled_rate_offset: -2,
+            gm_time_base_indicator: 3,
+            last_gm_phase_change: -(1 << 16),
+            scaled_last_gm_freq_change: 0x01020304,
+        };
+        let bytes = [
+            0x00, 0x80, 0xc2, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xfe, 0x00, 0x03, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04,
+        ];
+
+        let mut buffer = [0; 28];
+        let tlv = object.to_tlv(&mut buffer).unwrap();
+        assert_eq!(tlv.tlv_type, TlvType::OrganizationExtension);
+        assert_eq!(&*tlv.value, &bytes[..]);
+        assert_eq!(FollowUpInformationTlv::from_tlv(&tlv), Some(object));
+
+        // Other organization extensions are not Follow_Up information TLVs
+        let mut other = bytes;
+        other[5] = 0x02;
+        let tlv = Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&other[..]).into(),
+        };
+        assert_eq!(FollowUpInformationTlv::from_tlv(&tlv), None);
+    }
+
+    #[test]
+    fn follow_up_information_rate_ratio() {
+        let information = FollowUpInformationTlv::from_rate_ratio(1.0 + 1e-6);
+        assert_eq!(information.cumulative_scaled_rate_offset, 2199023);
+        assert!((information.rate_ratio() - (1.0 + 1e-6)).abs() < 1e-12);
+
+        let information = FollowUpInformationTlv::from_rate_ratio(1.0 - 2.0 / (1u64 << 41) as f64);
+        assert_eq!(information.cumulative_scaled_rate_offset, -2);
+        assert_eq!(
+            FollowUpInformationTlv::from_rate_ratio(1.0),
+            Default::default()
+        );
+    }
+
     #[test]
     fn timestamp_wireformat() {
         let representations = [
@@ -167,6 +167,10 @@ impl core::fmt::Display for SdoId {
 }
 
 impl SdoId {
+    /// The sdo id of messages of *IEEE 802.1AS*, which calls the major sdo id
+    /// transportSpecific
+    pub(crate) const GPTP: Self = Self(0x100);
+
     const fn high_byte(self) -> u8 {
         (self.0 >> 8) as u8
     }
@@ -1,11 +1,29 @@
-use crate::datastructures::{common::PortIdentity, WireFormat, WireFormatError};
+use fixed::types::I48F16;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use super::{Header, Message, MessageBody, SdoId};
+use crate::{
+    datastructures::{
+        common::{
+            ClockIdentity, ClockQuality, LeapIndicator, PortIdentity, TimeInterval, TimeSource,
+            Tlv, TlvSetBuilder, TlvType,
+        },
+        datasets::TimePropertiesDS,
+        WireFormat, WireFormatError,
+    },
+    observability::{
+        current::CurrentDS,
+        default::DefaultDS,
+        parent::ParentDS,
+        port::{DelayMechanism, PortDS, PortState},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct ManagementMessage {
-    pub(super) target_port_identity: PortIdentity,
-    pub(super) starting_boundary_hops: u8,
-    pub(super) boundary_hops: u8,
-    pub(super) action: ManagementAction,
+    pub(crate) target_port_identity: PortIdentity,
+    pub(crate) starting_boundary_hops: u8,
+    pub(crate) boundary_hops: u8,
+    pub(crate) action: ManagementAction,
 }
 
 impl ManagementMessage {
@@ -17,10 +35,15 @@ impl ManagementMessage {
         &self,
         buffer: &mut [u8],
     ) -> Result<(), crate::datastructures::WireFormatError> {
+        if buffer.len() < 14 {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
         self.target_port_identity.serialize(&mut buffer[0..10])?;
-        buffer[11] = self.starting_boundary_hops;
-        buffer[12] = self.boundary_hops;
-        buffer[13] = self.action.to_primitive();
+        buffer[10] = self.starting_boundary_hops;
+        buffer[11] = self.boundary_hops;
+        buffer[12] = self.action.to_primitive();
+        buffer[13] = 0;
 
         Ok(())
     }
@@ -33,17 +56,30 @@ impl ManagementMessage {
         }
         Ok(Self {
             target_port_identity: PortIdentity::deserialize(&buffer[0..10])?,
-            starting_boundary_hops: buffer[11],
-            boundary_hops: buffer[12],
-            action: ManagementAction::from_primitive(buffer[13]),
+            starting_boundary_hops: buffer[10],
+            boundary_hops: buffer[11],
+            action: ManagementAction::from_primitive(buffer[12] & 0x0f),
         })
     }
+
+    /// Whether this message is addressed to the port with the given identity,
+    /// taking the wildcards of *IEEE1588-2019 section 15.3.1* into account
+    pub(crate) fn is_addressed_to(&self, port_identity: PortIdentity) -> bool {
+        let target = self.target_port_identity;
+
+        (target.clock_identity == ClockIdentity([0xff; 8])
+            || target.clock_identity == port_identity.clock_identity)
+            && (target.port_number == 0xffff || target.port_number == port_identity.port_number)
+    }
 }
 
-/// See: 15.4.1.6
+/// The action field of a management message
+///
+/// See *IEEE1588-2019 section 15.4.1.6*
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(clippy::upper_case_acronyms)]
-pub(crate) enum ManagementAction {
+#[allow(missing_docs)] // These variants are named after the spec
+pub enum ManagementAction {
     Reserved,
     GET,
     SET,
@@ -53,7 +89,7 @@ pub(crate) enum ManagementAction {
 }
 
 impl ManagementAction {
-    pub fn to_primitive(self) -> u8 {
+    pub(crate) fn to_primitive(self) -> u8 {
         match self {
             Self::GET => 0x0,
             Self::SET => 0x1,
@@ -64,7 +100,7 @@ impl ManagementAction {
         }
     }
 
-    pub fn from_primitive(value: u8) -> Self {
+    pub(crate) fn from_primitive(value: u8) -> Self {
         match value {
             0x0 => Self::GET,
             0x1 => Self::SET,
@@ -74,4 +110,1317 @@ impl ManagementAction {
             0x5..=u8::MAX => Self::Reserved,
         }
     }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::GET => "GET",
+            Self::SET => "SET",
+            Self::RESPONSE => "RESPONSE",
+            Self::COMMAND => "COMMAND",
+            Self::ACKNOWLEDGE => "ACKNOWLEDGE",
+            Self::Reserved => "RESERVED",
+        }
+    }
+}
+
+impl core::fmt::Display for ManagementAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl core::str::FromStr for ManagementAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::GET,
+            Self::SET,
+            Self::RESPONSE,
+            Self::COMMAND,
+            Self::ACKNOWLEDGE,
+        ]
+        .into_iter()
+        .find(|action| action.name().eq_ignore_ascii_case(s))
+        .ok_or(())
+    }
+}
+
+/// The id identifying the data carried in a management TLV
+///
+/// See *IEEE1588-2019 section 15.5.2.3 and table 59*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)] // These variants are named after the spec
+pub enum ManagementId {
+    NullPtpManagement,
+    ClockDescription,
+    UserDescription,
+    SaveInNonVolatileStorage,
+    ResetNonVolatileStorage,
+    Initialize,
+    FaultLog,
+    FaultLogReset,
+    DefaultDataSet,
+    CurrentDataSet,
+    ParentDataSet,
+    TimePropertiesDataSet,
+    PortDataSet,
+    Priority1,
+    Priority2,
+    Domain,
+    SlaveOnly,
+    LogAnnounceInterval,
+    AnnounceReceiptTimeout,
+    LogSyncInterval,
+    VersionNumber,
+    EnablePort,
+    DisablePort,
+    Time,
+    ClockAccuracy,
+    UtcProperties,
+    TraceabilityProperties,
+    TimescaleProperties,
+    UnicastNegotiationEnable,
+    PathTraceList,
+    PathTraceEnable,
+    GrandmasterClusterTable,
+    UnicastMasterTable,
+    UnicastMasterMaxTableSize,
+    AcceptableMasterTable,
+    AcceptableMasterTableEnabled,
+    AcceptableMasterMaxTableSize,
+    AlternateMaster,
+    AlternateTimeOffsetEnable,
+    AlternateTimeOffsetName,
+    AlternateTimeOffsetMaxKey,
+    AlternateTimeOffsetProperties,
+    ExternalPortConfigurationEnabled,
+    MasterOnly,
+    HoldoverUpgradeEnable,
+    ExtPortConfigPortDataSet,
+    TransparentClockDefaultDataSet,
+    TransparentClockPortDataSet,
+    PrimaryDomain,
+    DelayMechanism,
+    LogMinPdelayReqInterval,
+    ImplementationSpecific(u16),
+    ProfileSpecific(u16),
+    Reserved(u16),
+}
+
+impl ManagementId {
+    pub(crate) fn to_primitive(self) -> u16 {
+        match self {
+            Self::NullPtpManagement => 0x0000,
+            Self::ClockDescription => 0x0001,
+            Self::UserDescription => 0x0002,
+            Self::SaveInNonVolatileStorage => 0x0003,
+            Self::ResetNonVolatileStorage => 0x0004,
+            Self::Initialize => 0x0005,
+            Self::FaultLog => 0x0006,
+            Self::FaultLogReset => 0x0007,
+            Self::DefaultDataSet => 0x2000,
+            Self::CurrentDataSet => 0x2001,
+            Self::ParentDataSet => 0x2002,
+            Self::TimePropertiesDataSet => 0x2003,
+            Self::PortDataSet => 0x2004,
+            Self::Priority1 => 0x2005,
+            Self::Priority2 => 0x2006,
+            Self::Domain => 0x2007,
+            Self::SlaveOnly => 0x2008,
+            Self::LogAnnounceInterval => 0x2009,
+            Self::AnnounceReceiptTimeout => 0x200a,
+            Self::LogSyncInterval => 0x200b,
+            Self::VersionNumber => 0x200c,
+            Self::EnablePort => 0x200d,
+            Self::DisablePort => 0x200e,
+            Self::Time => 0x200f,
+            Self::ClockAccuracy => 0x2010,
+            Self::UtcProperties => 0x2011,
+            Self::TraceabilityProperties => 0x2012,
+            Self::TimescaleProperties => 0x2013,
+            Self::UnicastNegotiationEnable => 0x2014,
+            Self::PathTraceList => 0x2015,
+            Self::PathTraceEnable => 0x2016,
+            Self::GrandmasterClusterTable => 0x2017,
+            Self::UnicastMasterTable => 0x2018,
+            Self::UnicastMasterMaxTableSize => 0x2019,
+            Self::AcceptableMasterTable => 0x201a,
+            Self::AcceptableMasterTableEnabled => 0x201b,
+            Self::AcceptableMasterMaxTableSize => 0x201c,
+            Self::AlternateMaster => 0x201d,
+            Self::AlternateTimeOffsetEnable => 0x201e,
+            Self::AlternateTimeOffsetName => 0x201f,
+            Self::AlternateTimeOffsetMaxKey => 0x2020,
+            Self::AlternateTimeOffsetProperties => 0x2021,
+            Self::ExternalPortConfigurationEnabled => 0x3000,
+            Self::MasterOnly => 0x3001,
+            Self::HoldoverUpgradeEnable => 0x3002,
+            Self::ExtPortConfigPortDataSet => 0x3003,
+            Self::TransparentClockDefaultDataSet => 0x4000,
+            Self::TransparentClockPortDataSet => 0x4001,
+            Self::PrimaryDomain => 0x4002,
+            Self::DelayMechanism => 0x6000,
+            Self::LogMinPdelayReqInterval => 0x6001,
+            Self::ImplementationSpecific(value) => value,
+            Self::ProfileSpecific(value) => value,
+            Self::Reserved(value) => value,
+        }
+    }
+
+    pub(crate) fn from_primitive(value: u16) -> Self {
+        match value {
+            0x0000 => Self::NullPtpManagement,
+            0x0001 => Self::ClockDescription,
+            0x0002 => Self::UserDescription,
+            0x0003 => Self::SaveInNonVolatileStorage,
+            0x0004 => Self::ResetNonVolatileStorage,
+            0x0005 => Self::Initialize,
+            0x0006 => Self::FaultLog,
+            0x0007 => Self::FaultLogReset,
+            0x2000 => Self::DefaultDataSet,
+            0x2001 => Self::CurrentDataSet,
+            0x2002 => Self::ParentDataSet,
+            0x2003 => Self::TimePropertiesDataSet,
+            0x2004 => Self::PortDataSet,
+            0x2005 => Self::Priority1,
+            0x2006 => Self::Priority2,
+            0x2007 => Self::Domain,
+            0x2008 => Self::SlaveOnly,
+            0x2009 => Self::LogAnnounceInterval,
+            0x200a => Self::AnnounceReceiptTimeout,
+            0x200b => Self::LogSyncInterval,
+            0x200c => Self::VersionNumber,
+            0x200d => Self::EnablePort,
+            0x200e => Self::DisablePort,
+            0x200f => Self::Time,
+            0x2010 => Self::ClockAccuracy,
+            0x2011 => Self::UtcProperties,
+            0x2012 => Self::TraceabilityProperties,
+            0x2013 => Self::TimescaleProperties,
+            0x2014 => Self::UnicastNegotiationEnable,
+            0x2015 => Self::PathTraceList,
+            0x2016 => Self::PathTraceEnable,
+            0x2017 => Self::GrandmasterClusterTable,
+            0x2018 => Self::UnicastMasterTable,
+            0x2019 => Self::UnicastMasterMaxTableSize,
+            0x201a => Self::AcceptableMasterTable,
+            0x201b => Self::AcceptableMasterTableEnabled,
+            0x201c => Self::AcceptableMasterMaxTableSize,
+            0x201d => Self::AlternateMaster,
+            0x201e => Self::AlternateTimeOffsetEnable,
+            0x201f => Self::AlternateTimeOffsetName,
+            0x2020 => Self::AlternateTimeOffsetMaxKey,
+            0x2021 => Self::AlternateTimeOffsetProperties,
+            0x3000 => Self::ExternalPortConfigurationEnabled,
+            0x3001 => Self::MasterOnly,
+            0x3002 => Self::HoldoverUpgradeEnable,
+            0x3003 => Self::ExtPortConfigPortDataSet,
+            0x4000 => Self::TransparentClockDefaultDataSet,
+            0x4001 => Self::TransparentClockPortDataSet,
+            0x4002 => Self::PrimaryDomain,
+            0x6000 => Self::DelayMechanism,
+            0x6001 => Self::LogMinPdelayReqInterval,
+            0xc000..=0xdfff => Self::ImplementationSpecific(value),
+            0xe000..=0xfffe => Self::ProfileSpecific(value),
+            _ => Self::Reserved(value),
+        }
+    }
+}
+
+/// The names used for the management ids in *IEEE1588-2019 table 59*
+const MANAGEMENT_ID_NAMES: [(ManagementId, &str); 51] = [
+    (ManagementId::NullPtpManagement, "NULL_PTP_MANAGEMENT"),
+    (ManagementId::ClockDescription, "CLOCK_DESCRIPTION"),
+    (ManagementId::UserDescription, "USER_DESCRIPTION"),
+    (
+        ManagementId::SaveInNonVolatileStorage,
+        "SAVE_IN_NON_VOLATILE_STORAGE",
+    ),
+    (
+        ManagementId::ResetNonVolatileStorage,
+        "RESET_NON_VOLATILE_STORAGE",
+    ),
+    (ManagementId::Initialize, "INITIALIZE"),
+    (ManagementId::FaultLog, "FAULT_LOG"),
+    (ManagementId::FaultLogReset, "FAULT_LOG_RESET"),
+    (ManagementId::DefaultDataSet, "DEFAULT_DATA_SET"),
+    (ManagementId::CurrentDataSet, "CURRENT_DATA_SET"),
+    (ManagementId::ParentDataSet, "PARENT_DATA_SET"),
+    (
+        ManagementId::TimePropertiesDataSet,
+        "TIME_PROPERTIES_DATA_SET",
+    ),
+    (ManagementId::PortDataSet, "PORT_DATA_SET"),
+    (ManagementId::Priority1, "PRIORITY1"),
+    (ManagementId::Priority2, "PRIORITY2"),
+    (ManagementId::Domain, "DOMAIN"),
+    (ManagementId::SlaveOnly, "SLAVE_ONLY"),
+    (ManagementId::LogAnnounceInterval, "LOG_ANNOUNCE_INTERVAL"),
+    (
+        ManagementId::AnnounceReceiptTimeout,
+        "ANNOUNCE_RECEIPT_TIMEOUT",
+    ),
+    (ManagementId::LogSyncInterval, "LOG_SYNC_INTERVAL"),
+    (ManagementId::VersionNumber, "VERSION_NUMBER"),
+    (ManagementId::EnablePort, "ENABLE_PORT"),
+    (ManagementId::DisablePort, "DISABLE_PORT"),
+    (ManagementId::Time, "TIME"),
+    (ManagementId::ClockAccuracy, "CLOCK_ACCURACY"),
+    (ManagementId::UtcProperties, "UTC_PROPERTIES"),
+    (
+        ManagementId::TraceabilityProperties,
+        "TRACEABILITY_PROPERTIES",
+    ),
+    (ManagementId::TimescaleProperties, "TIMESCALE_PROPERTIES"),
+    (
+        ManagementId::UnicastNegotiationEnable,
+        "UNICAST_NEGOTIATION_ENABLE",
+    ),
+    (ManagementId::PathTraceList, "PATH_TRACE_LIST"),
+    (ManagementId::PathTraceEnable, "PATH_TRACE_ENABLE"),
+    (
+        ManagementId::GrandmasterClusterTable,
+        "GRANDMASTER_CLUSTER_TABLE",
+    ),
+    (ManagementId::UnicastMasterTable, "UNICAST_MASTER_TABLE"),
+    (
+        ManagementId::UnicastMasterMaxTableSize,
+        "UNICAST_MASTER_MAX_TABLE_SIZE",
+    ),
+    (
+        ManagementId::AcceptableMasterTable,
+        "ACCEPTABLE_MASTER_TABLE",
+    ),
+    (
+        ManagementId::AcceptableMasterTableEnabled,
+        "ACCEPTABLE_MASTER_TABLE_ENABLED",
+    ),
+    (
+        ManagementId::AcceptableMasterMaxTableSize,
+        "ACCEPTABLE_MASTER_MAX_TABLE_SIZE",
+    ),
+    (ManagementId::AlternateMaster, "ALTERNATE_MASTER"),
+    (
+        ManagementId::AlternateTimeOffsetEnable,
+        "ALTERNATE_TIME_OFFSET_ENABLE",
+    ),
+    (
+        ManagementId::AlternateTimeOffsetName,
+        "ALTERNATE_TIME_OFFSET_NAME",
+    ),
+    (
+        ManagementId::AlternateTimeOffsetMaxKey,
+        "ALTERNATE_TIME_OFFSET_MAX_KEY",
+    ),
+    (
+        ManagementId::AlternateTimeOffsetProperties,
+        "ALTERNATE_TIME_OFFSET_PROPERTIES",
+    ),
+    (
+        ManagementId::ExternalPortConfigurationEnabled,
+        "EXTERNAL_PORT_CONFIGURATION_ENABLED",
+    ),
+    (ManagementId::MasterOnly, "MASTER_ONLY"),
+    (
+        ManagementId::HoldoverUpgradeEnable,
+        "HOLDOVER_UPGRADE_ENABLE",
+    ),
+    (
+        ManagementId::ExtPortConfigPortDataSet,
+        "EXT_PORT_CONFIG_PORT_DATA_SET",
+    ),
+    (
+        ManagementId::TransparentClockDefaultDataSet,
+        "TRANSPARENT_CLOCK_DEFAULT_DATA_SET",
+    ),
+    (
+        ManagementId::TransparentClockPortDataSet,
+        "TRANSPARENT_CLOCK_PORT_DATA_SET",
+    ),
+    (ManagementId::PrimaryDomain, "PRIMARY_DOMAIN"),
+    (ManagementId::DelayMechanism, "DELAY_MECHANISM"),
+    (
+        ManagementId::LogMinPdelayReqInterval,
+        "LOG_MIN_PDELAY_REQ_INTERVAL",
+    ),
+];
+
+impl core::fmt::Display for ManagementId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match MANAGEMENT_ID_NAMES.iter().find(|(id, _)| id == self) {
+            Some((_, name)) => f.write_str(name),
+            None => write!(f, "{:#06x}", self.to_primitive()),
+        }
+    }
+}
+
+impl core::str::FromStr for ManagementId {
+    type Err = ();
+
+    /// Parse a management id from its name in the spec (ignoring case), or
+    /// from its hexadecimal value prefixed with `0x`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix("0x") {
+            return u16::from_str_radix(hex, 16)
+                .map(Self::from_primitive)
+                .map_err(|_| ());
+        }
+
+        MANAGEMENT_ID_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s))
+            .map(|(id, _)| *id)
+            .ok_or(())
+    }
+}
+
+/// The error reported in a management error status TLV
+///
+/// See *IEEE1588-2019 section 15.5.4.1.4 and table 109*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)] // These variants are named after the spec
+pub enum ManagementErrorId {
+    ResponseTooBig,
+    NoSuchId,
+    WrongLength,
+    WrongValue,
+    NotSetable,
+    NotSupported,
+    Unpopulated,
+    GeneralError,
+    Reserved(u16),
+}
+
+impl ManagementErrorId {
+    pub(crate) fn to_primitive(self) -> u16 {
+        match self {
+            Self::ResponseTooBig => 0x0001,
+            Self::NoSuchId => 0x0002,
+            Self::WrongLength => 0x0003,
+            Self::WrongValue => 0x0004,
+            Self::NotSetable => 0x0005,
+            Self::NotSupported => 0x0006,
+            Self::Unpopulated => 0x0007,
+            Self::GeneralError => 0xfffe,
+            Self::Reserved(value) => value,
+        }
+    }
+
+    pub(crate) fn from_primitive(value: u16) -> Self {
+        match value {
+            0x0001 => Self::ResponseTooBig,
+            0x0002 => Self::NoSuchId,
+            0x0003 => Self::WrongLength,
+            0x0004 => Self::WrongValue,
+            0x0005 => Self::NotSetable,
+            0x0006 => Self::NotSupported,
+            0x0007 => Self::Unpopulated,
+            0xfffe => Self::GeneralError,
+            _ => Self::Reserved(value),
+        }
+    }
+}
+
+impl core::fmt::Display for ManagementErrorId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ResponseTooBig => f.write_str("RESPONSE_TOO_BIG"),
+            Self::NoSuchId => f.write_str("NO_SUCH_ID"),
+            Self::WrongLength => f.write_str("WRONG_LENGTH"),
+            Self::WrongValue => f.write_str("WRONG_VALUE"),
+            Self::NotSetable => f.write_str("NOT_SETABLE"),
+            Self::NotSupported => f.write_str("NOT_SUPPORTED"),
+            Self::Unpopulated => f.write_str("UNPOPULATED"),
+            Self::GeneralError => f.write_str("GENERAL_ERROR"),
+            Self::Reserved(value) => write!(f, "{value:#06x}"),
+        }
+    }
+}
+
+/// A management TLV with its data field still in wire format
+///
+/// See 15.5.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ManagementTlv<'a> {
+    pub(crate) management_id: ManagementId,
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> ManagementTlv<'a> {
+    pub(crate) fn from_tlv(tlv: &'a Tlv<'_>) -> Option<Self> {
+        if tlv.tlv_type != TlvType::Management || tlv.value.len() < 2 {
+            return None;
+        }
+
+        Some(Self {
+            management_id: ManagementId::from_primitive(u16::from_be_bytes([
+                tlv.value[0],
+                tlv.value[1],
+            ])),
+            data: &tlv.value[2..],
+        })
+    }
+}
+
+/// The content of a management error status TLV
+///
+/// See *IEEE1588-2019 section 15.5.4*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagementErrorStatus {
+    /// The error that occurred
+    pub error_id: ManagementErrorId,
+    /// The id of the management TLV that caused the error
+    pub management_id: ManagementId,
+}
+
+impl ManagementErrorStatus {
+    pub(crate) fn wire_size(&self) -> usize {
+        8
+    }
+
+    pub(crate) fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        if buffer.len() < 8 {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
+        buffer[0..2].copy_from_slice(&self.error_id.to_primitive().to_be_bytes());
+        buffer[2..4].copy_from_slice(&self.management_id.to_primitive().to_be_bytes());
+        buffer[4..8].fill(0);
+
+        Ok(())
+    }
+
+    /// Serialize into a management error status TLV, using `buffer` to store
+    /// the value of the TLV
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..self.wire_size())
+            .ok_or(WireFormatError::BufferTooShort)?;
+        self.serialize(value)?;
+
+        Ok(Tlv {
+            tlv_type: TlvType::ManagementErrorStatus,
+            value: (&*value).into(),
+        })
+    }
+
+    pub(crate) fn from_tlv(tlv: &Tlv<'_>) -> Option<Self> {
+        if tlv.tlv_type != TlvType::ManagementErrorStatus || tlv.value.len() < 8 {
+            return None;
+        }
+
+        // The optional display data is ignored
+        Some(Self {
+            error_id: ManagementErrorId::from_primitive(u16::from_be_bytes([
+                tlv.value[0],
+                tlv.value[1],
+            ])),
+            management_id: ManagementId::from_primitive(u16::from_be_bytes([
+                tlv.value[2],
+                tlv.value[3],
+            ])),
+        })
+    }
+}
+
+/// The decoded data field of a management TLV
+///
+/// Only the management ids supported by statime can be represented. For more
+/// details see *IEEE1588-2019 section 15.5.3*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagementData {
+    /// NULL_PTP_MANAGEMENT, carries no data
+    NullPtpManagement,
+    /// INITIALIZE, with the initialization key
+    Initialize {
+        /// The initialization key, only `0` (INITIALIZE_EVENT) is defined
+        key: u16,
+    },
+    /// DEFAULT_DATA_SET
+    DefaultDataSet(DefaultDS),
+    /// CURRENT_DATA_SET
+    CurrentDataSet(CurrentDS),
+    /// PARENT_DATA_SET
+    ParentDataSet(ParentDS),
+    /// TIME_PROPERTIES_DATA_SET
+    TimePropertiesDataSet(TimePropertiesDS),
+    /// PORT_DATA_SET
+    PortDataSet(PortDS),
+    /// PRIORITY1
+    Priority1(u8),
+    /// PRIORITY2
+    Priority2(u8),
+    /// DOMAIN
+    Domain(u8),
+    /// SLAVE_ONLY
+    SlaveOnly(bool),
+    /// LOG_ANNOUNCE_INTERVAL
+    LogAnnounceInterval(i8),
+    /// ANNOUNCE_RECEIPT_TIMEOUT
+    AnnounceReceiptTimeout(u8),
+    /// LOG_SYNC_INTERVAL
+    LogSyncInterval(i8),
+    /// VERSION_NUMBER
+    VersionNumber {
+        /// The major PTP version of the port
+        major: u8,
+        /// The minor PTP version of the port
+        minor: u8,
+    },
+    /// ENABLE_PORT, carries no data
+    EnablePort,
+    /// DISABLE_PORT, carries no data
+    DisablePort,
+    /// DELAY_MECHANISM
+    DelayMechanism(DelayMechanism),
+    /// LOG_MIN_PDELAY_REQ_INTERVAL
+    LogMinPdelayReqInterval(i8),
+}
+
+fn time_interval_from_nanos(nanos: i128) -> TimeInterval {
+    TimeInterval(I48F16::saturating_from_num(nanos))
+}
+
+fn time_interval_to_nanos(interval: TimeInterval) -> i128 {
+    interval.0.round().to_num()
+}
+
+impl ManagementData {
+    /// The management id belonging to this data
+    pub fn management_id(&self) -> ManagementId {
+        match self {
+            Self::NullPtpManagement => ManagementId::NullPtpManagement,
+            Self::Initialize { .. } => ManagementId::Initialize,
+            Self::DefaultDataSet(_) => ManagementId::DefaultDataSet,
+            Self::CurrentDataSet(_) => ManagementId::CurrentDataSet,
+            Self::ParentDataSet(_) => ManagementId::ParentDataSet,
+            Self::TimePropertiesDataSet(_) => ManagementId::TimePropertiesDataSet,
+            Self::PortDataSet(_) => ManagementId::PortDataSet,
+            Self::Priority1(_) => ManagementId::Priority1,
+            Self::Priority2(_) => ManagementId::Priority2,
+            Self::Domain(_) => ManagementId::Domain,
+            Self::SlaveOnly(_) => ManagementId::SlaveOnly,
+            Self::LogAnnounceInterval(_) => ManagementId::LogAnnounceInterval,
+            Self::AnnounceReceiptTimeout(_) => ManagementId::AnnounceReceiptTimeout,
+            Self::LogSyncInterval(_) => ManagementId::LogSyncInterval,
+            Self::VersionNumber { .. } => ManagementId::VersionNumber,
+            Self::EnablePort => ManagementId::EnablePort,
+            Self::DisablePort => ManagementId::DisablePort,
+            Self::DelayMechanism(_) => ManagementId::DelayMechanism,
+            Self::LogMinPdelayReqInterval(_) => ManagementId::LogMinPdelayReqInterval,
+        }
+    }
+
+    /// The size of the data field, excluding the management id
+    pub(crate) fn wire_size(&self) -> usize {
+        match self {
+            Self::NullPtpManagement | Self::EnablePort | Self::DisablePort => 0,
+            Self::Initialize { .. }
+            | Self::Priority1(_)
+            | Self::Priority2(_)
+            | Self::Domain(_)
+            | Self::SlaveOnly(_)
+            | Self::LogAnnounceInterval(_)
+            | Self::AnnounceReceiptTimeout(_)
+            | Self::LogSyncInterval(_)
+            | Self::VersionNumber { .. }
+            | Self::DelayMechanism(_)
+            | Self::LogMinPdelayReqInterval(_) => 2,
+            Self::TimePropertiesDataSet(_) => 4,
+            Self::CurrentDataSet(_) => 18,
+            Self::DefaultDataSet(_) => 20,
+            Self::PortDataSet(_) => 26,
+            Self::ParentDataSet(_) => 32,
+        }
+    }
+
+    /// Serialize the data field, excluding the management id
+    pub(crate) fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        let buffer = buffer
+            .get_mut(..self.wire_size())
+            .ok_or(WireFormatError::BufferTooShort)?;
+        buffer.fill(0);
+
+        match self {
+            Self::NullPtpManagement | Self::EnablePort | Self::DisablePort => {}
+            Self::Initialize { key } => buffer[0..2].copy_from_slice(&key.to_be_bytes()),
+            Self::DefaultDataSet(default_ds) => {
+                buffer[0] = default_ds.two_step as u8 | ((default_ds.slave_only as u8) << 1);
+                buffer[2..4].copy_from_slice(&default_ds.number_ports.to_be_bytes());
+                buffer[4] = default_ds.priority_1;
+                default_ds.clock_quality.serialize(&mut buffer[5..9])?;
+                buffer[9] = default_ds.priority_2;
+                default_ds.clock_identity.serialize(&mut buffer[10..18])?;
+                buffer[18] = default_ds.domain_number;
+            }
+            Self::CurrentDataSet(current_ds) => {
+                buffer[0..2].copy_from_slice(&current_ds.steps_removed.to_be_bytes());
+                time_interval_from_nanos(current_ds.offset_from_master)
+                    .serialize(&mut buffer[2..10])?;
+                time_interval_from_nanos(current_ds.mean_delay).serialize(&mut buffer[10..18])?;
+            }
+            Self::ParentDataSet(parent_ds) => {
+                parent_ds
+                    .parent_port_identity
+                    .serialize(&mut buffer[0..10])?;
+                buffer[10] = parent_ds.parent_stats as u8;
+                buffer[12..14].copy_from_slice(
+                    &parent_ds
+                        .observed_parent_offset_scaled_log_variance
+                        .to_be_bytes(),
+                );
+                buffer[14..18].copy_from_slice(
+                    &parent_ds
+                        .observed_parent_clock_phase_change_rate
+                        .to_be_bytes(),
+                );
+                buffer[18] = parent_ds.grandmaster_priority_1;
+                parent_ds
+                    .grandmaster_clock_quality
+                    .serialize(&mut buffer[19..23])?;
+                buffer[23] = parent_ds.grandmaster_priority_2;
+                parent_ds
+                    .grandmaster_identity
+                    .serialize(&mut buffer[24..32])?;
+            }
+            Self::TimePropertiesDataSet(time_properties_ds) => {
+                buffer[0..2].copy_from_slice(
+                    &time_properties_ds
+                        .current_utc_offset
+                        .unwrap_or_default()
+                        .to_be_bytes(),
+                );
+                buffer[2] = (time_properties_ds.leap_indicator == LeapIndicator::Leap61) as u8
+                    | ((time_properties_ds.leap_indicator == LeapIndicator::Leap59) as u8) << 1
+                    | (time_properties_ds.current_utc_offset.is_some() as u8) << 2
+                    | (time_properties_ds.ptp_timescale as u8) << 3
+                    | (time_properties_ds.time_traceable as u8) << 4
+                    | (time_properties_ds.frequency_traceable as u8) << 5;
+                buffer[3] = time_properties_ds.time_source.to_primitive();
+            }
+            Self::PortDataSet(port_ds) => {
+                port_ds.port_identity.serialize(&mut buffer[0..10])?;
+                buffer[10] = port_ds.port_state.to_primitive();
+                buffer[11] = port_ds.log_min_delay_req_interval as u8;
+                time_interval_from_nanos(port_ds.mean_link_delay).serialize(&mut buffer[12..20])?;
+                buffer[20] = port_ds.log_announce_interval as u8;
+                buffer[21] = port_ds.announce_receipt_timeout;
+                buffer[22] = port_ds.log_sync_interval as u8;
+                buffer[23] = port_ds.delay_mechanism.to_primitive();
+                buffer[24] = port_ds.log_min_pdelay_req_interval as u8;
+                buffer[25] = (port_ds.minor_version_number << 4) | (port_ds.version_number & 0x0f);
+            }
+            Self::Priority1(value)
+            | Self::Priority2(value)
+            | Self::Domain(value)
+            | Self::AnnounceReceiptTimeout(value) => buffer[0] = *value,
+            Self::SlaveOnly(slave_only) => buffer[0] = *slave_only as u8,
+            Self::LogAnnounceInterval(value)
+            | Self::LogSyncInterval(value)
+            | Self::LogMinPdelayReqInterval(value) => buffer[0] = *value as u8,
+            Self::VersionNumber { major, minor } => buffer[0] = (minor << 4) | (major & 0x0f),
+            Self::DelayMechanism(delay_mechanism) => buffer[0] = delay_mechanism.to_primitive(),
+        }
+
+        Ok(())
+    }
+
+    /// Serialize into a management TLV, using `buffer` to store the value of
+    /// the TLV
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..2 + self.wire_size())
+            .ok_or(WireFormatError::BufferTooShort)?;
+        value[0..2].copy_from_slice(&self.management_id().to_primitive().to_be_bytes());
+        self.serialize(&mut value[2..])?;
+
+        Ok(Tlv {
+            tlv_type: TlvType::Management,
+            value: (&*value).into(),
+        })
+    }
+
+    /// Deserialize the data field of a management TLV with the given id
+    ///
+    /// The `sdo_id` is taken from the header of the message carrying the TLV,
+    /// as the DEFAULT_DATA_SET does not contain it.
+    pub(crate) fn deserialize(
+        management_id: ManagementId,
+        buffer: &[u8],
+        sdo_id: SdoId,
+    ) -> Result<Self, ManagementErrorId> {
+        let expected_size = |size: usize| {
+            if buffer.len() < size {
+                Err(ManagementErrorId::WrongLength)
+            } else {
+                Ok(())
+            }
+        };
+        let invalid = |_| ManagementErrorId::WrongValue;
+
+        let data = match management_id {
+            ManagementId::NullPtpManagement => Self::NullPtpManagement,
+            ManagementId::EnablePort => Self::EnablePort,
+            ManagementId::DisablePort => Self::DisablePort,
+            ManagementId::Initialize => {
+                expected_size(2)?;
+                Self::Initialize {
+                    key: u16::from_be_bytes([buffer[0], buffer[1]]),
+                }
+            }
+            ManagementId::DefaultDataSet => {
+                expected_size(20)?;
+                Self::DefaultDataSet(DefaultDS {
+                    clock_identity: ClockIdentity::deserialize(&buffer[10..18]).map_err(invalid)?,
+                    two_step: buffer[0] & 1 > 0,
+                    number_ports: u16::from_be_bytes([buffer[2], buffer[3]]),
+                    clock_quality: ClockQuality::deserialize(&buffer[5..9]).map_err(invalid)?,
+                    priority_1: buffer[4],
+                    priority_2: buffer[9],
+                    domain_number: buffer[18],
+                    slave_only: buffer[0] & (1 << 1) > 0,
+                    sdo_id,
+                })
+            }
+            ManagementId::CurrentDataSet => {
+                expected_size(18)?;
+                Self::CurrentDataSet(CurrentDS {
+                    steps_removed: u16::from_be_bytes([buffer[0], buffer[1]]),
+                    offset_from_master: time_interval_to_nanos(
+                        TimeInterval::deserialize(&buffer[2..10]).map_err(invalid)?,
+                    ),
+                    mean_delay: time_interval_to_nanos(
+                        TimeInterval::deserialize(&buffer[10..18]).map_err(invalid)?,
+                    ),
+                })
+            }
+            ManagementId::ParentDataSet => {
+                expected_size(32)?;
+                Self::ParentDataSet(ParentDS {
+                    parent_port_identity: PortIdentity::deserialize(&buffer[0..10])
+                        .map_err(invalid)?,
+                    parent_stats: buffer[10] & 1 > 0,
+                    observed_parent_offset_scaled_log_variance: u16::from_be_bytes([
+                        buffer[12], buffer[13],
+                    ]),
+                    observed_parent_clock_phase_change_rate: u32::from_be_bytes(
+                        buffer[14..18].try_into().unwrap(),
+                    ),
+                    grandmaster_priority_1: buffer[18],
+                    grandmaster_clock_quality: ClockQuality::deserialize(&buffer[19..23])
+                        .map_err(invalid)?,
+                    grandmaster_priority_2: buffer[23],
+                    grandmaster_identity: ClockIdentity::deserialize(&buffer[24..32])
+                        .map_err(invalid)?,
+                    // Not part of the management data set
+                    grandmaster_id: None,
+                    total_time_inaccuracy: None,
+                })
+            }
+            ManagementId::TimePropertiesDataSet => {
+                expected_size(4)?;
+                let flags = buffer[2];
+                let leap_indicator = if flags & 1 > 0 {
+                    LeapIndicator::Leap61
+                } else if flags & (1 << 1) > 0 {
+                    LeapIndicator::Leap59
+                } else {
+                    LeapIndicator::NoLeap
+                };
+
+                Self::TimePropertiesDataSet(TimePropertiesDS {
+                    current_utc_offset: (flags & (1 << 2) > 0)
+                        .then_some(i16::from_be_bytes([buffer[0], buffer[1]])),
+                    leap_indicator,
+                    time_traceable: flags & (1 << 4) > 0,
+                    frequency_traceable: flags & (1 << 5) > 0,
+                    ptp_timescale: flags & (1 << 3) > 0,
+                    time_source: TimeSource::from_primitive(buffer[3]),
+                })
+            }
+            ManagementId::PortDataSet => {
+                expected_size(26)?;
+                Self::PortDataSet(PortDS {
+                    port_identity: PortIdentity::deserialize(&buffer[0..10]).map_err(invalid)?,
+                    port_state: PortState::from_primitive(buffer[10])
+                        .ok_or(ManagementErrorId::WrongValue)?,
+                    log_min_delay_req_interval: buffer[11] as i8,
+                    mean_link_delay: time_interval_to_nanos(
+                        TimeInterval::deserialize(&buffer[12..20]).map_err(invalid)?,
+                    ),
+                    log_announce_interval: buffer[20] as i8,
+                    announce_receipt_timeout: buffer[21],
+                    log_sync_interval: buffer[22] as i8,
+                    delay_mechanism: DelayMechanism::from_primitive(buffer[23])
+                        .ok_or(ManagementErrorId::WrongValue)?,
+                    log_min_pdelay_req_interval: buffer[24] as i8,
+                    version_number: buffer[25] & 0x0f,
+                    minor_version_number: buffer[25] >> 4,
+                })
+            }
+            ManagementId::Priority1 => {
+                expected_size(2)?;
+                Self::Priority1(buffer[0])
+            }
+            ManagementId::Priority2 => {
+                expected_size(2)?;
+                Self::Priority2(buffer[0])
+            }
+            ManagementId::Domain => {
+                expected_size(2)?;
+                Self::Domain(buffer[0])
+            }
+            ManagementId::SlaveOnly => {
+                expected_size(2)?;
+                Self::SlaveOnly(buffer[0] & 1 > 0)
+            }
+            ManagementId::LogAnnounceInterval => {
+                expected_size(2)?;
+                Self::LogAnnounceInterval(buffer[0] as i8)
+            }
+            ManagementId::AnnounceReceiptTimeout => {
+                expected_size(2)?;
+                Self::AnnounceReceiptTimeout(buffer[0])
+            }
+            ManagementId::LogSyncInterval => {
+                expected_size(2)?;
+                Self::LogSyncInterval(buffer[0] as i8)
+            }
+            ManagementId::VersionNumber => {
+                expected_size(2)?;
+                Self::VersionNumber {
+                    major: buffer[0] & 0x0f,
+                    minor: buffer[0] >> 4,
+                }
+            }
+            ManagementId::DelayMechanism => {
+                expected_size(2)?;
+                Self::DelayMechanism(
+                    DelayMechanism::from_primitive(buffer[0])
+                        .ok_or(ManagementErrorId::WrongValue)?,
+                )
+            }
+            ManagementId::LogMinPdelayReqInterval => {
+                expected_size(2)?;
+                Self::LogMinPdelayReqInterval(buffer[0] as i8)
+            }
+            ManagementId::Reserved(_) => return Err(ManagementErrorId::NoSuchId),
+            _ => return Err(ManagementErrorId::NotSupported),
+        };
+
+        Ok(data)
+    }
+}
+
+/// Errors that can occur when building or parsing a management message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagementMessageError {
+    /// The provided buffer is too short
+    BufferTooShort,
+    /// The message could not be parsed
+    Malformed,
+    /// The message is not a management message
+    NotManagement,
+    /// The message contains neither a management nor a management error
+    /// status TLV
+    MissingTlv,
+}
+
+impl core::fmt::Display for ManagementMessageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooShort => f.write_str("a buffer is too short"),
+            Self::Malformed => f.write_str("the message could not be parsed"),
+            Self::NotManagement => f.write_str("not a management message"),
+            Self::MissingTlv => f.write_str("the message has no management TLV"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ManagementMessageError {}
+
+impl From<WireFormatError> for ManagementMessageError {
+    fn from(value: WireFormatError) -> Self {
+        match value {
+            WireFormatError::BufferTooShort => Self::BufferTooShort,
+            _ => Self::Malformed,
+        }
+    }
+}
+
+/// The request carried by a [`ManagementRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagementRequestBody {
+    /// Request the current value of the data identified by the id
+    Get(ManagementId),
+    /// Update the data to the given value
+    Set(ManagementData),
+    /// Execute the given command, e.g. [`ManagementData::EnablePort`]
+    Command(ManagementData),
+}
+
+/// A management message as sent by a management node
+///
+/// See *IEEE1588-2019 section 15*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagementRequest {
+    /// Identity of the management node sending the request
+    pub source_port_identity: PortIdentity,
+    /// Identity of the targeted port, all ones is used as wildcard for both the
+    /// clock identity and port number
+    pub target_port_identity: PortIdentity,
+    /// Domain of the targeted PTP instance
+    pub domain_number: u8,
+    /// Sdo id of the targeted PTP instance
+    pub sdo_id: SdoId,
+    /// Sequence id used to match responses to this request
+    pub sequence_id: u16,
+    /// The number of boundary clocks this request may pass through
+    pub boundary_hops: u8,
+    /// The actual request
+    pub body: ManagementRequestBody,
+}
+
+impl ManagementRequest {
+    /// Serialize the request into `buffer`, returning the length of the message
+    pub fn serialize(&self, buffer: &mut [u8]) -> Result<usize, ManagementMessageError> {
+        let mut value_buffer = [0; 64];
+        let (action, tlv) = match self.body {
+            ManagementRequestBody::Get(management_id) => {
+                // A GET carries no data, see IEEE1588-2019 section 15.4.1.6
+                value_buffer[0..2].copy_from_slice(&management_id.to_primitive().to_be_bytes());
+                let tlv = Tlv {
+                    tlv_type: TlvType::Management,
+                    value: (&value_buffer[..2]).into(),
+                };
+                (ManagementAction::GET, tlv)
+            }
+            ManagementRequestBody::Set(data) => {
+                (ManagementAction::SET, data.to_tlv(&mut value_buffer)?)
+            }
+            ManagementRequestBody::Command(data) => {
+                (ManagementAction::COMMAND, data.to_tlv(&mut value_buffer)?)
+            }
+        };
+
+        let mut tlv_buffer = [0; 68];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        tlv_builder.add(tlv)?;
+
+        let message = Message {
+            header: Header {
+                sdo_id: self.sdo_id,
+                domain_number: self.domain_number,
+                source_port_identity: self.source_port_identity,
+                sequence_id: self.sequence_id,
+                log_message_interval: 0x7f,
+                ..Default::default()
+            },
+            body: MessageBody::Management(ManagementMessage {
+                target_port_identity: self.target_port_identity,
+                starting_boundary_hops: self.boundary_hops,
+                boundary_hops: self.boundary_hops,
+                action,
+            }),
+            suffix: tlv_builder.build(),
+        };
+
+        Ok(message.serialize(buffer)?)
+    }
+}
+
+/// The content of a [`ManagementResponse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagementResponseBody {
+    /// The requested data, or the acknowledged command
+    Data(ManagementData),
+    /// The request could not be handled
+    Error(ManagementErrorStatus),
+    /// The response carries a management TLV with an id whose data statime can
+    /// not decode
+    Undecoded(ManagementId),
+}
+
+/// A management message as received by a management node
+///
+/// See *IEEE1588-2019 section 15*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagementResponse {
+    /// Identity of the port that sent this response
+    pub source_port_identity: PortIdentity,
+    /// Identity of the port the response is meant for
+    pub target_port_identity: PortIdentity,
+    /// Sequence id of the request this is a response to
+    pub sequence_id: u16,
+    /// Action of the message, usually [`ManagementAction::RESPONSE`] or
+    /// [`ManagementAction::ACKNOWLEDGE`]
+    pub action: ManagementAction,
+    /// The content of the first management (error status) TLV
+    pub body: ManagementResponseBody,
+}
+
+impl ManagementResponse {
+    /// Parse a management message from `buffer`
+    pub fn deserialize(buffer: &[u8]) -> Result<Self, ManagementMessageError> {
+        let message = Message::deserialize(buffer)?;
+        let MessageBody::Management(management) = message.body else {
+            return Err(ManagementMessageError::NotManagement);
+        };
+
+        let body = message
+            .suffix
+            .tlv()
+            .find_map(|tlv| {
+                if let Some(status) = ManagementErrorStatus::from_tlv(&tlv) {
+                    return Some(ManagementResponseBody::Error(status));
+                }
+
+                let management_tlv = ManagementTlv::from_tlv(&tlv)?;
+                Some(
+                    match ManagementData::deserialize(
+                        management_tlv.management_id,
+                        management_tlv.data,
+                        message.header.sdo_id,
+                    ) {
+                        Ok(data) => ManagementResponseBody::Data(data),
+                        Err(_) => ManagementResponseBody::Undecoded(management_tlv.management_id),
+                    },
+                )
+            })
+            .ok_or(ManagementMessageError::MissingTlv)?;
+
+        Ok(Self {
+            source_port_identity: message.header.source_port_identity,
+            target_port_identity: management.target_port_identity,
+            sequence_id: message.header.sequence_id,
+            action: management.action,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn management_message_wireformat() {
+        let representations = [(
+            [
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x03, 0x02, 0x01,
+                0x00u8,
+            ],
+            ManagementMessage {
+                target_port_identity: PortIdentity {
+                    clock_identity: ClockIdentity([0xff; 8]),
+                    port_number: 0xffff,
+                },
+                starting_boundary_hops: 3,
+                boundary_hops: 2,
+                action: ManagementAction::SET,
+            },
+        )];
+
+        for (byte_representation, object_representation) in representations {
+            // Test the serialization output
+            let mut serialization_buffer = [0; 14];
+            object_representation
+                .serialize_content(&mut serialization_buffer)
+                .unwrap();
+            assert_eq!(serialization_buffer, byte_representation);
+
+            // Test the deserialization output
+            let deserialized_data =
+                ManagementMessage::deserialize_content(&byte_representation).unwrap();
+            assert_eq!(deserialized_data, object_representation);
+        }
+    }
+
+    #[test]
+    fn management_message_addressing() {
+        let own = PortIdentity {
+            clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+            port_number: 1,
+        };
+        let message = |clock_identity, port_number| ManagementMessage {
+            target_port_identity: PortIdentity {
+                clock_identity: ClockIdentity(clock_identity),
+                port_number,
+            },
+            starting_boundary_hops: 0,
+            boundary_hops: 0,
+            action: ManagementAction::GET,
+        };
+
+        assert!(message([0xff; 8], 0xffff).is_addressed_to(own));
+        assert!(message([0xff; 8], 1).is_addressed_to(own));
+        assert!(message([1, 2, 3, 4, 5, 6, 7, 8], 0xffff).is_addressed_to(own));
+        assert!(!message([0xff; 8], 2).is_addressed_to(own));
+        assert!(!message([8, 7, 6, 5, 4, 3, 2, 1], 1).is_addressed_to(own));
+    }
+
+    #[test]
+    fn management_data_wireformat() {
+        let representations = [
+            (
+                &[
+                    0x03, 0x00, 0x00, 0x02, 0x80, 0xf8, 0xfe, 0x4e, 0x9d, 0x7f, 0x01, 0x02, 0x03,
+                    0x04, 0x05, 0x06, 0x07, 0x08, 0x2a, 0x00u8,
+                ][..],
+                ManagementData::DefaultDataSet(DefaultDS {
+                    clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+                    two_step: true,
+                    number_ports: 2,
+                    clock_quality: ClockQuality {
+                        clock_class: 248,
+                        clock_accuracy: crate::config::ClockAccuracy::Unknown,
+                        offset_scaled_log_variance: 0x4e9d,
+                    },
+                    priority_1: 128,
+                    priority_2: 127,
+                    domain_number: 42,
+                    slave_only: true,
+                    sdo_id: SdoId::default(),
+                }),
+            ),
+            (
+                &[
+                    0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x64, 0x00, 0x00u8,
+                ][..],
+                ManagementData::CurrentDataSet(CurrentDS {
+                    steps_removed: 1,
+                    offset_from_master: 1000,
+                    mean_delay: 100,
+                }),
+            ),
+            (
+                &[0x00, 0x25, 0x0d, 0x20u8][..],
+                ManagementData::TimePropertiesDataSet(TimePropertiesDS {
+                    current_utc_offset: Some(37),
+                    leap_indicator: LeapIndicator::Leap61,
+                    time_traceable: false,
+                    frequency_traceable: false,
+                    ptp_timescale: true,
+                    time_source: TimeSource::Gnss,
+                }),
+            ),
+            (
+                &[
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x01, 0x09, 0x00, 0x00,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0xff, 0x01, 0x00, 0x12u8,
+                ][..],
+                ManagementData::PortDataSet(PortDS {
+                    port_identity: PortIdentity {
+                        clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+                        port_number: 1,
+                    },
+                    port_state: PortState::Slave,
+                    log_min_delay_req_interval: 0,
+                    mean_link_delay: 0,
+                    log_announce_interval: 1,
+                    announce_receipt_timeout: 3,
+                    log_sync_interval: -1,
+                    delay_mechanism: DelayMechanism::E2E,
+                    log_min_pdelay_req_interval: 0,
+                    version_number: 2,
+                    minor_version_number: 1,
+                }),
+            ),
+            (&[0x64, 0x00u8][..], ManagementData::Priority1(100)),
+            (&[0x01, 0x00u8][..], ManagementData::SlaveOnly(true)),
+            (&[0xfe, 0x00u8][..], ManagementData::LogSyncInterval(-2)),
+        ];
+
+        for (byte_representation, object_representation) in representations {
+            // Test the serialization output
+            let mut serialization_buffer = [0; 32];
+            object_representation
+                .serialize(&mut serialization_buffer)
+                .unwrap();
+            assert_eq!(
+                &serialization_buffer[..object_representation.wire_size()],
+                byte_representation
+            );
+
+            // Test the deserialization output
+            let deserialized_data = ManagementData::deserialize(
+                object_representation.management_id(),
+                byte_representation,
+                SdoId::default(),
+            )
+            .unwrap();
+            assert_eq!(deserialized_data, object_representation);
+        }
+    }
+
+    #[test]
+    fn management_data_errors() {
+        assert_eq!(
+            ManagementData::deserialize(ManagementId::Priority1, &[], SdoId::default()),
+            Err(ManagementErrorId::WrongLength)
+        );
+        assert_eq!(
+            ManagementData::deserialize(ManagementId::FaultLog, &[], SdoId::default()),
+            Err(ManagementErrorId::NotSupported)
+        );
+    }
+
+    #[test]
+    fn management_id_names() {
+        assert_eq!(
+            "PARENT_DATA_SET".parse::<ManagementId>(),
+            Ok(ManagementId::ParentDataSet)
+        );
+        assert_eq!(
+            "priority1".parse::<ManagementId>(),
+            Ok(ManagementId::Priority1)
+        );
+        assert_eq!(
+            "0xc001".parse::<ManagementId>(),
+            Ok(ManagementId::ImplementationSpecific(0xc001))
+        );
+        assert_eq!("NOT_AN_ID".parse::<ManagementId>(), Err(()));
+
+        for (id, name) in MANAGEMENT_ID_NAMES {
+            assert_eq!(ManagementId::from_primitive(id.to_primitive()), id);
+            assert_eq!(name.parse::<ManagementId>(), Ok(id));
+        }
+    }
+
+    #[test]
+    fn management_request_roundtrip() {
+        let request = ManagementRequest {
+            source_port_identity: PortIdentity {
+                clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+                port_number: 1,
+            },
+            target_port_identity: PortIdentity {
+                clock_identity: ClockIdentity([0xff; 8]),
+                port_number: 0xffff,
+            },
+            domain_number: 3,
+            sdo_id: SdoId::default(),
+            sequence_id: 7,
+            boundary_hops: 1,
+            body: ManagementRequestBody::Set(ManagementData::Priority1(100)),
+        };
+
+        let mut buffer = [0; 128];
+        let length = request.serialize(&mut buffer).unwrap();
+        // header, management fields, and a TLV with id and 2 bytes of data
+        assert_eq!(length, 34 + 14 + 4 + 4);
+
+        let parsed = ManagementResponse::deserialize(&buffer[..length]).unwrap();
+        assert_eq!(parsed.source_port_identity, request.source_port_identity);
+        assert_eq!(parsed.target_port_identity, request.target_port_identity);
+        assert_eq!(parsed.sequence_id, 7);
+        assert_eq!(parsed.action, ManagementAction::SET);
+        assert_eq!(
+            parsed.body,
+            ManagementResponseBody::Data(ManagementData::Priority1(100))
+        );
+    }
 }
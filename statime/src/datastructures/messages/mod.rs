@@ -5,14 +5,15 @@ pub(crate) use delay_req::*;
 pub(crate) use delay_resp::*;
 pub(crate) use follow_up::*;
 pub use header::*;
+pub use management::*;
 pub(crate) use p_delay_req::*;
 pub(crate) use p_delay_resp::*;
 pub(crate) use p_delay_resp_follow_up::*;
+pub(crate) use signalling::*;
 pub(crate) use sync::*;
 
-use self::{management::ManagementMessage, signalling::SignalingMessage};
 use super::{
-    common::{PortIdentity, TimeInterval, TlvSet, WireTimestamp},
+    common::{ClockIdentity, PortIdentity, TimeInterval, TlvSet, WireTimestamp},
     datasets::InternalDefaultDS,
     WireFormatError,
 };
@@ -33,6 +34,7 @@ mod p_delay_req;
 mod p_delay_resp;
 mod p_delay_resp_follow_up;
 mod signalling;
+mod smpte;
 mod sync;
 
 /// Maximum length of a packet
@@ -257,6 +259,28 @@ impl Message<'_> {
         }
     }
 
+    /// A Sync message carrying its own origin timestamp
+    pub(crate) fn one_step_sync(
+        default_ds: &InternalDefaultDS,
+        port_identity: PortIdentity,
+        sequence_id: u16,
+        timestamp: Time,
+    ) -> Self {
+        let header = Header {
+            two_step_flag: false,
+            correction_field: timestamp.subnano(),
+            ..base_header(default_ds, port_identity, sequence_id)
+        };
+
+        Message {
+            header,
+            body: MessageBody::Sync(SyncMessage {
+                origin_timestamp: timestamp.into(),
+            }),
+            suffix: TlvSet::default(),
+        }
+    }
+
     pub(crate) fn follow_up(
         default_ds: &InternalDefaultDS,
         port_identity: PortIdentity,
@@ -299,7 +323,10 @@ impl Message<'_> {
             origin_timestamp: Default::default(),
             current_utc_offset: time_properties_ds.current_utc_offset.unwrap_or_default(),
             grandmaster_priority_1: global.parent_ds.grandmaster_priority_1,
-            grandmaster_clock_quality: global.parent_ds.grandmaster_clock_quality,
+            // How well we track the grandmaster, when we are not it ourselves
+            grandmaster_clock_quality: global
+                .tracking_clock_quality
+                .unwrap_or(global.parent_ds.grandmaster_clock_quality),
             grandmaster_priority_2: global.parent_ds.grandmaster_priority_2,
             grandmaster_identity: global.parent_ds.grandmaster_identity,
             steps_removed: global.current_ds.steps_removed,
@@ -399,6 +426,33 @@ impl Message<'_> {
         }
     }
 
+    /// A Pdelay_Resp message that carries the turnaround time of the request
+    /// in its correction field, for a request received at `request_time` and
+    /// answered at `response_time`
+    pub(crate) fn one_step_pdelay_resp(
+        default_ds: &InternalDefaultDS,
+        port_identity: PortIdentity,
+        request_header: Header,
+        request_time: Time,
+        response_time: Time,
+    ) -> Self {
+        let turnaround = TimeInterval::from(response_time - request_time);
+
+        // See IEEE 1588-2019 section 11.4.2 item c) 3)
+        Message {
+            header: Header {
+                two_step_flag: false,
+                correction_field: TimeInterval(request_header.correction_field.0 + turnaround.0),
+                ..base_header(default_ds, port_identity, request_header.sequence_id)
+            },
+            body: MessageBody::PDelayResp(PDelayRespMessage {
+                request_receive_timestamp: Default::default(),
+                requesting_port_identity: request_header.source_port_identity,
+            }),
+            suffix: TlvSet::default(),
+        }
+    }
+
     pub(crate) fn pdelay_resp_follow_up(
         default_ds: &InternalDefaultDS,
         port_identity: PortIdentity,
@@ -418,6 +472,80 @@ impl Message<'_> {
 }
 
 impl<'a> Message<'a> {
+    pub(crate) fn management_response(
+        default_ds: &InternalDefaultDS,
+        port_identity: PortIdentity,
+        request_header: Header,
+        request: &ManagementMessage,
+        action: ManagementAction,
+        suffix: TlvSet<'a>,
+    ) -> Self {
+        // See IEEE1588-2019 section 15.4.1.4 and 15.4.1.5 for the boundary hops
+        let starting_boundary_hops = request
+            .starting_boundary_hops
+            .saturating_sub(request.boundary_hops);
+
+        Message {
+            header: Header {
+                log_message_interval: 0x7f,
+                ..base_header(default_ds, port_identity, request_header.sequence_id)
+            },
+            body: MessageBody::Management(ManagementMessage {
+                target_port_identity: request_header.source_port_identity,
+                starting_boundary_hops,
+                boundary_hops: starting_boundary_hops,
+                action,
+            }),
+            suffix,
+        }
+    }
+
+    /// The management message that carries the synchronization metadata TLV of
+    /// *SMPTE ST 2059-2 section 9* to all ports in the domain
+    pub(crate) fn smpte_metadata(
+        default_ds: &InternalDefaultDS,
+        port_identity: PortIdentity,
+        sequence_id: u16,
+        suffix: TlvSet<'a>,
+    ) -> Self {
+        Message {
+            header: Header {
+                log_message_interval: 0x7f,
+                ..base_header(default_ds, port_identity, sequence_id)
+            },
+            body: MessageBody::Management(ManagementMessage {
+                target_port_identity: PortIdentity {
+                    clock_identity: ClockIdentity([0xff; 8]),
+                    port_number: 0xffff,
+                },
+                starting_boundary_hops: 0,
+                boundary_hops: 0,
+                action: ManagementAction::COMMAND,
+            }),
+            suffix,
+        }
+    }
+
+    pub(crate) fn signaling(
+        default_ds: &InternalDefaultDS,
+        port_identity: PortIdentity,
+        target_port_identity: PortIdentity,
+        sequence_id: u16,
+        suffix: TlvSet<'a>,
+    ) -> Self {
+        Message {
+            header: Header {
+                unicast_flag: true,
+                log_message_interval: 0x7f,
+                ..base_header(default_ds, port_identity, sequence_id)
+            },
+            body: MessageBody::Signaling(SignalingMessage {
+                target_port_identity,
+            }),
+            suffix,
+        }
+    }
+
     pub(crate) fn header(&self) -> &Header {
         &self.header
     }
@@ -1,8 +1,12 @@
-use crate::datastructures::{common::PortIdentity, WireFormat, WireFormatError};
+use super::MessageType;
+use crate::datastructures::{
+    common::{ClockIdentity, PortIdentity, Tlv, TlvType},
+    WireFormat, WireFormatError,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct SignalingMessage {
-    pub(super) target_port_identity: PortIdentity,
+    pub(crate) target_port_identity: PortIdentity,
 }
 
 impl SignalingMessage {
@@ -30,4 +34,206 @@ impl SignalingMessage {
             target_port_identity,
         })
     }
+
+    pub(crate) fn is_addressed_to(&self, port_identity: PortIdentity) -> bool {
+        let target = self.target_port_identity;
+
+        (target.clock_identity == ClockIdentity([0xff; 8])
+            || target.clock_identity == port_identity.clock_identity)
+            && (target.port_number == 0xffff || target.port_number == port_identity.port_number)
+    }
+}
+
+/// The unicast negotiation TLVs carried by signaling messages
+///
+/// See *IEEE1588-2019 section 16.1.4*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnicastNegotiationTlv {
+    Request {
+        message_type: MessageType,
+        log_inter_message_period: i8,
+        duration: u32,
+    },
+    Grant {
+        message_type: MessageType,
+        log_inter_message_period: i8,
+        duration: u32,
+        renewal_invited: bool,
+    },
+    Cancel {
+        message_type: MessageType,
+    },
+    AcknowledgeCancel {
+        message_type: MessageType,
+    },
+}
+
+impl UnicastNegotiationTlv {
+    fn tlv_type(&self) -> TlvType {
+        match self {
+            Self::Request { .. } => TlvType::RequestUnicastTransmission,
+            Self::Grant { .. } => TlvType::GrantUnicastTransmission,
+            Self::Cancel { .. } => TlvType::CancelUnicastTransmission,
+            Self::AcknowledgeCancel { .. } => TlvType::AcknowledgeCancelUnicastTransmission,
+        }
+    }
+
+    fn message_type(&self) -> MessageType {
+        match *self {
+            Self::Request { message_type, .. }
+            | Self::Grant { message_type, .. }
+            | Self::Cancel { message_type }
+            | Self::AcknowledgeCancel { message_type } => message_type,
+        }
+    }
+
+    fn wire_size(&self) -> usize {
+        match self {
+            Self::Request { .. } => 6,
+            Self::Grant { .. } => 8,
+            Self::Cancel { .. } | Self::AcknowledgeCancel { .. } => 2,
+        }
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        if buffer.len() < self.wire_size() {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
+        // The message type is stored in the upper nibble, the lower one is reserved
+        buffer[0] = (self.message_type() as u8) << 4;
+
+        match *self {
+            Self::Request {
+                log_inter_message_period,
+                duration,
+                ..
+            } => {
+                buffer[1] = log_inter_message_period as u8;
+                buffer[2..6].copy_from_slice(&duration.to_be_bytes());
+            }
+            Self::Grant {
+                log_inter_message_period,
+                duration,
+                renewal_invited,
+                ..
+            } => {
+                buffer[1] = log_inter_message_period as u8;
+                buffer[2..6].copy_from_slice(&duration.to_be_bytes());
+                buffer[6] = 0;
+                buffer[7] = renewal_invited as u8;
+            }
+            Self::Cancel { .. } | Self::AcknowledgeCancel { .. } => {
+                buffer[1] = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..self.wire_size())
+            .ok_or(WireFormatError::BufferTooShort)?;
+        self.serialize(value)?;
+
+        Ok(Tlv {
+            tlv_type: self.tlv_type(),
+            value: (&*value).into(),
+        })
+    }
+
+    /// Parse a TLV, returns `None` for TLVs that are not unicast negotiation
+    /// TLVs or are malformed
+    pub(crate) fn from_tlv(tlv: &Tlv<'_>) -> Option<Self> {
+        let value = &tlv.value;
+        let message_type = MessageType::try_from(value.first()? >> 4).ok()?;
+
+        match tlv.tlv_type {
+            TlvType::RequestUnicastTransmission => Some(Self::Request {
+                message_type,
+                log_inter_message_period: *value.get(1)? as i8,
+                duration: u32::from_be_bytes(value.get(2..6)?.try_into().ok()?),
+            }),
+            TlvType::GrantUnicastTransmission => Some(Self::Grant {
+                message_type,
+                log_inter_message_period: *value.get(1)? as i8,
+                duration: u32::from_be_bytes(value.get(2..6)?.try_into().ok()?),
+                renewal_invited: value.get(7)? & 1 != 0,
+            }),
+            TlvType::CancelUnicastTransmission => Some(Self::Cancel { message_type }),
+            TlvType::AcknowledgeCancelUnicastTransmission => {
+                Some(Self::AcknowledgeCancel { message_type })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicast_negotiation_tlv_wireformat() {
+        let representations = [
+            (
+                UnicastNegotiationTlv::Request {
+                    message_type: MessageType::Sync,
+                    log_inter_message_period: -3,
+                    duration: 300,
+                },
+                TlvType::RequestUnicastTransmission,
+                &[0x00, 0xfd, 0x00, 0x00, 0x01, 0x2c][..],
+            ),
+            (
+                UnicastNegotiationTlv::Grant {
+                    message_type: MessageType::Announce,
+                    log_inter_message_period: 1,
+                    duration: 60,
+                    renewal_invited: true,
+                },
+                TlvType::GrantUnicastTransmission,
+                &[0xb0, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x01][..],
+            ),
+            (
+                UnicastNegotiationTlv::Cancel {
+                    message_type: MessageType::DelayResp,
+                },
+                TlvType::CancelUnicastTransmission,
+                &[0x90, 0x00][..],
+            ),
+            (
+                UnicastNegotiationTlv::AcknowledgeCancel {
+                    message_type: MessageType::Sync,
+                },
+                TlvType::AcknowledgeCancelUnicastTransmission,
+                &[0x00, 0x00][..],
+            ),
+        ];
+
+        for (object, tlv_type, bytes) in representations {
+            let mut buffer = [0; 8];
+            let tlv = object.to_tlv(&mut buffer).unwrap();
+            assert_eq!(tlv.tlv_type, tlv_type);
+            assert_eq!(&*tlv.value, bytes);
+
+            assert_eq!(UnicastNegotiationTlv::from_tlv(&tlv), Some(object));
+        }
+    }
+
+    #[test]
+    fn unicast_negotiation_tlv_malformed() {
+        let tlv = Tlv {
+            tlv_type: TlvType::RequestUnicastTransmission,
+            value: (&[0x00, 0x00, 0x00][..]).into(),
+        };
+        assert_eq!(UnicastNegotiationTlv::from_tlv(&tlv), None);
+
+        let tlv = Tlv {
+            tlv_type: TlvType::Management,
+            value: (&[0x00, 0x00][..]).into(),
+        };
+        assert_eq!(UnicastNegotiationTlv::from_tlv(&tlv), None);
+    }
 }
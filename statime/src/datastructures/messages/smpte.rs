@@ -0,0 +1,156 @@
+use crate::{
+    config::Profile,
+    datastructures::{
+        common::{Tlv, TlvType},
+        WireFormatError,
+    },
+    observability::smpte::{MasterLockingStatus, SynchronizationMetadata},
+};
+
+/// Wire format of the synchronization metadata TLV of *SMPTE ST 2059-2
+/// section 9*
+impl SynchronizationMetadata {
+    const ORGANIZATION_ID: [u8; 3] = [0x68, 0x97, 0xe8];
+    const ORGANIZATION_SUB_TYPE: [u8; 3] = [0x00, 0x00, 0x01];
+    pub(crate) const WIRE_SIZE: usize = 48;
+    /// Size of the management message carrying the TLV: header, management
+    /// body, TLV header and value
+    pub(crate) const MESSAGE_WIRE_SIZE: usize = 34 + 14 + 4 + Self::WIRE_SIZE;
+
+    /// The metadata a grandmaster with the given profile sends, `None` when the
+    /// profile is not the SMPTE profile
+    pub(crate) fn for_grandmaster(profile: Profile) -> Option<Self> {
+        match profile {
+            Profile::Smpte2059 { metadata } => Some(metadata),
+            _ => None,
+        }
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) -> Result<(), WireFormatError> {
+        if buffer.len() < Self::WIRE_SIZE {
+            return Err(WireFormatError::BufferTooShort);
+        }
+
+        buffer[0..3].copy_from_slice(&Self::ORGANIZATION_ID);
+        buffer[3..6].copy_from_slice(&Self::ORGANIZATION_SUB_TYPE);
+        buffer[6..10].copy_from_slice(&self.frame_rate_numerator.to_be_bytes());
+        buffer[10..14].copy_from_slice(&self.frame_rate_denominator.to_be_bytes());
+        buffer[14] = self.master_locking_status.to_primitive();
+        buffer[15] = self.drop_frame as u8 | (self.color_frame_identification as u8) << 1;
+        buffer[16..20].copy_from_slice(&self.current_local_offset.to_be_bytes());
+        buffer[20..24].copy_from_slice(&self.jump_seconds.to_be_bytes());
+        buffer[24..30].copy_from_slice(&self.time_of_next_jump.to_be_bytes()[2..]);
+        buffer[30..36].copy_from_slice(&self.time_of_next_jam.to_be_bytes()[2..]);
+        buffer[36..42].copy_from_slice(&self.time_of_previous_jam.to_be_bytes()[2..]);
+        buffer[42..46].copy_from_slice(&self.previous_jam_local_offset.to_be_bytes());
+        buffer[46] = self.daylight_saving as u8
+            | (self.daylight_saving_at_next_jump as u8) << 1
+            | (self.daylight_saving_at_previous_jam as u8) << 2;
+        buffer[47] = self.leap_second_jump as u8;
+
+        Ok(())
+    }
+
+    pub(crate) fn to_tlv<'a>(self, buffer: &'a mut [u8]) -> Result<Tlv<'a>, WireFormatError> {
+        let value = buffer
+            .get_mut(..Self::WIRE_SIZE)
+            .ok_or(WireFormatError::BufferTooShort)?;
+        self.serialize(value)?;
+
+        Ok(Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&*value).into(),
+        })
+    }
+
+    /// Parse a TLV, returns `None` for TLVs that are not a synchronization
+    /// metadata TLV or are malformed
+    pub(crate) fn from_tlv(tlv: &Tlv<'_>) -> Option<Self> {
+        let value = &tlv.value;
+        if tlv.tlv_type != TlvType::OrganizationExtension
+            || value.len() != Self::WIRE_SIZE
+            || value[0..3] != Self::ORGANIZATION_ID
+            || value[3..6] != Self::ORGANIZATION_SUB_TYPE
+        {
+            return None;
+        }
+
+        let u48 = |bytes: &[u8]| {
+            let mut buffer = [0; 8];
+            buffer[2..].copy_from_slice(bytes);
+            u64::from_be_bytes(buffer)
+        };
+
+        Some(Self {
+            frame_rate_numerator: u32::from_be_bytes(value[6..10].try_into().ok()?),
+            frame_rate_denominator: u32::from_be_bytes(value[10..14].try_into().ok()?),
+            master_locking_status: MasterLockingStatus::from_primitive(value[14])?,
+            drop_frame: value[15] & 1 != 0,
+            color_frame_identification: value[15] & (1 << 1) != 0,
+            current_local_offset: i32::from_be_bytes(value[16..20].try_into().ok()?),
+            jump_seconds: i32::from_be_bytes(value[20..24].try_into().ok()?),
+            time_of_next_jump: u48(&value[24..30]),
+            time_of_next_jam: u48(&value[30..36]),
+            time_of_previous_jam: u48(&value[36..42]),
+            previous_jam_local_offset: i32::from_be_bytes(value[42..46].try_into().ok()?),
+            daylight_saving: value[46] & 1 != 0,
+            daylight_saving_at_next_jump: value[46] & (1 << 1) != 0,
+            daylight_saving_at_previous_jam: value[46] & (1 << 2) != 0,
+            leap_second_jump: value[47] & 1 != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synchronization_metadata_tlv_wireformat() {
+        let object = SynchronizationMetadata {
+            frame_rate_numerator: 30000,
+            frame_rate_denominator: 1001,
+            master_locking_status: MasterLockingStatus::Locked,
+            drop_frame: true,
+            color_frame_identification: false,
+            current_local_offset: -3600,
+            jump_seconds: 3600,
+            time_of_next_jump: 0x0102_0304_0506,
+            time_of_next_jam: 0x0a0b_0c0d_0e0f,
+            time_of_previous_jam: 1,
+            previous_jam_local_offset: -7200,
+            daylight_saving: false,
+            daylight_saving_at_next_jump: true,
+            daylight_saving_at_previous_jam: true,
+            leap_second_jump: false,
+        };
+        let bytes = [
+            0x68, 0x97, 0xe8, 0x00, 0x00, 0x01, 0x00, 0x00, 0x75, 0x30, 0x00, 0x00, 0x03, 0xe9,
+            0x04, 0x01, 0xff, 0xff, 0xf1, 0xf0, 0x00, 0x00, 0x0e, 0x10, 0x01, 0x02, 0x03, 0x04,
+            0x05, 0x06, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0xff, 0xff, 0xe3, 0xe0, 0x06, 0x00,
+        ];
+
+        let mut buffer = [0; 48];
+        let tlv = object.to_tlv(&mut buffer).unwrap();
+        assert_eq!(tlv.tlv_type, TlvType::OrganizationExtension);
+        assert_eq!(&*tlv.value, &bytes[..]);
+        assert_eq!(SynchronizationMetadata::from_tlv(&tlv), Some(object));
+
+        // Unknown locking states are malformed
+        let mut malformed = bytes;
+        malformed[14] = 5;
+        let tlv = Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&malformed[..]).into(),
+        };
+        assert_eq!(SynchronizationMetadata::from_tlv(&tlv), None);
+
+        // As are truncated TLVs
+        let tlv = Tlv {
+            tlv_type: TlvType::OrganizationExtension,
+            value: (&bytes[..40]).into(),
+        };
+        assert_eq!(SynchronizationMetadata::from_tlv(&tlv), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Implementation of [AnyFilter]
+
+use super::{
+    BasicFilter, Filter, FilterUpdate, HoldoverEstimate, KalmanConfiguration, KalmanFilter,
+    LinregConfiguration, LinregFilter, PiConfiguration, PiFilter,
+};
+use crate::{port::Measurement, time::Duration, Clock};
+
+/// Configuration of an [`AnyFilter`], which selects the filter as well
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnyFilterConfiguration {
+    /// A [`BasicFilter`] with the given gain
+    Basic(f64),
+    /// A [`KalmanFilter`]
+    Kalman(KalmanConfiguration),
+    /// A [`PiFilter`]
+    Pi(PiConfiguration),
+    /// A [`LinregFilter`]
+    Linreg(LinregConfiguration),
+}
+
+impl Default for AnyFilterConfiguration {
+    fn default() -> Self {
+        Self::Kalman(KalmanConfiguration::default())
+    }
+}
+
+/// Any of the filters of this crate, chosen at runtime by its configuration.
+///
+/// The ports of a [`PtpInstance`](`crate::PtpInstance`) all have the same
+/// filter type. With this filter, each port can still use a different filter,
+/// as selected by the filter configuration passed to
+/// [`PtpInstance::add_port`](`crate::PtpInstance::add_port`).
+// Without an allocator the filters can't be boxed
+#[allow(clippy::large_enum_variant)]
+pub enum AnyFilter {
+    /// A [`BasicFilter`]
+    Basic(BasicFilter),
+    /// A [`KalmanFilter`]
+    Kalman(KalmanFilter),
+    /// A [`PiFilter`]
+    Pi(PiFilter),
+    /// A [`LinregFilter`]
+    Linreg(LinregFilter),
+}
+
+impl Filter for AnyFilter {
+    type Config = AnyFilterConfiguration;
+
+    fn new(config: AnyFilterConfiguration) -> Self {
+        match config {
+            AnyFilterConfiguration::Basic(gain) => Self::Basic(BasicFilter::new(gain)),
+            AnyFilterConfiguration::Kalman(config) => Self::Kalman(KalmanFilter::new(config)),
+            AnyFilterConfiguration::Pi(config) => Self::Pi(PiFilter::new(config)),
+            AnyFilterConfiguration::Linreg(config) => Self::Linreg(LinregFilter::new(config)),
+        }
+    }
+
+    fn measurement<C: Clock>(&mut self, m: Measurement, clock: &mut C) -> FilterUpdate {
+        match self {
+            Self::Basic(filter) => filter.measurement(m, clock),
+            Self::Kalman(filter) => filter.measurement(m, clock),
+            Self::Pi(filter) => filter.measurement(m, clock),
+            Self::Linreg(filter) => filter.measurement(m, clock),
+        }
+    }
+
+    fn update<C: Clock>(&mut self, clock: &mut C) -> FilterUpdate {
+        match self {
+            Self::Basic(filter) => filter.update(clock),
+            Self::Kalman(filter) => filter.update(clock),
+            Self::Pi(filter) => filter.update(clock),
+            Self::Linreg(filter) => filter.update(clock),
+        }
+    }
+
+    fn demobilize<C: Clock>(self, clock: &mut C) {
+        match self {
+            Self::Basic(filter) => filter.demobilize(clock),
+            Self::Kalman(filter) => filter.demobilize(clock),
+            Self::Pi(filter) => filter.demobilize(clock),
+            Self::Linreg(filter) => filter.demobilize(clock),
+        }
+    }
+
+    fn offset_uncertainty(&self) -> Option<Duration> {
+        match self {
+            Self::Basic(filter) => filter.offset_uncertainty(),
+            Self::Kalman(filter) => filter.offset_uncertainty(),
+            Self::Pi(filter) => filter.offset_uncertainty(),
+            Self::Linreg(filter) => filter.offset_uncertainty(),
+        }
+    }
+
+    fn holdover_estimate(&self) -> Option<HoldoverEstimate> {
+        match self {
+            Self::Basic(filter) => filter.holdover_estimate(),
+            Self::Kalman(filter) => filter.holdover_estimate(),
+            Self::Pi(filter) => filter.holdover_estimate(),
+            Self::Linreg(filter) => filter.holdover_estimate(),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use super::matrix::{Matrix, Vector};
 #[allow(unused_imports)]
 use crate::float_polyfill::FloatPolyfill;
 use crate::{
-    filters::Filter,
+    filters::{Filter, HoldoverEstimate},
     port::Measurement,
     time::{Duration, Time},
 };
@@ -622,6 +622,23 @@ impl Filter for KalmanFilter {
         // of correct
         self.change_frequency(0.0, clock);
     }
+
+    fn offset_uncertainty(&self) -> Option<Duration> {
+        self.running_filter.0.as_ref()?;
+
+        Some(Duration::from_fixed_nanos(
+            self.running_filter.offset_uncertainty(&self.config) * 1e9,
+        ))
+    }
+
+    fn holdover_estimate(&self) -> Option<HoldoverEstimate> {
+        // Without a measurement there is nothing to hold over
+        Some(HoldoverEstimate {
+            offset_uncertainty: self.offset_uncertainty()?,
+            frequency_uncertainty: self.running_filter.freq_offset_uncertainty(&self.config),
+            wander: self.wander,
+        })
+    }
 }
 
 impl KalmanFilter {
@@ -0,0 +1,481 @@
+//! Implementation of [LinregFilter]
+
+use super::{moving_median::MovingMedian, pi::ServoState, Filter, FilterUpdate};
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
+use crate::{
+    port::Measurement,
+    time::{Duration, Time},
+    Clock,
+};
+
+/// Configuration options for [LinregFilter]
+///
+/// The defaults are those of linuxptp.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinregConfiguration {
+    /// Threshold above which the time is corrected by a step on the first
+    /// update of the clock, rather than slewed. `None` always slews.
+    pub first_step_threshold: Option<Duration>,
+    /// Threshold above which the time is corrected by a step at any time.
+    /// `None` never steps after the first update.
+    pub step_threshold: Option<Duration>,
+    /// Maximum frequency offset of the clock (ppm)
+    pub max_frequency: f64,
+}
+
+impl Default for LinregConfiguration {
+    fn default() -> Self {
+        Self {
+            first_step_threshold: Some(Duration::from_micros(20)),
+            step_threshold: None,
+            max_frequency: 500.0,
+        }
+    }
+}
+
+// The window sizes are powers of two, from 4 up to 64 measurements
+const MIN_SIZE: usize = 2;
+const MAX_SIZE: usize = 6;
+const MAX_POINTS: usize = 1 << MAX_SIZE;
+const SIZES: usize = MAX_SIZE - MIN_SIZE + 1;
+
+// Smoothing factor of the average prediction error
+const ERR_SMOOTH: f64 = 0.02;
+// Number of predictions averaged before the error is smoothed
+const ERR_INITIAL_UPDATES: usize = 10;
+// Maximum ratio of two prediction errors to consider them equal
+const ERR_EQUALS: f64 = 1.05;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Point {
+    // Time of the clock without our frequency corrections
+    x: Duration,
+    // Time of the master
+    y: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Regression {
+    // Rate of the master relative to the uncorrected clock
+    slope: f64,
+    // Offset of the master to the clock at the reference (ns)
+    intercept: f64,
+    // Time covered by the window (ns)
+    span: f64,
+    // Average error of the intercept predicted by the previous regression
+    err: f64,
+    err_updates: usize,
+}
+
+/// A servo estimating the frequency and offset of the clock by linear
+/// regression, equivalent to the `linreg` servo of linuxptp.
+///
+/// The regression runs over windows of the last 4, 8, up to 64
+/// measurements. Each window predicts the offset of the next measurement, and
+/// the largest window with the smallest prediction error is used to steer the
+/// clock. With a lot of packet delay variation it converges faster than a PI
+/// servo, and is less affected by outliers.
+#[derive(Debug)]
+pub struct LinregFilter {
+    config: LinregConfiguration,
+
+    // Ring buffer of the last measurements
+    points: [Point; MAX_POINTS],
+    num_points: usize,
+    last_point: usize,
+
+    // The current time, in both the x and y of the points
+    reference: Point,
+    last_update: Option<Time>,
+
+    results: [Option<Regression>; SIZES],
+    // Selected window size, as power of two
+    size: Option<usize>,
+
+    // Frequency correction of the clock (ppb)
+    clock_freq: f64,
+
+    first_update: bool,
+    frequency_initialized: bool,
+    delays: MovingMedian,
+}
+
+impl Filter for LinregFilter {
+    type Config = LinregConfiguration;
+
+    fn new(config: LinregConfiguration) -> Self {
+        Self {
+            config,
+            points: [Point::default(); MAX_POINTS],
+            num_points: 0,
+            last_point: 0,
+            reference: Point::default(),
+            last_update: None,
+            results: [None; SIZES],
+            size: None,
+            clock_freq: 0.0,
+            first_update: true,
+            frequency_initialized: false,
+            delays: MovingMedian::new(),
+        }
+    }
+
+    fn measurement<C: Clock>(&mut self, m: Measurement, clock: &mut C) -> FilterUpdate {
+        let mut update = FilterUpdate::default();
+
+        if let Some(delay) = m.peer_delay.or(m.delay) {
+            update.mean_delay = Some(self.delays.absorb(delay));
+        }
+
+        let Some(offset) = m.offset else {
+            return update;
+        };
+
+        if !self.frequency_initialized {
+            // We cannot read the frequency of the clock, so start from a known
+            // one
+            if let Err(error) = clock.set_frequency(0.0) {
+                log::error!("Could not initialize clock frequency: {:?}", error);
+            } else {
+                self.frequency_initialized = true;
+            }
+        }
+
+        let (ppb, state) = self.sample(offset, m.event_time);
+
+        match state {
+            ServoState::Unlocked => {}
+            ServoState::Jump => {
+                if let Err(error) = clock.set_frequency(ppb / 1e3) {
+                    log::error!("Could not adjust clock frequency: {:?}", error);
+                }
+                if let Err(error) = clock.step_clock(-offset) {
+                    log::error!("Could not step clock: {:?}", error);
+                } else {
+                    log::info!("Stepped clock by {}", -offset);
+                }
+            }
+            ServoState::Locked => {
+                if let Err(error) = clock.set_frequency(ppb / 1e3) {
+                    log::error!("Could not adjust clock frequency: {:?}", error);
+                }
+            }
+        }
+
+        log::info!(
+            "Offset to master: {}ns, frequency {}ppb, window {:?}, {:?}",
+            offset.nanos_lossy(),
+            ppb,
+            self.size.map(|size| 1 << size),
+            state
+        );
+
+        update
+    }
+
+    fn update<C: Clock>(&mut self, _clock: &mut C) -> FilterUpdate {
+        // ignore
+        Default::default()
+    }
+
+    fn demobilize<C: Clock>(self, _clock: &mut C) {
+        // Like ptp4l, the clock keeps its last frequency
+    }
+}
+
+impl LinregFilter {
+    // Move the reference, and the intercepts along with it
+    fn move_reference(&mut self, x: Duration, y: Duration) {
+        self.reference.x += x;
+        self.reference.y += y;
+
+        for result in self.results.iter_mut().flatten() {
+            result.intercept += x.nanos_lossy() * result.slope - y.nanos_lossy();
+        }
+    }
+
+    fn update_reference(&mut self, time: Time) {
+        if let Some(last_update) = self.last_update {
+            let y_interval = time - last_update;
+            // Without our frequency correction
+            let x_interval = Duration::from_fixed_nanos(
+                y_interval.nanos_lossy() / (1.0 + self.clock_freq / 1e9),
+            );
+            self.move_reference(x_interval, y_interval);
+        }
+
+        self.last_update = Some(time);
+    }
+
+    fn add_point(&mut self, offset: Duration) {
+        self.last_point = (self.last_point + 1) % MAX_POINTS;
+        self.points[self.last_point] = Point {
+            x: self.reference.x,
+            y: self.reference.y - offset,
+        };
+        self.num_points = (self.num_points + 1).min(MAX_POINTS);
+    }
+
+    fn regress(&mut self) {
+        let y0 = (self.points[self.last_point].y - self.reference.y).nanos_lossy();
+
+        let mut x_sum = 0.0;
+        let mut y_sum = 0.0;
+        let mut xy_sum = 0.0;
+        let mut x2_sum = 0.0;
+        let mut x = 0.0;
+        let mut i = 0;
+
+        for size in MIN_SIZE..=MAX_SIZE {
+            let n = 1 << size;
+            if n > self.num_points {
+                break;
+            }
+
+            // The windows grow from the newest point back, so the sums of the
+            // smaller windows are reused
+            while i < n {
+                let point = self.points[(MAX_POINTS + self.last_point - i) % MAX_POINTS];
+                x = (point.x - self.reference.x).nanos_lossy();
+                let y = (point.y - self.reference.y).nanos_lossy();
+
+                x_sum += x;
+                y_sum += y;
+                xy_sum += x * y;
+                x2_sum += x * x;
+                i += 1;
+            }
+
+            let n = n as f64;
+            // Measurements all at the same time give no slope, the previous
+            // regression of this window is kept until they spread out
+            let denominator = x2_sum - x_sum * x_sum / n;
+            if denominator <= f64::EPSILON * x2_sum {
+                continue;
+            }
+            let slope = (xy_sum - x_sum * y_sum / n) / denominator;
+            let intercept = (y_sum - slope * x_sum) / n;
+
+            let result = &mut self.results[size - MIN_SIZE];
+            let (err, err_updates) = match result {
+                Some(previous) => {
+                    let e = (previous.intercept - y0).abs();
+                    if previous.err_updates < ERR_INITIAL_UPDATES {
+                        let updates = previous.err_updates + 1;
+                        (
+                            (previous.err * previous.err_updates as f64 + e) / updates as f64,
+                            updates,
+                        )
+                    } else {
+                        (
+                            previous.err + ERR_SMOOTH * (e - previous.err),
+                            previous.err_updates,
+                        )
+                    }
+                }
+                None => (0.0, 0),
+            };
+
+            *result = Some(Regression {
+                slope,
+                intercept,
+                span: -x,
+                err,
+                err_updates,
+            });
+        }
+    }
+
+    // Select the largest window with the smallest prediction error
+    fn update_size(&mut self) {
+        let mut best: Option<(usize, f64)> = None;
+
+        for size in MIN_SIZE..=MAX_SIZE {
+            let Some(result) = self.results[size - MIN_SIZE] else {
+                continue;
+            };
+
+            match best {
+                None => best = Some((size, result.err)),
+                Some((_, best_err))
+                    if best_err * ERR_EQUALS > result.err
+                        && result.err_updates >= ERR_INITIAL_UPDATES =>
+                {
+                    best = Some((size, result.err))
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.size = best.map(|(size, _)| size);
+    }
+
+    fn exceeds(threshold: Option<Duration>, offset: f64) -> bool {
+        threshold.map_or(false, |threshold| offset.abs() > threshold.nanos_lossy())
+    }
+
+    // Returns the frequency correction of the clock in ppb
+    fn sample(&mut self, offset: Duration, time: Time) -> (f64, ServoState) {
+        self.update_reference(time);
+        self.add_point(offset);
+        self.regress();
+        self.update_size();
+
+        let Some(mut result) = self.size.and_then(|size| self.results[size - MIN_SIZE]) else {
+            // Not enough measurements yet
+            return (self.clock_freq, ServoState::Unlocked);
+        };
+
+        let state = if (self.first_update
+            && Self::exceeds(self.config.first_step_threshold, result.intercept))
+            || Self::exceeds(self.config.step_threshold, result.intercept)
+        {
+            // The clock is stepped by the offset, which the time of the clock
+            // at the last update and the intercepts follow
+            self.move_reference(Duration::ZERO, -offset);
+            self.last_update = self.last_update.map(|time| time - offset);
+            result.intercept += offset.nanos_lossy();
+            ServoState::Jump
+        } else {
+            ServoState::Locked
+        };
+        self.first_update = false;
+
+        // Run at the frequency of the master, and correct the offset over half
+        // the window, so the correction hardly affects the next regression
+        let max_frequency = self.config.max_frequency * 1e3;
+        let correction = if result.span > 0.0 {
+            result.intercept / (result.span / 2.0) * 1e9
+        } else {
+            0.0
+        };
+        self.clock_freq =
+            (1e9 * (result.slope - 1.0) + correction).clamp(-max_frequency, max_frequency);
+
+        (self.clock_freq, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A clock running `skew` too fast, which is corrected by the filter
+    struct Simulation {
+        filter: LinregFilter,
+        master: f64,
+        local: f64,
+        skew: f64,
+        frequency: f64,
+        noise: u64,
+    }
+
+    impl Simulation {
+        fn new(config: LinregConfiguration, offset: f64, skew: f64) -> Self {
+            Self {
+                filter: LinregFilter::new(config),
+                master: 1000.0,
+                local: 1000.0 + offset,
+                skew,
+                frequency: 0.0,
+                noise: 1,
+            }
+        }
+
+        // Advance a second, and measure with up to `noise` seconds of error
+        fn step(&mut self, noise: f64) -> ServoState {
+            self.master += 1.0;
+            self.local += 1.0 + self.skew + self.frequency * 1e-9;
+
+            // A simple deterministic pseudorandom generator
+            self.noise = self.noise.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let random = (self.noise >> 11) as f64 / (1u64 << 53) as f64;
+            let offset = self.local - self.master + noise * (2.0 * random - 1.0);
+
+            let offset = Duration::from_seconds(offset);
+            let (ppb, state) = self
+                .filter
+                .sample(offset, Time::from_fixed_nanos(self.local * 1e9));
+            match state {
+                ServoState::Unlocked => {}
+                ServoState::Jump => {
+                    self.frequency = ppb;
+                    self.local -= offset.seconds();
+                }
+                ServoState::Locked => self.frequency = ppb,
+            }
+            state
+        }
+
+        fn offset(&self) -> f64 {
+            self.local - self.master
+        }
+    }
+
+    #[test]
+    fn step_then_converge() {
+        let mut simulation = Simulation::new(Default::default(), 50e-6, 10e-6);
+
+        for _ in 0..3 {
+            assert_eq!(simulation.step(0.0), ServoState::Unlocked);
+        }
+        assert_eq!(simulation.step(0.0), ServoState::Jump);
+        assert!(simulation.offset().abs() < 1e-6, "{}", simulation.offset());
+
+        for _ in 0..60 {
+            assert_eq!(simulation.step(0.0), ServoState::Locked);
+        }
+        assert!(simulation.offset().abs() < 1e-9, "{}", simulation.offset());
+        assert!(
+            (simulation.frequency + 10e3).abs() < 1.0,
+            "{}",
+            simulation.frequency
+        );
+    }
+
+    #[test]
+    fn small_offset_is_slewed() {
+        let mut simulation = Simulation::new(Default::default(), 10e-6, 0.0);
+        for _ in 0..3 {
+            simulation.step(0.0);
+        }
+        assert_eq!(simulation.step(0.0), ServoState::Locked);
+    }
+
+    #[test]
+    fn window_grows_with_noise() {
+        let mut simulation = Simulation::new(Default::default(), 0.0, 10e-6);
+        for _ in 0..500 {
+            simulation.step(1e-6);
+        }
+        assert_eq!(simulation.filter.size, Some(MAX_SIZE));
+        assert!(simulation.offset().abs() < 1e-6, "{}", simulation.offset());
+    }
+
+    #[test]
+    fn measurements_at_the_same_time() {
+        let mut filter = LinregFilter::new(Default::default());
+        for _ in 0..8 {
+            let (ppb, state) = filter.sample(Duration::from_micros(1), Time::from_secs(10));
+            assert_eq!((ppb, state), (0.0, ServoState::Unlocked));
+        }
+        assert!(filter.results.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn max_frequency() {
+        let mut simulation = Simulation::new(
+            LinregConfiguration {
+                max_frequency: 5.0,
+                ..Default::default()
+            },
+            0.0,
+            10e-6,
+        );
+        for _ in 0..10 {
+            simulation.step(0.0);
+        }
+        assert_eq!(simulation.frequency, -5e3);
+    }
+}
@@ -1,12 +1,21 @@
 //! Definitions and implementations for the abstracted measurement filters
 
+mod any;
 mod basic;
 mod kalman;
+mod linreg;
 mod matrix;
+mod moving_median;
+mod pi;
 
+pub use any::{AnyFilter, AnyFilterConfiguration};
 pub use basic::BasicFilter;
 pub use kalman::{KalmanConfiguration, KalmanFilter};
+pub use linreg::{LinregConfiguration, LinregFilter};
+pub use pi::{PiConfiguration, PiFilter};
 
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
 use crate::{port::Measurement, time::Duration, Clock};
 
 /// Information on the result of the [`Filter`] and the actions it needs from
@@ -23,6 +32,34 @@ pub struct FilterUpdate {
     pub mean_delay: Option<Duration>,
 }
 
+/// How the uncertainty of the time of a clock grows once its filter gets no
+/// more measurements, while the clock keeps running at the frequency it was
+/// steered to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoldoverEstimate {
+    /// Standard deviation of the offset of the clock at the last measurement
+    pub offset_uncertainty: Duration,
+    /// Standard deviation of the frequency offset of the clock, in seconds per
+    /// second
+    pub frequency_uncertainty: f64,
+    /// How fast the frequency of the clock wanders off, as the growth of the
+    /// variance of the frequency in s²/s³
+    pub wander: f64,
+}
+
+impl HoldoverEstimate {
+    /// The uncertainty of the time of the clock `elapsed` after the last
+    /// measurement
+    pub fn uncertainty_after(&self, elapsed: Duration) -> Duration {
+        let t = elapsed.seconds();
+        // In nanoseconds, as seconds lose the precision we need here
+        let variance = self.offset_uncertainty.nanos_lossy().powi(2)
+            + (self.frequency_uncertainty * t * 1e9).powi(2)
+            + self.wander * t * t * t / 3.0 * 1e18;
+        Duration::from_fixed_nanos(variance.sqrt())
+    }
+}
+
 /// A filter for post-processing time measurements.
 ///
 /// Filters are responsible for dealing with the network noise, and should
@@ -31,6 +68,7 @@ pub struct FilterUpdate {
 ///
 /// This crate provides a simple [`BasicFilter`] which is
 /// suitable for most needs, but users can implement their own if desired.
+/// [`AnyFilter`] selects one of the filters of this crate at runtime.
 pub trait Filter {
     /// Configuration for this [`Filter`]
     ///
@@ -51,4 +89,50 @@ pub trait Filter {
     /// Handle ending of time synchronization from the source
     /// associated with this filter.
     fn demobilize<C: Clock>(self, clock: &mut C);
+
+    /// Standard deviation of the offset of the clock to the master, as
+    /// estimated by the filter now. While the filter steers the clock, the
+    /// clock quality that master ports announce is derived from it. `None`
+    /// when the filter has no estimate of its uncertainty.
+    fn offset_uncertainty(&self) -> Option<Duration> {
+        None
+    }
+
+    /// How the time of the clock drifts off when the filter is demobilized
+    /// now, used for the holdover of the instance. `None` when the filter has
+    /// no estimate of its uncertainty.
+    fn holdover_estimate(&self) -> Option<HoldoverEstimate> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holdover_uncertainty() {
+        let estimate = HoldoverEstimate {
+            offset_uncertainty: Duration::from_nanos(30),
+            frequency_uncertainty: 1e-9,
+            wander: 0.0,
+        };
+        let after = estimate.uncertainty_after(Duration::ZERO).nanos_lossy();
+        assert!((after - 30.0).abs() < 1e-6, "{after}");
+        // The offset and frequency errors are independent
+        let after = estimate
+            .uncertainty_after(Duration::from_secs(40))
+            .nanos_lossy();
+        assert!((after - 50.0).abs() < 1e-6, "{after}");
+
+        let estimate = HoldoverEstimate {
+            offset_uncertainty: Duration::ZERO,
+            frequency_uncertainty: 0.0,
+            wander: 3e-18,
+        };
+        let after = estimate
+            .uncertainty_after(Duration::from_secs(100))
+            .nanos_lossy();
+        assert!((after - 1000.0).abs() < 1e-6, "{after}");
+    }
 }
@@ -0,0 +1,70 @@
+//! The delay filter of [PiFilter](super::PiFilter) and
+//! [LinregFilter](super::LinregFilter)
+
+use crate::time::Duration;
+
+// Like the default delay filter of ptp4l, the mean delay is the median of the
+// last measurements
+const DELAY_FILTER_LENGTH: usize = 10;
+
+#[derive(Debug)]
+pub(super) struct MovingMedian {
+    samples: [Duration; DELAY_FILTER_LENGTH],
+    len: usize,
+    next: usize,
+}
+
+impl MovingMedian {
+    pub(super) fn new() -> Self {
+        Self {
+            samples: [Duration::ZERO; DELAY_FILTER_LENGTH],
+            len: 0,
+            next: 0,
+        }
+    }
+
+    /// Add a sample, returning the median of the last samples
+    pub(super) fn absorb(&mut self, sample: Duration) -> Duration {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % DELAY_FILTER_LENGTH;
+        self.len = (self.len + 1).min(DELAY_FILTER_LENGTH);
+
+        let mut sorted = self.samples;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable();
+        if self.len % 2 == 0 {
+            (sorted[self.len / 2 - 1] + sorted[self.len / 2]) / 2
+        } else {
+            sorted[self.len / 2]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_delay() {
+        let mut median = MovingMedian::new();
+        assert_eq!(
+            median.absorb(Duration::from_nanos(10)),
+            Duration::from_nanos(10)
+        );
+        assert_eq!(
+            median.absorb(Duration::from_nanos(30)),
+            Duration::from_nanos(20)
+        );
+        assert_eq!(
+            median.absorb(Duration::from_nanos(1000)),
+            Duration::from_nanos(30)
+        );
+        for _ in 0..DELAY_FILTER_LENGTH {
+            median.absorb(Duration::from_nanos(50));
+        }
+        assert_eq!(
+            median.absorb(Duration::from_nanos(1000)),
+            Duration::from_nanos(50)
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Implementation of [PiFilter]
+
+use super::{moving_median::MovingMedian, Filter, FilterUpdate};
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
+use crate::{
+    port::Measurement,
+    time::{Duration, Time},
+    Clock,
+};
+
+/// Configuration options for [PiFilter]
+///
+/// The defaults are those of linuxptp with hardware timestamping.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PiConfiguration {
+    /// Proportional constant of the servo. When `None`, it is derived from
+    /// the sync interval as `kp_scale * interval^kp_exponent`, but at most
+    /// `kp_norm_max / interval`.
+    pub kp: Option<f64>,
+    /// Scale of the derived proportional constant
+    pub kp_scale: f64,
+    /// Exponent of the sync interval in the derived proportional constant
+    pub kp_exponent: f64,
+    /// Limit of the derived proportional constant times the sync interval
+    pub kp_norm_max: f64,
+
+    /// Integral constant of the servo. When `None`, it is derived from the
+    /// sync interval as `ki_scale * interval^ki_exponent`, but at most
+    /// `ki_norm_max / interval`.
+    pub ki: Option<f64>,
+    /// Scale of the derived integral constant
+    pub ki_scale: f64,
+    /// Exponent of the sync interval in the derived integral constant
+    pub ki_exponent: f64,
+    /// Limit of the derived integral constant times the sync interval
+    pub ki_norm_max: f64,
+
+    /// Threshold above which the time is corrected by a step on the first
+    /// update of the clock, rather than slewed. `None` always slews.
+    pub first_step_threshold: Option<Duration>,
+    /// Threshold above which the time is corrected by a step at any time,
+    /// after which the frequency is estimated again. `None` never steps after
+    /// the first update.
+    pub step_threshold: Option<Duration>,
+    /// Maximum frequency offset of the clock (ppm)
+    pub max_frequency: f64,
+}
+
+impl Default for PiConfiguration {
+    fn default() -> Self {
+        Self {
+            kp: None,
+            kp_scale: 0.7,
+            kp_exponent: -0.3,
+            kp_norm_max: 0.7,
+            ki: None,
+            ki_scale: 0.3,
+            ki_exponent: 0.4,
+            ki_norm_max: 0.3,
+            first_step_threshold: Some(Duration::from_micros(20)),
+            step_threshold: None,
+            max_frequency: 500.0,
+        }
+    }
+}
+
+// Extra margin on the time between the first two samples, so rounding does
+// not delay the frequency estimate by a sample
+const FREQ_EST_MARGIN: f64 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ServoState {
+    Unlocked,
+    Jump,
+    Locked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    // Waiting for the first sample
+    Empty,
+    // Waiting for a second sample, long enough after this one to estimate the
+    // frequency from
+    First { offset: f64, time: Time },
+    // Steering the clock
+    Running,
+}
+
+/// A proportional-integral servo, equivalent to the `pi` servo of linuxptp.
+///
+/// Starting from the frequency the clock already runs at, its frequency is
+/// estimated from the first two measurements, after which the time is
+/// corrected by a step when it is off by more than the first step threshold.
+/// From then on, the clock is slewed by adjusting its frequency in proportion
+/// to the offset, while the integral of the offsets tracks the frequency error
+/// of the clock.
+#[derive(Debug)]
+pub struct PiFilter {
+    config: PiConfiguration,
+    stage: Stage,
+
+    // Frequency correction of the clock (ppb), tracked by the integral term
+    drift: f64,
+    kp: f64,
+    ki: f64,
+
+    // Sync interval the constants are derived for (s), estimated from the
+    // time between measurements
+    interval: f64,
+    interval_candidate: Option<f64>,
+    last_measurement: Option<Time>,
+
+    first_update: bool,
+    frequency_initialized: bool,
+    delays: MovingMedian,
+}
+
+impl Filter for PiFilter {
+    type Config = PiConfiguration;
+
+    fn new(config: PiConfiguration) -> Self {
+        let mut filter = Self {
+            config,
+            stage: Stage::Empty,
+            drift: 0.0,
+            kp: 0.0,
+            ki: 0.0,
+            interval: 1.0,
+            interval_candidate: None,
+            last_measurement: None,
+            first_update: true,
+            frequency_initialized: false,
+            delays: MovingMedian::new(),
+        };
+        filter.set_interval(1.0);
+        filter
+    }
+
+    fn measurement<C: Clock>(&mut self, m: Measurement, clock: &mut C) -> FilterUpdate {
+        let mut update = FilterUpdate::default();
+
+        if let Some(delay) = m.peer_delay.or(m.delay) {
+            update.mean_delay = Some(self.delays.absorb(delay));
+        }
+
+        let Some(offset) = m.offset else {
+            return update;
+        };
+
+        self.estimate_interval(m.event_time);
+
+        if !self.frequency_initialized {
+            self.initialize_frequency(clock);
+        }
+
+        let (ppb, state) = self.sample(offset.nanos_lossy(), m.event_time);
+
+        match state {
+            ServoState::Unlocked => {}
+            ServoState::Jump => {
+                if let Err(error) = clock.set_frequency(-ppb / 1e3) {
+                    log::error!("Could not adjust clock frequency: {:?}", error);
+                }
+                if let Err(error) = clock.step_clock(-offset) {
+                    log::error!("Could not step clock: {:?}", error);
+                } else {
+                    log::info!("Stepped clock by {}", -offset);
+                }
+            }
+            ServoState::Locked => {
+                if let Err(error) = clock.set_frequency(-ppb / 1e3) {
+                    log::error!("Could not adjust clock frequency: {:?}", error);
+                }
+            }
+        }
+
+        log::info!(
+            "Offset to master: {}ns, frequency {}ppb, {:?}",
+            offset.nanos_lossy(),
+            -ppb,
+            state
+        );
+
+        update
+    }
+
+    fn update<C: Clock>(&mut self, _clock: &mut C) -> FilterUpdate {
+        // ignore
+        Default::default()
+    }
+
+    fn demobilize<C: Clock>(self, _clock: &mut C) {
+        // Like ptp4l, the clock keeps its last frequency
+    }
+}
+
+impl PiFilter {
+    // Like linuxptp, the integral starts from the frequency the clock already
+    // runs at, so a restart does not throw away its frequency estimate
+    fn initialize_frequency<C: Clock>(&mut self, clock: &mut C) {
+        match clock.get_frequency() {
+            Ok(ppm) => {
+                let max_frequency = self.config.max_frequency * 1e3;
+                self.drift = (-ppm * 1e3).clamp(-max_frequency, max_frequency);
+                self.frequency_initialized = true;
+            }
+            Err(error) => log::error!("Could not read clock frequency: {:?}", error),
+        }
+    }
+
+    fn set_interval(&mut self, interval: f64) {
+        let config = &self.config;
+        self.interval = interval;
+        self.kp = config.kp.unwrap_or_else(|| {
+            (config.kp_scale * interval.powf(config.kp_exponent)).min(config.kp_norm_max / interval)
+        });
+        self.ki = config.ki.unwrap_or_else(|| {
+            (config.ki_scale * interval.powf(config.ki_exponent)).min(config.ki_norm_max / interval)
+        });
+    }
+
+    // The sync interval is a power of two, which we adopt once two
+    // consecutive measurements agree on it, so a lost sync message does not
+    // change the constants
+    fn estimate_interval(&mut self, time: Time) {
+        if let Some(last) = self.last_measurement {
+            let elapsed = (time - last).seconds();
+            if elapsed > 0.0 {
+                let log_interval = elapsed.log2().round().clamp(-10.0, 10.0);
+                let interval = 2f64.powi(log_interval as i32);
+                if self.interval_candidate == Some(interval) && interval != self.interval {
+                    self.set_interval(interval);
+                }
+                self.interval_candidate = Some(interval);
+            }
+        }
+        self.last_measurement = Some(time);
+    }
+
+    fn exceeds(threshold: Option<Duration>, offset: f64) -> bool {
+        threshold.map_or(false, |threshold| offset.abs() > threshold.nanos_lossy())
+    }
+
+    // Returns the frequency correction in ppb, which is subtracted from the
+    // frequency of the clock
+    fn sample(&mut self, offset: f64, time: Time) -> (f64, ServoState) {
+        let max_frequency = self.config.max_frequency * 1e3;
+
+        let (ppb, state) = match self.stage {
+            Stage::Empty => {
+                self.stage = Stage::First { offset, time };
+                (self.drift, ServoState::Unlocked)
+            }
+            Stage::First {
+                offset: first_offset,
+                time: first_time,
+            } => {
+                let elapsed = (time - first_time).nanos_lossy();
+                if elapsed <= 0.0 {
+                    self.stage = Stage::Empty;
+                    return (self.drift, ServoState::Unlocked);
+                }
+
+                // Wait long enough before estimating the frequency
+                let freq_est_interval = (0.016 / self.ki).min(1000.0);
+                if elapsed / 1e9 * (1.0 + FREQ_EST_MARGIN) < freq_est_interval {
+                    return (self.drift, ServoState::Unlocked);
+                }
+
+                self.drift += (1e9 - self.drift) * (offset - first_offset) / elapsed;
+                self.drift = self.drift.clamp(-max_frequency, max_frequency);
+
+                let state = if (self.first_update
+                    && Self::exceeds(self.config.first_step_threshold, offset))
+                    || Self::exceeds(self.config.step_threshold, offset)
+                {
+                    ServoState::Jump
+                } else {
+                    ServoState::Locked
+                };
+
+                self.stage = Stage::Running;
+                (self.drift, state)
+            }
+            Stage::Running => {
+                // Start over when the offset got too large, the clock is
+                // stepped once the frequency is estimated again
+                if Self::exceeds(self.config.step_threshold, offset) {
+                    self.stage = Stage::Empty;
+                    return (self.drift, ServoState::Unlocked);
+                }
+
+                let ki_term = self.ki * offset;
+                let ppb = self.kp * offset + self.drift + ki_term;
+                if ppb.abs() > max_frequency {
+                    (ppb.clamp(-max_frequency, max_frequency), ServoState::Locked)
+                } else {
+                    self.drift += ki_term;
+                    (ppb, ServoState::Locked)
+                }
+            }
+        };
+
+        if state != ServoState::Unlocked {
+            self.first_update = false;
+        }
+
+        (ppb, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(secs: f64) -> Time {
+        Time::from_fixed_nanos(secs * 1e9)
+    }
+
+    struct FrequencyClock(f64);
+
+    impl Clock for FrequencyClock {
+        type Error = ();
+
+        fn now(&self) -> Time {
+            Time::default()
+        }
+
+        fn step_clock(&mut self, _offset: Duration) -> Result<Time, Self::Error> {
+            Ok(Time::default())
+        }
+
+        fn set_frequency(&mut self, ppm: f64) -> Result<Time, Self::Error> {
+            self.0 = ppm;
+            Ok(Time::default())
+        }
+
+        fn get_frequency(&self) -> Result<f64, Self::Error> {
+            Ok(self.0)
+        }
+
+        fn set_properties(
+            &mut self,
+            _time_properties_ds: &crate::config::TimePropertiesDS,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn starts_from_clock_frequency() {
+        let mut filter = PiFilter::new(PiConfiguration::default());
+        let mut clock = FrequencyClock(-12.5);
+        let measurement = |time, offset| Measurement {
+            event_time: time,
+            offset: Some(Duration::from_nanos(offset)),
+            delay: None,
+            peer_delay: None,
+            raw_sync_offset: None,
+            raw_delay_offset: None,
+        };
+
+        filter.measurement(measurement(time(0.0), 0), &mut clock);
+        assert_eq!(filter.drift, 12_500.0);
+        assert_eq!(clock.0, -12.5);
+
+        // Without offset changes, the clock keeps its frequency
+        filter.measurement(measurement(time(1.0), 0), &mut clock);
+        assert!((clock.0 + 12.5).abs() < 1e-9, "{}", clock.0);
+    }
+
+    #[test]
+    fn derived_constants() {
+        let mut filter = PiFilter::new(PiConfiguration::default());
+        assert!((filter.kp - 0.7).abs() < 1e-9);
+        assert!((filter.ki - 0.3).abs() < 1e-9);
+
+        filter.set_interval(0.125);
+        assert!((filter.kp - 0.7 * 0.125f64.powf(-0.3)).abs() < 1e-9);
+        assert!((filter.ki - 0.3 * 0.125f64.powf(0.4)).abs() < 1e-9);
+
+        // Limited by the norm max
+        filter.set_interval(16.0);
+        assert!((filter.ki - 0.3 / 16.0).abs() < 1e-9);
+
+        let mut filter = PiFilter::new(PiConfiguration {
+            kp: Some(0.1),
+            ki: Some(0.001),
+            ..Default::default()
+        });
+        filter.set_interval(0.125);
+        assert_eq!((filter.kp, filter.ki), (0.1, 0.001));
+    }
+
+    #[test]
+    fn estimate_interval() {
+        let mut filter = PiFilter::new(PiConfiguration::default());
+        filter.estimate_interval(time(10.0));
+        filter.estimate_interval(time(10.25));
+        assert_eq!(filter.interval, 1.0);
+        filter.estimate_interval(time(10.49));
+        assert_eq!(filter.interval, 0.25);
+        // A single lost message does not change it
+        filter.estimate_interval(time(11.0));
+        filter.estimate_interval(time(11.25));
+        assert_eq!(filter.interval, 0.25);
+    }
+
+    #[test]
+    fn first_step_then_slew() {
+        let mut filter = PiFilter::new(PiConfiguration::default());
+
+        assert_eq!(
+            filter.sample(1_000_000.0, time(0.0)),
+            (0.0, ServoState::Unlocked)
+        );
+        // Too soon after the first sample to estimate the frequency
+        assert_eq!(
+            filter.sample(1_000_000.0, time(0.01)),
+            (0.0, ServoState::Unlocked)
+        );
+
+        // 100ns/s fast, and too far off, so the clock is stepped
+        let (ppb, state) = filter.sample(1_000_100.0, time(1.0));
+        assert_eq!(state, ServoState::Jump);
+        assert!((ppb - 100.0).abs() < 1e-3, "{ppb}");
+
+        // After which it is only slewed
+        let (ppb, state) = filter.sample(50_000.0, time(2.0));
+        assert_eq!(state, ServoState::Locked);
+        let expected = 0.7 * 50_000.0 + 0.3 * 50_000.0 + 100.0;
+        assert!((ppb - expected).abs() < 1e-3, "{ppb}");
+        assert!((filter.drift - (100.0 + 0.3 * 50_000.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn small_first_offset_is_slewed() {
+        let mut filter = PiFilter::new(PiConfiguration::default());
+        filter.sample(1_000.0, time(0.0));
+        let (ppb, state) = filter.sample(900.0, time(1.0));
+        assert_eq!(state, ServoState::Locked);
+        assert!((ppb + 100.0).abs() < 1e-3, "{ppb}");
+    }
+
+    #[test]
+    fn max_frequency() {
+        let mut filter = PiFilter::new(PiConfiguration {
+            max_frequency: 100.0,
+            ..Default::default()
+        });
+        filter.sample(0.0, time(0.0));
+        filter.sample(0.0, time(1.0));
+
+        // The integral is not updated while the frequency is clamped
+        assert_eq!(
+            filter.sample(1_000_000.0, time(2.0)),
+            (100_000.0, ServoState::Locked)
+        );
+        assert_eq!(filter.drift, 0.0);
+        assert_eq!(
+            filter.sample(-1_000_000.0, time(3.0)),
+            (-100_000.0, ServoState::Locked)
+        );
+    }
+
+    #[test]
+    fn step_threshold_restarts() {
+        let mut filter = PiFilter::new(PiConfiguration {
+            step_threshold: Some(Duration::from_micros(100)),
+            ..Default::default()
+        });
+        filter.sample(0.0, time(0.0));
+        assert_eq!(filter.sample(0.0, time(1.0)).1, ServoState::Locked);
+
+        assert_eq!(filter.sample(200_000.0, time(2.0)).1, ServoState::Unlocked);
+        assert_eq!(filter.sample(200_000.0, time(3.0)).1, ServoState::Unlocked);
+        assert_eq!(filter.sample(200_000.0, time(4.0)).1, ServoState::Jump);
+    }
+}
@@ -9,6 +9,12 @@ pub(crate) trait FloatPolyfill {
     fn powi(self, n: i32) -> Self;
     #[cfg(not(feature = "std"))]
     fn exp(self) -> Self;
+    #[cfg(not(feature = "std"))]
+    fn log2(self) -> Self;
+    #[cfg(not(feature = "std"))]
+    fn powf(self, n: Self) -> Self;
+    #[cfg(not(feature = "std"))]
+    fn round(self) -> Self;
 }
 
 impl FloatPolyfill for f64 {
@@ -42,4 +48,19 @@ impl FloatPolyfill for f64 {
     fn exp(self) -> Self {
         libm::exp(self)
     }
+
+    #[cfg(not(feature = "std"))]
+    fn log2(self) -> Self {
+        libm::log2(self)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn powf(self, n: Self) -> Self {
+        libm::pow(self, n)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn round(self) -> Self {
+        libm::round(self)
+    }
 }
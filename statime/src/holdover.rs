@@ -0,0 +1,234 @@
+use crate::{
+    config::{telecom, ClockAccuracy, ClockQuality, HoldoverConfig, HoldoverDegradation, Profile},
+    filters::HoldoverEstimate,
+    time::Duration,
+};
+
+/// The holdover of a clock, started when the port that steered it stopped
+/// being a slave, or when the clock lost the reference that disciplined it as
+/// grandmaster
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Holdover {
+    /// How long the clock has been in holdover
+    pub(crate) elapsed: Duration,
+    /// How the uncertainty of the time grows from then on, if known
+    pub(crate) estimate: Option<HoldoverEstimate>,
+    /// The quality of the grandmaster the clock followed, our own when it
+    /// lost its reference
+    pub(crate) grandmaster: ClockQuality,
+    /// Whether the clock lost its own reference as grandmaster, rather than
+    /// the master a port followed
+    pub(crate) reference: bool,
+}
+
+impl Holdover {
+    /// Whether the grandmaster the clock followed was locked to a primary
+    /// reference, which is what the holdover classes claim
+    fn grandmaster_locked(&self, profile: Profile) -> bool {
+        match profile {
+            Profile::TelecomFullTiming { .. } | Profile::TelecomPartialTiming { .. } => matches!(
+                self.grandmaster.clock_class,
+                telecom::CLASS_LOCKED | telecom::CLASS_GM_HOLDOVER | telecom::CLASS_BC_HOLDOVER
+            ),
+            Profile::Default
+            | Profile::Gptp { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => matches!(self.grandmaster.clock_class, 6 | 13),
+        }
+    }
+
+    /// The clock quality, given the quality of the local clock and whether
+    /// the instance uses the PTP timescale
+    pub(crate) fn clock_quality(
+        &self,
+        config: &HoldoverConfig,
+        local: ClockQuality,
+        profile: Profile,
+        ptp_timescale: bool,
+    ) -> ClockQuality {
+        // Following a grandmaster that wasn't locked, there is nothing to hold
+        // over
+        if !self.grandmaster_locked(profile) {
+            return local;
+        }
+
+        if self.elapsed > config.max_duration {
+            return ClockQuality {
+                clock_class: telecom::CLASS_FREE_RUNNING,
+                clock_accuracy: ClockAccuracy::Unknown,
+                offset_scaled_log_variance: 0xffff,
+            };
+        }
+
+        // Without an estimate, we cannot claim to be within the budget
+        let in_spec = self.estimate.map_or(false, |estimate| {
+            estimate.uncertainty_after(self.elapsed) <= config.time_error_budget
+        });
+
+        let clock_class = match profile {
+            // The category of the frequency source of a T-GM is unknown, so
+            // out of spec it claims the lowest one
+            Profile::TelecomFullTiming { .. } | Profile::TelecomPartialTiming { .. } => {
+                match (self.reference, in_spec) {
+                    (true, true) => telecom::CLASS_GM_HOLDOVER,
+                    (true, false) => telecom::CLASS_GM_HOLDOVER_OUT_OF_SPEC_3,
+                    (false, true) => telecom::CLASS_BC_HOLDOVER,
+                    (false, false) => telecom::CLASS_BC_HOLDOVER_OUT_OF_SPEC,
+                }
+            }
+            Profile::Default
+            | Profile::Gptp { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => match (in_spec, config.degradation, ptp_timescale) {
+                (true, _, true) => 7,
+                (true, _, false) => 14,
+                (false, HoldoverDegradation::AlternativeA, true) => 52,
+                (false, HoldoverDegradation::AlternativeA, false) => 58,
+                (false, HoldoverDegradation::AlternativeB, true) => 187,
+                (false, HoldoverDegradation::AlternativeB, false) => 193,
+            },
+        };
+
+        // The accuracy follows the uncertainty of the time
+        let clock_accuracy = self.estimate.map_or(local.clock_accuracy, |estimate| {
+            ClockAccuracy::from_time_error(estimate.uncertainty_after(self.elapsed))
+        });
+
+        ClockQuality {
+            clock_class,
+            clock_accuracy,
+            ..local
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCAL: ClockQuality = ClockQuality {
+        clock_class: 248,
+        clock_accuracy: ClockAccuracy::NS100,
+        offset_scaled_log_variance: 0x4e5d,
+    };
+
+    const GRANDMASTER: ClockQuality = ClockQuality {
+        clock_class: 6,
+        clock_accuracy: ClockAccuracy::NS25,
+        offset_scaled_log_variance: 0x4e5d,
+    };
+
+    fn holdover(elapsed: i64) -> Holdover {
+        Holdover {
+            elapsed: Duration::from_secs(elapsed),
+            // Leaves a budget of 1 microsecond after 1000 seconds
+            estimate: Some(HoldoverEstimate {
+                offset_uncertainty: Duration::ZERO,
+                frequency_uncertainty: 1e-9,
+                wander: 0.0,
+            }),
+            grandmaster: GRANDMASTER,
+            reference: false,
+        }
+    }
+
+    #[test]
+    fn holdover_classes() {
+        let config = HoldoverConfig::default();
+        let class = |holdover: Holdover, profile: Profile, ptp_timescale: bool| {
+            holdover
+                .clock_quality(&config, LOCAL, profile, ptp_timescale)
+                .clock_class
+        };
+
+        assert_eq!(class(holdover(0), Profile::Default, true), 7);
+        assert_eq!(class(holdover(999), Profile::Default, true), 7);
+        assert_eq!(class(holdover(999), Profile::Default, false), 14);
+        assert_eq!(class(holdover(1001), Profile::Default, true), 187);
+        assert_eq!(class(holdover(1001), Profile::Default, false), 193);
+
+        let telecom = Profile::TelecomFullTiming {
+            local_priority: 128,
+        };
+        assert_eq!(class(holdover(999), telecom, true), 135);
+        assert_eq!(class(holdover(1001), telecom, true), 165);
+
+        // A grandmaster that lost its own reference
+        let reference = |elapsed| Holdover {
+            reference: true,
+            ..holdover(elapsed)
+        };
+        assert_eq!(class(reference(999), Profile::Default, false), 14);
+        assert_eq!(class(reference(999), telecom, true), 7);
+        assert_eq!(class(reference(1001), telecom, true), 160);
+        assert_eq!(class(reference(24 * 60 * 60 + 1), telecom, true), 248);
+
+        // A telecom boundary clock in holdover may follow one that is in
+        // holdover itself
+        let following_holdover = Holdover {
+            grandmaster: ClockQuality {
+                clock_class: telecom::CLASS_BC_HOLDOVER,
+                ..GRANDMASTER
+            },
+            ..holdover(999)
+        };
+        assert_eq!(class(following_holdover, telecom, true), 135);
+        assert_eq!(
+            following_holdover.clock_quality(&config, LOCAL, Profile::Default, true),
+            LOCAL
+        );
+
+        // Following a grandmaster that wasn't locked, the clock keeps its own
+        // quality
+        let unlocked = Holdover {
+            grandmaster: ClockQuality {
+                clock_class: 248,
+                ..GRANDMASTER
+            },
+            ..holdover(0)
+        };
+        assert_eq!(
+            unlocked.clock_quality(&config, LOCAL, Profile::Default, true),
+            LOCAL
+        );
+        assert_eq!(unlocked.clock_quality(&config, LOCAL, telecom, true), LOCAL);
+
+        // Without an estimate, the clock is out of spec right away
+        let unknown = Holdover {
+            estimate: None,
+            ..holdover(0)
+        };
+        assert_eq!(class(unknown, Profile::Default, true), 187);
+
+        // The accuracy follows the estimate, the variance stays that of the
+        // local clock
+        let quality = holdover(100).clock_quality(&config, LOCAL, Profile::Default, true);
+        assert_eq!(quality.clock_accuracy, ClockAccuracy::NS100);
+        assert_eq!(quality.offset_scaled_log_variance, 0x4e5d);
+        let quality = holdover(101).clock_quality(&config, LOCAL, Profile::Default, true);
+        assert_eq!(quality.clock_accuracy, ClockAccuracy::NS250);
+        let quality = unknown.clock_quality(&config, LOCAL, Profile::Default, true);
+        assert_eq!(quality.clock_accuracy, ClockAccuracy::NS100);
+
+        // And after the maximum duration the clock is free running
+        let quality =
+            holdover(24 * 60 * 60 + 1).clock_quality(&config, LOCAL, Profile::Default, true);
+        assert_eq!(quality.clock_class, 248);
+        assert_eq!(quality.clock_accuracy, ClockAccuracy::Unknown);
+    }
+
+    #[test]
+    fn degradation_alternative_a() {
+        let config = HoldoverConfig {
+            degradation: HoldoverDegradation::AlternativeA,
+            ..Default::default()
+        };
+        let class = |ptp_timescale| {
+            holdover(2000)
+                .clock_quality(&config, LOCAL, Profile::Default, ptp_timescale)
+                .clock_class
+        };
+        assert_eq!(class(true), 52);
+        assert_eq!(class(false), 58);
+    }
+}
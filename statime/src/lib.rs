@@ -1,6 +1,7 @@
 //! Statime is a library providing an implementation of PTP version 2.1
 //! (IEEE1588-2019). It provides all the building blocks to setup PTP ordinary
-//! and boundary clocks.
+//! and boundary clocks, as well as end-to-end transparent clocks through the
+//! [`transparent_clock`] module.
 //!
 //! `statime` is designed to be able to work with many different underlying
 //! platforms, including embedded targets. This does mean that it cannot use the
@@ -70,6 +71,7 @@
 //! * [`std::error::Error`] implementations for error types
 //! * Implementations of the [`config::AcceptableMasterList`] trait on types in
 //!   [`std`]
+//! * Conversion of [`std::net::IpAddr`] into a [`config::PortAddress`]
 //! * Usage of methods on [`f32`] and [`f64`] directly from [`std`] instead of
 //!   [`libm`]
 //!
@@ -89,14 +91,30 @@ pub mod config;
 pub(crate) mod datastructures;
 pub mod filters;
 mod float_polyfill;
+mod holdover;
 pub mod observability;
 pub mod port;
 mod ptp_instance;
 pub mod time;
+pub mod transparent_clock;
 
 pub use clock::Clock;
 pub use ptp_instance::PtpInstance;
 
+/// Types for building and parsing management messages
+///
+/// Statime answers management requests on its ports. These types allow
+/// implementing the other side, a management node that queries and configures
+/// PTP instances over the network. See *IEEE1588-2019 section 15* for the
+/// details of the protocol.
+pub mod management {
+    pub use crate::datastructures::messages::{
+        ManagementAction, ManagementData, ManagementErrorId, ManagementErrorStatus, ManagementId,
+        ManagementMessageError, ManagementRequest, ManagementRequestBody, ManagementResponse,
+        ManagementResponseBody,
+    };
+}
+
 /// Helper types used for fuzzing
 ///
 /// Enabled by the `fuzz` `feature`
@@ -2,7 +2,7 @@ use crate::datastructures::datasets::InternalCurrentDS;
 
 /// A concrete implementation of the PTP Current dataset (IEEE1588-2019 section
 /// 8.2.2)
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CurrentDS {
     /// See *IEEE1588-2019 section 8.2.2.2*.
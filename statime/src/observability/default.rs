@@ -4,12 +4,16 @@ use crate::datastructures::datasets::InternalDefaultDS;
 /// 8.2.1)
 ///
 /// See [InternalDefaultDS](crate::datastructures::datasets::InternalDefaultDS).
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DefaultDS {
     /// The identity of a PTP node.
     /// See *IEEE1588-2019 section 8.2.1.2.2*.
     pub clock_identity: crate::config::ClockIdentity,
+    /// Whether the PTP instance sends follow up messages with the transmit
+    /// time of its event messages, rather than only one-step messages.
+    /// See *IEEE1588-2019 section 8.2.1.2.1*.
+    pub two_step: bool,
     /// The amount of PTP ports on this PTP instance.
     /// See *IEEE1588-2019 section 8.2.1.2.3*.
     pub number_ports: u16,
@@ -31,6 +35,7 @@ impl From<&InternalDefaultDS> for DefaultDS {
     fn from(v: &InternalDefaultDS) -> Self {
         Self {
             clock_identity: v.clock_identity,
+            two_step: v.two_step,
             number_ports: v.number_ports,
             clock_quality: v.clock_quality,
             priority_1: v.priority_1,
@@ -5,10 +5,16 @@ pub mod current;
 pub mod default;
 /// A concrete implementation of the PTP Parent dataset (IEEE1588-2019 section 8.2.3)
 pub mod parent;
+/// A concrete implementation of the PTP Port dataset (IEEE1588-2019 section 8.2.15)
+pub mod port;
+/// The synchronization metadata of the SMPTE ST 2059-2 profile
+pub mod smpte;
 
 use crate::datastructures::datasets::TimePropertiesDS;
 
-use self::{current::CurrentDS, default::DefaultDS, parent::ParentDS};
+use self::{
+    current::CurrentDS, default::DefaultDS, parent::ParentDS, smpte::SynchronizationMetadata,
+};
 
 /// Observable version of the InstanceState struct
 #[derive(Debug, Copy, Clone)]
@@ -22,4 +28,7 @@ pub struct ObservableInstanceState {
     pub parent_ds: ParentDS,
     /// A concrete implementation of the PTP Time Properties dataset (IEEE1588-2019 section 8.2.4)
     pub time_properties_ds: TimePropertiesDS,
+    /// The SMPTE ST 2059-2 synchronization metadata of the grandmaster, when
+    /// known
+    pub smpte_metadata: Option<SynchronizationMetadata>,
 }
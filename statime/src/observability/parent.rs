@@ -24,6 +24,15 @@ pub struct ParentDS {
     pub grandmaster_priority_1: u8,
     /// See *IEEE1588-2019 section 8.2.3.9*.
     pub grandmaster_priority_2: u8,
+    /// The grandmasterID of the IEEE_C37_238 TLV of the parent, only with the
+    /// [`Profile::Power`](`crate::config::Profile::Power`) profile. See *IEEE
+    /// C37.238-2017*.
+    pub grandmaster_id: Option<u16>,
+    /// The totalTimeInaccuracy in nanoseconds of the IEEE_C37_238 TLV of the
+    /// parent, only with the [`Profile::Power`](`crate::config::Profile::Power`)
+    /// profile. This includes the inaccuracy of the grandmaster and of all
+    /// clocks up to the parent, but not that of this instance.
+    pub total_time_inaccuracy: Option<u32>,
 }
 
 impl From<&InternalParentDS> for ParentDS {
@@ -38,6 +47,8 @@ impl From<&InternalParentDS> for ParentDS {
             grandmaster_clock_quality: v.grandmaster_clock_quality,
             grandmaster_priority_1: v.grandmaster_priority_1,
             grandmaster_priority_2: v.grandmaster_priority_2,
+            grandmaster_id: v.power_profile.map(|tlv| tlv.grandmaster_id),
+            total_time_inaccuracy: v.power_profile.map(|tlv| tlv.total_time_inaccuracy),
         }
     }
 }
@@ -0,0 +1,121 @@
+use crate::datastructures::common::PortIdentity;
+
+/// A concrete implementation of the PTP Port dataset (IEEE1588-2019 section
+/// 8.2.15)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortDS {
+    /// See *IEEE1588-2019 section 8.2.15.2.1*.
+    pub port_identity: PortIdentity,
+    /// See *IEEE1588-2019 section 8.2.15.3.1*.
+    pub port_state: PortState,
+    /// See *IEEE1588-2019 section 8.2.15.3.2*.
+    pub log_min_delay_req_interval: i8,
+    /// The mean link delay in nanoseconds as measured by the peer to peer
+    /// delay mechanism. See *IEEE1588-2019 section 8.2.15.3.3*.
+    pub mean_link_delay: i128,
+    /// See *IEEE1588-2019 section 8.2.15.4.1*.
+    pub log_announce_interval: i8,
+    /// See *IEEE1588-2019 section 8.2.15.4.2*.
+    pub announce_receipt_timeout: u8,
+    /// See *IEEE1588-2019 section 8.2.15.4.3*.
+    pub log_sync_interval: i8,
+    /// See *IEEE1588-2019 section 8.2.15.4.4*.
+    pub delay_mechanism: DelayMechanism,
+    /// See *IEEE1588-2019 section 8.2.15.4.5*.
+    pub log_min_pdelay_req_interval: i8,
+    /// See *IEEE1588-2019 section 8.2.15.4.6*.
+    pub version_number: u8,
+    /// See *IEEE1588-2019 section 8.2.15.4.7*.
+    pub minor_version_number: u8,
+}
+
+/// The state of a port as reported in the [`PortDS`]
+///
+/// For more details see *IEEE1588-2019 section 8.2.15.3.1 and table 21*
+#[allow(missing_docs)] // These varaiants are pretty self explaining
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortState {
+    Initializing,
+    Faulty,
+    Disabled,
+    Listening,
+    PreMaster,
+    Master,
+    Passive,
+    Uncalibrated,
+    Slave,
+}
+
+impl PortState {
+    pub(crate) fn to_primitive(self) -> u8 {
+        match self {
+            Self::Initializing => 0x01,
+            Self::Faulty => 0x02,
+            Self::Disabled => 0x03,
+            Self::Listening => 0x04,
+            Self::PreMaster => 0x05,
+            Self::Master => 0x06,
+            Self::Passive => 0x07,
+            Self::Uncalibrated => 0x08,
+            Self::Slave => 0x09,
+        }
+    }
+
+    pub(crate) fn from_primitive(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Initializing),
+            0x02 => Some(Self::Faulty),
+            0x03 => Some(Self::Disabled),
+            0x04 => Some(Self::Listening),
+            0x05 => Some(Self::PreMaster),
+            0x06 => Some(Self::Master),
+            0x07 => Some(Self::Passive),
+            0x08 => Some(Self::Uncalibrated),
+            0x09 => Some(Self::Slave),
+            _ => None,
+        }
+    }
+}
+
+/// The delay mechanism of a port as reported in the [`PortDS`]
+///
+/// For more details see *IEEE1588-2019 section 8.2.15.4.4 and table 21*
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DelayMechanism {
+    /// The port uses the delay request-response mechanism
+    E2E,
+    /// The port uses the peer delay mechanism
+    P2P,
+    /// The port uses the peer delay mechanism on a shared (CMLDS) link
+    CommonP2P,
+    /// The port uses a delay mechanism defined by a profile or transport
+    Special,
+    /// The port does not measure delay
+    NoMechanism,
+}
+
+impl DelayMechanism {
+    pub(crate) fn to_primitive(self) -> u8 {
+        match self {
+            Self::E2E => 0x01,
+            Self::P2P => 0x02,
+            Self::CommonP2P => 0x03,
+            Self::Special => 0x04,
+            Self::NoMechanism => 0xfe,
+        }
+    }
+
+    pub(crate) fn from_primitive(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::E2E),
+            0x02 => Some(Self::P2P),
+            0x03 => Some(Self::CommonP2P),
+            0x04 => Some(Self::Special),
+            0xfe => Some(Self::NoMechanism),
+            _ => None,
+        }
+    }
+}
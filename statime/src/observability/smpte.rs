@@ -0,0 +1,85 @@
+/// The synchronization metadata of *SMPTE ST 2059-2 section 9*
+///
+/// A grandmaster with the [`Profile::Smpte2059`](`crate::config::Profile`)
+/// profile sends this in a management message to its slaves, which use it to
+/// generate video reference signals aligned to PTP time. All times are in
+/// seconds of the PTP timescale.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SynchronizationMetadata {
+    /// Numerator of the default video frame rate of the system
+    /// (defaultSystemFrameRate), for example 30000 for 29.97 frames per second
+    pub frame_rate_numerator: u32,
+    /// Denominator of the default video frame rate of the system, for example
+    /// 1001 for 29.97 frames per second
+    pub frame_rate_denominator: u32,
+    /// How well the grandmaster is locked to its reference
+    pub master_locking_status: MasterLockingStatus,
+    /// Time code uses drop frame counting (timeAddressFlags bit 0)
+    pub drop_frame: bool,
+    /// Color frame identification is in use (timeAddressFlags bit 1)
+    pub color_frame_identification: bool,
+    /// Offset in seconds of local time from PTP time (currentLocalOffset)
+    pub current_local_offset: i32,
+    /// Change in seconds of the local offset at the next discontinuity
+    /// (jumpSeconds)
+    pub jump_seconds: i32,
+    /// Time of the next discontinuity of the local offset (timeOfNextJump)
+    pub time_of_next_jump: u64,
+    /// Time of the next daily jam (timeOfNextJam), zero when none is scheduled
+    pub time_of_next_jam: u64,
+    /// Time of the previous daily jam (timeOfPreviousJam)
+    pub time_of_previous_jam: u64,
+    /// Local offset at the previous daily jam (previousJamLocalOffset)
+    pub previous_jam_local_offset: i32,
+    /// Daylight saving time is in effect (daylightSaving bit 0)
+    pub daylight_saving: bool,
+    /// Daylight saving time is in effect after the next discontinuity
+    /// (daylightSaving bit 1)
+    pub daylight_saving_at_next_jump: bool,
+    /// Daylight saving time was in effect at the previous jam (daylightSaving
+    /// bit 2)
+    pub daylight_saving_at_previous_jam: bool,
+    /// The next discontinuity is caused by a leap second (leapSecondJump)
+    pub leap_second_jump: bool,
+}
+
+/// The masterLockingStatus of *SMPTE ST 2059-2 section 9*
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MasterLockingStatus {
+    /// The grandmaster does not report a locking status
+    #[default]
+    NotInUse,
+    /// The grandmaster is not locked to a reference
+    FreeRun,
+    /// The grandmaster is locking to a reference, and may step its time
+    ColdLocking,
+    /// The grandmaster is locking to a reference by adjusting its frequency
+    WarmLocking,
+    /// The grandmaster is locked to its reference
+    Locked,
+}
+
+impl MasterLockingStatus {
+    pub(crate) fn to_primitive(self) -> u8 {
+        match self {
+            Self::NotInUse => 0,
+            Self::FreeRun => 1,
+            Self::ColdLocking => 2,
+            Self::WarmLocking => 3,
+            Self::Locked => 4,
+        }
+    }
+
+    pub(crate) fn from_primitive(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::NotInUse),
+            1 => Some(Self::FreeRun),
+            2 => Some(Self::ColdLocking),
+            3 => Some(Self::WarmLocking),
+            4 => Some(Self::Locked),
+            _ => None,
+        }
+    }
+}
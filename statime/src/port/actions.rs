@@ -3,7 +3,7 @@ use core::iter::Fuse;
 use arrayvec::ArrayVec;
 
 use crate::{
-    datastructures::common::{PortIdentity, Tlv, TlvSetIterator},
+    datastructures::common::{PortAddress, PortIdentity, Tlv, TlvSetIterator},
     filters::FilterUpdate,
 };
 
@@ -65,6 +65,7 @@ pub struct TimestampContext {
 pub(super) enum TimestampContextInner {
     Sync {
         id: u16,
+        destination: Option<PortAddress>,
     },
     DelayReq {
         id: u16,
@@ -92,10 +93,14 @@ pub enum PortAction<'a> {
     /// Packets marked as link local should be sent per the instructions
     /// for sending peer to peer delay mechanism messages of the relevant
     /// transport specification of PTP.
+    ///
+    /// Packets with a destination should be sent to that address only,
+    /// instead of to the multicast address of the transport.
     SendEvent {
         context: TimestampContext,
         data: &'a [u8],
         link_local: bool,
+        destination: Option<PortAddress>,
     },
     /// Send a general packet
     ///
@@ -104,7 +109,14 @@ pub enum PortAction<'a> {
     /// Packets marked as link local should be sent per the instructions
     /// for sending peer to peer delay mechanism messages of the relevant
     /// transport specification of PTP.
-    SendGeneral { data: &'a [u8], link_local: bool },
+    ///
+    /// Packets with a destination should be sent to that address only,
+    /// instead of to the multicast address of the transport.
+    SendGeneral {
+        data: &'a [u8],
+        link_local: bool,
+        destination: Option<PortAddress>,
+    },
     /// Call [`Port::handle_announce_timer`](`super::Port::handle_announce_timer`) in `duration` from now
     ResetAnnounceTimer { duration: core::time::Duration },
     /// Call [`Port::handle_sync_timer`](`super::Port::handle_sync_timer`) in
@@ -116,6 +128,8 @@ pub enum PortAction<'a> {
     ResetAnnounceReceiptTimer { duration: core::time::Duration },
     /// Call [`Port::handle_filter_update_timer`](`super::Port::handle_filter_update_timer`) in `duration` from now
     ResetFilterUpdateTimer { duration: core::time::Duration },
+    /// Call [`Port::handle_unicast_negotiation_timer`](`super::Port::handle_unicast_negotiation_timer`) in `duration` from now
+    ResetUnicastNegotiationTimer { duration: core::time::Duration },
     /// Forward this TLV to the announce timer call of all other ports.
     /// The receiver must ensure the TLV is yielded only once to the announce
     /// method of a port.
@@ -125,7 +139,7 @@ pub enum PortAction<'a> {
     ForwardTLV { tlv: ForwardedTLV<'a> },
 }
 
-const MAX_ACTIONS: usize = 2;
+const MAX_ACTIONS: usize = 3;
 
 /// An Iterator over [`PortAction`]s
 ///
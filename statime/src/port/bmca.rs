@@ -3,12 +3,13 @@ use rand::Rng;
 use super::{InBmca, Port, PortActionIterator, Running};
 use crate::{
     bmc::bmca::{BestAnnounceMessage, RecommendedState},
-    config::{AcceptableMasterList, LeapIndicator, TimePropertiesDS, TimeSource},
+    config::{AcceptableMasterList, PortAddress, Profile, TimePropertiesDS},
     datastructures::{
         datasets::{InternalCurrentDS, InternalDefaultDS, InternalParentDS},
-        messages::Message,
+        messages::{Message, PowerProfileTlv},
     },
     filters::Filter,
+    observability::smpte::SynchronizationMetadata,
     port::{
         state::{PortState, SlaveState},
         PortAction,
@@ -22,10 +23,42 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         &'b mut self,
         message: &Message<'b>,
         announce: crate::datastructures::messages::AnnounceMessage,
+        source: Option<PortAddress>,
     ) -> PortActionIterator<'b> {
+        if let (Some(unicast), Some(source)) = (self.unicast.as_mut(), source) {
+            unicast.record_master(source, message.header.source_port_identity);
+        }
+
+        // The partial timing telecom profile only accepts masters from the unicast
+        // master table
+        if matches!(
+            self.lifecycle.state.default_ds.profile,
+            Profile::TelecomPartialTiming { .. }
+        ) && !self.is_unicast_master(message, source)
+        {
+            log::debug!(
+                "Ignoring announce message from a master outside of the unicast master table"
+            );
+            return actions![];
+        }
+
+        // The power profile requires the IEEE_C37_238 TLV in every announce message
+        let power_profile = message
+            .suffix
+            .tlv()
+            .find_map(|tlv| PowerProfileTlv::from_tlv(&tlv));
+        if matches!(
+            self.lifecycle.state.default_ds.profile,
+            Profile::Power { .. }
+        ) && power_profile.is_none()
+        {
+            log::debug!("Ignoring announce message without a valid IEEE_C37_238 TLV");
+            return actions![];
+        }
+
         if self
             .bmca
-            .register_announce_message(&message.header, &announce)
+            .register_announce_message(&message.header, &announce, power_profile)
         {
             actions![PortAction::ResetAnnounceReceiptTimer {
                 duration: self.config.announce_duration(&mut self.rng),
@@ -35,6 +68,15 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
             actions![]
         }
     }
+
+    fn is_unicast_master(&self, message: &Message, source: Option<PortAddress>) -> bool {
+        match (self.unicast.as_ref(), source) {
+            (Some(unicast), Some(source)) => {
+                message.header.unicast_flag && unicast.is_master(source)
+            }
+            _ => false,
+        }
+    }
 }
 
 // BMCA related functionality of the port
@@ -54,7 +96,9 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
         // in the global operation of the best master clock algorithm or in the update
         // of data sets. We still need them during the calculation of the recommended
         // port state though to avoid getting multiple masters in the segment.
-        if self.config.master_only || matches!(self.port_state, PortState::Faulty) {
+        if self.config.master_only
+            || matches!(self.port_state, PortState::Faulty | PortState::Disabled)
+        {
             None
         } else {
             self.lifecycle.local_best
@@ -73,6 +117,7 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
         &mut self,
         recommended_state: RecommendedState,
         time_properties_ds: &mut TimePropertiesDS,
+        local_time_properties_ds: &TimePropertiesDS,
         current_ds: &mut InternalCurrentDS,
         parent_ds: &mut InternalParentDS,
         default_ds: &InternalDefaultDS,
@@ -81,9 +126,6 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
 
         match recommended_state {
             RecommendedState::M1(defaultds) | RecommendedState::M2(defaultds) => {
-                // a slave-only PTP port should never end up in the master state
-                debug_assert!(!default_ds.slave_only);
-
                 current_ds.steps_removed = 0;
                 current_ds.offset_from_master = Duration::ZERO;
                 current_ds.mean_delay = Duration::ZERO;
@@ -94,13 +136,12 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                 parent_ds.grandmaster_clock_quality = defaultds.clock_quality;
                 parent_ds.grandmaster_priority_1 = defaultds.priority_1;
                 parent_ds.grandmaster_priority_2 = defaultds.priority_2;
+                parent_ds.power_profile = PowerProfileTlv::for_grandmaster(defaultds.profile);
+                parent_ds.smpte_metadata =
+                    SynchronizationMetadata::for_grandmaster(defaultds.profile);
 
-                time_properties_ds.leap_indicator = LeapIndicator::NoLeap;
-                time_properties_ds.current_utc_offset = None;
-                time_properties_ds.ptp_timescale = true;
-                time_properties_ds.time_traceable = false;
-                time_properties_ds.frequency_traceable = false;
-                time_properties_ds.time_source = TimeSource::InternalOscillator;
+                // As grandmaster we announce the timescale of our own clock
+                *time_properties_ds = *local_time_properties_ds;
             }
             RecommendedState::M3(_) | RecommendedState::P1(_) | RecommendedState::P2(_) => {}
             RecommendedState::S1(announce_message) => {
@@ -109,11 +150,21 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
 
                 current_ds.steps_removed = announce_message.steps_removed + 1;
 
+                // The SMPTE metadata is only valid for the parent that sent it
+                if parent_ds.parent_port_identity != announce_message.header.source_port_identity {
+                    parent_ds.smpte_metadata = None;
+                }
+
                 parent_ds.parent_port_identity = announce_message.header.source_port_identity;
                 parent_ds.grandmaster_identity = announce_message.grandmaster_identity;
                 parent_ds.grandmaster_clock_quality = announce_message.grandmaster_clock_quality;
                 parent_ds.grandmaster_priority_1 = announce_message.grandmaster_priority_1;
                 parent_ds.grandmaster_priority_2 = announce_message.grandmaster_priority_2;
+                // The port only becomes slave when its best announce message is the best
+                parent_ds.power_profile = self
+                    .lifecycle
+                    .local_best
+                    .and_then(|best| best.power_profile);
 
                 *time_properties_ds = announce_message.time_properties();
 
@@ -146,7 +197,7 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                 let remote_master = announce_message.header.source_port_identity;
 
                 let update_state = match &self.port_state {
-                    PortState::Faulty => false,
+                    PortState::Faulty | PortState::Disabled => false,
                     PortState::Listening | PortState::Master | PortState::Passive => true,
                     PortState::Slave(old_state) => old_state.remote_master() != remote_master,
                 };
@@ -166,8 +217,12 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
             RecommendedState::M1(_) | RecommendedState::M2(_) | RecommendedState::M3(_) => {
                 if default_ds.slave_only {
                     match self.port_state {
-                        PortState::Listening | PortState::Faulty => { /* do nothing */ }
-                        PortState::Slave(_) | PortState::Passive => {
+                        PortState::Listening | PortState::Faulty | PortState::Disabled => {
+                            /* do nothing */
+                        }
+                        // A master port can end up here when slave-only was enabled through a
+                        // management message
+                        PortState::Slave(_) | PortState::Passive | PortState::Master => {
                             self.set_forced_port_state(PortState::Listening);
 
                             // consistent with Port<InBmca>::new()
@@ -175,11 +230,6 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                             let reset_announce = PortAction::ResetAnnounceReceiptTimer { duration };
                             self.lifecycle.pending_action = actions![reset_announce];
                         }
-                        PortState::Master => {
-                            let msg = "slave-only PTP port should not be in master state";
-                            debug_assert!(!default_ds.slave_only, "{msg}");
-                            log::error!("{msg}");
-                        }
                     }
                 } else {
                     match self.port_state {
@@ -193,7 +243,9 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                                 PortAction::ResetSyncTimer { duration }
                             ];
                         }
-                        PortState::Master | PortState::Faulty => { /* do nothing */ }
+                        PortState::Master | PortState::Faulty | PortState::Disabled => {
+                            /* do nothing */
+                        }
                     }
                 }
             }
@@ -201,7 +253,7 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                 PortState::Listening | PortState::Slave(_) | PortState::Master => {
                     self.set_forced_port_state(PortState::Passive)
                 }
-                PortState::Passive | PortState::Faulty => {}
+                PortState::Passive | PortState::Faulty | PortState::Disabled => {}
             },
         }
     }
@@ -211,10 +263,15 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
 mod tests {
     use super::*;
     use crate::{
-        datastructures::messages::{
-            AnnounceMessage, Header, Message, MessageBody, PtpVersion, MAX_DATA_LEN,
+        config::{LeapIndicator, TimeSource, UnicastNegotiationConfig},
+        datastructures::{
+            common::TlvSetBuilder,
+            messages::{AnnounceMessage, Header, Message, MessageBody, PtpVersion, MAX_DATA_LEN},
+        },
+        port::{
+            tests::{setup_test_port, setup_test_state},
+            unicast::UnicastState,
         },
-        port::tests::{setup_test_port, setup_test_state},
         time::Time,
     };
 
@@ -341,4 +398,139 @@ mod tests {
         port.calculate_best_local_announce_message();
         assert!(port.best_local_announce_message_for_bmca().is_some());
     }
+
+    #[test]
+    fn test_announce_receive_partial_timing() {
+        const MASTER: PortAddress = PortAddress::Ipv4([192, 0, 2, 2]);
+        const OTHER: PortAddress = PortAddress::Ipv4([192, 0, 2, 3]);
+
+        let state = setup_test_state();
+        state.borrow_mut().default_ds.profile = Profile::TelecomPartialTiming {
+            local_priority: 128,
+        };
+
+        let mut port = setup_test_port(&state);
+        port.unicast = Some(UnicastState::new(
+            &[MASTER],
+            Some(&UnicastNegotiationConfig { grant_duration: 60 }),
+        ));
+
+        let mut announce = default_announce_message();
+        announce.header.source_port_identity.clock_identity.0 = [1, 2, 3, 4, 5, 6, 7, 8];
+        announce.header.unicast_flag = true;
+        let announce_message = Message {
+            header: announce.header,
+            body: MessageBody::Announce(announce),
+            suffix: Default::default(),
+        };
+        let mut packet = [0; MAX_DATA_LEN];
+        let packet_len = announce_message.serialize(&mut packet).unwrap();
+        let packet = &packet[..packet_len];
+
+        // Masters outside of the unicast master table are ignored
+        let mut actions = port.handle_general_receive_from(packet, OTHER);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        // As are multicast announce messages
+        let mut actions = port.handle_general_receive(packet);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let mut actions = port.handle_general_receive_from(packet, MASTER);
+        let Some(PortAction::ResetAnnounceReceiptTimer { .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        // A multicast announce from the address of the master is still ignored
+        let mut multicast = announce_message;
+        multicast.header.unicast_flag = false;
+        let mut packet = [0; MAX_DATA_LEN];
+        let packet_len = multicast.serialize(&mut packet).unwrap();
+        let mut actions = port.handle_general_receive_from(&packet[..packet_len], MASTER);
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_announce_receive_power_profile() {
+        let state = setup_test_state();
+        state.borrow_mut().default_ds.profile = Profile::Power {
+            grandmaster_id: 5,
+            time_inaccuracy: 100,
+        };
+
+        let mut port = setup_test_port(&state);
+
+        let mut announce = default_announce_message();
+        announce.header.source_port_identity.clock_identity.0 = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut announce_message = Message {
+            header: announce.header,
+            body: MessageBody::Announce(announce),
+            suffix: Default::default(),
+        };
+        let mut packet = [0; MAX_DATA_LEN];
+        let packet_len = announce_message.serialize(&mut packet).unwrap();
+
+        // Announce messages without the IEEE_C37_238 TLV are ignored
+        let mut actions = port.handle_general_receive(&packet[..packet_len]);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let power_profile = PowerProfileTlv {
+            grandmaster_id: 7,
+            total_time_inaccuracy: 250,
+        };
+        let mut tlv_value = [0; PowerProfileTlv::WIRE_SIZE];
+        let mut tlv_buffer = [0; 32];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        tlv_builder
+            .add(power_profile.to_tlv(&mut tlv_value).unwrap())
+            .unwrap();
+        announce_message.suffix = tlv_builder.build();
+        let packet_len = announce_message.serialize(&mut packet).unwrap();
+
+        for _ in 0..3 {
+            let mut actions = port.handle_general_receive(&packet[..packet_len]);
+            let Some(PortAction::ResetAnnounceReceiptTimer { .. }) = actions.next() else {
+                panic!("Unexpected action");
+            };
+            assert!(actions.next().is_none());
+        }
+
+        let mut port = port.start_bmca();
+        port.calculate_best_local_announce_message();
+        let best = port.best_local_announce_message_for_bmca().unwrap();
+        assert_eq!(best.power_profile, Some(power_profile));
+    }
+
+    #[test]
+    fn test_master_announces_local_time_properties() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state).start_bmca();
+
+        let local_time_properties_ds = TimePropertiesDS::new_ptp_time(
+            Some(37),
+            LeapIndicator::NoLeap,
+            true,
+            true,
+            TimeSource::Gnss,
+        );
+        let default_ds = state.borrow().default_ds;
+        let mut time_properties_ds = TimePropertiesDS::default();
+        let mut current_ds = InternalCurrentDS::default();
+        let mut parent_ds = InternalParentDS::new(default_ds);
+
+        port.set_recommended_state(
+            RecommendedState::M1(default_ds),
+            &mut time_properties_ds,
+            &local_time_properties_ds,
+            &mut current_ds,
+            &mut parent_ds,
+            &default_ds,
+        );
+
+        assert_eq!(time_properties_ds, local_time_properties_ds);
+    }
 }
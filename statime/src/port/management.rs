@@ -0,0 +1,676 @@
+use core::ops::RangeInclusive;
+
+use rand::Rng;
+
+use super::{state::PortState, Port, PortAction, PortActionIterator, Running};
+use crate::{
+    config::{DelayMechanism, Profile},
+    datastructures::{
+        common::{TlvSetBuilder, TlvType},
+        datasets::InternalDefaultDS,
+        messages::{
+            ManagementAction, ManagementData, ManagementErrorId, ManagementErrorStatus,
+            ManagementId, ManagementMessage, ManagementTlv, Message,
+        },
+    },
+    filters::Filter,
+    observability::{port::DelayMechanism as PortDSDelayMechanism, smpte::SynchronizationMetadata},
+    time::Interval,
+    Clock,
+};
+
+/// Log intervals that can be configured through management messages.
+///
+/// Anything outside this range is rejected, as it would either flood the
+/// network or effectively stop the port from sending messages.
+const LOG_INTERVAL_RANGE: RangeInclusive<i8> = -8..=8;
+
+/// Changes to the instance wide default dataset requested through management
+/// messages.
+///
+/// A running port only has read access to the instance state, so these are
+/// applied at the start of the next BMCA run.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DefaultDSChanges {
+    priority_1: Option<u8>,
+    priority_2: Option<u8>,
+    domain_number: Option<u8>,
+    slave_only: Option<bool>,
+}
+
+impl DefaultDSChanges {
+    pub(crate) fn apply(self, default_ds: &mut InternalDefaultDS) {
+        if let Some(priority_1) = self.priority_1 {
+            default_ds.priority_1 = priority_1;
+        }
+        if let Some(priority_2) = self.priority_2 {
+            default_ds.priority_2 = priority_2;
+        }
+        if let Some(domain_number) = self.domain_number {
+            default_ds.domain_number = domain_number;
+        }
+        if let Some(slave_only) = self.slave_only {
+            default_ds.slave_only = slave_only;
+        }
+    }
+}
+
+impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
+    pub(super) fn handle_management<'b>(
+        &'b mut self,
+        message: &Message<'b>,
+        management: ManagementMessage,
+    ) -> PortActionIterator<'b> {
+        if !management.is_addressed_to(self.port_identity) {
+            return actions![];
+        }
+
+        // SMPTE ST 2059-2 carries its metadata in management messages without a
+        // management TLV
+        if let Some(metadata) = message
+            .suffix
+            .tlv()
+            .find_map(|tlv| SynchronizationMetadata::from_tlv(&tlv))
+        {
+            self.handle_smpte_metadata(message, metadata);
+            return actions![];
+        }
+
+        let response_action = match management.action {
+            ManagementAction::GET | ManagementAction::SET => ManagementAction::RESPONSE,
+            ManagementAction::COMMAND => ManagementAction::ACKNOWLEDGE,
+            // Responses are for management nodes, which we are not
+            ManagementAction::RESPONSE
+            | ManagementAction::ACKNOWLEDGE
+            | ManagementAction::Reserved => return actions![],
+        };
+
+        let Some(tlv) = message
+            .suffix
+            .tlv()
+            .find(|tlv| tlv.tlv_type == TlvType::Management)
+        else {
+            log::debug!("Ignoring management message without management TLV");
+            return actions![];
+        };
+        let Some(request) = ManagementTlv::from_tlv(&tlv) else {
+            log::debug!("Ignoring management message with malformed management TLV");
+            return actions![];
+        };
+
+        log::debug!(
+            "Received management {:?} for {:?}",
+            management.action,
+            request.management_id
+        );
+
+        let mut extra_action = None;
+        let result = match management.action {
+            // The data field of a GET is ignored, some implementations send a zero
+            // filled data field of the expected size.
+            ManagementAction::GET => self.management_get(request.management_id),
+            ManagementAction::SET => ManagementData::deserialize(
+                request.management_id,
+                request.data,
+                message.header.sdo_id,
+            )
+            .and_then(|data| self.management_set(data)),
+            _ => ManagementData::deserialize(
+                request.management_id,
+                request.data,
+                message.header.sdo_id,
+            )
+            .and_then(|data| {
+                extra_action = self.management_command(data)?;
+                Ok(data)
+            }),
+        };
+
+        let mut value_buffer = [0; 64];
+        let response_tlv = match result {
+            Ok(data) => data.to_tlv(&mut value_buffer),
+            Err(error_id) => {
+                log::debug!(
+                    "Management {:?} for {:?} failed: {:?}",
+                    management.action,
+                    request.management_id,
+                    error_id
+                );
+                ManagementErrorStatus {
+                    error_id,
+                    management_id: request.management_id,
+                }
+                .to_tlv(&mut value_buffer)
+            }
+        };
+
+        let mut tlv_buffer = [0; 68];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        if let Err(error) = response_tlv.and_then(|tlv| tlv_builder.add(tlv)) {
+            log::error!("Statime bug: Could not build management TLV: {:?}", error);
+            return actions![];
+        }
+
+        let response = Message::management_response(
+            &self.lifecycle.state.default_ds,
+            self.port_identity,
+            message.header,
+            &management,
+            response_action,
+            tlv_builder.build(),
+        );
+
+        let packet_length = match response.serialize(&mut self.packet_buffer) {
+            Ok(length) => length,
+            Err(error) => {
+                log::error!("Could not serialize management response: {:?}", error);
+                return actions![];
+            }
+        };
+
+        let send = PortAction::SendGeneral {
+            data: &self.packet_buffer[..packet_length],
+            link_local: false,
+            destination: None,
+        };
+
+        match extra_action {
+            Some(action) => actions![send, action],
+            None => actions![send],
+        }
+    }
+
+    fn handle_smpte_metadata(&mut self, message: &Message, metadata: SynchronizationMetadata) {
+        let state = &self.lifecycle.state;
+        if !matches!(state.default_ds.profile, Profile::Smpte2059 { .. }) {
+            log::debug!("Ignoring SMPTE metadata outside of the SMPTE profile");
+            return;
+        }
+
+        if !matches!(self.port_state, PortState::Slave(_))
+            || message.header.source_port_identity != state.parent_ds.parent_port_identity
+        {
+            log::debug!("Ignoring SMPTE metadata not sent by our parent");
+            return;
+        }
+
+        self.smpte_metadata = Some(metadata);
+    }
+
+    fn management_get(
+        &self,
+        management_id: ManagementId,
+    ) -> Result<ManagementData, ManagementErrorId> {
+        let state = &self.lifecycle.state;
+
+        // Report the values as they will be after the next BMCA run
+        let mut default_ds = state.default_ds;
+        self.default_ds_changes.apply(&mut default_ds);
+
+        let data = match management_id {
+            ManagementId::NullPtpManagement => ManagementData::NullPtpManagement,
+            ManagementId::DefaultDataSet => ManagementData::DefaultDataSet((&default_ds).into()),
+            ManagementId::CurrentDataSet => {
+                ManagementData::CurrentDataSet((&state.current_ds).into())
+            }
+            ManagementId::ParentDataSet => ManagementData::ParentDataSet((&state.parent_ds).into()),
+            ManagementId::TimePropertiesDataSet => {
+                ManagementData::TimePropertiesDataSet(state.time_properties_ds)
+            }
+            ManagementId::PortDataSet => ManagementData::PortDataSet(self.port_ds()),
+            ManagementId::Priority1 => ManagementData::Priority1(default_ds.priority_1),
+            ManagementId::Priority2 => ManagementData::Priority2(default_ds.priority_2),
+            ManagementId::Domain => ManagementData::Domain(default_ds.domain_number),
+            ManagementId::SlaveOnly => ManagementData::SlaveOnly(default_ds.slave_only),
+            ManagementId::LogAnnounceInterval => {
+                ManagementData::LogAnnounceInterval(self.config.announce_interval.as_log_2())
+            }
+            ManagementId::AnnounceReceiptTimeout => {
+                ManagementData::AnnounceReceiptTimeout(self.config.announce_receipt_timeout)
+            }
+            ManagementId::LogSyncInterval => {
+                ManagementData::LogSyncInterval(self.config.sync_interval.as_log_2())
+            }
+            ManagementId::VersionNumber => {
+                let port_ds = self.port_ds();
+                ManagementData::VersionNumber {
+                    major: port_ds.version_number,
+                    minor: port_ds.minor_version_number,
+                }
+            }
+            ManagementId::DelayMechanism => match self.config.delay_mechanism {
+                DelayMechanism::E2E { .. } => {
+                    ManagementData::DelayMechanism(PortDSDelayMechanism::E2E)
+                }
+                DelayMechanism::P2P { .. } => {
+                    ManagementData::DelayMechanism(PortDSDelayMechanism::P2P)
+                }
+            },
+            ManagementId::LogMinPdelayReqInterval => {
+                ManagementData::LogMinPdelayReqInterval(self.port_ds().log_min_pdelay_req_interval)
+            }
+            ManagementId::Reserved(_) => return Err(ManagementErrorId::NoSuchId),
+            _ => return Err(ManagementErrorId::NotSupported),
+        };
+
+        Ok(data)
+    }
+
+    fn management_set(
+        &mut self,
+        data: ManagementData,
+    ) -> Result<ManagementData, ManagementErrorId> {
+        match data {
+            ManagementData::NullPtpManagement => {}
+            ManagementData::Priority1(priority_1) => {
+                self.default_ds_changes.priority_1 = Some(priority_1)
+            }
+            ManagementData::Priority2(priority_2) => {
+                self.default_ds_changes.priority_2 = Some(priority_2)
+            }
+            ManagementData::Domain(domain_number) => {
+                self.default_ds_changes.domain_number = Some(domain_number)
+            }
+            ManagementData::SlaveOnly(slave_only) => {
+                self.default_ds_changes.slave_only = Some(slave_only)
+            }
+            ManagementData::LogAnnounceInterval(log_interval) => {
+                if !LOG_INTERVAL_RANGE.contains(&log_interval) {
+                    return Err(ManagementErrorId::WrongValue);
+                }
+                self.config.announce_interval = Interval::from_log_2(log_interval);
+            }
+            ManagementData::AnnounceReceiptTimeout(timeout) => {
+                // See IEEE1588-2019 section 7.7.3.1
+                if timeout < 2 {
+                    return Err(ManagementErrorId::WrongValue);
+                }
+                self.config.announce_receipt_timeout = timeout;
+            }
+            ManagementData::LogSyncInterval(log_interval) => {
+                if !LOG_INTERVAL_RANGE.contains(&log_interval) {
+                    return Err(ManagementErrorId::WrongValue);
+                }
+                self.config.sync_interval = Interval::from_log_2(log_interval);
+            }
+            ManagementData::LogMinPdelayReqInterval(log_interval) => {
+                if !LOG_INTERVAL_RANGE.contains(&log_interval) {
+                    return Err(ManagementErrorId::WrongValue);
+                }
+                match &mut self.config.delay_mechanism {
+                    DelayMechanism::P2P { interval } => {
+                        *interval = Interval::from_log_2(log_interval)
+                    }
+                    DelayMechanism::E2E { .. } => return Err(ManagementErrorId::NotSetable),
+                }
+            }
+            ManagementData::DefaultDataSet(_)
+            | ManagementData::CurrentDataSet(_)
+            | ManagementData::ParentDataSet(_)
+            | ManagementData::TimePropertiesDataSet(_)
+            | ManagementData::PortDataSet(_)
+            | ManagementData::VersionNumber { .. }
+            | ManagementData::DelayMechanism(_) => return Err(ManagementErrorId::NotSetable),
+            ManagementData::Initialize { .. }
+            | ManagementData::EnablePort
+            | ManagementData::DisablePort => return Err(ManagementErrorId::NotSupported),
+        }
+
+        // Respond with the updated values
+        self.management_get(data.management_id())
+    }
+
+    fn management_command(
+        &mut self,
+        data: ManagementData,
+    ) -> Result<Option<PortAction<'static>>, ManagementErrorId> {
+        match data {
+            ManagementData::NullPtpManagement => Ok(None),
+            ManagementData::EnablePort => {
+                if !matches!(self.port_state, PortState::Disabled) {
+                    return Ok(None);
+                }
+
+                self.set_forced_port_state(PortState::Listening);
+
+                // consistent with Port<InBmca>::new()
+                let duration = self.config.announce_duration(&mut self.rng);
+                Ok(Some(PortAction::ResetAnnounceReceiptTimer { duration }))
+            }
+            ManagementData::DisablePort => {
+                if !matches!(self.port_state, PortState::Disabled) {
+                    self.set_forced_port_state(PortState::Disabled);
+                }
+
+                Ok(None)
+            }
+            ManagementData::Initialize { key } => {
+                // Only the INITIALIZE_EVENT key is defined, see IEEE1588-2019 section
+                // 15.5.3.2.3
+                if key != 0 {
+                    return Err(ManagementErrorId::WrongValue);
+                }
+
+                // A disabled port stays disabled until it is explicitly enabled
+                if matches!(self.port_state, PortState::Disabled) {
+                    return Ok(None);
+                }
+
+                self.set_forced_port_state(PortState::Listening);
+                self.peer_delay.reset();
+                self.mean_delay = None;
+
+                let duration = self.config.announce_duration(&mut self.rng);
+                Ok(Some(PortAction::ResetAnnounceReceiptTimer { duration }))
+            }
+            _ => Err(ManagementErrorId::NotSupported),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::ClockIdentity,
+        datastructures::{
+            common::{PortIdentity, Tlv},
+            messages::{Header, MessageBody, MAX_DATA_LEN},
+        },
+        observability::smpte::MasterLockingStatus,
+        port::{
+            state::SlaveState,
+            tests::{setup_test_port, setup_test_state},
+        },
+    };
+
+    fn management_request<'a>(
+        buffer: &'a mut [u8],
+        action: ManagementAction,
+        management_id: ManagementId,
+        data: &[u8],
+    ) -> &'a [u8] {
+        let mut value = [0; 64];
+        value[0..2].copy_from_slice(&management_id.to_primitive().to_be_bytes());
+        value[2..][..data.len()].copy_from_slice(data);
+
+        let mut tlv_buffer = [0; 68];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        tlv_builder
+            .add(Tlv {
+                tlv_type: TlvType::Management,
+                value: (&value[..2 + data.len()]).into(),
+            })
+            .unwrap();
+
+        let message = Message {
+            header: Header {
+                source_port_identity: PortIdentity {
+                    clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+                    port_number: 1,
+                },
+                sequence_id: 42,
+                ..Default::default()
+            },
+            body: MessageBody::Management(ManagementMessage {
+                target_port_identity: PortIdentity {
+                    clock_identity: ClockIdentity([0xff; 8]),
+                    port_number: 0xffff,
+                },
+                starting_boundary_hops: 1,
+                boundary_hops: 1,
+                action,
+            }),
+            suffix: tlv_builder.build(),
+        };
+
+        let length = message.serialize(buffer).unwrap();
+        &buffer[..length]
+    }
+
+    fn parse_response(data: &[u8]) -> (ManagementAction, Tlv<'_>) {
+        let message = Message::deserialize(data).unwrap();
+        assert_eq!(message.header.sequence_id, 42);
+
+        let MessageBody::Management(management) = message.body else {
+            panic!("Unexpected message type");
+        };
+        assert_eq!(
+            management.target_port_identity,
+            PortIdentity {
+                clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+                port_number: 1,
+            }
+        );
+
+        let tlv = message.suffix.tlv().next().unwrap();
+        (management.action, tlv)
+    }
+
+    #[test]
+    fn test_management_get() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let request = management_request(
+            &mut buffer,
+            ManagementAction::GET,
+            ManagementId::DefaultDataSet,
+            &[],
+        );
+
+        let mut actions = port.handle_general_receive(request);
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+
+        let (action, tlv) = parse_response(data);
+        assert_eq!(action, ManagementAction::RESPONSE);
+
+        let response = ManagementTlv::from_tlv(&tlv).unwrap();
+        let ManagementData::DefaultDataSet(default_ds) =
+            ManagementData::deserialize(response.management_id, response.data, Default::default())
+                .unwrap()
+        else {
+            panic!("Unexpected management data");
+        };
+        assert_eq!(default_ds.priority_1, 255);
+        assert_eq!(default_ds.priority_2, 255);
+        assert!(default_ds.two_step);
+    }
+
+    #[test]
+    fn test_management_set_applied_at_bmca() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let request = management_request(
+            &mut buffer,
+            ManagementAction::SET,
+            ManagementId::Priority1,
+            &[100, 0],
+        );
+
+        let mut actions = port.handle_general_receive(request);
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+
+        let (action, tlv) = parse_response(data);
+        assert_eq!(action, ManagementAction::RESPONSE);
+        let response = ManagementTlv::from_tlv(&tlv).unwrap();
+        assert_eq!(response.management_id, ManagementId::Priority1);
+        assert_eq!(response.data, &[100, 0]);
+        drop(actions);
+
+        // Not yet applied to the instance state
+        assert_eq!(state.borrow().default_ds.priority_1, 255);
+
+        let mut port = port.start_bmca();
+        let changes = port.take_default_ds_changes();
+        changes.apply(&mut state.borrow_mut().default_ds);
+        assert_eq!(state.borrow().default_ds.priority_1, 100);
+
+        // The changes are only applied once
+        assert_eq!(port.take_default_ds_changes().priority_1, None);
+    }
+
+    #[test]
+    fn test_management_set_not_setable() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let request = management_request(
+            &mut buffer,
+            ManagementAction::SET,
+            ManagementId::TimePropertiesDataSet,
+            &[0, 0, 0, 0xa0],
+        );
+
+        let mut actions = port.handle_general_receive(request);
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+
+        let (action, tlv) = parse_response(data);
+        assert_eq!(action, ManagementAction::RESPONSE);
+        assert_eq!(
+            ManagementErrorStatus::from_tlv(&tlv),
+            Some(ManagementErrorStatus {
+                error_id: ManagementErrorId::NotSetable,
+                management_id: ManagementId::TimePropertiesDataSet,
+            })
+        );
+    }
+
+    #[test]
+    fn test_management_disable_enable_port() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let request = management_request(
+            &mut buffer,
+            ManagementAction::COMMAND,
+            ManagementId::DisablePort,
+            &[],
+        );
+
+        let mut actions = port.handle_general_receive(request);
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        let (action, _) = parse_response(data);
+        assert_eq!(action, ManagementAction::ACKNOWLEDGE);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        assert!(matches!(port.port_state, PortState::Disabled));
+
+        // A disabled port does not become master on its own
+        let mut actions = port.handle_announce_receipt_timer();
+        assert!(actions.next().is_none());
+        drop(actions);
+        assert!(matches!(port.port_state, PortState::Disabled));
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let request = management_request(
+            &mut buffer,
+            ManagementAction::COMMAND,
+            ManagementId::EnablePort,
+            &[],
+        );
+
+        let mut actions = port.handle_general_receive(request);
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::SendGeneral { .. })
+        ));
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::ResetAnnounceReceiptTimer { .. })
+        ));
+        drop(actions);
+
+        assert!(matches!(port.port_state, PortState::Listening));
+    }
+
+    #[test]
+    fn test_management_wrong_target() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let length = management_request(
+            &mut buffer,
+            ManagementAction::GET,
+            ManagementId::DefaultDataSet,
+            &[],
+        )
+        .len();
+
+        // Target port number is at offset 42 and 43
+        buffer[42..44].copy_from_slice(&5u16.to_be_bytes());
+
+        let mut actions = port.handle_general_receive(&buffer[..length]);
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_smpte_metadata_from_parent() {
+        let metadata = SynchronizationMetadata {
+            frame_rate_numerator: 25,
+            frame_rate_denominator: 1,
+            master_locking_status: MasterLockingStatus::Locked,
+            ..Default::default()
+        };
+        let parent = PortIdentity {
+            clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+            port_number: 1,
+        };
+
+        let state = setup_test_state();
+
+        let mut value = [0; SynchronizationMetadata::WIRE_SIZE];
+        let mut tlv_buffer = [0; SynchronizationMetadata::WIRE_SIZE + 4];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        tlv_builder
+            .add(metadata.to_tlv(&mut value).unwrap())
+            .unwrap();
+        let mut buffer = [0; MAX_DATA_LEN];
+        let length =
+            Message::smpte_metadata(&state.borrow().default_ds, parent, 1, tlv_builder.build())
+                .serialize(&mut buffer)
+                .unwrap();
+        let request = &buffer[..length];
+
+        let mut state_ref = state.borrow_mut();
+        state_ref.default_ds.profile = Profile::Smpte2059 {
+            metadata: Default::default(),
+        };
+        state_ref.parent_ds.parent_port_identity = parent;
+        drop(state_ref);
+
+        // Only slaves accept the metadata
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert!(port.handle_general_receive(request).next().is_none());
+        assert_eq!(port.start_bmca().take_smpte_metadata(), None);
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Slave(SlaveState::new(parent)));
+        assert!(port.handle_general_receive(request).next().is_none());
+        assert_eq!(port.start_bmca().take_smpte_metadata(), Some(metadata));
+
+        // And only from their parent
+        state.borrow_mut().parent_ds.parent_port_identity = Default::default();
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Slave(SlaveState::new(parent)));
+        assert!(port.handle_general_receive(request).next().is_none());
+        assert_eq!(port.start_bmca().take_smpte_metadata(), None);
+    }
+}
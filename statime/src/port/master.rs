@@ -1,41 +1,72 @@
 use super::{state::PortState, ForwardedTLVProvider, Port, PortActionIterator, Running};
 use crate::{
+    config::Profile,
     datastructures::{
-        common::{PortIdentity, TlvSetBuilder},
-        messages::{DelayReqMessage, Header, Message, MAX_DATA_LEN},
+        common::{PortAddress, PortIdentity, TlvSetBuilder},
+        messages::{
+            DelayReqMessage, FollowUpInformationTlv, Header, Message, MessageType, PowerProfileTlv,
+            MAX_DATA_LEN,
+        },
     },
     filters::Filter,
+    observability::smpte::SynchronizationMetadata,
     port::{actions::TimestampContextInner, PortAction, TimestampContext},
     time::Time,
+    Clock,
 };
 
-impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
+impl<'a, A, C: Clock, F: Filter, R> Port<Running<'a>, A, R, C, F> {
     pub(super) fn send_sync(&mut self) -> PortActionIterator {
         if matches!(self.port_state, PortState::Master) {
+            if !self.is_as_capable() {
+                return actions![PortAction::ResetSyncTimer {
+                    duration: self.config.sync_interval.as_core_duration(),
+                }];
+            }
+
+            let Some((destination, duration)) =
+                self.next_transmission(MessageType::Sync, self.config.sync_interval)
+            else {
+                return actions![PortAction::ResetSyncTimer {
+                    duration: self.config.sync_interval.as_core_duration(),
+                }];
+            };
+
             log::trace!("sending sync message");
 
             let seq_id = self.sync_seq_ids.generate();
-            let packet_length =
-                match Message::sync(&self.lifecycle.state.default_ds, self.port_identity, seq_id)
-                    .serialize(&mut self.packet_buffer)
-                {
-                    Ok(message) => message,
-                    Err(error) => {
-                        log::error!("Statime bug: Could not serialize sync: {:?}", error);
-                        return actions![];
-                    }
-                };
+            let mut message = if self.config.one_step {
+                Message::one_step_sync(
+                    &self.lifecycle.state.default_ds,
+                    self.port_identity,
+                    seq_id,
+                    self.clock.now(),
+                )
+            } else {
+                Message::sync(&self.lifecycle.state.default_ds, self.port_identity, seq_id)
+            };
+            message.header.unicast_flag = destination.is_some();
+
+            let packet_length = match message.serialize(&mut self.packet_buffer) {
+                Ok(message) => message,
+                Err(error) => {
+                    log::error!("Statime bug: Could not serialize sync: {:?}", error);
+                    return actions![];
+                }
+            };
 
             actions![
-                PortAction::ResetSyncTimer {
-                    duration: self.config.sync_interval.as_core_duration(),
-                },
+                PortAction::ResetSyncTimer { duration },
                 PortAction::SendEvent {
                     context: TimestampContext {
-                        inner: TimestampContextInner::Sync { id: seq_id },
+                        inner: TimestampContextInner::Sync {
+                            id: seq_id,
+                            destination,
+                        },
                     },
                     data: &self.packet_buffer[..packet_length],
-                    link_local: false,
+                    link_local: self.link_local_only(),
+                    destination,
                 }
             ]
         } else {
@@ -43,16 +74,43 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         }
     }
 
-    pub(super) fn handle_sync_timestamp(&mut self, id: u16, timestamp: Time) -> PortActionIterator {
-        if matches!(self.port_state, PortState::Master) {
-            let packet_length = match Message::follow_up(
+    pub(super) fn handle_sync_timestamp(
+        &mut self,
+        id: u16,
+        destination: Option<PortAddress>,
+        timestamp: Time,
+    ) -> PortActionIterator {
+        // One-step syncs need no follow up
+        if matches!(self.port_state, PortState::Master) && !self.config.one_step {
+            let mut message = Message::follow_up(
                 &self.lifecycle.state.default_ds,
                 self.port_identity,
                 id,
                 timestamp,
-            )
-            .serialize(&mut self.packet_buffer)
-            {
+            );
+            message.header.unicast_flag = destination.is_some();
+
+            // gPTP requires the Follow_Up information TLV, with the rate of the
+            // grandmaster relative to our clock as measured by the slave port
+            let mut tlv_value = [0; FollowUpInformationTlv::WIRE_SIZE];
+            let mut tlv_buffer = [0; MAX_DATA_LEN];
+            if self.link_local_only() {
+                let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+                let information =
+                    FollowUpInformationTlv::from_rate_ratio(self.lifecycle.state.gm_rate_ratio);
+                let tlv = match information.to_tlv(&mut tlv_value) {
+                    Ok(tlv) => tlv,
+                    Err(error) => {
+                        log::error!("Statime bug: Could not serialize tlv {:?}", error);
+                        return actions![];
+                    }
+                };
+                // Will not fail as the buffer is large enough for the TLV
+                tlv_builder.add(tlv).unwrap();
+                message.suffix = tlv_builder.build();
+            }
+
+            let packet_length = match message.serialize(&mut self.packet_buffer) {
                 Ok(length) => length,
                 Err(error) => {
                     log::error!(
@@ -65,18 +123,60 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
 
             actions![PortAction::SendGeneral {
                 data: &self.packet_buffer[..packet_length],
-                link_local: false,
+                link_local: self.link_local_only(),
+                destination,
             }]
         } else {
             actions![]
         }
     }
 
+    /// The IEEE_C37_238 TLV of our announce messages. As boundary clock we
+    /// add our own inaccuracy to that of the path to the grandmaster.
+    fn power_profile_tlv(&self) -> Option<PowerProfileTlv> {
+        let Profile::Power {
+            time_inaccuracy, ..
+        } = self.lifecycle.state.default_ds.profile
+        else {
+            return None;
+        };
+
+        let mut tlv = self.lifecycle.state.parent_ds.power_profile?;
+        if self.lifecycle.state.current_ds.steps_removed > 0 {
+            tlv.total_time_inaccuracy = tlv.total_time_inaccuracy.saturating_add(time_inaccuracy);
+        }
+
+        Some(tlv)
+    }
+
+    /// The SMPTE synchronization metadata we send along with our announce
+    /// messages, that of the grandmaster
+    fn smpte_metadata_to_send(&self) -> Option<SynchronizationMetadata> {
+        match self.lifecycle.state.default_ds.profile {
+            Profile::Smpte2059 { .. } => self.lifecycle.state.parent_ds.smpte_metadata,
+            _ => None,
+        }
+    }
+
     pub(super) fn send_announce(
         &mut self,
         tlv_provider: &mut impl ForwardedTLVProvider,
     ) -> PortActionIterator {
         if matches!(self.port_state, PortState::Master) {
+            if !self.is_as_capable() {
+                return actions![PortAction::ResetAnnounceTimer {
+                    duration: self.config.announce_interval.as_core_duration(),
+                }];
+            }
+
+            let Some((destination, duration)) =
+                self.next_transmission(MessageType::Announce, self.config.announce_interval)
+            else {
+                return actions![PortAction::ResetAnnounceTimer {
+                    duration: self.config.announce_interval.as_core_duration(),
+                }];
+            };
+
             log::trace!("sending announce message");
 
             let mut tlv_buffer = [0; MAX_DATA_LEN];
@@ -89,6 +189,20 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
             );
             let mut tlv_margin = MAX_DATA_LEN - message.wire_size();
 
+            // Room for the SMPTE metadata message sent along with the announce
+            let smpte_metadata = self.smpte_metadata_to_send();
+            if smpte_metadata.is_some() {
+                tlv_margin -= SynchronizationMetadata::MESSAGE_WIRE_SIZE;
+            }
+
+            let mut power_profile_value = [0; PowerProfileTlv::WIRE_SIZE];
+            if let Some(power_profile) = self.power_profile_tlv() {
+                // Will not fail, the buffers are large enough
+                let tlv = power_profile.to_tlv(&mut power_profile_value).unwrap();
+                tlv_margin -= tlv.wire_size();
+                tlv_builder.add(tlv).unwrap();
+            }
+
             while let Some(tlv) = tlv_provider.next_if_smaller(tlv_margin) {
                 assert!(tlv.size() < tlv_margin);
                 if self.lifecycle.state.parent_ds.parent_port_identity != tlv.sender_identity {
@@ -102,14 +216,9 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
             }
 
             message.suffix = tlv_builder.build();
+            message.header.unicast_flag = destination.is_some();
 
-            let packet_length = match Message::announce(
-                &self.lifecycle.state,
-                self.port_identity,
-                self.announce_seq_ids.generate(),
-            )
-            .serialize(&mut self.packet_buffer)
-            {
+            let packet_length = match message.serialize(&mut self.packet_buffer) {
                 Ok(length) => length,
                 Err(error) => {
                     log::error!(
@@ -120,15 +229,52 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
                 }
             };
 
-            actions![
-                PortAction::ResetAnnounceTimer {
-                    duration: self.config.announce_interval.as_core_duration(),
-                },
-                PortAction::SendGeneral {
-                    data: &self.packet_buffer[..packet_length],
-                    link_local: false,
+            let link_local = self.link_local_only();
+            let (announce_buffer, smpte_buffer) = self.packet_buffer.split_at_mut(packet_length);
+            let announce = PortAction::SendGeneral {
+                data: announce_buffer,
+                link_local,
+                destination,
+            };
+
+            let Some(metadata) = smpte_metadata else {
+                return actions![PortAction::ResetAnnounceTimer { duration }, announce];
+            };
+
+            let mut smpte_value = [0; SynchronizationMetadata::WIRE_SIZE];
+            let mut smpte_tlv_buffer = [0; SynchronizationMetadata::WIRE_SIZE + 4];
+            let mut smpte_tlv_builder = TlvSetBuilder::new(&mut smpte_tlv_buffer);
+            // Will not fail, the buffers are large enough
+            smpte_tlv_builder
+                .add(metadata.to_tlv(&mut smpte_value).unwrap())
+                .unwrap();
+
+            let mut smpte_message = Message::smpte_metadata(
+                &self.lifecycle.state.default_ds,
+                self.port_identity,
+                self.management_seq_ids.generate(),
+                smpte_tlv_builder.build(),
+            );
+            smpte_message.header.unicast_flag = destination.is_some();
+
+            match smpte_message.serialize(smpte_buffer) {
+                Ok(length) => actions![
+                    PortAction::ResetAnnounceTimer { duration },
+                    announce,
+                    PortAction::SendGeneral {
+                        data: &smpte_buffer[..length],
+                        link_local,
+                        destination,
+                    }
+                ],
+                Err(error) => {
+                    log::error!(
+                        "Statime bug: Could not serialize SMPTE metadata message {:?}",
+                        error
+                    );
+                    actions![PortAction::ResetAnnounceTimer { duration }, announce]
                 }
-            ]
+            }
         } else {
             actions![]
         }
@@ -139,16 +285,35 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         header: Header,
         message: DelayReqMessage,
         timestamp: Time,
+        source: Option<PortAddress>,
     ) -> PortActionIterator {
         if matches!(self.port_state, PortState::Master) {
             log::debug!("Received DelayReq");
-            let delay_resp_message = Message::delay_resp(
+
+            // With unicast negotiation we only respond to ports that were granted
+            // delay responses
+            let destination = match (&self.unicast, source) {
+                (None, Some(source)) if self.config.hybrid_e2e => Some(source),
+                (None, _) => None,
+                (Some(unicast), Some(source))
+                    if unicast.has_grant(source, MessageType::DelayResp) =>
+                {
+                    Some(source)
+                }
+                (Some(_), _) => {
+                    log::debug!("Ignoring DelayReq without unicast grant");
+                    return actions![];
+                }
+            };
+
+            let mut delay_resp_message = Message::delay_resp(
                 header,
                 message,
                 self.port_identity,
                 self.config.min_delay_req_interval(),
                 timestamp,
             );
+            delay_resp_message.header.unicast_flag = destination.is_some();
 
             let packet_length = match delay_resp_message.serialize(&mut self.packet_buffer) {
                 Ok(length) => length,
@@ -161,6 +326,7 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
             actions![PortAction::SendGeneral {
                 data: &self.packet_buffer[..packet_length],
                 link_local: false,
+                destination,
             }]
         } else {
             actions![]
@@ -173,12 +339,22 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         timestamp: Time,
     ) -> PortActionIterator {
         log::debug!("Received PDelayReq");
-        let pdelay_resp_message = Message::pdelay_resp(
-            &self.lifecycle.state.default_ds,
-            self.port_identity,
-            header,
-            timestamp,
-        );
+        let pdelay_resp_message = if self.config.one_step {
+            Message::one_step_pdelay_resp(
+                &self.lifecycle.state.default_ds,
+                self.port_identity,
+                header,
+                timestamp,
+                self.clock.now(),
+            )
+        } else {
+            Message::pdelay_resp(
+                &self.lifecycle.state.default_ds,
+                self.port_identity,
+                header,
+                timestamp,
+            )
+        };
 
         let packet_length = match pdelay_resp_message.serialize(&mut self.packet_buffer) {
             Ok(length) => length,
@@ -197,6 +373,7 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
                 }
             },
             link_local: true,
+            destination: None,
         }]
     }
 
@@ -206,6 +383,11 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         requestor_identity: PortIdentity,
         timestamp: Time,
     ) -> PortActionIterator {
+        // One-step responses need no follow up
+        if self.config.one_step {
+            return actions![];
+        }
+
         let pdelay_resp_follow_up_messgae = Message::pdelay_resp_follow_up(
             &self.lifecycle.state.default_ds,
             self.port_identity,
@@ -225,6 +407,7 @@ impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         actions![PortAction::SendGeneral {
             data: &self.packet_buffer[..packet_length],
             link_local: true,
+            destination: None,
         }]
     }
 }
@@ -235,16 +418,18 @@ mod tests {
 
     use super::*;
     use crate::{
-        config::DelayMechanism,
+        config::{ClockAccuracy, ClockQuality, DelayMechanism},
         datastructures::{
-            common::{PortIdentity, TimeInterval},
-            messages::{Header, MessageBody},
+            common::{PortIdentity, TimeInterval, Tlv, TlvType},
+            messages::{Header, MessageBody, SdoId},
         },
+        filters::BasicFilter,
+        observability::smpte::MasterLockingStatus,
         port::{
-            tests::{setup_test_port, setup_test_state},
-            NoForwardedTLVs,
+            tests::{setup_test_port, setup_test_port_custom_filter, setup_test_state},
+            ForwardedTLV, ForwardedTLVProvider, NoForwardedTLVs,
         },
-        time::Interval,
+        time::{Duration, Interval},
     };
 
     #[test]
@@ -273,11 +458,13 @@ mod tests {
                 origin_timestamp: Time::from_micros(0).into(),
             },
             Time::from_fixed_nanos(U96F32::from_bits((200000 << 32) + (500 << 16))),
+            None,
         );
 
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = action.next()
         else {
             panic!("Unexpected resulting action");
@@ -326,11 +513,13 @@ mod tests {
                 origin_timestamp: Time::from_micros(0).into(),
             },
             Time::from_fixed_nanos(U96F32::from_bits((220000 << 32) + (300 << 16))),
+            None,
         );
 
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = action.next()
         else {
             panic!("Unexpected resulting action");
@@ -361,6 +550,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hybrid_delay_response() {
+        let state = setup_test_state();
+
+        let mut port = setup_test_port(&state);
+        port.config.hybrid_e2e = true;
+        port.set_forced_port_state(PortState::Master);
+
+        let source = PortAddress::Ipv4([192, 168, 1, 2]);
+        let mut action = port.handle_delay_req(
+            Header::default(),
+            DelayReqMessage {
+                origin_timestamp: Time::from_micros(0).into(),
+            },
+            Time::from_micros(200),
+            Some(source),
+        );
+
+        let Some(PortAction::SendGeneral {
+            data,
+            link_local: false,
+            destination: Some(destination),
+        }) = action.next()
+        else {
+            panic!("Unexpected resulting action");
+        };
+        assert!(action.next().is_none());
+        assert_eq!(destination, source);
+
+        let msg = Message::deserialize(data).unwrap();
+        assert!(msg.header.unicast_flag);
+        assert!(matches!(msg.body, MessageBody::DelayResp(_)));
+    }
+
     #[test]
     fn test_announce() {
         let state = setup_test_state();
@@ -386,6 +609,7 @@ mod tests {
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -412,6 +636,7 @@ mod tests {
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -430,6 +655,94 @@ mod tests {
         assert_ne!(msg2_header.sequence_id, msg_header.sequence_id);
     }
 
+    #[test]
+    fn test_announce_forwarded_tlv() {
+        struct OneTlv(Option<ForwardedTLV<'static>>);
+
+        impl ForwardedTLVProvider for OneTlv {
+            fn next_if_smaller(&mut self, max_size: usize) -> Option<ForwardedTLV<'_>> {
+                self.0.take().filter(|tlv| tlv.size() < max_size)
+            }
+        }
+
+        let state = setup_test_state();
+        let parent_port_identity = state.borrow().parent_ds.parent_port_identity;
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+
+        let mut tlv_provider = OneTlv(Some(ForwardedTLV {
+            tlv: Tlv {
+                tlv_type: TlvType::PathTrace,
+                value: b"abcd".as_slice().into(),
+            },
+            sender_identity: parent_port_identity,
+        }));
+        let mut actions = port.send_announce(&mut tlv_provider);
+
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::ResetAnnounceTimer { .. })
+        ));
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+
+        let msg = Message::deserialize(data).unwrap();
+        let mut tlvs = msg.suffix.tlv();
+        let tlv = tlvs.next().unwrap();
+        assert_eq!(tlv.tlv_type, TlvType::PathTrace);
+        assert_eq!(&tlv.value[..], b"abcd");
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn test_announce_tracking_clock_quality() {
+        fn announced_clock_quality<A, C: Clock, F: Filter, R>(
+            port: &mut Port<Running<'_>, A, R, C, F>,
+        ) -> ClockQuality {
+            let mut actions = port.send_announce(&mut NoForwardedTLVs);
+            assert!(matches!(
+                actions.next(),
+                Some(PortAction::ResetAnnounceTimer { .. })
+            ));
+            let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+                panic!("Unexpected action");
+            };
+
+            let MessageBody::Announce(announce) = Message::deserialize(data).unwrap().body else {
+                panic!("Unexpected message type");
+            };
+            announce.grandmaster_clock_quality
+        }
+
+        let state = setup_test_state();
+        let grandmaster = ClockQuality {
+            clock_class: 6,
+            clock_accuracy: ClockAccuracy::NS100,
+            offset_scaled_log_variance: 0x4000,
+        };
+        state.borrow_mut().parent_ds.grandmaster_clock_quality = grandmaster;
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert_eq!(announced_clock_quality(&mut port), grandmaster);
+
+        drop(port);
+
+        // While another port tracks the grandmaster we announce how well it does
+        let tracking = ClockQuality {
+            clock_accuracy: ClockAccuracy::US1,
+            offset_scaled_log_variance: 0x5000,
+            ..grandmaster
+        };
+        state.borrow_mut().tracking_clock_quality = Some(tracking);
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert_eq!(announced_clock_quality(&mut port), tracking);
+    }
+
     #[test]
     fn test_sync() {
         let state = setup_test_state();
@@ -455,6 +768,7 @@ mod tests {
             context,
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -471,18 +785,20 @@ mod tests {
         };
 
         let id = match context.inner {
-            TimestampContextInner::Sync { id } => id,
+            TimestampContextInner::Sync { id, .. } => id,
             _ => panic!("Wrong type of context"),
         };
 
         let mut actions = port.handle_sync_timestamp(
             id,
+            None,
             Time::from_fixed_nanos(U96F32::from_bits((601300 << 32) + (230 << 16))),
         );
 
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -522,6 +838,7 @@ mod tests {
             context,
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -538,18 +855,20 @@ mod tests {
         };
 
         let id = match context.inner {
-            TimestampContextInner::Sync { id } => id,
+            TimestampContextInner::Sync { id, .. } => id,
             _ => panic!("wrong type of context"),
         };
 
         let mut actions = port.handle_sync_timestamp(
             id,
+            None,
             Time::from_fixed_nanos(U96F32::from_bits((1000601300 << 32) + (543 << 16))),
         );
 
         let Some(PortAction::SendGeneral {
             data,
             link_local: false,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -580,6 +899,198 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_announce_power_profile() {
+        fn announced_power_profile<A, C: Clock, F: Filter, R>(
+            port: &mut Port<Running<'_>, A, R, C, F>,
+        ) -> Option<PowerProfileTlv> {
+            let mut actions = port.send_announce(&mut NoForwardedTLVs);
+            assert!(matches!(
+                actions.next(),
+                Some(PortAction::ResetAnnounceTimer { .. })
+            ));
+            let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+                panic!("Unexpected action");
+            };
+
+            let message = Message::deserialize(data).unwrap();
+            let mut tlvs = message.suffix.tlv();
+            let power_profile = PowerProfileTlv::from_tlv(&tlvs.next()?);
+            assert!(tlvs.next().is_none());
+            power_profile
+        }
+
+        let state = setup_test_state();
+        let profile = Profile::Power {
+            grandmaster_id: 5,
+            time_inaccuracy: 100,
+        };
+        let mut state_ref = state.borrow_mut();
+        state_ref.default_ds.profile = profile;
+        state_ref.parent_ds.power_profile = PowerProfileTlv::for_grandmaster(profile);
+        drop(state_ref);
+
+        // As grandmaster we send our own inaccuracy
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert_eq!(
+            announced_power_profile(&mut port),
+            Some(PowerProfileTlv {
+                grandmaster_id: 5,
+                total_time_inaccuracy: 100,
+            })
+        );
+
+        drop(port);
+
+        // As boundary clock we add it to that of the grandmaster
+        let mut state_ref = state.borrow_mut();
+        state_ref.current_ds.steps_removed = 2;
+        state_ref.parent_ds.power_profile = Some(PowerProfileTlv {
+            grandmaster_id: 7,
+            total_time_inaccuracy: 250,
+        });
+        drop(state_ref);
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert_eq!(
+            announced_power_profile(&mut port),
+            Some(PowerProfileTlv {
+                grandmaster_id: 7,
+                total_time_inaccuracy: 350,
+            })
+        );
+
+        drop(port);
+
+        // Other profiles do not send the TLV
+        state.borrow_mut().default_ds.profile = Profile::Default;
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        assert_eq!(announced_power_profile(&mut port), None);
+    }
+
+    #[test]
+    fn test_announce_smpte_metadata() {
+        let metadata = SynchronizationMetadata {
+            frame_rate_numerator: 50,
+            frame_rate_denominator: 1,
+            master_locking_status: MasterLockingStatus::FreeRun,
+            current_local_offset: 7200,
+            ..Default::default()
+        };
+        let profile = Profile::Smpte2059 { metadata };
+
+        let state = setup_test_state();
+        let mut state_ref = state.borrow_mut();
+        state_ref.default_ds.profile = profile;
+        state_ref.parent_ds.smpte_metadata = SynchronizationMetadata::for_grandmaster(profile);
+        drop(state_ref);
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+
+        let mut actions = port.send_announce(&mut NoForwardedTLVs);
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::ResetAnnounceTimer { .. })
+        ));
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        let announce = Message::deserialize(data).unwrap();
+        assert!(matches!(announce.body, MessageBody::Announce(_)));
+
+        // The metadata follows in a management message to all ports
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+        let message = Message::deserialize(data).unwrap();
+        let MessageBody::Management(management) = message.body else {
+            panic!("Unexpected message type");
+        };
+        assert!(management.is_addressed_to(PortIdentity::default()));
+        let mut tlvs = message.suffix.tlv();
+        assert_eq!(
+            SynchronizationMetadata::from_tlv(&tlvs.next().unwrap()),
+            Some(metadata)
+        );
+        assert!(tlvs.next().is_none());
+        drop(actions);
+        drop(port);
+
+        // Other profiles do not send the metadata
+        state.borrow_mut().default_ds.profile = Profile::Default;
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        let mut actions = port.send_announce(&mut NoForwardedTLVs);
+        assert!(actions.next().is_some());
+        assert!(actions.next().is_some());
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_sync_gptp() {
+        let state = setup_test_state();
+        let mut state_ref = state.borrow_mut();
+        state_ref.default_ds.profile = Profile::Gptp {
+            neighbor_prop_delay_threshold: Duration::from_nanos(800),
+        };
+        state_ref.default_ds.sdo_id = SdoId::GPTP;
+        drop(state_ref);
+
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+
+        // Without peer delay measurements the port is not asCapable
+        let mut actions = port.send_sync();
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::ResetSyncTimer { .. })
+        ));
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let mut actions = port.handle_sync_timestamp(5, None, Time::from_micros(600));
+        let Some(PortAction::SendGeneral {
+            data,
+            link_local: true,
+            destination: None,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+
+        let follow = Message::deserialize(data).unwrap();
+        assert_eq!(follow.header.sdo_id, SdoId::GPTP);
+
+        let mut tlvs = follow.suffix.tlv();
+        let information = FollowUpInformationTlv::from_tlv(&tlvs.next().unwrap());
+        assert_eq!(information, Some(FollowUpInformationTlv::default()));
+        assert!(tlvs.next().is_none());
+        drop(actions);
+        drop(port);
+
+        // The rate of the grandmaster relative to our clock is forwarded
+        state.borrow_mut().gm_rate_ratio = 1.0 + 1e-6;
+        let mut port = setup_test_port(&state);
+        port.set_forced_port_state(PortState::Master);
+        let mut actions = port.handle_sync_timestamp(6, None, Time::from_micros(700));
+        let Some(PortAction::SendGeneral { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        let follow = Message::deserialize(data).unwrap();
+        let information = follow
+            .suffix
+            .tlv()
+            .find_map(|tlv| FollowUpInformationTlv::from_tlv(&tlv))
+            .unwrap();
+        assert_eq!(information.cumulative_scaled_rate_offset, 2199023);
+    }
+
     #[test]
     fn test_peer_delay() {
         let state = setup_test_state();
@@ -592,6 +1103,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -614,6 +1126,7 @@ mod tests {
         let Some(PortAction::SendGeneral {
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -631,4 +1144,117 @@ mod tests {
         assert!(actions.next().is_none());
         drop(actions);
     }
+
+    struct FixedClock(Time);
+
+    impl Clock for FixedClock {
+        type Error = ();
+
+        fn now(&self) -> Time {
+            self.0
+        }
+
+        fn set_frequency(&mut self, _freq: f64) -> Result<Time, Self::Error> {
+            Ok(self.0)
+        }
+
+        fn get_frequency(&self) -> Result<f64, Self::Error> {
+            Ok(0.0)
+        }
+
+        fn step_clock(&mut self, _offset: Duration) -> Result<Time, Self::Error> {
+            Ok(self.0)
+        }
+
+        fn set_properties(
+            &mut self,
+            _time_properties_ds: &crate::config::TimePropertiesDS,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_one_step_sync() {
+        let state = setup_test_state();
+
+        let mut port = setup_test_port_custom_filter::<BasicFilter, _>(
+            &state,
+            0.25,
+            FixedClock(Time::from_micros(600)),
+        );
+        port.config.one_step = true;
+
+        port.set_forced_port_state(PortState::Master);
+        let mut actions = port.send_sync();
+
+        assert!(matches!(
+            actions.next(),
+            Some(PortAction::ResetSyncTimer { .. })
+        ));
+        let Some(PortAction::SendEvent {
+            context,
+            data,
+            link_local: false,
+            destination: None,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let sync = Message::deserialize(data).unwrap();
+        assert!(!sync.header.two_step_flag);
+        let MessageBody::Sync(sync) = sync.body else {
+            panic!("Unexpected message type");
+        };
+        assert_eq!(sync.origin_timestamp, Time::from_micros(600).into());
+
+        // No follow up is sent
+        let mut actions = port.handle_send_timestamp(context, Time::from_micros(601));
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_one_step_peer_delay() {
+        let state = setup_test_state();
+
+        let mut port = setup_test_port_custom_filter::<BasicFilter, _>(
+            &state,
+            0.25,
+            FixedClock(Time::from_micros(520)),
+        );
+        port.config.one_step = true;
+
+        let request_header = Header {
+            correction_field: TimeInterval(1000.into()),
+            ..Default::default()
+        };
+        let mut actions = port.handle_pdelay_req(request_header, Time::from_micros(500));
+
+        let Some(PortAction::SendEvent {
+            context,
+            data,
+            link_local: true,
+            destination: None,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let response = Message::deserialize(data).unwrap();
+        assert!(!response.header.two_step_flag);
+        assert_eq!(response.header.correction_field, TimeInterval(21000.into()));
+        let MessageBody::PDelayResp(response_body) = response.body else {
+            panic!("Unexpected message sent by port");
+        };
+        assert_eq!(response_body.request_receive_timestamp, Default::default());
+
+        // No follow up is sent
+        let mut actions = port.handle_send_timestamp(context, Time::from_micros(521));
+        assert!(actions.next().is_none());
+    }
 }
@@ -13,7 +13,10 @@ pub use measurement::Measurement;
 use rand::Rng;
 use state::PortState;
 
-use self::sequence_id::SequenceIdGenerator;
+use self::{
+    management::DefaultDSChanges, peer_delay::PeerDelay, sequence_id::SequenceIdGenerator,
+    unicast::UnicastState,
+};
 pub use crate::datastructures::messages::MAX_DATA_LEN;
 #[cfg(doc)]
 use crate::PtpInstance;
@@ -23,12 +26,16 @@ use crate::{
         bmca::{BestAnnounceMessage, Bmca},
     },
     clock::Clock,
-    config::PortConfig,
+    config::{DelayMechanism, PortConfig, Profile},
     datastructures::{
-        common::PortIdentity,
+        common::{PortAddress, PortIdentity},
         messages::{Message, MessageBody},
     },
-    filters::Filter,
+    filters::{Filter, HoldoverEstimate},
+    observability::{
+        port::{DelayMechanism as PortDSDelayMechanism, PortDS},
+        smpte::SynchronizationMetadata,
+    },
     ptp_instance::PtpInstanceState,
     time::{Duration, Time},
 };
@@ -55,15 +62,27 @@ macro_rules! actions {
             crate::port::PortActionIterator::from(list)
         }
     };
+    [$action1:expr, $action2:expr, $action3:expr] => {
+        {
+            let mut list = ::arrayvec::ArrayVec::new();
+            list.push($action1);
+            list.push($action2);
+            list.push($action3);
+            crate::port::PortActionIterator::from(list)
+        }
+    };
 }
 
 mod actions;
 mod bmca;
+mod management;
 mod master;
 mod measurement;
+pub(crate) mod peer_delay;
 mod sequence_id;
 mod slave;
 pub(crate) mod state;
+mod unicast;
 
 /// A single port of the PTP instance
 ///
@@ -110,6 +129,9 @@ pub(crate) mod state;
 /// #         fn set_frequency(&mut self, _: f64) -> Result<statime::time::Time, Self::Error> {
 /// #             unimplemented!()
 /// #         }
+/// #         fn get_frequency(&self) -> Result<f64, Self::Error> {
+/// #             unimplemented!()
+/// #         }
 /// #         fn set_properties(&mut self, _: &statime::config::TimePropertiesDS) -> Result<(), Self::Error> {
 /// #             unimplemented!()
 /// #         }
@@ -133,7 +155,12 @@ pub(crate) mod state;
 ///     announce_receipt_timeout: 0,
 ///     sync_interval: interval,
 ///     master_only: false,
+///     local_priority: 128,
 ///     delay_asymmetry: Default::default(),
+///     one_step: false,
+///     hybrid_e2e: false,
+///     unicast_master_table: Default::default(),
+///     unicast_negotiation: None,
 /// };
 /// let filter_config = 1.0;
 /// let clock = system::Clock {};
@@ -167,7 +194,7 @@ pub(crate) mod state;
 /// #     }
 /// #     pub struct UdpSocket;
 /// #     impl UdpSocket {
-/// #         pub fn send(&mut self, buf: &[u8], link_local: bool) -> statime::time::Time { unimplemented!() }
+/// #         pub fn send(&mut self, buf: &[u8], link_local: bool, destination: Option<statime::config::PortAddress>) -> statime::time::Time { unimplemented!() }
 /// #     }
 /// # }
 /// struct MyPortResources {
@@ -176,6 +203,7 @@ pub(crate) mod state;
 ///     delay_req_timer: system::Timer,
 ///     announce_receipt_timer: system::Timer,
 ///     filter_update_timer: system::Timer,
+///     unicast_negotiation_timer: system::Timer,
 ///     time_critical_socket: system::UdpSocket,
 ///     general_socket: system::UdpSocket,
 ///     send_timestamp: Option<(TimestampContext, Time)>
@@ -184,12 +212,12 @@ pub(crate) mod state;
 /// fn handle_actions(resources: &mut MyPortResources, actions: PortActionIterator) {
 ///     for action in actions {
 ///         match action {
-///             PortAction::SendEvent { context, data, link_local } => {
-///                 let timestamp = resources.time_critical_socket.send(data, link_local);
+///             PortAction::SendEvent { context, data, link_local, destination } => {
+///                 let timestamp = resources.time_critical_socket.send(data, link_local, destination);
 ///                 resources.send_timestamp = Some((context, timestamp));
 ///             }
-///             PortAction::SendGeneral { data, link_local } => {
-///                 resources.general_socket.send(data, link_local);
+///             PortAction::SendGeneral { data, link_local, destination } => {
+///                 resources.general_socket.send(data, link_local, destination);
 ///             }
 ///             PortAction::ResetAnnounceTimer { duration } => {
 ///                 resources.announce_timer.expire_in(duration)
@@ -204,6 +232,9 @@ pub(crate) mod state;
 ///             PortAction::ResetFilterUpdateTimer { duration } => {
 ///                 resources.filter_update_timer.expire_in(duration)
 ///             }
+///             PortAction::ResetUnicastNegotiationTimer { duration } => {
+///                 resources.unicast_negotiation_timer.expire_in(duration)
+///             }
 ///             PortAction::ForwardTLV { .. } => {}
 ///         }
 ///     }
@@ -232,6 +263,7 @@ pub(crate) mod state;
 /// #     delay_req_timer: system::Timer,
 /// #     announce_receipt_timer: system::Timer,
 /// #     filter_update_timer: system::Timer,
+/// #     unicast_negotiation_timer: system::Timer,
 /// #     time_critical_socket: system::UdpSocket,
 /// #     general_socket: system::UdpSocket,
 /// #     send_timestamp: Option<(statime::port::TimestampContext, statime::time::Time)>
@@ -254,6 +286,8 @@ pub(crate) mod state;
 ///         running_port.handle_announce_receipt_timer()
 ///     } else if resources.filter_update_timer.has_expired() {
 ///         running_port.handle_filter_update_timer()
+///     } else if resources.unicast_negotiation_timer.has_expired() {
+///         running_port.handle_unicast_negotiation_timer()
 ///     } else if let Some((data, timestamp)) = resources.time_critical_socket.recv() {
 ///         running_port.handle_event_receive(data, timestamp)
 ///     } else if let Some((data, _timestamp)) = resources.general_socket.recv() {
@@ -285,28 +319,24 @@ pub struct Port<L, A, R, C, F: Filter> {
     announce_seq_ids: SequenceIdGenerator,
     sync_seq_ids: SequenceIdGenerator,
     delay_seq_ids: SequenceIdGenerator,
-    pdelay_seq_ids: SequenceIdGenerator,
 
     filter: F,
     mean_delay: Option<Duration>,
-    peer_delay_state: PeerDelayState,
-}
+    peer_delay: PeerDelay,
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum PeerDelayState {
-    Empty,
-    Measuring {
-        id: u16,
-        responder_identity: Option<PortIdentity>,
-        request_send_time: Option<Time>,
-        request_recv_time: Option<Time>,
-        response_send_time: Option<Time>,
-        response_recv_time: Option<Time>,
-    },
-    PostMeasurement {
-        id: u16,
-        responder_identity: PortIdentity,
-    },
+    default_ds_changes: DefaultDSChanges,
+
+    unicast: Option<UnicastState>,
+    signaling_seq_ids: SequenceIdGenerator,
+
+    // SMPTE synchronization metadata received from our parent, applied to the
+    // parent dataset at the start of the next BMCA run
+    smpte_metadata: Option<SynchronizationMetadata>,
+    management_seq_ids: SequenceIdGenerator,
+
+    // Set when this port stops being a slave, with the estimate of its filter
+    // if it has one, until the next BMCA starts the holdover of the instance
+    holdover_estimate: Option<Option<HoldoverEstimate>>,
 }
 
 /// Type state of [`Port`] entered by [`Port::end_bmca`]
@@ -335,8 +365,8 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         timestamp: Time,
     ) -> PortActionIterator<'_> {
         match context.inner {
-            actions::TimestampContextInner::Sync { id } => {
-                self.handle_sync_timestamp(id, timestamp)
+            actions::TimestampContextInner::Sync { id, destination } => {
+                self.handle_sync_timestamp(id, destination, timestamp)
             }
             actions::TimestampContextInner::DelayReq { id } => {
                 self.handle_delay_timestamp(id, timestamp)
@@ -375,6 +405,8 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         // ourselves
         match self.port_state {
             PortState::Master => (),
+            // a disabled port stays silent until it is enabled again
+            PortState::Disabled => return actions![],
             _ => self.set_forced_port_state(PortState::Master),
         }
 
@@ -398,6 +430,11 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         PortActionIterator::from_filter(update)
     }
 
+    /// Handle the unicast negotiation timer going off
+    pub fn handle_unicast_negotiation_timer(&mut self) -> PortActionIterator<'_> {
+        self.handle_unicast_negotiation_tick()
+    }
+
     /// Set this [`Port`] into [`InBmca`] mode to use it with
     /// [`PtpInstance::bmca`].
     pub fn start_bmca(self) -> Port<InBmca<'a>, A, R, C, F> {
@@ -418,11 +455,16 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
             announce_seq_ids: self.announce_seq_ids,
             sync_seq_ids: self.sync_seq_ids,
             delay_seq_ids: self.delay_seq_ids,
-            pdelay_seq_ids: self.pdelay_seq_ids,
 
             filter: self.filter,
             mean_delay: self.mean_delay,
-            peer_delay_state: self.peer_delay_state,
+            peer_delay: self.peer_delay,
+            default_ds_changes: self.default_ds_changes,
+            unicast: self.unicast,
+            signaling_seq_ids: self.signaling_seq_ids,
+            smpte_metadata: self.smpte_metadata,
+            management_seq_ids: self.management_seq_ids,
+            holdover_estimate: self.holdover_estimate,
         }
     }
 
@@ -443,6 +485,21 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         {
             return ControlFlow::Break(actions![]);
         }
+        // gPTP only exchanges time information with peers that are asCapable
+        if !self.is_as_capable()
+            && matches!(
+                message.body,
+                MessageBody::Announce(_) | MessageBody::Sync(_) | MessageBody::FollowUp(_)
+            )
+        {
+            return ControlFlow::Break(actions![]);
+        }
+        // a disabled port only listens to management messages
+        if matches!(self.port_state, PortState::Disabled)
+            && !matches!(message.body, MessageBody::Management(_))
+        {
+            return ControlFlow::Break(actions![]);
+        }
         ControlFlow::Continue(message)
     }
 
@@ -451,6 +508,29 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         &'b mut self,
         data: &'b [u8],
         timestamp: Time,
+    ) -> PortActionIterator<'b> {
+        self.handle_event(data, timestamp, None)
+    }
+
+    /// Handle a message over the event channel that was received from
+    /// `source`
+    ///
+    /// This should be used instead of [`Port::handle_event_receive`] when the
+    /// port is configured for unicast or hybrid operation.
+    pub fn handle_event_receive_from<'b>(
+        &'b mut self,
+        data: &'b [u8],
+        timestamp: Time,
+        source: PortAddress,
+    ) -> PortActionIterator<'b> {
+        self.handle_event(data, timestamp, Some(source))
+    }
+
+    fn handle_event<'b>(
+        &'b mut self,
+        data: &'b [u8],
+        timestamp: Time,
+        source: Option<PortAddress>,
     ) -> PortActionIterator<'b> {
         let message = match self.parse_and_filter(data) {
             ControlFlow::Continue(value) => value,
@@ -458,32 +538,62 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
         };
 
         match message.body {
-            MessageBody::Sync(sync) => self.handle_sync(message.header, sync, timestamp),
+            MessageBody::Sync(sync) => {
+                self.record_master_address(&message.header, source);
+                self.handle_sync(message.header, sync, timestamp)
+            }
             MessageBody::DelayReq(delay_request) => {
-                self.handle_delay_req(message.header, delay_request, timestamp)
+                self.handle_delay_req(message.header, delay_request, timestamp, source)
             }
             MessageBody::PDelayReq(_) => self.handle_pdelay_req(message.header, timestamp),
             MessageBody::PDelayResp(peer_delay_response) => {
                 self.handle_peer_delay_response(message.header, peer_delay_response, timestamp)
             }
-            _ => self.handle_general_internal(message),
+            _ => self.handle_general_internal(message, source),
         }
     }
 
     /// Handle a general ptp message
     pub fn handle_general_receive<'b>(&'b mut self, data: &'b [u8]) -> PortActionIterator<'b> {
+        self.handle_general(data, None)
+    }
+
+    /// Handle a general ptp message that was received from `source`
+    ///
+    /// This should be used instead of [`Port::handle_general_receive`] when
+    /// the port is configured for unicast or hybrid operation.
+    pub fn handle_general_receive_from<'b>(
+        &'b mut self,
+        data: &'b [u8],
+        source: PortAddress,
+    ) -> PortActionIterator<'b> {
+        self.handle_general(data, Some(source))
+    }
+
+    fn handle_general<'b>(
+        &'b mut self,
+        data: &'b [u8],
+        source: Option<PortAddress>,
+    ) -> PortActionIterator<'b> {
         let message = match self.parse_and_filter(data) {
             ControlFlow::Continue(value) => value,
             ControlFlow::Break(value) => return value,
         };
 
-        self.handle_general_internal(message)
+        self.handle_general_internal(message, source)
     }
 
-    fn handle_general_internal<'b>(&'b mut self, message: Message<'b>) -> PortActionIterator<'b> {
+    fn handle_general_internal<'b>(
+        &'b mut self,
+        message: Message<'b>,
+        source: Option<PortAddress>,
+    ) -> PortActionIterator<'b> {
         match message.body {
-            MessageBody::Announce(announce) => self.handle_announce(&message, announce),
-            MessageBody::FollowUp(follow_up) => self.handle_follow_up(message.header, follow_up),
+            MessageBody::Announce(announce) => self.handle_announce(&message, announce, source),
+            MessageBody::FollowUp(follow_up) => {
+                self.handle_follow_up_information(&message);
+                self.handle_follow_up(message.header, follow_up)
+            }
             MessageBody::DelayResp(delay_response) => {
                 self.handle_delay_resp(message.header, delay_response)
             }
@@ -497,12 +607,69 @@ impl<'a, A: AcceptableMasterList, C: Clock, F: Filter, R: Rng> Port<Running<'a>,
                 log::warn!("Received event message over general interface");
                 actions![]
             }
-            MessageBody::Management(_) | MessageBody::Signaling(_) => actions![],
+            MessageBody::Management(management) => self.handle_management(&message, management),
+            MessageBody::Signaling(signaling) => self.handle_signaling(&message, signaling, source),
         }
     }
 }
 
+impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
+    /// Whether the link of this port can carry time information. Always true,
+    /// except for gPTP where it depends on the peer delay measurements.
+    pub(crate) fn is_as_capable(&self) -> bool {
+        match self.lifecycle.state.default_ds.profile {
+            Profile::Default
+            | Profile::TelecomFullTiming { .. }
+            | Profile::TelecomPartialTiming { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => true,
+            Profile::Gptp {
+                neighbor_prop_delay_threshold,
+            } => self.peer_delay.is_as_capable(neighbor_prop_delay_threshold),
+        }
+    }
+
+    /// gPTP only sends messages to the link-local peer delay address
+    fn link_local_only(&self) -> bool {
+        matches!(
+            self.lifecycle.state.default_ds.profile,
+            Profile::Gptp { .. }
+        )
+    }
+}
+
 impl<'a, A, C, F: Filter, R> Port<InBmca<'a>, A, R, C, F> {
+    pub(crate) fn take_default_ds_changes(&mut self) -> DefaultDSChanges {
+        core::mem::take(&mut self.default_ds_changes)
+    }
+
+    pub(crate) fn take_smpte_metadata(&mut self) -> Option<SynchronizationMetadata> {
+        self.smpte_metadata.take()
+    }
+
+    pub(crate) fn take_holdover_estimate(&mut self) -> Option<Option<HoldoverEstimate>> {
+        self.holdover_estimate.take()
+    }
+
+    /// With gPTP, the ratio of the frequency of the grandmaster to that of our
+    /// clock, while this port steers it
+    pub(crate) fn gm_rate_ratio(&self) -> Option<f64> {
+        match &self.port_state {
+            PortState::Slave(state) => state.gm_rate_ratio,
+            _ => None,
+        }
+    }
+
+    /// The uncertainty of the clock as estimated by the filter, while this
+    /// port steers it
+    pub(crate) fn offset_uncertainty(&self) -> Option<Duration> {
+        if self.is_steering() {
+            self.filter.offset_uncertainty()
+        } else {
+            None
+        }
+    }
+
     /// End a BMCA cycle and make the
     /// [`handle_*`](`Port::handle_send_timestamp`) methods available again
     pub fn end_bmca(self) -> (Port<Running<'a>, A, R, C, F>, PortActionIterator<'static>) {
@@ -523,10 +690,15 @@ impl<'a, A, C, F: Filter, R> Port<InBmca<'a>, A, R, C, F> {
                 announce_seq_ids: self.announce_seq_ids,
                 sync_seq_ids: self.sync_seq_ids,
                 delay_seq_ids: self.delay_seq_ids,
-                pdelay_seq_ids: self.pdelay_seq_ids,
                 filter: self.filter,
                 mean_delay: self.mean_delay,
-                peer_delay_state: self.peer_delay_state,
+                peer_delay: self.peer_delay,
+                default_ds_changes: self.default_ds_changes,
+                unicast: self.unicast,
+                signaling_seq_ids: self.signaling_seq_ids,
+                smpte_metadata: self.smpte_metadata,
+                management_seq_ids: self.management_seq_ids,
+                holdover_estimate: self.holdover_estimate,
             },
             self.lifecycle.pending_action,
         )
@@ -542,6 +714,18 @@ impl<L, A, R, C: Clock, F: Filter> Port<L, A, R, C, F> {
             state
         );
         core::mem::swap(&mut self.port_state, &mut state);
+
+        // The clock keeps the frequency the filter steered it to, see how far
+        // the time may drift off before the filter is gone
+        match (&state, &self.port_state) {
+            (PortState::Slave(_), PortState::Slave(_)) => {}
+            (PortState::Slave(_), _) => {
+                self.holdover_estimate = Some(self.filter.holdover_estimate());
+            }
+            (_, PortState::Slave(_)) => self.holdover_estimate = None,
+            _ => {}
+        }
+
         if matches!(state, PortState::Slave(_) | PortState::Faulty)
             || matches!(self.port_state, PortState::Faulty)
         {
@@ -563,6 +747,48 @@ impl<L, A, R, C, F: Filter> Port<L, A, R, C, F> {
         matches!(self.port_state, PortState::Master)
     }
 
+    /// The current [`PortDS`] of this [`Port`]
+    pub fn port_ds(&self) -> PortDS {
+        let port_state = match self.port_state {
+            PortState::Faulty => crate::observability::port::PortState::Faulty,
+            PortState::Disabled => crate::observability::port::PortState::Disabled,
+            PortState::Listening => crate::observability::port::PortState::Listening,
+            PortState::Master => crate::observability::port::PortState::Master,
+            PortState::Passive => crate::observability::port::PortState::Passive,
+            PortState::Slave(_) => crate::observability::port::PortState::Slave,
+        };
+
+        let (delay_mechanism, log_min_delay_req_interval, log_min_pdelay_req_interval) = match self
+            .config
+            .delay_mechanism
+        {
+            DelayMechanism::E2E { interval } => (PortDSDelayMechanism::E2E, interval.as_log_2(), 0),
+            DelayMechanism::P2P { interval } => (PortDSDelayMechanism::P2P, 0, interval.as_log_2()),
+        };
+
+        let mean_link_delay = match delay_mechanism {
+            PortDSDelayMechanism::P2P => self
+                .mean_delay
+                .map(|delay| delay.nanos_rounded())
+                .unwrap_or_default(),
+            _ => 0,
+        };
+
+        PortDS {
+            port_identity: self.port_identity,
+            port_state,
+            log_min_delay_req_interval,
+            mean_link_delay,
+            log_announce_interval: self.config.announce_interval.as_log_2(),
+            announce_receipt_timeout: self.config.announce_receipt_timeout,
+            log_sync_interval: self.config.sync_interval.as_log_2(),
+            delay_mechanism,
+            log_min_pdelay_req_interval,
+            version_number: 2,
+            minor_version_number: 1,
+        }
+    }
+
     pub(crate) fn state(&self) -> &PortState {
         &self.port_state
     }
@@ -582,15 +808,62 @@ impl<'a, A, C, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
         port_identity: PortIdentity,
         mut rng: R,
     ) -> Self {
+        let profile = state_refcell.borrow().default_ds.profile;
+        let peer_delay_profile = match profile {
+            Profile::Gptp { .. } => Some("gPTP"),
+            Profile::Power { .. } => Some("the power profile"),
+            _ => None,
+        };
+        if let Some(profile_name) = peer_delay_profile {
+            if matches!(config.delay_mechanism, DelayMechanism::E2E { .. }) {
+                log::warn!(
+                    "Port {} uses the end to end delay mechanism, which is not supported by {}",
+                    port_identity.port_number,
+                    profile_name
+                );
+            }
+        }
+        if matches!(profile, Profile::TelecomPartialTiming { .. })
+            && (config.unicast_master_table.is_empty() || config.unicast_negotiation.is_none())
+        {
+            log::warn!(
+                "Port {} does not use unicast negotiation with a unicast master table, which is \
+                 required by the partial timing telecom profile",
+                port_identity.port_number
+            );
+        }
+
         let duration = config.announce_duration(&mut rng);
         let bmca = Bmca::new(
             config.acceptable_master_list,
             config.announce_interval.as_duration().into(),
             port_identity,
+            config.local_priority,
+            profile,
         );
 
         let filter = F::new(filter_config.clone());
 
+        let unicast =
+            if config.unicast_master_table.is_empty() && config.unicast_negotiation.is_none() {
+                None
+            } else {
+                Some(UnicastState::new(
+                    &config.unicast_master_table,
+                    config.unicast_negotiation.as_ref(),
+                ))
+            };
+        let pending_action = if config.unicast_negotiation.is_some() {
+            actions![
+                PortAction::ResetAnnounceReceiptTimer { duration },
+                PortAction::ResetUnicastNegotiationTimer {
+                    duration: core::time::Duration::ZERO,
+                }
+            ]
+        } else {
+            actions![PortAction::ResetAnnounceReceiptTimer { duration }]
+        };
+
         Port {
             config: PortConfig {
                 acceptable_master_list: (),
@@ -599,7 +872,12 @@ impl<'a, A, C, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
                 announce_receipt_timeout: config.announce_receipt_timeout,
                 sync_interval: config.sync_interval,
                 master_only: config.master_only,
+                local_priority: config.local_priority,
                 delay_asymmetry: config.delay_asymmetry,
+                one_step: config.one_step,
+                hybrid_e2e: config.hybrid_e2e,
+                unicast_master_table: config.unicast_master_table,
+                unicast_negotiation: config.unicast_negotiation,
             },
             filter_config,
             clock,
@@ -609,17 +887,22 @@ impl<'a, A, C, F: Filter, R: Rng> Port<InBmca<'a>, A, R, C, F> {
             rng,
             packet_buffer: [0; MAX_DATA_LEN],
             lifecycle: InBmca {
-                pending_action: actions![PortAction::ResetAnnounceReceiptTimer { duration }],
+                pending_action,
                 local_best: None,
                 state_refcell,
             },
             announce_seq_ids: SequenceIdGenerator::new(),
             sync_seq_ids: SequenceIdGenerator::new(),
             delay_seq_ids: SequenceIdGenerator::new(),
-            pdelay_seq_ids: SequenceIdGenerator::new(),
             filter,
             mean_delay: None,
-            peer_delay_state: PeerDelayState::Empty,
+            peer_delay: PeerDelay::new(),
+            default_ds_changes: Default::default(),
+            unicast,
+            signaling_seq_ids: SequenceIdGenerator::new(),
+            smpte_metadata: None,
+            management_seq_ids: SequenceIdGenerator::new(),
+            holdover_estimate: None,
         }
     }
 }
@@ -647,6 +930,10 @@ mod tests {
             Ok(Time::default())
         }
 
+        fn get_frequency(&self) -> Result<f64, Self::Error> {
+            Ok(0.0)
+        }
+
         fn now(&self) -> Time {
             panic!("Shouldn't be called");
         }
@@ -666,35 +953,16 @@ mod tests {
     pub(super) fn setup_test_port(
         state: &AtomicRefCell<PtpInstanceState>,
     ) -> Port<Running<'_>, AcceptAnyMaster, rand::rngs::mock::StepRng, TestClock, BasicFilter> {
-        let port = Port::<_, _, _, _, BasicFilter>::new(
-            &state,
-            PortConfig {
-                acceptable_master_list: AcceptAnyMaster,
-                delay_mechanism: DelayMechanism::E2E {
-                    interval: Interval::from_log_2(1),
-                },
-                announce_interval: Interval::from_log_2(1),
-                announce_receipt_timeout: 3,
-                sync_interval: Interval::from_log_2(0),
-                master_only: false,
-                delay_asymmetry: Duration::ZERO,
-            },
-            0.25,
-            TestClock,
-            Default::default(),
-            rand::rngs::mock::StepRng::new(2, 1),
-        );
-
-        let (port, _) = port.end_bmca();
-        port
+        setup_test_port_custom_filter::<BasicFilter, _>(state, 0.25, TestClock)
     }
 
-    pub(super) fn setup_test_port_custom_filter<F: Filter>(
+    pub(super) fn setup_test_port_custom_filter<F: Filter, C: Clock>(
         state: &AtomicRefCell<PtpInstanceState>,
         filter_config: F::Config,
-    ) -> Port<Running<'_>, AcceptAnyMaster, rand::rngs::mock::StepRng, TestClock, F> {
+        clock: C,
+    ) -> Port<Running<'_>, AcceptAnyMaster, rand::rngs::mock::StepRng, C, F> {
         let port = Port::<_, _, _, _, F>::new(
-            &state,
+            state,
             PortConfig {
                 acceptable_master_list: AcceptAnyMaster,
                 delay_mechanism: DelayMechanism::E2E {
@@ -704,10 +972,15 @@ mod tests {
                 announce_receipt_timeout: 3,
                 sync_interval: Interval::from_log_2(0),
                 master_only: false,
+                local_priority: 128,
                 delay_asymmetry: Duration::ZERO,
+                one_step: false,
+                hybrid_e2e: false,
+                unicast_master_table: Default::default(),
+                unicast_negotiation: None,
             },
             filter_config,
-            TestClock,
+            clock,
             Default::default(),
             rand::rngs::mock::StepRng::new(2, 1),
         );
@@ -724,6 +997,9 @@ mod tests {
             domain_number: 0,
             slave_only: false,
             sdo_id: Default::default(),
+            profile: Default::default(),
+            clock_quality: None,
+            holdover: None,
         });
 
         let parent_ds = InternalParentDS::new(default_ds);
@@ -733,6 +1009,12 @@ mod tests {
             current_ds: Default::default(),
             parent_ds,
             time_properties_ds: Default::default(),
+            local_time_properties_ds: Default::default(),
+            local_clock_quality: default_ds.clock_quality,
+            holdover_config: None,
+            holdover: None,
+            tracking_clock_quality: None,
+            gm_rate_ratio: 1.0,
         });
         state
     }
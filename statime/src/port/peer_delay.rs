@@ -0,0 +1,443 @@
+use super::sequence_id::SequenceIdGenerator;
+#[allow(unused_imports)]
+use crate::float_polyfill::FloatPolyfill;
+use crate::{
+    datastructures::{
+        common::PortIdentity,
+        messages::{Header, PDelayRespFollowUpMessage, PDelayRespMessage},
+    },
+    time::{Duration, Time},
+};
+
+/// Requester side of the peer delay mechanism, measuring the delay of the link
+/// to the peer of a port
+///
+/// See *IEEE1588-2019 section 11.4*
+#[derive(Debug)]
+pub(crate) struct PeerDelay {
+    state: PeerDelayState,
+    seq_ids: SequenceIdGenerator,
+    previous_response: Option<PreviousResponse>,
+    /// The ratio of the frequency of the clock of our peer to that of ours
+    neighbor_rate_ratio: Option<f64>,
+    lost_responses: u16,
+    link_delay: Option<Duration>,
+}
+
+/// Timestamps of the response of the previous measurement, used to determine
+/// the rate ratio between us and our peer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PreviousResponse {
+    responder_identity: PortIdentity,
+    send_time: Time,
+    recv_time: Time,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PeerDelayState {
+    Empty,
+    Measuring {
+        id: u16,
+        responder_identity: Option<PortIdentity>,
+        request_send_time: Option<Time>,
+        request_recv_time: Option<Time>,
+        response_send_time: Option<Time>,
+        response_recv_time: Option<Time>,
+    },
+    PostMeasurement {
+        id: u16,
+        responder_identity: PortIdentity,
+    },
+}
+
+/// The number of consecutive requests without response that are tolerated
+/// before the link is no longer considered capable of carrying time
+/// information, *IEEE 802.1AS-2020 section 11.5.3*
+const ALLOWED_LOST_RESPONSES: u16 = 3;
+
+/// Rate ratios further than this from one are considered measurement errors
+const MAX_NEIGHBOR_RATE_DEVIATION: f64 = 0.001;
+
+/// A completed measurement of the link delay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PeerDelayMeasurement {
+    pub(crate) event_time: Time,
+    pub(crate) peer_delay: Duration,
+}
+
+/// Responses to a single peer delay request came from multiple devices, which
+/// means the link is not a point to point link
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MultipleResponders;
+
+impl PeerDelay {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: PeerDelayState::Empty,
+            seq_ids: SequenceIdGenerator::new(),
+            previous_response: None,
+            neighbor_rate_ratio: None,
+            lost_responses: 0,
+            link_delay: None,
+        }
+    }
+
+    /// Forget the measurement in progress and everything learned about the
+    /// peer
+    pub(crate) fn reset(&mut self) {
+        self.state = PeerDelayState::Empty;
+        self.previous_response = None;
+        self.neighbor_rate_ratio = None;
+        self.lost_responses = 0;
+        self.link_delay = None;
+    }
+
+    /// Start a new measurement, returns the sequence id for its request
+    pub(crate) fn start_measurement(&mut self) -> u16 {
+        if matches!(self.state, PeerDelayState::Measuring { .. }) {
+            self.lost_responses = self.lost_responses.saturating_add(1);
+        }
+
+        let id = self.seq_ids.generate();
+
+        self.state = PeerDelayState::Measuring {
+            id,
+            responder_identity: None,
+            request_send_time: None,
+            request_recv_time: None,
+            response_send_time: None,
+            response_recv_time: None,
+        };
+
+        id
+    }
+
+    pub(crate) fn handle_request_timestamp(
+        &mut self,
+        timestamp_id: u16,
+        timestamp: Time,
+    ) -> Option<PeerDelayMeasurement> {
+        match self.state {
+            PeerDelayState::Measuring {
+                id,
+                request_send_time: Some(_),
+                ..
+            } if id == timestamp_id => {
+                log::error!("Double send timestamp for pdelay request");
+                None
+            }
+            PeerDelayState::Measuring {
+                id,
+                ref mut request_send_time,
+                ..
+            } if id == timestamp_id => {
+                *request_send_time = Some(timestamp);
+                self.take_measurement()
+            }
+            _ => {
+                log::warn!("Late timestamp for pdelay request ignored");
+                None
+            }
+        }
+    }
+
+    /// Handle a peer delay response received by the port with identity
+    /// `port_identity`
+    pub(crate) fn handle_response(
+        &mut self,
+        port_identity: PortIdentity,
+        header: Header,
+        message: PDelayRespMessage,
+        recv_time: Time,
+    ) -> Result<Option<PeerDelayMeasurement>, MultipleResponders> {
+        if port_identity != message.requesting_port_identity {
+            return Ok(None);
+        }
+
+        match self.state {
+            PeerDelayState::PostMeasurement {
+                id,
+                responder_identity,
+            } if id == header.sequence_id && responder_identity != header.source_port_identity => {
+                self.multiple_responders()
+            }
+            PeerDelayState::Measuring {
+                id,
+                responder_identity: Some(identity),
+                ..
+            } if id == header.sequence_id && identity != header.source_port_identity => {
+                self.multiple_responders()
+            }
+            PeerDelayState::Measuring {
+                id,
+                response_recv_time: Some(_),
+                ..
+            } if id == header.sequence_id => {
+                log::warn!("Duplicate PDelayResp message");
+                Ok(None)
+            }
+            PeerDelayState::Measuring {
+                id,
+                ref mut request_recv_time,
+                ref mut response_recv_time,
+                ref mut response_send_time,
+                ref mut responder_identity,
+                ..
+            } if id == header.sequence_id => {
+                *response_recv_time = Some(recv_time - Duration::from(header.correction_field));
+                *request_recv_time = Some(message.request_receive_timestamp.into());
+                *responder_identity = Some(header.source_port_identity);
+
+                if !header.two_step_flag {
+                    *response_send_time = Some(message.request_receive_timestamp.into());
+                }
+                Ok(self.take_measurement())
+            }
+            _ => {
+                log::warn!("Unexpected PDelayResp message");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Handle a peer delay response follow up received by the port with
+    /// identity `port_identity`
+    pub(crate) fn handle_response_follow_up(
+        &mut self,
+        port_identity: PortIdentity,
+        header: Header,
+        message: PDelayRespFollowUpMessage,
+    ) -> Result<Option<PeerDelayMeasurement>, MultipleResponders> {
+        if port_identity != message.requesting_port_identity {
+            return Ok(None);
+        }
+
+        match self.state {
+            PeerDelayState::PostMeasurement {
+                id,
+                responder_identity,
+            } if id == header.sequence_id && responder_identity != header.source_port_identity => {
+                self.multiple_responders()
+            }
+            PeerDelayState::Measuring {
+                id,
+                responder_identity: Some(identity),
+                ..
+            } if id == header.sequence_id && identity != header.source_port_identity => {
+                self.multiple_responders()
+            }
+            PeerDelayState::Measuring {
+                id,
+                response_send_time: Some(_),
+                ..
+            } if id == header.sequence_id => {
+                log::warn!("Duplicate PDelayRespFollowUp message");
+                Ok(None)
+            }
+            PeerDelayState::Measuring {
+                id,
+                ref mut response_send_time,
+                ref mut responder_identity,
+                ..
+            } if id == header.sequence_id => {
+                *response_send_time = Some(
+                    Time::from(message.response_origin_timestamp)
+                        + Duration::from(header.correction_field),
+                );
+                *responder_identity = Some(header.source_port_identity);
+                Ok(self.take_measurement())
+            }
+            _ => {
+                log::warn!("Unexpected PDelayRespFollowUp message");
+                Ok(None)
+            }
+        }
+    }
+
+    /// The ratio of the frequency of the clock of our peer to that of ours, if
+    /// it was measured
+    pub(crate) fn neighbor_rate_ratio(&self) -> Option<f64> {
+        self.neighbor_rate_ratio
+    }
+
+    /// Whether the link to our peer can be used to carry time information, see
+    /// *IEEE 802.1AS-2020 section 11.2.2*
+    pub(crate) fn is_as_capable(&self, neighbor_prop_delay_threshold: Duration) -> bool {
+        self.lost_responses <= ALLOWED_LOST_RESPONSES
+            && self.neighbor_rate_ratio.is_some()
+            && matches!(self.link_delay, Some(delay) if delay <= neighbor_prop_delay_threshold)
+    }
+
+    fn multiple_responders(&mut self) -> Result<Option<PeerDelayMeasurement>, MultipleResponders> {
+        self.previous_response = None;
+        self.neighbor_rate_ratio = None;
+        self.link_delay = None;
+        Err(MultipleResponders)
+    }
+
+    fn update_neighbor_rate_ratio(
+        &mut self,
+        responder_identity: PortIdentity,
+        send_time: Time,
+        recv_time: Time,
+    ) {
+        if let Some(previous) = self.previous_response {
+            if previous.responder_identity == responder_identity {
+                let ratio = (send_time - previous.send_time).nanos_lossy()
+                    / (recv_time - previous.recv_time).nanos_lossy();
+
+                self.neighbor_rate_ratio = if (ratio - 1.0).abs() <= MAX_NEIGHBOR_RATE_DEVIATION {
+                    Some(ratio)
+                } else {
+                    log::warn!("Ignoring unlikely neighbor rate ratio {}", ratio);
+                    None
+                };
+            } else {
+                self.neighbor_rate_ratio = None;
+            }
+        }
+
+        self.previous_response = Some(PreviousResponse {
+            responder_identity,
+            send_time,
+            recv_time,
+        });
+    }
+
+    fn take_measurement(&mut self) -> Option<PeerDelayMeasurement> {
+        if let PeerDelayState::Measuring {
+            request_send_time: Some(request_send_time),
+            request_recv_time: Some(request_recv_time),
+            response_send_time: Some(response_send_time),
+            response_recv_time: Some(response_recv_time),
+            responder_identity: Some(responder_identity),
+            id,
+        } = self.state
+        {
+            self.state = PeerDelayState::PostMeasurement {
+                id,
+                responder_identity,
+            };
+
+            self.update_neighbor_rate_ratio(
+                responder_identity,
+                response_send_time,
+                response_recv_time,
+            );
+
+            // The round trip is measured by our clock, convert it to the time base
+            // of the peer before subtracting its turnaround time
+            let round_trip = response_recv_time - request_send_time;
+            let round_trip = match self.neighbor_rate_ratio {
+                Some(ratio) => round_trip * ratio,
+                None => round_trip,
+            };
+            let peer_delay = (round_trip - (response_send_time - request_recv_time)) / 2.0;
+
+            self.lost_responses = 0;
+            self.link_delay = Some(peer_delay);
+
+            Some(PeerDelayMeasurement {
+                event_time: response_recv_time,
+                peer_delay,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::common::{ClockIdentity, WireTimestamp};
+
+    const OWN_IDENTITY: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([1; 8]),
+        port_number: 1,
+    };
+
+    const PEER_IDENTITY: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([2; 8]),
+        port_number: 1,
+    };
+
+    /// Run a one step measurement starting at `start`, with the clock of the
+    /// peer running at `rate` times our frequency
+    fn measure(
+        peer_delay: &mut PeerDelay,
+        start: Time,
+        link_delay: Duration,
+        rate: f64,
+    ) -> Option<PeerDelayMeasurement> {
+        let id = peer_delay.start_measurement();
+
+        let peer_time = |time: Time| {
+            Time::from_fixed_nanos(
+                (Time::from_secs(1) + (time - Time::from_secs(0)) * rate).nanos(),
+            )
+        };
+
+        let request_recv_time = peer_time(start + link_delay);
+
+        let header = Header {
+            sequence_id: id,
+            source_port_identity: PEER_IDENTITY,
+            ..Default::default()
+        };
+        let message = PDelayRespMessage {
+            request_receive_timestamp: WireTimestamp::from(request_recv_time),
+            requesting_port_identity: OWN_IDENTITY,
+        };
+
+        assert_eq!(peer_delay.handle_request_timestamp(id, start), None);
+        peer_delay
+            .handle_response(OWN_IDENTITY, header, message, start + link_delay * 2)
+            .unwrap()
+    }
+
+    #[test]
+    fn neighbor_rate_ratio() {
+        let mut peer_delay = PeerDelay::new();
+        let link_delay = Duration::from_micros(50);
+
+        let measurement = measure(&mut peer_delay, Time::from_secs(10), link_delay, 1.0001);
+        assert!(measurement.is_some());
+        assert_eq!(peer_delay.neighbor_rate_ratio, None);
+        assert!(!peer_delay.is_as_capable(Duration::from_micros(100)));
+
+        let measurement =
+            measure(&mut peer_delay, Time::from_secs(11), link_delay, 1.0001).unwrap();
+        let ratio = peer_delay.neighbor_rate_ratio.unwrap();
+        assert!((ratio - 1.0001).abs() < 1e-9);
+        assert!((measurement.peer_delay - link_delay * 1.0001).abs() < Duration::from_nanos(1));
+
+        assert!(peer_delay.is_as_capable(Duration::from_micros(100)));
+        assert!(!peer_delay.is_as_capable(Duration::from_micros(40)));
+    }
+
+    #[test]
+    fn lost_responses() {
+        let mut peer_delay = PeerDelay::new();
+        let link_delay = Duration::from_micros(50);
+        let threshold = Duration::from_micros(100);
+
+        measure(&mut peer_delay, Time::from_secs(10), link_delay, 1.0);
+        measure(&mut peer_delay, Time::from_secs(11), link_delay, 1.0);
+        assert!(peer_delay.is_as_capable(threshold));
+
+        for _ in 0..=ALLOWED_LOST_RESPONSES {
+            peer_delay.start_measurement();
+        }
+        assert!(peer_delay.is_as_capable(threshold));
+
+        peer_delay.start_measurement();
+        assert!(!peer_delay.is_as_capable(threshold));
+
+        measure(&mut peer_delay, Time::from_secs(20), link_delay, 1.0);
+        assert!(peer_delay.is_as_capable(threshold));
+
+        peer_delay.reset();
+        assert!(!peer_delay.is_as_capable(threshold));
+    }
+}
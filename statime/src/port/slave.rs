@@ -2,16 +2,24 @@ use rand::Rng;
 
 use super::{
     state::{DelayState, PortState},
-    Measurement, PeerDelayState, Port, PortActionIterator, Running,
+    Measurement, Port, PortActionIterator, Running,
 };
 use crate::{
-    config::DelayMechanism,
-    datastructures::messages::{
-        DelayRespMessage, FollowUpMessage, Header, Message, PDelayRespFollowUpMessage,
-        PDelayRespMessage, SyncMessage,
+    config::{DelayMechanism, Profile},
+    datastructures::{
+        common::PortAddress,
+        messages::{
+            DelayRespMessage, FollowUpInformationTlv, FollowUpMessage, Header, Message,
+            PDelayRespFollowUpMessage, PDelayRespMessage, SyncMessage,
+        },
     },
     filters::Filter,
-    port::{actions::TimestampContextInner, state::SyncState, PortAction, TimestampContext},
+    port::{
+        actions::TimestampContextInner,
+        peer_delay::{MultipleResponders, PeerDelayMeasurement},
+        state::SyncState,
+        PortAction, TimestampContext,
+    },
     time::{Duration, Interval, Time},
     Clock,
 };
@@ -68,26 +76,17 @@ impl<'a, A, C: Clock, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         timestamp_id: u16,
         timestamp: Time,
     ) -> PortActionIterator {
-        match self.peer_delay_state {
-            PeerDelayState::Measuring {
-                id,
-                request_send_time: Some(_),
-                ..
-            } if id == timestamp_id => {
-                log::error!("Double send timestamp for pdelay request");
-                actions![]
-            }
-            PeerDelayState::Measuring {
-                id,
-                ref mut request_send_time,
-                ..
-            } if id == timestamp_id => {
-                *request_send_time = Some(timestamp);
-                self.handle_time_measurement()
-            }
-            _ => {
-                log::warn!("Late timestamp for pdelay request ignored");
-                actions![]
+        let measurement = self
+            .peer_delay
+            .handle_request_timestamp(timestamp_id, timestamp);
+        self.handle_peer_delay_measurement(Ok(measurement))
+    }
+
+    /// Remember the address the current master sends its messages from
+    pub(super) fn record_master_address(&mut self, header: &Header, source: Option<PortAddress>) {
+        if let PortState::Slave(ref mut state) = self.port_state {
+            if header.source_port_identity == state.remote_master && source.is_some() {
+                state.remote_master_address = source;
             }
         }
     }
@@ -200,6 +199,43 @@ impl<'a, A, C: Clock, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         }
     }
 
+    /// Handle the Follow_Up information TLV that gPTP masters attach to their
+    /// Follow_Up messages, with the rate of the grandmaster relative to them
+    pub(super) fn handle_follow_up_information(&mut self, message: &Message) {
+        if !matches!(
+            self.lifecycle.state.default_ds.profile,
+            Profile::Gptp { .. }
+        ) {
+            return;
+        }
+
+        let Some(information) = message
+            .suffix
+            .tlv()
+            .find_map(|tlv| FollowUpInformationTlv::from_tlv(&tlv))
+        else {
+            log::debug!("Received Follow_Up without Follow_Up information TLV");
+            return;
+        };
+
+        log::trace!(
+            "Follow_Up information: rate ratio to grandmaster {}, time base {}",
+            information.rate_ratio(),
+            information.gm_time_base_indicator,
+        );
+
+        // Our timestamps come from the clock the filter steers, so the measured
+        // neighbor rate ratio already includes its frequency correction
+        if let PortState::Slave(state) = &mut self.port_state {
+            if message.header.source_port_identity == state.remote_master {
+                state.gm_rate_ratio = self
+                    .peer_delay
+                    .neighbor_rate_ratio()
+                    .map(|neighbor_rate_ratio| information.rate_ratio() * neighbor_rate_ratio);
+            }
+        }
+    }
+
     pub(super) fn handle_delay_resp(
         &mut self,
         header: Header,
@@ -250,62 +286,10 @@ impl<'a, A, C: Clock, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         message: PDelayRespMessage,
         recv_time: Time,
     ) -> PortActionIterator {
-        if self.port_identity != message.requesting_port_identity {
-            return actions![];
-        }
-
-        match self.peer_delay_state {
-            PeerDelayState::PostMeasurement {
-                id,
-                responder_identity,
-            } if id == header.sequence_id && responder_identity != header.source_port_identity => {
-                log::error!(
-                    "Responses from multiple devices to peer delay request, disabling port!"
-                );
-                self.set_forced_port_state(PortState::Faulty);
-                actions![]
-            }
-            PeerDelayState::Measuring {
-                id,
-                responder_identity: Some(identity),
-                ..
-            } if id == header.sequence_id && identity != header.source_port_identity => {
-                log::error!(
-                    "Responses from multiple devices to peer delay request, disabling port!"
-                );
-                self.set_forced_port_state(PortState::Faulty);
-                actions![]
-            }
-            PeerDelayState::Measuring {
-                id,
-                response_recv_time: Some(_),
-                ..
-            } if id == header.sequence_id => {
-                log::warn!("Duplicate PDelayResp message");
-                actions![]
-            }
-            PeerDelayState::Measuring {
-                id,
-                ref mut request_recv_time,
-                ref mut response_recv_time,
-                ref mut response_send_time,
-                ref mut responder_identity,
-                ..
-            } if id == header.sequence_id => {
-                *response_recv_time = Some(recv_time - Duration::from(header.correction_field));
-                *request_recv_time = Some(message.request_receive_timestamp.into());
-                *responder_identity = Some(header.source_port_identity);
-
-                if !header.two_step_flag {
-                    *response_send_time = Some(message.request_receive_timestamp.into());
-                }
-                self.handle_time_measurement()
-            }
-            _ => {
-                log::warn!("Unexpected PDelayResp message");
-                actions![]
-            }
-        }
+        let measurement =
+            self.peer_delay
+                .handle_response(self.port_identity, header, message, recv_time);
+        self.handle_peer_delay_measurement(measurement)
     }
 
     pub(super) fn handle_peer_delay_response_follow_up(
@@ -313,93 +297,54 @@ impl<'a, A, C: Clock, F: Filter, R> Port<Running<'a>, A, R, C, F> {
         header: Header,
         message: PDelayRespFollowUpMessage,
     ) -> PortActionIterator {
-        if self.port_identity != message.requesting_port_identity {
-            return actions![];
-        }
+        let measurement =
+            self.peer_delay
+                .handle_response_follow_up(self.port_identity, header, message);
+        self.handle_peer_delay_measurement(measurement)
+    }
 
-        match self.peer_delay_state {
-            PeerDelayState::PostMeasurement {
-                id,
-                responder_identity,
-            } if id == header.sequence_id && responder_identity != header.source_port_identity => {
-                log::error!(
-                    "Responses from multiple devices to peer delay request, disabling port!"
-                );
-                self.set_forced_port_state(PortState::Faulty);
-                actions![]
+    fn handle_peer_delay_measurement<'b>(
+        &mut self,
+        measurement: Result<Option<PeerDelayMeasurement>, MultipleResponders>,
+    ) -> PortActionIterator<'b> {
+        match measurement {
+            Ok(Some(PeerDelayMeasurement {
+                event_time,
+                peer_delay,
+            })) => {
+                let measurement = Measurement {
+                    event_time,
+                    peer_delay: Some(peer_delay),
+                    ..Default::default()
+                };
+
+                log::info!("Measurement: {:?}", measurement);
+
+                if matches!(self.port_state, PortState::Faulty) {
+                    log::info!("Recovered port");
+                    self.set_forced_port_state(PortState::Listening);
+                }
+
+                let filter_updates = self.filter.measurement(measurement, &mut self.clock);
+                if let Some(mean_delay) = filter_updates.mean_delay {
+                    self.mean_delay = Some(mean_delay);
+                }
+                PortActionIterator::from_filter(filter_updates)
             }
-            PeerDelayState::Measuring {
-                id,
-                responder_identity: Some(identity),
-                ..
-            } if id == header.sequence_id && identity != header.source_port_identity => {
+            Ok(None) => actions![],
+            Err(MultipleResponders) => {
                 log::error!(
                     "Responses from multiple devices to peer delay request, disabling port!"
                 );
                 self.set_forced_port_state(PortState::Faulty);
                 actions![]
             }
-            PeerDelayState::Measuring {
-                id,
-                response_send_time: Some(_),
-                ..
-            } if id == header.sequence_id => {
-                log::warn!("Duplicate PDelayRespFollowUp message");
-                actions![]
-            }
-            PeerDelayState::Measuring {
-                id,
-                ref mut response_send_time,
-                ref mut responder_identity,
-                ..
-            } if id == header.sequence_id => {
-                *response_send_time = Some(
-                    Time::from(message.response_origin_timestamp)
-                        + Duration::from(header.correction_field),
-                );
-                *responder_identity = Some(header.source_port_identity);
-                self.handle_time_measurement()
-            }
-            _ => {
-                log::warn!("Unexpected PDelayRespFollowUp message");
-                actions![]
-            }
         }
     }
 
     fn extract_measurement(&mut self) -> Option<Measurement> {
         let mut result = Measurement::default();
 
-        if let PeerDelayState::Measuring {
-            request_send_time: Some(request_send_time),
-            request_recv_time: Some(request_recv_time),
-            response_send_time: Some(response_send_time),
-            response_recv_time: Some(response_recv_time),
-            responder_identity: Some(responder_identity),
-            id,
-        } = self.peer_delay_state
-        {
-            result.event_time = response_recv_time;
-            result.peer_delay = Some(
-                ((response_recv_time - request_send_time)
-                    - (response_send_time - request_recv_time))
-                    / 2.0,
-            );
-            self.peer_delay_state = PeerDelayState::PostMeasurement {
-                id,
-                responder_identity,
-            };
-
-            log::info!("Measurement: {:?}", result);
-
-            if matches!(self.port_state, PortState::Faulty) {
-                log::info!("Recovered port");
-                self.set_forced_port_state(PortState::Listening);
-            }
-
-            return Some(result);
-        }
-
         match self.port_state {
             PortState::Slave(ref mut state) => {
                 if let SyncState::Measuring {
@@ -459,7 +404,18 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
         &mut self,
         log_min_pdelay_req_interval: Interval,
     ) -> PortActionIterator {
-        let pdelay_id = self.pdelay_seq_ids.generate();
+        let random = self.rng.sample::<f64, _>(rand::distributions::Open01);
+        let factor = random * 2.0f64;
+        let duration = log_min_pdelay_req_interval
+            .as_core_duration()
+            .mul_f64(factor);
+
+        // keep the timer running so measurements resume once the port is enabled
+        if matches!(self.port_state, PortState::Disabled) {
+            return actions![PortAction::ResetDelayRequestTimer { duration }];
+        }
+
+        let pdelay_id = self.peer_delay.start_measurement();
 
         let pdelay_req = Message::pdelay_req(
             &self.lifecycle.state.default_ds,
@@ -474,21 +430,6 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
             }
         };
 
-        self.peer_delay_state = PeerDelayState::Measuring {
-            id: pdelay_id,
-            responder_identity: None,
-            request_send_time: None,
-            request_recv_time: None,
-            response_send_time: None,
-            response_recv_time: None,
-        };
-
-        let random = self.rng.sample::<f64, _>(rand::distributions::Open01);
-        let factor = random * 2.0f64;
-        let duration = log_min_pdelay_req_interval
-            .as_core_duration()
-            .mul_f64(factor);
-
         actions![
             PortAction::ResetDelayRequestTimer { duration },
             PortAction::SendEvent {
@@ -497,6 +438,7 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
                 },
                 data: &self.packet_buffer[..message_length],
                 link_local: true,
+                destination: None,
             }
         ]
     }
@@ -509,12 +451,21 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
             PortState::Slave(ref mut state) => {
                 log::debug!("Starting new delay measurement");
 
+                // With unicast the request goes to the current master only, in hybrid
+                // mode to the address its syncs come from
+                let destination = match &self.unicast {
+                    Some(unicast) => unicast.master_address(state.remote_master()),
+                    None if self.config.hybrid_e2e => state.remote_master_address,
+                    None => None,
+                };
+
                 let delay_id = self.delay_seq_ids.generate();
-                let delay_req = Message::delay_req(
+                let mut delay_req = Message::delay_req(
                     &self.lifecycle.state.default_ds,
                     self.port_identity,
                     delay_id,
                 );
+                delay_req.header.unicast_flag = destination.is_some();
 
                 let message_length = match delay_req.serialize(&mut self.packet_buffer) {
                     Ok(length) => length,
@@ -544,6 +495,7 @@ impl<'a, A, C: Clock, F: Filter, R: Rng> Port<Running<'a>, A, R, C, F> {
                         },
                         data: &self.packet_buffer[..message_length],
                         link_local: false,
+                        destination,
                     }
                 ]
             }
@@ -565,7 +517,7 @@ mod tests {
         filters::FilterUpdate,
         port::{
             state::SlaveState,
-            tests::{setup_test_port_custom_filter, setup_test_state},
+            tests::{setup_test_port_custom_filter, setup_test_state, TestClock},
             Measurement,
         },
     };
@@ -608,7 +560,7 @@ mod tests {
     fn test_sync_without_delay_msg() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
         port.mean_delay = Some(Duration::from_micros(100));
@@ -688,7 +640,7 @@ mod tests {
     fn test_delay_asymmetry() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         port.config.delay_asymmetry = Duration::from_micros(100);
 
@@ -728,7 +680,7 @@ mod tests {
     fn test_sync_with_delay() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
 
@@ -770,6 +722,7 @@ mod tests {
             context,
             data,
             link_local: false,
+            destination: None,
         }) = action.next()
         else {
             panic!("Unexpected action");
@@ -853,6 +806,7 @@ mod tests {
             context,
             data,
             link_local: false,
+            destination: None,
         }) = action.next()
         else {
             panic!("Unexpected action");
@@ -933,11 +887,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hybrid_delay_request() {
+        let state = setup_test_state();
+
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
+        port.config.hybrid_e2e = true;
+
+        let remote_master = PortIdentity {
+            port_number: 5,
+            ..Default::default()
+        };
+        port.set_forced_port_state(PortState::Slave(SlaveState::new(remote_master)));
+
+        // Without a known master address the request is multicast
+        let mut action = port.send_delay_request();
+        assert!(matches!(
+            action.next(),
+            Some(PortAction::ResetDelayRequestTimer { .. })
+        ));
+        assert!(matches!(
+            action.next(),
+            Some(PortAction::SendEvent {
+                destination: None,
+                ..
+            })
+        ));
+        drop(action);
+
+        let master_address = PortAddress::Ipv4([192, 168, 1, 1]);
+        let mut buffer = [0; 64];
+        let length = Message::sync(&state.borrow().default_ds, remote_master, 1)
+            .serialize(&mut buffer)
+            .unwrap();
+        let mut action = port.handle_event_receive_from(
+            &buffer[..length],
+            Time::from_micros(50),
+            master_address,
+        );
+        assert!(action.next().is_none());
+        drop(action);
+
+        let mut action = port.send_delay_request();
+        assert!(matches!(
+            action.next(),
+            Some(PortAction::ResetDelayRequestTimer { .. })
+        ));
+        let Some(PortAction::SendEvent {
+            data,
+            link_local: false,
+            destination: Some(destination),
+            ..
+        }) = action.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert_eq!(destination, master_address);
+        assert!(Message::deserialize(data).unwrap().header.unicast_flag);
+    }
+
     #[test]
     fn test_follow_up_before_sync() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
         port.mean_delay = Some(Duration::from_micros(100));
@@ -992,7 +1005,7 @@ mod tests {
     fn test_old_followup_during() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
         port.mean_delay = Some(Duration::from_micros(100));
@@ -1053,7 +1066,7 @@ mod tests {
     fn test_reset_after_missing_followup() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
         port.mean_delay = Some(Duration::from_micros(100));
@@ -1125,7 +1138,7 @@ mod tests {
     fn test_ignore_unrelated_delayresp() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
 
         let state = SlaveState::new(Default::default());
 
@@ -1168,6 +1181,7 @@ mod tests {
             context,
             data,
             link_local: false,
+            destination: None,
         }) = action.next()
         else {
             panic!("Unexpected action");
@@ -1265,7 +1279,7 @@ mod tests {
     fn test_peer_delay_1step() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
         port.config.delay_mechanism = DelayMechanism::P2P {
             interval: Interval::from_log_2(1),
         };
@@ -1284,6 +1298,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -1330,7 +1345,7 @@ mod tests {
     fn test_peer_delay_2step() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
         port.config.delay_mechanism = DelayMechanism::P2P {
             interval: Interval::from_log_2(1),
         };
@@ -1349,6 +1364,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -1412,7 +1428,7 @@ mod tests {
     fn test_peer_delay_2step_followup_before_response() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
         port.config.delay_mechanism = DelayMechanism::P2P {
             interval: Interval::from_log_2(1),
         };
@@ -1431,6 +1447,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -1495,7 +1512,7 @@ mod tests {
     fn test_peer_delay_faulty() {
         let state = setup_test_state();
 
-        let mut port = setup_test_port_custom_filter::<TestFilter>(&state, ());
+        let mut port = setup_test_port_custom_filter::<TestFilter, _>(&state, (), TestClock);
         port.config.delay_mechanism = DelayMechanism::P2P {
             interval: Interval::from_log_2(1),
         };
@@ -1514,6 +1531,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
@@ -1587,6 +1605,7 @@ mod tests {
             context,
             data,
             link_local: true,
+            destination: None,
         }) = actions.next()
         else {
             panic!("Unexpected action");
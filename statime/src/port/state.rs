@@ -1,7 +1,7 @@
 use core::fmt::{Display, Formatter};
 
 use crate::{
-    datastructures::common::PortIdentity,
+    datastructures::common::{PortAddress, PortIdentity},
     time::{Duration, Time},
 };
 
@@ -10,6 +10,7 @@ use crate::{
 pub(crate) enum PortState {
     #[default]
     Faulty,
+    Disabled,
     Listening,
     Master,
     Passive,
@@ -24,6 +25,7 @@ impl Display for PortState {
             PortState::Passive => write!(f, "Passive"),
             PortState::Slave(_) => write!(f, "Slave"),
             PortState::Faulty => write!(f, "Faulty"),
+            PortState::Disabled => write!(f, "Disabled"),
         }
     }
 }
@@ -31,11 +33,17 @@ impl Display for PortState {
 #[derive(Debug)]
 pub(crate) struct SlaveState {
     pub(super) remote_master: PortIdentity,
+    /// The address the sync messages of the remote master come from
+    pub(super) remote_master_address: Option<PortAddress>,
 
     pub(super) sync_state: SyncState,
     pub(super) delay_state: DelayState,
 
     pub(super) last_raw_sync_offset: Option<Duration>,
+
+    /// With gPTP, the ratio of the frequency of the grandmaster to that of
+    /// our clock
+    pub(super) gm_rate_ratio: Option<f64>,
 }
 
 impl SlaveState {
@@ -68,9 +76,11 @@ impl SlaveState {
     pub(super) fn new(remote_master: PortIdentity) -> Self {
         SlaveState {
             remote_master,
+            remote_master_address: None,
             sync_state: SyncState::Empty,
             delay_state: DelayState::Empty,
             last_raw_sync_offset: None,
+            gm_rate_ratio: None,
         }
     }
 }
@@ -0,0 +1,1156 @@
+use arrayvec::ArrayVec;
+
+use super::{state::PortState, Port, PortAction, PortActionIterator, Running};
+use crate::{
+    config::{DelayMechanism, UnicastNegotiationConfig, MAX_UNICAST_MASTERS},
+    datastructures::{
+        common::{ClockIdentity, PortAddress, PortIdentity, TlvSetBuilder},
+        messages::{Message, MessageType, SignalingMessage, UnicastNegotiationTlv},
+    },
+    filters::Filter,
+    time::Interval,
+};
+
+/// Maximum number of grants a port gives out, summed over all message types
+const MAX_UNICAST_GRANTS: usize = 32;
+
+/// Time between two calls of the unicast negotiation timer, grant durations
+/// are counted in these ticks
+const NEGOTIATION_TICK: core::time::Duration = core::time::Duration::from_secs(1);
+
+/// Seconds to wait before repeating a request that was denied or not answered
+const REQUEST_RETRY_SECONDS: u32 = 4;
+
+/// Unicast state of a port, both as grantor and as requester
+///
+/// With static unicast tables every port in the table has a permanent grant.
+/// Otherwise grants are given out and requested through unicast negotiation.
+///
+/// See *IEEE1588-2019 sections 16.1 and 17.6*
+#[derive(Debug)]
+pub(super) struct UnicastState {
+    // None when the port uses static unicast tables instead of negotiation
+    grant_duration: Option<u32>,
+    grants: ArrayVec<UnicastGrant, MAX_UNICAST_GRANTS>,
+    masters: ArrayVec<UnicastMaster, MAX_UNICAST_MASTERS>,
+    // The requests of the current tick did not fit in a single call
+    round_in_progress: bool,
+}
+
+/// Unicast transmission granted by this port to another port
+#[derive(Debug)]
+struct UnicastGrant {
+    address: PortAddress,
+    message_type: MessageType,
+    log_inter_message_period: i8,
+    remaining_seconds: u32,
+    // Number of intervals of the port until the next message is due
+    countdown: u32,
+    // A message is due in the current interval
+    pending: bool,
+}
+
+/// An entry of the unicast master table with the grants we requested from it
+#[derive(Debug)]
+struct UnicastMaster {
+    address: PortAddress,
+    // Learned from the messages the master sends us
+    identity: Option<PortIdentity>,
+    announce: RequestState,
+    sync: RequestState,
+    delay_resp: RequestState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct RequestState {
+    // Seconds left of the current grant, zero when not granted
+    granted_seconds: u32,
+    // Seconds until the next request is sent
+    next_request: u32,
+}
+
+impl RequestState {
+    fn needs_message(&self, wanted: bool) -> bool {
+        if wanted {
+            self.next_request == 0
+        } else {
+            self.granted_seconds > 0
+        }
+    }
+
+    fn step(&mut self) {
+        self.granted_seconds = self.granted_seconds.saturating_sub(1);
+        self.next_request = self.next_request.saturating_sub(1);
+    }
+}
+
+impl UnicastMaster {
+    fn request_state(&mut self, message_type: MessageType) -> Option<&mut RequestState> {
+        match message_type {
+            MessageType::Announce => Some(&mut self.announce),
+            MessageType::Sync => Some(&mut self.sync),
+            MessageType::DelayResp => Some(&mut self.delay_resp),
+            _ => None,
+        }
+    }
+}
+
+impl UnicastState {
+    pub(super) fn new(
+        master_table: &[PortAddress],
+        negotiation: Option<&UnicastNegotiationConfig>,
+    ) -> Self {
+        let mut grants = ArrayVec::new();
+        if negotiation.is_none() {
+            for &address in master_table {
+                for message_type in [
+                    MessageType::Announce,
+                    MessageType::Sync,
+                    MessageType::DelayResp,
+                ] {
+                    grants.push(UnicastGrant {
+                        address,
+                        message_type,
+                        // Static grants follow the interval configured for the port
+                        log_inter_message_period: i8::MIN,
+                        remaining_seconds: u32::MAX,
+                        countdown: 0,
+                        pending: false,
+                    });
+                }
+            }
+        }
+
+        Self {
+            grant_duration: negotiation.map(|config| config.grant_duration),
+            grants,
+            masters: master_table
+                .iter()
+                .map(|&address| UnicastMaster {
+                    address,
+                    identity: None,
+                    announce: Default::default(),
+                    sync: Default::default(),
+                    delay_resp: Default::default(),
+                })
+                .collect(),
+            round_in_progress: false,
+        }
+    }
+
+    fn negotiates(&self) -> bool {
+        self.grant_duration.is_some()
+    }
+
+    /// The address of the master with the given identity, if it is in the
+    /// unicast master table
+    pub(super) fn master_address(&self, identity: PortIdentity) -> Option<PortAddress> {
+        self.masters
+            .iter()
+            .find(|master| master.identity == Some(identity))
+            .map(|master| master.address)
+    }
+
+    /// Whether the address is in the unicast master table
+    pub(super) fn is_master(&self, address: PortAddress) -> bool {
+        self.masters.iter().any(|master| master.address == address)
+    }
+
+    /// Remember the identity of the port sending from an address in the unicast
+    /// master table
+    pub(super) fn record_master(&mut self, address: PortAddress, identity: PortIdentity) {
+        if let Some(master) = self.masters.iter_mut().find(|m| m.address == address) {
+            master.identity = Some(identity);
+        }
+    }
+
+    pub(super) fn has_grant(&self, address: PortAddress, message_type: MessageType) -> bool {
+        self.grants
+            .iter()
+            .any(|grant| grant.address == address && grant.message_type == message_type)
+    }
+
+    /// The next grantee a message of the given type should be sent to, and
+    /// whether more grantees are waiting in the current interval.
+    fn next_destination(
+        &mut self,
+        message_type: MessageType,
+        base_log_interval: i8,
+    ) -> Option<(PortAddress, bool)> {
+        let is_pending = |grant: &UnicastGrant| grant.message_type == message_type && grant.pending;
+
+        if !self.grants.iter().any(is_pending) {
+            // Start of a new interval
+            for grant in self
+                .grants
+                .iter_mut()
+                .filter(|grant| grant.message_type == message_type)
+            {
+                if grant.countdown == 0 {
+                    grant.pending = true;
+                    let shift = grant
+                        .log_inter_message_period
+                        .saturating_sub(base_log_interval)
+                        .clamp(0, 16);
+                    grant.countdown = 1 << shift;
+                }
+                grant.countdown -= 1;
+            }
+        }
+
+        let grant = self.grants.iter_mut().find(|grant| is_pending(grant))?;
+        grant.pending = false;
+        let address = grant.address;
+
+        Some((address, self.grants.iter().any(is_pending)))
+    }
+
+    fn step(&mut self) {
+        self.grants.retain(|grant| {
+            grant.remaining_seconds = grant.remaining_seconds.saturating_sub(1);
+            if grant.remaining_seconds == 0 {
+                log::debug!(
+                    "Unicast grant of {:?} to {:?} expired",
+                    grant.message_type,
+                    grant.address
+                );
+            }
+            grant.remaining_seconds > 0
+        });
+
+        for master in &mut self.masters {
+            master.announce.step();
+            master.sync.step();
+            master.delay_resp.step();
+        }
+    }
+
+    fn cancel(&mut self, address: PortAddress, message_type: MessageType) {
+        self.grants
+            .retain(|grant| grant.address != address || grant.message_type != message_type);
+
+        if let Some(state) = self
+            .masters
+            .iter_mut()
+            .find(|master| master.address == address)
+            .and_then(|master| master.request_state(message_type))
+        {
+            state.granted_seconds = 0;
+            state.next_request = REQUEST_RETRY_SECONDS;
+        }
+    }
+}
+
+impl<'a, A, C, F: Filter, R> Port<Running<'a>, A, R, C, F> {
+    /// Where to send the next announce or sync message, and when to send the
+    /// one after it.
+    ///
+    /// Returns `None` when unicast negotiation is used and no grantee is due in
+    /// this interval.
+    pub(super) fn next_transmission(
+        &mut self,
+        message_type: MessageType,
+        interval: Interval,
+    ) -> Option<(Option<PortAddress>, core::time::Duration)> {
+        match self.unicast.as_mut() {
+            Some(unicast) => {
+                let (address, more) =
+                    unicast.next_destination(message_type, interval.as_log_2())?;
+                let duration = if more {
+                    core::time::Duration::ZERO
+                } else {
+                    interval.as_core_duration()
+                };
+                Some((Some(address), duration))
+            }
+            None => Some((None, interval.as_core_duration())),
+        }
+    }
+
+    pub(super) fn handle_signaling<'b>(
+        &'b mut self,
+        message: &Message<'b>,
+        signaling: SignalingMessage,
+        source: Option<PortAddress>,
+    ) -> PortActionIterator<'b> {
+        let negotiates = self.unicast.as_ref().map(UnicastState::negotiates);
+        if negotiates != Some(true) || !signaling.is_addressed_to(self.port_identity) {
+            return actions![];
+        }
+
+        let mut tlv_buffer = [0; 128];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        let mut has_response = false;
+
+        for tlv in message.suffix.tlv() {
+            let Some(tlv) = UnicastNegotiationTlv::from_tlv(&tlv) else {
+                continue;
+            };
+
+            let response = match tlv {
+                UnicastNegotiationTlv::Request {
+                    message_type,
+                    log_inter_message_period,
+                    duration,
+                } => match source {
+                    Some(source) => Some(self.handle_unicast_request(
+                        source,
+                        message_type,
+                        log_inter_message_period,
+                        duration,
+                    )),
+                    None => {
+                        log::debug!("Ignoring unicast request from unknown address");
+                        None
+                    }
+                },
+                UnicastNegotiationTlv::Grant {
+                    message_type,
+                    duration,
+                    renewal_invited,
+                    ..
+                } => {
+                    self.handle_unicast_grant(
+                        message.header.source_port_identity,
+                        source,
+                        message_type,
+                        duration,
+                        renewal_invited,
+                    );
+                    None
+                }
+                UnicastNegotiationTlv::Cancel { message_type } => source.map(|source| {
+                    log::debug!("Unicast {:?} cancelled by {:?}", message_type, source);
+                    if let Some(unicast) = self.unicast.as_mut() {
+                        unicast.cancel(source, message_type);
+                    }
+                    UnicastNegotiationTlv::AcknowledgeCancel { message_type }
+                }),
+                UnicastNegotiationTlv::AcknowledgeCancel { .. } => None,
+            };
+
+            if let Some(response) = response {
+                let mut value_buffer = [0; 8];
+                if let Err(error) = response
+                    .to_tlv(&mut value_buffer)
+                    .and_then(|tlv| tlv_builder.add(tlv))
+                {
+                    log::warn!(
+                        "Too many unicast negotiation TLVs to respond to: {:?}",
+                        error
+                    );
+                    break;
+                }
+                has_response = true;
+            }
+        }
+
+        let (Some(destination), true) = (source, has_response) else {
+            return actions![];
+        };
+
+        let response = Message::signaling(
+            &self.lifecycle.state.default_ds,
+            self.port_identity,
+            message.header.source_port_identity,
+            self.signaling_seq_ids.generate(),
+            tlv_builder.build(),
+        );
+
+        let packet_length = match response.serialize(&mut self.packet_buffer) {
+            Ok(length) => length,
+            Err(error) => {
+                log::error!("Could not serialize signaling message: {:?}", error);
+                return actions![];
+            }
+        };
+
+        actions![PortAction::SendGeneral {
+            data: &self.packet_buffer[..packet_length],
+            link_local: false,
+            destination: Some(destination),
+        }]
+    }
+
+    fn handle_unicast_request(
+        &mut self,
+        source: PortAddress,
+        message_type: MessageType,
+        log_inter_message_period: i8,
+        duration: u32,
+    ) -> UnicastNegotiationTlv {
+        let denied = UnicastNegotiationTlv::Grant {
+            message_type,
+            log_inter_message_period,
+            duration: 0,
+            renewal_invited: false,
+        };
+
+        let base_interval = match message_type {
+            MessageType::Announce => self.config.announce_interval,
+            MessageType::Sync => self.config.sync_interval,
+            MessageType::DelayResp => match self.config.delay_mechanism {
+                DelayMechanism::E2E { interval } => interval,
+                DelayMechanism::P2P { .. } => return denied,
+            },
+            _ => return denied,
+        };
+
+        // A slave only clock never sends these messages, and we don't send them
+        // faster than configured for multicast
+        if self.lifecycle.state.default_ds.slave_only
+            || log_inter_message_period < base_interval.as_log_2()
+            || duration == 0
+        {
+            log::debug!(
+                "Denied unicast {:?} to {:?} at log interval {}",
+                message_type,
+                source,
+                log_inter_message_period
+            );
+            return denied;
+        }
+
+        let Some(unicast) = self.unicast.as_mut() else {
+            return denied;
+        };
+        let Some(grant_duration) = unicast.grant_duration else {
+            return denied;
+        };
+        let remaining_seconds = duration.min(grant_duration);
+
+        match unicast
+            .grants
+            .iter_mut()
+            .find(|grant| grant.address == source && grant.message_type == message_type)
+        {
+            Some(grant) => {
+                grant.log_inter_message_period = log_inter_message_period;
+                grant.remaining_seconds = remaining_seconds;
+            }
+            None => {
+                let grant = UnicastGrant {
+                    address: source,
+                    message_type,
+                    log_inter_message_period,
+                    remaining_seconds,
+                    countdown: 0,
+                    pending: false,
+                };
+                if unicast.grants.try_push(grant).is_err() {
+                    log::warn!("Unicast grant table full, denied {:?}", message_type);
+                    return denied;
+                }
+                log::debug!("Granted unicast {:?} to {:?}", message_type, source);
+            }
+        }
+
+        UnicastNegotiationTlv::Grant {
+            message_type,
+            log_inter_message_period,
+            duration: remaining_seconds,
+            renewal_invited: true,
+        }
+    }
+
+    fn handle_unicast_grant(
+        &mut self,
+        identity: PortIdentity,
+        source: Option<PortAddress>,
+        message_type: MessageType,
+        duration: u32,
+        renewal_invited: bool,
+    ) {
+        let Some(unicast) = self.unicast.as_mut() else {
+            return;
+        };
+
+        let Some(master) = unicast.masters.iter_mut().find(|master| match source {
+            Some(source) => master.address == source,
+            None => master.identity == Some(identity),
+        }) else {
+            log::debug!("Ignoring unicast grant from unknown master");
+            return;
+        };
+
+        master.identity = Some(identity);
+        let address = master.address;
+        let Some(state) = master.request_state(message_type) else {
+            return;
+        };
+
+        if duration == 0 {
+            log::debug!("Unicast {:?} denied by {:?}", message_type, address);
+            state.granted_seconds = 0;
+            state.next_request = REQUEST_RETRY_SECONDS;
+        } else {
+            state.granted_seconds = duration;
+            // Renew halfway through the grant, or request a new grant once it
+            // expires if the master does not want us to renew.
+            state.next_request = if renewal_invited {
+                duration / 2
+            } else {
+                duration
+            };
+        }
+    }
+
+    pub(super) fn handle_unicast_negotiation_tick(&mut self) -> PortActionIterator<'_> {
+        let current_master = match &self.port_state {
+            PortState::Slave(state) => Some(state.remote_master()),
+            _ => None,
+        };
+        let delay_resp_interval = match self.config.delay_mechanism {
+            DelayMechanism::E2E { interval } => Some(interval),
+            DelayMechanism::P2P { .. } => None,
+        };
+        let want_announce = !self.config.master_only;
+
+        let Some(unicast) = self.unicast.as_mut() else {
+            return actions![];
+        };
+        let Some(grant_duration) = unicast.grant_duration else {
+            return actions![];
+        };
+
+        if !unicast.round_in_progress {
+            unicast.step();
+        }
+
+        // keep the timer running so negotiation resumes once the port is enabled
+        if matches!(self.port_state, PortState::Disabled) {
+            unicast.round_in_progress = false;
+            return actions![PortAction::ResetUnicastNegotiationTimer {
+                duration: NEGOTIATION_TICK,
+            }];
+        }
+
+        let needs_message = |master: &UnicastMaster| {
+            let is_current = current_master.is_some() && master.identity == current_master;
+            master.announce.needs_message(want_announce)
+                || master.sync.needs_message(is_current)
+                || master
+                    .delay_resp
+                    .needs_message(is_current && delay_resp_interval.is_some())
+        };
+
+        let Some(master) = unicast.masters.iter_mut().find(|m| needs_message(m)) else {
+            unicast.round_in_progress = false;
+            return actions![PortAction::ResetUnicastNegotiationTimer {
+                duration: NEGOTIATION_TICK,
+            }];
+        };
+
+        let is_current = current_master.is_some() && master.identity == current_master;
+        let requests = [
+            (
+                MessageType::Announce,
+                &mut master.announce,
+                want_announce.then_some(self.config.announce_interval),
+            ),
+            (
+                MessageType::Sync,
+                &mut master.sync,
+                is_current.then_some(self.config.sync_interval),
+            ),
+            (
+                MessageType::DelayResp,
+                &mut master.delay_resp,
+                delay_resp_interval.filter(|_| is_current),
+            ),
+        ];
+
+        let mut tlv_buffer = [0; 64];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        for (message_type, state, interval) in requests {
+            if !state.needs_message(interval.is_some()) {
+                continue;
+            }
+
+            let tlv = match interval {
+                Some(interval) => {
+                    state.next_request = REQUEST_RETRY_SECONDS;
+                    UnicastNegotiationTlv::Request {
+                        message_type,
+                        log_inter_message_period: interval.as_log_2(),
+                        duration: grant_duration,
+                    }
+                }
+                None => {
+                    state.granted_seconds = 0;
+                    UnicastNegotiationTlv::Cancel { message_type }
+                }
+            };
+
+            let mut value_buffer = [0; 8];
+            if let Err(error) = tlv
+                .to_tlv(&mut value_buffer)
+                .and_then(|tlv| tlv_builder.add(tlv))
+            {
+                log::error!("Statime bug: Could not build unicast TLV: {:?}", error);
+                return actions![];
+            }
+        }
+
+        let target_port_identity = master.identity.unwrap_or(PortIdentity {
+            clock_identity: ClockIdentity([0xff; 8]),
+            port_number: 0xffff,
+        });
+        let destination = master.address;
+
+        unicast.round_in_progress = unicast.masters.iter().any(needs_message);
+        let duration = if unicast.round_in_progress {
+            core::time::Duration::ZERO
+        } else {
+            NEGOTIATION_TICK
+        };
+
+        let message = Message::signaling(
+            &self.lifecycle.state.default_ds,
+            self.port_identity,
+            target_port_identity,
+            self.signaling_seq_ids.generate(),
+            tlv_builder.build(),
+        );
+
+        let packet_length = match message.serialize(&mut self.packet_buffer) {
+            Ok(length) => length,
+            Err(error) => {
+                log::error!("Could not serialize signaling message: {:?}", error);
+                return actions![];
+            }
+        };
+
+        actions![
+            PortAction::ResetUnicastNegotiationTimer { duration },
+            PortAction::SendGeneral {
+                data: &self.packet_buffer[..packet_length],
+                link_local: false,
+                destination: Some(destination),
+            }
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{
+        datastructures::{
+            datasets::InternalDefaultDS,
+            messages::{MessageBody, MAX_DATA_LEN},
+        },
+        port::{
+            state::SlaveState,
+            tests::{setup_test_port, setup_test_state, TestClock},
+        },
+        time::Time,
+    };
+
+    const SLAVE: PortAddress = PortAddress::Ipv4([192, 0, 2, 1]);
+    const OTHER_SLAVE: PortAddress = PortAddress::Ipv4([192, 0, 2, 3]);
+    const MASTER: PortAddress = PortAddress::Ipv4([192, 0, 2, 2]);
+
+    const REMOTE_IDENTITY: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+        port_number: 1,
+    };
+
+    type TestPort<'a> = Port<
+        Running<'a>,
+        crate::config::AcceptAnyMaster,
+        rand::rngs::mock::StepRng,
+        TestClock,
+        crate::filters::BasicFilter,
+    >;
+
+    fn enable_unicast(port: &mut TestPort, master_table: &[PortAddress], grant_duration: u32) {
+        port.unicast = Some(UnicastState::new(
+            master_table,
+            Some(&UnicastNegotiationConfig { grant_duration }),
+        ));
+    }
+
+    fn signaling_packet(default_ds: &InternalDefaultDS, tlvs: &[UnicastNegotiationTlv]) -> Vec<u8> {
+        let mut tlv_buffer = [0; 128];
+        let mut tlv_builder = TlvSetBuilder::new(&mut tlv_buffer);
+        for tlv in tlvs {
+            let mut value_buffer = [0; 8];
+            tlv_builder
+                .add(tlv.to_tlv(&mut value_buffer).unwrap())
+                .unwrap();
+        }
+
+        let message = Message::signaling(
+            default_ds,
+            REMOTE_IDENTITY,
+            PortIdentity {
+                clock_identity: ClockIdentity([0xff; 8]),
+                port_number: 0xffff,
+            },
+            1,
+            tlv_builder.build(),
+        );
+
+        let mut buffer = [0; MAX_DATA_LEN];
+        let length = message.serialize(&mut buffer).unwrap();
+        buffer[..length].to_vec()
+    }
+
+    fn signaling_tlvs(data: &[u8]) -> Vec<UnicastNegotiationTlv> {
+        let message = Message::deserialize(data).unwrap();
+        assert!(message.header.unicast_flag);
+        assert!(matches!(message.body, MessageBody::Signaling(_)));
+
+        message
+            .suffix
+            .tlv()
+            .filter_map(|tlv| UnicastNegotiationTlv::from_tlv(&tlv))
+            .collect()
+    }
+
+    fn request(
+        port: &mut TestPort,
+        source: PortAddress,
+        tlvs: &[UnicastNegotiationTlv],
+    ) -> Vec<UnicastNegotiationTlv> {
+        let packet = signaling_packet(&port.lifecycle.state.default_ds, tlvs);
+
+        let mut actions = port.handle_general_receive_from(&packet, source);
+        let Some(PortAction::SendGeneral {
+            data,
+            link_local: false,
+            destination: Some(destination),
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert_eq!(destination, source);
+        assert!(actions.next().is_none());
+
+        signaling_tlvs(data)
+    }
+
+    fn sync_destination(port: &mut TestPort) -> Option<(PortAddress, core::time::Duration)> {
+        let mut actions = port.handle_sync_timer();
+        let Some(PortAction::ResetSyncTimer { duration }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+
+        match actions.next() {
+            Some(PortAction::SendEvent {
+                destination: Some(destination),
+                ..
+            }) => {
+                assert!(actions.next().is_none());
+                Some((destination, duration))
+            }
+            None => None,
+            _ => panic!("Unexpected action"),
+        }
+    }
+
+    #[test]
+    fn test_unicast_grant_sync() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[], 60);
+        port.set_forced_port_state(PortState::Master);
+
+        let response = request(
+            &mut port,
+            SLAVE,
+            &[UnicastNegotiationTlv::Request {
+                message_type: MessageType::Sync,
+                log_inter_message_period: 0,
+                duration: 300,
+            }],
+        );
+        assert_eq!(
+            response,
+            [UnicastNegotiationTlv::Grant {
+                message_type: MessageType::Sync,
+                log_inter_message_period: 0,
+                duration: 60,
+                renewal_invited: true,
+            }]
+        );
+
+        let mut actions = port.handle_sync_timer();
+        let Some(PortAction::ResetSyncTimer { duration }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        assert_eq!(duration, core::time::Duration::from_secs(1));
+        let Some(PortAction::SendEvent {
+            context,
+            data,
+            link_local: false,
+            destination: Some(SLAVE),
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert!(Message::deserialize(data).unwrap().header.unicast_flag);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let mut actions = port.handle_send_timestamp(context, Time::from_micros(500));
+        let Some(PortAction::SendGeneral {
+            data,
+            link_local: false,
+            destination: Some(SLAVE),
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        let follow_up = Message::deserialize(data).unwrap();
+        assert!(follow_up.header.unicast_flag);
+        assert!(matches!(follow_up.body, MessageBody::FollowUp(_)));
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_unicast_deny() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[], 60);
+        port.set_forced_port_state(PortState::Master);
+
+        // Faster than the configured sync interval of the port
+        let response = request(
+            &mut port,
+            SLAVE,
+            &[
+                UnicastNegotiationTlv::Request {
+                    message_type: MessageType::Sync,
+                    log_inter_message_period: -3,
+                    duration: 300,
+                },
+                UnicastNegotiationTlv::Request {
+                    message_type: MessageType::PDelayResp,
+                    log_inter_message_period: 0,
+                    duration: 300,
+                },
+            ],
+        );
+        assert_eq!(
+            response,
+            [
+                UnicastNegotiationTlv::Grant {
+                    message_type: MessageType::Sync,
+                    log_inter_message_period: -3,
+                    duration: 0,
+                    renewal_invited: false,
+                },
+                UnicastNegotiationTlv::Grant {
+                    message_type: MessageType::PDelayResp,
+                    log_inter_message_period: 0,
+                    duration: 0,
+                    renewal_invited: false,
+                }
+            ]
+        );
+
+        assert_eq!(sync_destination(&mut port), None);
+
+        // Delay requests are only answered for ports with a grant
+        let mut actions = port.handle_delay_req(
+            Default::default(),
+            crate::datastructures::messages::DelayReqMessage {
+                origin_timestamp: Default::default(),
+            },
+            Time::from_micros(0),
+            Some(SLAVE),
+        );
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_unicast_sync_schedule() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[], 60);
+        port.set_forced_port_state(PortState::Master);
+
+        for (address, log_inter_message_period) in [(SLAVE, 0), (OTHER_SLAVE, 1)] {
+            request(
+                &mut port,
+                address,
+                &[UnicastNegotiationTlv::Request {
+                    message_type: MessageType::Sync,
+                    log_inter_message_period,
+                    duration: 60,
+                }],
+            );
+        }
+
+        let second = core::time::Duration::from_secs(1);
+        let immediately = core::time::Duration::ZERO;
+
+        // Both are due in the first interval, only one event send per call
+        assert_eq!(sync_destination(&mut port), Some((SLAVE, immediately)));
+        assert_eq!(sync_destination(&mut port), Some((OTHER_SLAVE, second)));
+
+        // The second port only gets a sync every other interval
+        assert_eq!(sync_destination(&mut port), Some((SLAVE, second)));
+        assert_eq!(sync_destination(&mut port), Some((SLAVE, immediately)));
+        assert_eq!(sync_destination(&mut port), Some((OTHER_SLAVE, second)));
+    }
+
+    #[test]
+    fn test_unicast_static_table() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        port.unicast = Some(UnicastState::new(&[SLAVE, OTHER_SLAVE], None));
+        port.set_forced_port_state(PortState::Master);
+
+        let second = core::time::Duration::from_secs(1);
+        let immediately = core::time::Duration::ZERO;
+
+        // Every table entry gets a sync each interval, without negotiation
+        for _ in 0..2 {
+            assert_eq!(sync_destination(&mut port), Some((SLAVE, immediately)));
+            assert_eq!(sync_destination(&mut port), Some((OTHER_SLAVE, second)));
+        }
+
+        // Negotiation requests are ignored
+        let packet = signaling_packet(
+            &port.lifecycle.state.default_ds,
+            &[UnicastNegotiationTlv::Request {
+                message_type: MessageType::Sync,
+                log_inter_message_period: 0,
+                duration: 60,
+            }],
+        );
+        let mut actions = port.handle_general_receive_from(&packet, MASTER);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        let mut actions = port.handle_unicast_negotiation_timer();
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        // Delay requests are only answered for table entries
+        let mut delay_req = [0; MAX_DATA_LEN];
+        let length = Message::delay_req(&port.lifecycle.state.default_ds, REMOTE_IDENTITY, 1)
+            .serialize(&mut delay_req)
+            .unwrap();
+
+        let mut actions = port.handle_event_receive_from(
+            &delay_req[..length],
+            Time::from_micros(100),
+            OTHER_SLAVE,
+        );
+        let Some(PortAction::SendGeneral {
+            destination: Some(OTHER_SLAVE),
+            ..
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        drop(actions);
+
+        let mut actions =
+            port.handle_event_receive_from(&delay_req[..length], Time::from_micros(100), MASTER);
+        assert!(actions.next().is_none());
+    }
+
+    #[test]
+    fn test_unicast_grant_expiry_and_cancel() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[], 2);
+        port.set_forced_port_state(PortState::Master);
+
+        for address in [SLAVE, OTHER_SLAVE] {
+            request(
+                &mut port,
+                address,
+                &[UnicastNegotiationTlv::Request {
+                    message_type: MessageType::Sync,
+                    log_inter_message_period: 0,
+                    duration: 60,
+                }],
+            );
+        }
+
+        let response = request(
+            &mut port,
+            OTHER_SLAVE,
+            &[UnicastNegotiationTlv::Cancel {
+                message_type: MessageType::Sync,
+            }],
+        );
+        assert_eq!(
+            response,
+            [UnicastNegotiationTlv::AcknowledgeCancel {
+                message_type: MessageType::Sync
+            }]
+        );
+
+        let second = core::time::Duration::from_secs(1);
+        assert_eq!(sync_destination(&mut port), Some((SLAVE, second)));
+
+        for _ in 0..2 {
+            let mut actions = port.handle_unicast_negotiation_timer();
+            let Some(PortAction::ResetUnicastNegotiationTimer { duration }) = actions.next() else {
+                panic!("Unexpected action");
+            };
+            assert_eq!(duration, second);
+            assert!(actions.next().is_none());
+        }
+
+        assert_eq!(sync_destination(&mut port), None);
+    }
+
+    fn negotiation_tick(port: &mut TestPort) -> Option<(PortAddress, Vec<UnicastNegotiationTlv>)> {
+        let mut actions = port.handle_unicast_negotiation_timer();
+        let Some(PortAction::ResetUnicastNegotiationTimer { .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+
+        match actions.next() {
+            Some(PortAction::SendGeneral {
+                data,
+                destination: Some(destination),
+                ..
+            }) => Some((destination, signaling_tlvs(data))),
+            None => None,
+            _ => panic!("Unexpected action"),
+        }
+    }
+
+    #[test]
+    fn test_unicast_request_and_renew() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[MASTER], 60);
+
+        let announce_request = UnicastNegotiationTlv::Request {
+            message_type: MessageType::Announce,
+            log_inter_message_period: 1,
+            duration: 60,
+        };
+        assert_eq!(
+            negotiation_tick(&mut port),
+            Some((MASTER, [announce_request].to_vec()))
+        );
+
+        // Unanswered requests are repeated
+        for _ in 1..REQUEST_RETRY_SECONDS {
+            assert_eq!(negotiation_tick(&mut port), None);
+        }
+        assert_eq!(
+            negotiation_tick(&mut port),
+            Some((MASTER, [announce_request].to_vec()))
+        );
+
+        let grant = signaling_packet(
+            &port.lifecycle.state.default_ds,
+            &[UnicastNegotiationTlv::Grant {
+                message_type: MessageType::Announce,
+                log_inter_message_period: 1,
+                duration: 60,
+                renewal_invited: true,
+            }],
+        );
+        let mut actions = port.handle_general_receive_from(&grant, MASTER);
+        assert!(actions.next().is_none());
+        drop(actions);
+
+        // The grant is renewed halfway through
+        for _ in 1..30 {
+            assert_eq!(negotiation_tick(&mut port), None);
+        }
+        assert_eq!(
+            negotiation_tick(&mut port),
+            Some((MASTER, [announce_request].to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_unicast_request_from_current_master() {
+        let state = setup_test_state();
+        let mut port = setup_test_port(&state);
+        enable_unicast(&mut port, &[MASTER], 60);
+
+        port.unicast
+            .as_mut()
+            .unwrap()
+            .record_master(MASTER, REMOTE_IDENTITY);
+        port.set_forced_port_state(PortState::Slave(SlaveState::new(REMOTE_IDENTITY)));
+
+        assert_eq!(
+            negotiation_tick(&mut port),
+            Some((
+                MASTER,
+                [
+                    UnicastNegotiationTlv::Request {
+                        message_type: MessageType::Announce,
+                        log_inter_message_period: 1,
+                        duration: 60,
+                    },
+                    UnicastNegotiationTlv::Request {
+                        message_type: MessageType::Sync,
+                        log_inter_message_period: 0,
+                        duration: 60,
+                    },
+                    UnicastNegotiationTlv::Request {
+                        message_type: MessageType::DelayResp,
+                        log_inter_message_period: 1,
+                        duration: 60,
+                    }
+                ]
+                .to_vec()
+            ))
+        );
+
+        for message_type in [MessageType::Sync, MessageType::DelayResp] {
+            let grant = signaling_packet(
+                &port.lifecycle.state.default_ds,
+                &[UnicastNegotiationTlv::Grant {
+                    message_type,
+                    log_inter_message_period: 0,
+                    duration: 60,
+                    renewal_invited: true,
+                }],
+            );
+            let mut actions = port.handle_general_receive_from(&grant, MASTER);
+            assert!(actions.next().is_none());
+        }
+
+        // Delay requests go to the master directly
+        let mut actions = port.handle_delay_request_timer();
+        let Some(PortAction::ResetDelayRequestTimer { .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        let Some(PortAction::SendEvent {
+            destination: Some(MASTER),
+            ..
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        drop(actions);
+
+        // Grants from a master that is no longer used are cancelled
+        port.set_forced_port_state(PortState::Listening);
+        assert_eq!(
+            negotiation_tick(&mut port),
+            Some((
+                MASTER,
+                [
+                    UnicastNegotiationTlv::Cancel {
+                        message_type: MessageType::Sync,
+                    },
+                    UnicastNegotiationTlv::Cancel {
+                        message_type: MessageType::DelayResp,
+                    }
+                ]
+                .to_vec()
+            ))
+        );
+    }
+}
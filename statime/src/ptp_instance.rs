@@ -11,16 +11,20 @@ use crate::float_polyfill::FloatPolyfill;
 use crate::{
     bmc::{acceptable_master::AcceptableMasterList, bmca::Bmca},
     clock::Clock,
-    config::{InstanceConfig, PortConfig},
+    config::{
+        telecom, ClockAccuracy, ClockQuality, HoldoverConfig, InstanceConfig, PortConfig, Profile,
+    },
     datastructures::{
         common::PortIdentity,
         datasets::{InternalCurrentDS, InternalDefaultDS, InternalParentDS, TimePropertiesDS},
     },
-    filters::Filter,
+    filters::{Filter, HoldoverEstimate},
+    holdover::Holdover,
     observability::{
-        current::CurrentDS, default::DefaultDS, parent::ParentDS, ObservableInstanceState,
+        current::CurrentDS, default::DefaultDS, parent::ParentDS, smpte::SynchronizationMetadata,
+        ObservableInstanceState,
     },
-    port::{InBmca, Port},
+    port::{state::PortState, InBmca, Port},
     time::Duration,
 };
 
@@ -29,6 +33,9 @@ use crate::{
 /// This object handles the complete running of the PTP protocol once created.
 /// It provides all the logic for both ordinary and boundary clock mode.
 ///
+/// All ports of an instance use the filter type `F`. To choose the filter of
+/// each port separately, use [`AnyFilter`](`crate::filters::AnyFilter`).
+///
 /// # Example
 ///
 /// ```no_run
@@ -44,6 +51,9 @@ use crate::{
 /// #     fn set_frequency(&mut self, _: f64) -> Result<statime::time::Time, Self::Error> {
 /// #         unimplemented!()
 /// #     }
+/// #     fn get_frequency(&self) -> Result<f64, Self::Error> {
+/// #         unimplemented!()
+/// #     }
 /// #     fn set_properties(&mut self, _: &TimePropertiesDS) -> Result<(), Self::Error> {
 /// #         unimplemented!()
 /// #     }
@@ -68,6 +78,9 @@ use crate::{
 ///     domain_number: 0,
 ///     slave_only: false,
 ///     sdo_id: Default::default(),
+///     profile: Default::default(),
+///     clock_quality: None,
+///     holdover: None,
 /// };
 /// let time_properties_ds = TimePropertiesDS::new_arbitrary_time(false, false, TimeSource::InternalOscillator);
 ///
@@ -97,6 +110,20 @@ pub(crate) struct PtpInstanceState {
     pub(crate) current_ds: InternalCurrentDS,
     pub(crate) parent_ds: InternalParentDS,
     pub(crate) time_properties_ds: TimePropertiesDS,
+    // The time properties of our own clock, used when we are the grandmaster
+    pub(crate) local_time_properties_ds: TimePropertiesDS,
+    // The quality of our own clock, which the announced quality degrades from
+    // in holdover
+    pub(crate) local_clock_quality: ClockQuality,
+    pub(crate) holdover_config: Option<HoldoverConfig>,
+    // Since the last port that steered our clock stopped being a slave
+    pub(crate) holdover: Option<Holdover>,
+    // The quality of the grandmaster as we track it, announced by master ports
+    // while another port steers our clock
+    pub(crate) tracking_clock_quality: Option<ClockQuality>,
+    // The ratio of the frequency of the grandmaster to that of our clock, sent
+    // by master ports in the Follow_Up information TLV of gPTP
+    pub(crate) gm_rate_ratio: f64,
 }
 
 impl PtpInstanceState {
@@ -107,6 +134,22 @@ impl PtpInstanceState {
     ) {
         debug_assert_eq!(self.default_ds.number_ports as usize, ports.len());
 
+        // Apply changes requested through management messages before they are
+        // used in the state decision
+        for port in ports.iter_mut() {
+            port.take_default_ds_changes().apply(&mut self.default_ds);
+            if let Some(metadata) = port.take_smpte_metadata() {
+                self.parent_ds.smpte_metadata = Some(metadata);
+            }
+        }
+
+        // We are compared as the grandmaster we would be, which doesn't have
+        // a master to track
+        self.default_ds.clock_quality = self.grandmaster_clock_quality();
+
+        // The grandmaster we follow until the state decisions below
+        let grandmaster = self.parent_ds.grandmaster_clock_quality;
+
         for port in ports.iter_mut() {
             port.calculate_best_local_announce_message()
         }
@@ -115,9 +158,15 @@ impl PtpInstanceState {
             ports
                 .iter()
                 .filter_map(|port| port.best_local_announce_message_for_bmca()),
+            self.default_ds.profile,
         );
 
         for port in ports.iter_mut() {
+            // A disabled port only leaves that state through a management command
+            if matches!(port.state(), PortState::Disabled) {
+                continue;
+            }
+
             let recommended_state = Bmca::<()>::calculate_recommended_state(
                 &self.default_ds,
                 ebest,
@@ -134,6 +183,7 @@ impl PtpInstanceState {
                 port.set_recommended_state(
                     recommended_state,
                     &mut self.time_properties_ds,
+                    &self.local_time_properties_ds,
                     &mut self.current_ds,
                     &mut self.parent_ds,
                     &self.default_ds,
@@ -141,17 +191,122 @@ impl PtpInstanceState {
             }
         }
 
+        self.update_holdover(ports, bmca_interval, grandmaster);
+
+        // Without a slave port we are the grandmaster, or hold over its frequency
+        self.gm_rate_ratio = ports
+            .iter()
+            .find_map(|port| port.gm_rate_ratio())
+            .unwrap_or(1.0);
+
+        self.tracking_clock_quality = self.derive_tracking_clock_quality(ports);
+
         // And update announce message ages
         for port in ports.iter_mut() {
             port.step_announce_age(bmca_interval);
         }
     }
+
+    fn update_holdover<A, C: Clock, F: Filter, R: Rng>(
+        &mut self,
+        ports: &mut [&mut Port<InBmca<'_>, A, R, C, F>],
+        bmca_interval: Duration,
+        grandmaster: ClockQuality,
+    ) {
+        if let Some(holdover) = &mut self.holdover {
+            holdover.elapsed += bmca_interval;
+        }
+
+        for port in ports.iter_mut() {
+            if let Some(estimate) = port.take_holdover_estimate() {
+                self.holdover = Some(Holdover {
+                    elapsed: Duration::ZERO,
+                    estimate,
+                    grandmaster,
+                    reference: false,
+                });
+            }
+        }
+
+        if ports.iter().any(|port| port.is_steering()) {
+            self.holdover = None;
+        }
+    }
+
+    /// The quality of the grandmaster as seen through our clock while a port
+    /// steers it, with the accuracy and variance derived from the uncertainty
+    /// of its filter
+    fn derive_tracking_clock_quality<A, C: Clock, F: Filter, R>(
+        &self,
+        ports: &[&mut Port<InBmca<'_>, A, R, C, F>],
+    ) -> Option<ClockQuality> {
+        let uncertainty = ports.iter().find_map(|port| port.offset_uncertainty())?;
+
+        // We can't track the time better than the grandmaster knows it
+        let grandmaster = self.parent_ds.grandmaster_clock_quality;
+        let clock_accuracy = ClockAccuracy::from_time_error(uncertainty);
+        let clock_accuracy = if clock_accuracy
+            .cmp_numeric(&grandmaster.clock_accuracy)
+            .is_lt()
+        {
+            grandmaster.clock_accuracy
+        } else {
+            clock_accuracy
+        };
+
+        Some(ClockQuality {
+            clock_accuracy,
+            offset_scaled_log_variance: ClockQuality::scaled_log_variance(uncertainty)
+                .max(grandmaster.offset_scaled_log_variance),
+            ..grandmaster
+        })
+    }
+
+    /// The quality of our clock as the grandmaster, degraded when it is in
+    /// holdover
+    fn grandmaster_clock_quality(&self) -> ClockQuality {
+        let Some(holdover) = self.holdover.filter(|_| !self.default_ds.slave_only) else {
+            return self.local_clock_quality;
+        };
+
+        // Having lost its own reference, the clock always degrades
+        let config = match (self.holdover_config, holdover.reference) {
+            (Some(config), _) => config,
+            (None, true) => HoldoverConfig::default(),
+            (None, false) => return self.local_clock_quality,
+        };
+
+        holdover.clock_quality(
+            &config,
+            self.local_clock_quality,
+            self.default_ds.profile,
+            self.local_time_properties_ds.is_ptp(),
+        )
+    }
 }
 
 impl<F> PtpInstance<F> {
-    /// Construct a new [`PtpInstance`] with the given config and time
-    /// properties
+    /// Construct a new [`PtpInstance`] with the given config and the time
+    /// properties of the local clock
     pub fn new(config: InstanceConfig, time_properties_ds: TimePropertiesDS) -> Self {
+        let telecom_domains = match config.profile {
+            Profile::Default
+            | Profile::Gptp { .. }
+            | Profile::Power { .. }
+            | Profile::Smpte2059 { .. } => None,
+            Profile::TelecomFullTiming { .. } => Some(telecom::FULL_TIMING_DOMAINS),
+            Profile::TelecomPartialTiming { .. } => Some(telecom::PARTIAL_TIMING_DOMAINS),
+        };
+        if let Some(domains) = telecom_domains {
+            if !domains.contains(&config.domain_number) {
+                log::warn!(
+                    "Domain {} is outside of the range {:?} of the telecom profile",
+                    config.domain_number,
+                    domains
+                );
+            }
+        }
+
         let default_ds = InternalDefaultDS::new(config);
 
         Self {
@@ -160,6 +315,12 @@ impl<F> PtpInstance<F> {
                 current_ds: Default::default(),
                 parent_ds: InternalParentDS::new(default_ds),
                 time_properties_ds,
+                local_time_properties_ds: time_properties_ds,
+                local_clock_quality: default_ds.clock_quality,
+                holdover_config: config.holdover,
+                holdover: None,
+                tracking_clock_quality: None,
+                gm_rate_ratio: 1.0,
             }),
             log_bmca_interval: AtomicI8::new(i8::MAX),
             _filter: PhantomData,
@@ -185,6 +346,13 @@ impl<F> PtpInstance<F> {
     pub fn time_properties_ds(&self) -> TimePropertiesDS {
         self.state.borrow().time_properties_ds
     }
+
+    /// Return the SMPTE ST 2059-2 synchronization metadata of the grandmaster,
+    /// if the instance uses the [`Profile::Smpte2059`] profile and the
+    /// metadata is known
+    pub fn smpte_metadata(&self) -> Option<SynchronizationMetadata> {
+        self.state.borrow().parent_ds.smpte_metadata
+    }
 }
 
 impl<F: Filter> PtpInstance<F> {
@@ -210,6 +378,9 @@ impl<F: Filter> PtpInstance<F> {
             clock_identity: state.default_ds.clock_identity,
             port_number: state.default_ds.number_ports,
         };
+        // The instance is only one-step when all of its ports are
+        let other_ports_two_step = state.default_ds.number_ports > 0 && state.default_ds.two_step;
+        state.default_ds.two_step = other_ports_two_step || !config.one_step;
         state.default_ds.number_ports += 1;
 
         Port::new(
@@ -238,6 +409,61 @@ impl<F: Filter> PtpInstance<F> {
         )
     }
 
+    /// Update the time properties of the local clock, for example when a leap
+    /// second is coming up. They are announced from the next
+    /// [`PtpInstance::bmca`] on, when this instance is the grandmaster.
+    ///
+    /// Like [`PtpInstance::bmca`], this may only be called while all ports are
+    /// in the BMCA state.
+    pub fn set_local_time_properties(&self, time_properties_ds: TimePropertiesDS) {
+        self.state.borrow_mut().local_time_properties_ds = time_properties_ds;
+    }
+
+    /// Update the quality of the local clock, for example when it loses its
+    /// time reference. It is used by the next [`PtpInstance::bmca`], and
+    /// announced from then on when this instance is the grandmaster. In
+    /// holdover, the announced quality is degraded from it.
+    ///
+    /// Like [`PtpInstance::bmca`], this may only be called while all ports are
+    /// in the BMCA state.
+    pub fn set_clock_quality(&self, clock_quality: ClockQuality) {
+        let mut state = self.state.borrow_mut();
+        state.local_clock_quality = clock_quality;
+        state.default_ds.clock_quality = clock_quality;
+    }
+
+    /// Start the holdover of the clock when it lost the reference that
+    /// disciplined it as grandmaster, such as a GNSS receiver. From the next
+    /// [`PtpInstance::bmca`] on, the quality set with
+    /// [`PtpInstance::set_clock_quality`] is degraded as described at
+    /// [`HoldoverConfig`], with the default configuration when the instance
+    /// has none. The estimate is that of the filter that disciplined the
+    /// clock, see [`Filter::holdover_estimate`].
+    ///
+    /// Like [`PtpInstance::bmca`], this may only be called while all ports are
+    /// in the BMCA state.
+    pub fn start_reference_holdover(&self, estimate: Option<HoldoverEstimate>) {
+        let mut state = self.state.borrow_mut();
+        state.holdover = Some(Holdover {
+            elapsed: Duration::ZERO,
+            estimate,
+            grandmaster: state.local_clock_quality,
+            reference: true,
+        });
+    }
+
+    /// End the holdover started by [`PtpInstance::start_reference_holdover`],
+    /// once the reference disciplines the clock again
+    ///
+    /// Like [`PtpInstance::bmca`], this may only be called while all ports are
+    /// in the BMCA state.
+    pub fn end_reference_holdover(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.holdover.map_or(false, |holdover| holdover.reference) {
+            state.holdover = None;
+        }
+    }
+
     /// Time to wait between calls to [`PtpInstance::bmca`]
     pub fn bmca_interval(&self) -> core::time::Duration {
         core::time::Duration::from_secs_f64(
@@ -253,6 +479,7 @@ impl<F: Filter> PtpInstance<F> {
             current_ds: (&state.current_ds).into(),
             parent_ds: (&state.parent_ds).into(),
             time_properties_ds: state.time_properties_ds,
+            smpte_metadata: state.parent_ds.smpte_metadata,
         }
     }
 }
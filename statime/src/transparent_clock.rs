@@ -0,0 +1,1335 @@
+//! Transparent clock implementation
+//!
+//! A transparent clock does not synchronize to a master itself. Instead it
+//! forwards PTP messages between its ports and measures how long event messages
+//! spend inside the device. This residence time is added to the correction
+//! field of the messages, so that slaves further down the network can
+//! compensate for it.
+//!
+//! The [`TransparentClock`] implemented here is a two-step transparent clock.
+//! Residence times of Sync messages are added to the corresponding Follow_Up
+//! messages. One-step Sync messages are forwarded as two-step Sync messages
+//! with a Follow_Up generated by the transparent clock.
+//!
+//! Depending on the configured [`DelayMechanism`] it is either an end-to-end
+//! or a peer-to-peer transparent clock. An end-to-end transparent clock also
+//! forwards Delay_Req messages, and adds their residence times to the
+//! corresponding Delay_Resp messages. A peer-to-peer transparent clock instead
+//! measures the delay of the link on each of its ports with the peer delay
+//! mechanism, and adds the delay of the link a Sync message was received on to
+//! the correction of its Follow_Up as well. It only forwards Sync and Follow_Up
+//! messages.
+//!
+//! For more details see *IEEE1588-2019 sections 10.2 and 10.3*
+
+use arrayvec::ArrayVec;
+
+use crate::{
+    config::{DelayMechanism, InstanceConfig, TransparentClockConfig, TransparentClockConfigError},
+    datastructures::{
+        common::{PortIdentity, TimeInterval, WireTimestamp},
+        datasets::InternalDefaultDS,
+        messages::{FollowUpMessage, Header, Message, MessageBody, MAX_DATA_LEN},
+    },
+    port::peer_delay::{MultipleResponders, PeerDelay, PeerDelayMeasurement},
+    time::{Duration, Time},
+};
+
+/// Maximum number of ports of a [`TransparentClock`]
+pub const MAX_TRANSPARENT_CLOCK_PORTS: usize = 16;
+
+/// Number of forwarded event messages for which the residence times are kept
+const MAX_RESIDENCE_ENTRIES: usize = 16;
+
+/// Largest Follow_Up message that can be held back until the residence time of
+/// its Sync message is known
+const MAX_HELD_FOLLOW_UP_LEN: usize = 128;
+
+/// Weight of the previous link delay when averaging in a new peer delay
+/// measurement
+const LINK_DELAY_SMOOTHING: i32 = 8;
+
+/// A two-step end-to-end or peer-to-peer transparent clock
+///
+/// The transparent clock does not do any I/O itself. The user should call
+/// [`handle_event_receive`](`Self::handle_event_receive`) and
+/// [`handle_general_receive`](`Self::handle_general_receive`) for every packet
+/// received on one of its ports, perform the returned
+/// [`TransparentClockAction`]s, and report the transmit timestamps of sent
+/// event messages through
+/// [`handle_send_timestamp`](`Self::handle_send_timestamp`).
+///
+/// A peer-to-peer transparent clock additionally needs a timer for each of its
+/// ports. It should be started by calling
+/// [`handle_delay_request_timer`](`Self::handle_delay_request_timer`) for each
+/// port once, and that function should be called again whenever the timer set
+/// by a [`TransparentClockAction::ResetDelayRequestTimer`] expires.
+///
+/// Ports are identified by their index, starting at 0. All timestamps must be
+/// taken from the same clock, or from clocks that are kept synchronized to each
+/// other.
+///
+/// # Example
+///
+/// ```no_run
+/// # mod system {
+/// #     pub fn recv() -> (usize, bool, std::vec::Vec<u8>, statime::time::Time) { unimplemented!() }
+/// #     pub fn send_event(port: usize, data: &[u8], link_local: bool) -> statime::time::Time { unimplemented!() }
+/// #     pub fn send_general(port: usize, data: &[u8], link_local: bool) { unimplemented!() }
+/// # }
+/// use statime::{
+///     config::{ClockIdentity, DelayMechanism, SdoId, TransparentClockConfig},
+///     time::Interval,
+///     transparent_clock::{TransparentClock, TransparentClockAction},
+/// };
+///
+/// let mut clock = TransparentClock::new(TransparentClockConfig {
+///     clock_identity: ClockIdentity::from_mac_address([1, 2, 3, 4, 5, 6]),
+///     number_ports: 2,
+///     domain_number: 0,
+///     sdo_id: SdoId::default(),
+///     delay_mechanism: DelayMechanism::E2E {
+///         interval: Interval::ONE_SECOND,
+///     },
+/// })
+/// .unwrap();
+///
+/// loop {
+///     let (port, is_event, data, timestamp) = system::recv();
+///
+///     let actions = if is_event {
+///         clock.handle_event_receive(port, &data, timestamp)
+///     } else {
+///         clock.handle_general_receive(port, &data)
+///     };
+///
+///     let mut sent_events = vec![];
+///     for action in actions {
+///         match action {
+///             TransparentClockAction::SendEvent { port, context, data, link_local } => {
+///                 sent_events.push((context, system::send_event(port, data, link_local)));
+///             }
+///             TransparentClockAction::SendGeneral { port, data, link_local } => {
+///                 system::send_general(port, data, link_local);
+///             }
+///             // Only used by a peer-to-peer transparent clock
+///             TransparentClockAction::ResetDelayRequestTimer { .. } => {}
+///         }
+///     }
+///
+///     for (context, timestamp) in sent_events {
+///         for action in clock.handle_send_timestamp(context, timestamp) {
+///             if let TransparentClockAction::SendGeneral { port, data, link_local } = action {
+///                 system::send_general(port, data, link_local);
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TransparentClock {
+    config: TransparentClockConfig,
+    // Only used for the headers of the messages we originate ourselves
+    default_ds: InternalDefaultDS,
+    peer_delays: [PeerDelay; MAX_TRANSPARENT_CLOCK_PORTS],
+    link_delays: [Option<Duration>; MAX_TRANSPARENT_CLOCK_PORTS],
+    entries: ArrayVec<ResidenceEntry, MAX_RESIDENCE_ENTRIES>,
+    next_entry_id: u32,
+    buffers: [[u8; MAX_DATA_LEN]; MAX_TRANSPARENT_CLOCK_PORTS],
+}
+
+/// An event message forwarded by the transparent clock
+#[derive(Debug)]
+struct ResidenceEntry {
+    id: u32,
+    kind: ResidenceKind,
+    domain_number: u8,
+    source_port_identity: PortIdentity,
+    sequence_id: u16,
+    ingress_port: usize,
+    ingress_time: Time,
+    // Delay of the link the message was received on, zero for an end-to-end
+    // transparent clock
+    link_delay: Duration,
+    residence_times: [Option<Duration>; MAX_TRANSPARENT_CLOCK_PORTS],
+}
+
+#[derive(Debug)]
+enum ResidenceKind {
+    Sync {
+        // Set when the Sync was received as a one-step Sync, in which case we
+        // generate the Follow_Up ourselves
+        one_step: Option<(Header, WireTimestamp)>,
+        // Follow_Up that arrived before all transmit timestamps were known
+        held_follow_up: Option<ArrayVec<u8, MAX_HELD_FOLLOW_UP_LEN>>,
+    },
+    DelayReq,
+}
+
+/// Identification of an event message sent by the transparent clock
+///
+/// The caller receives this from a [`TransparentClockAction::SendEvent`] and
+/// should return it to the [`TransparentClock`] with
+/// [`TransparentClock::handle_send_timestamp`] once the transmit timestamp of
+/// that packet is known.
+///
+/// This type is non-copy and non-clone on purpose to ensure a single
+/// [`handle_send_timestamp`](`TransparentClock::handle_send_timestamp`) per
+/// [`SendEvent`](`TransparentClockAction::SendEvent`).
+#[derive(Debug)]
+pub struct TransparentClockTimestampContext {
+    inner: TimestampContextInner,
+}
+
+#[derive(Debug)]
+enum TimestampContextInner {
+    Forward {
+        entry_id: u32,
+        port: usize,
+    },
+    PDelayReq {
+        port: usize,
+        id: u16,
+    },
+    PDelayResp {
+        port: usize,
+        id: u16,
+        requestor_identity: PortIdentity,
+    },
+}
+
+/// An action the [`TransparentClock`] needs the user to perform
+#[derive(Debug)]
+#[must_use]
+#[allow(missing_docs)] // Explaining the fields as well as the variants does not add value
+pub enum TransparentClockAction<'a> {
+    /// Send a time-critical packet on the port with the given index
+    ///
+    /// Once the packet is sent and the transmit timestamp known the user should
+    /// return the given [`TransparentClockTimestampContext`] using
+    /// [`TransparentClock::handle_send_timestamp`].
+    ///
+    /// Packets marked as link local should be sent to the multicast address
+    /// for peer delay messages of the transport.
+    SendEvent {
+        port: usize,
+        context: TransparentClockTimestampContext,
+        data: &'a [u8],
+        link_local: bool,
+    },
+    /// Send a general packet on the port with the given index
+    ///
+    /// Packets marked as link local should be sent to the multicast address
+    /// for peer delay messages of the transport.
+    SendGeneral {
+        port: usize,
+        data: &'a [u8],
+        link_local: bool,
+    },
+    /// Call [`TransparentClock::handle_delay_request_timer`] for the port with
+    /// the given index in `duration` from now
+    ResetDelayRequestTimer {
+        port: usize,
+        duration: core::time::Duration,
+    },
+}
+
+/// An Iterator over [`TransparentClockAction`]s
+#[derive(Debug)]
+#[must_use]
+pub struct TransparentClockActionIterator<'a> {
+    internal: arrayvec::IntoIter<TransparentClockAction<'a>, MAX_TRANSPARENT_CLOCK_PORTS>,
+}
+
+impl<'a> TransparentClockActionIterator<'a> {
+    /// Get an empty Iterator
+    pub fn empty() -> Self {
+        Self::from(ArrayVec::new())
+    }
+
+    fn from(list: ArrayVec<TransparentClockAction<'a>, MAX_TRANSPARENT_CLOCK_PORTS>) -> Self {
+        Self {
+            internal: list.into_iter(),
+        }
+    }
+
+    fn single(action: TransparentClockAction<'a>) -> Self {
+        let mut list = ArrayVec::new();
+        list.push(action);
+        Self::from(list)
+    }
+}
+
+impl<'a> Iterator for TransparentClockActionIterator<'a> {
+    type Item = TransparentClockAction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.internal.next()
+    }
+}
+
+impl TransparentClock {
+    /// Create a transparent clock with the given configuration
+    ///
+    /// Fails when the number of ports is larger than
+    /// [`MAX_TRANSPARENT_CLOCK_PORTS`]
+    pub fn new(config: TransparentClockConfig) -> Result<Self, TransparentClockConfigError> {
+        if config.number_ports > MAX_TRANSPARENT_CLOCK_PORTS {
+            return Err(TransparentClockConfigError::TooManyPorts);
+        }
+
+        let default_ds = InternalDefaultDS::new(InstanceConfig {
+            clock_identity: config.clock_identity,
+            priority_1: 255,
+            priority_2: 255,
+            domain_number: config.domain_number,
+            sdo_id: config.sdo_id,
+            slave_only: true,
+            profile: Default::default(),
+            clock_quality: None,
+            holdover: None,
+        });
+
+        Ok(Self {
+            config,
+            default_ds,
+            peer_delays: core::array::from_fn(|_| PeerDelay::new()),
+            link_delays: [None; MAX_TRANSPARENT_CLOCK_PORTS],
+            entries: ArrayVec::new(),
+            next_entry_id: 0,
+            buffers: [[0; MAX_DATA_LEN]; MAX_TRANSPARENT_CLOCK_PORTS],
+        })
+    }
+
+    /// The measured delay of the link on the port with the given index
+    ///
+    /// This is only known for a peer-to-peer transparent clock, once a peer
+    /// delay measurement on that port has completed.
+    pub fn link_delay(&self, port: usize) -> Option<Duration> {
+        self.link_delays[port]
+    }
+
+    /// Handle the expiry of the delay request timer of a port
+    ///
+    /// For a peer-to-peer transparent clock this sends a peer delay request on
+    /// the port, and resets the timer.
+    pub fn handle_delay_request_timer(
+        &mut self,
+        port: usize,
+    ) -> TransparentClockActionIterator<'_> {
+        debug_assert!(port < self.config.number_ports);
+
+        let DelayMechanism::P2P { interval } = self.config.delay_mechanism else {
+            return TransparentClockActionIterator::empty();
+        };
+
+        let id = self.peer_delays[port].start_measurement();
+        let message = Message::pdelay_req(&self.default_ds, self.port_identity(port), id);
+
+        let buffer = &mut self.buffers[port];
+        let length = message.serialize(buffer).unwrap();
+
+        let mut actions = ArrayVec::new();
+        actions.push(TransparentClockAction::ResetDelayRequestTimer {
+            port,
+            duration: interval.as_core_duration(),
+        });
+        actions.push(TransparentClockAction::SendEvent {
+            port,
+            context: TransparentClockTimestampContext {
+                inner: TimestampContextInner::PDelayReq { port, id },
+            },
+            data: &buffer[..length],
+            link_local: true,
+        });
+        TransparentClockActionIterator::from(actions)
+    }
+
+    /// Handle a message received on the event socket of a port
+    ///
+    /// `timestamp` is the receive timestamp of the packet. General messages
+    /// passed to this function are handled as if they were passed to
+    /// [`handle_general_receive`](`Self::handle_general_receive`), which is
+    /// convenient for transports that use a single socket.
+    pub fn handle_event_receive<'a>(
+        &'a mut self,
+        port: usize,
+        data: &'a [u8],
+        timestamp: Time,
+    ) -> TransparentClockActionIterator<'a> {
+        let Some(message) = self.parse(port, data) else {
+            return TransparentClockActionIterator::empty();
+        };
+
+        let peer_to_peer = self.is_peer_to_peer();
+        let kind = match message.body {
+            MessageBody::Sync(sync) if !message.header.two_step_flag => ResidenceKind::Sync {
+                one_step: Some((message.header, sync.origin_timestamp)),
+                held_follow_up: None,
+            },
+            MessageBody::Sync(_) => ResidenceKind::Sync {
+                one_step: None,
+                held_follow_up: None,
+            },
+            MessageBody::DelayReq(_) if !peer_to_peer => ResidenceKind::DelayReq,
+            MessageBody::PDelayReq(_) if peer_to_peer => {
+                return self.respond_pdelay_req(port, message.header, timestamp)
+            }
+            MessageBody::PDelayResp(pdelay_resp) if peer_to_peer => {
+                let measurement = self.peer_delays[port].handle_response(
+                    self.port_identity(port),
+                    message.header,
+                    pdelay_resp,
+                    timestamp,
+                );
+                self.handle_link_delay_measurement(port, measurement);
+                return TransparentClockActionIterator::empty();
+            }
+            // Peer delay messages are link local and are not forwarded by an
+            // end-to-end transparent clock, and a peer-to-peer transparent
+            // clock does not forward Delay_Req messages
+            MessageBody::DelayReq(_) | MessageBody::PDelayReq(_) | MessageBody::PDelayResp(_) => {
+                return TransparentClockActionIterator::empty()
+            }
+            _ => return self.handle_general_message(port, message, data),
+        };
+
+        let link_delay = if peer_to_peer {
+            let Some(link_delay) = self.link_delays[port] else {
+                log::debug!("Dropping Sync received before the link delay is known");
+                return TransparentClockActionIterator::empty();
+            };
+            link_delay
+        } else {
+            Duration::ZERO
+        };
+
+        let entry_id = self.next_entry_id;
+        self.next_entry_id = self.next_entry_id.wrapping_add(1);
+
+        if self.entries.is_full() {
+            self.entries.remove(0);
+        }
+        self.entries.push(ResidenceEntry {
+            id: entry_id,
+            kind,
+            domain_number: message.header.domain_number,
+            source_port_identity: message.header.source_port_identity,
+            sequence_id: message.header.sequence_id,
+            ingress_port: port,
+            ingress_time: timestamp,
+            link_delay,
+            residence_times: [None; MAX_TRANSPARENT_CLOCK_PORTS],
+        });
+
+        let context = |egress_port| TransparentClockTimestampContext {
+            inner: TimestampContextInner::Forward {
+                entry_id,
+                port: egress_port,
+            },
+        };
+
+        let mut actions = ArrayVec::new();
+        if matches!(message.body, MessageBody::Sync(_)) && !message.header.two_step_flag {
+            // Our transmit timestamp is not known before sending, so the Sync
+            // continues as a two-step Sync
+            let message = Message {
+                header: Header {
+                    two_step_flag: true,
+                    ..message.header
+                },
+                ..message
+            };
+
+            for (egress_port, buffer) in self.buffers.iter_mut().enumerate() {
+                if egress_port >= self.config.number_ports || egress_port == port {
+                    continue;
+                }
+
+                // The message was parsed from at most MAX_DATA_LEN bytes, so it fits
+                let length = message.serialize(buffer).unwrap();
+                actions.push(TransparentClockAction::SendEvent {
+                    port: egress_port,
+                    context: context(egress_port),
+                    data: &buffer[..length],
+                    link_local: false,
+                });
+            }
+        } else {
+            for egress_port in (0..self.config.number_ports).filter(|&p| p != port) {
+                actions.push(TransparentClockAction::SendEvent {
+                    port: egress_port,
+                    context: context(egress_port),
+                    data,
+                    link_local: false,
+                });
+            }
+        }
+
+        TransparentClockActionIterator::from(actions)
+    }
+
+    /// Handle a message received on the general socket of a port
+    pub fn handle_general_receive<'a>(
+        &'a mut self,
+        port: usize,
+        data: &'a [u8],
+    ) -> TransparentClockActionIterator<'a> {
+        let Some(message) = self.parse(port, data) else {
+            return TransparentClockActionIterator::empty();
+        };
+
+        self.handle_general_message(port, message, data)
+    }
+
+    fn handle_general_message<'a>(
+        &'a mut self,
+        port: usize,
+        message: Message<'a>,
+        data: &'a [u8],
+    ) -> TransparentClockActionIterator<'a> {
+        let peer_to_peer = self.is_peer_to_peer();
+        match message.body {
+            MessageBody::FollowUp(_) => self.forward_follow_up(port, message, data),
+            MessageBody::PDelayRespFollowUp(follow_up) if peer_to_peer => {
+                let measurement = self.peer_delays[port].handle_response_follow_up(
+                    self.port_identity(port),
+                    message.header,
+                    follow_up,
+                );
+                self.handle_link_delay_measurement(port, measurement);
+                TransparentClockActionIterator::empty()
+            }
+            MessageBody::DelayResp(delay_resp) if !peer_to_peer => {
+                let residence_time = self
+                    .find_entry(
+                        false,
+                        message.header.domain_number,
+                        delay_resp.requesting_port_identity,
+                        message.header.sequence_id,
+                    )
+                    .and_then(|entry| entry.residence_times[port]);
+
+                let Some(residence_time) = residence_time else {
+                    log::debug!("Dropping Delay_Resp for an unknown Delay_Req");
+                    return TransparentClockActionIterator::empty();
+                };
+
+                self.forward_corrected(port, message, |_| Some(residence_time))
+            }
+            MessageBody::Announce(_) | MessageBody::Signaling(_) | MessageBody::Management(_)
+                if !peer_to_peer =>
+            {
+                let actions = (0..self.config.number_ports)
+                    .filter(|&p| p != port)
+                    .map(|egress_port| TransparentClockAction::SendGeneral {
+                        port: egress_port,
+                        data,
+                        link_local: false,
+                    })
+                    .collect();
+
+                TransparentClockActionIterator::from(actions)
+            }
+            // Peer delay messages are link local and are not forwarded,
+            // event messages can't be forwarded without a receive timestamp,
+            // and a peer-to-peer transparent clock only forwards Sync and
+            // Follow_Up messages
+            _ => TransparentClockActionIterator::empty(),
+        }
+    }
+
+    /// Handle the transmit timestamp of an event message sent by the
+    /// transparent clock
+    pub fn handle_send_timestamp(
+        &mut self,
+        context: TransparentClockTimestampContext,
+        timestamp: Time,
+    ) -> TransparentClockActionIterator<'_> {
+        match context.inner {
+            TimestampContextInner::Forward { entry_id, port } => {
+                self.handle_forward_timestamp(entry_id, port, timestamp)
+            }
+            TimestampContextInner::PDelayReq { port, id } => {
+                let measurement = self.peer_delays[port].handle_request_timestamp(id, timestamp);
+                self.handle_link_delay_measurement(port, Ok(measurement));
+                TransparentClockActionIterator::empty()
+            }
+            TimestampContextInner::PDelayResp {
+                port,
+                id,
+                requestor_identity,
+            } => {
+                let message = Message::pdelay_resp_follow_up(
+                    &self.default_ds,
+                    self.port_identity(port),
+                    requestor_identity,
+                    id,
+                    timestamp,
+                );
+
+                let buffer = &mut self.buffers[port];
+                let length = message.serialize(buffer).unwrap();
+                TransparentClockActionIterator::single(TransparentClockAction::SendGeneral {
+                    port,
+                    data: &buffer[..length],
+                    link_local: true,
+                })
+            }
+        }
+    }
+
+    fn handle_forward_timestamp(
+        &mut self,
+        entry_id: u32,
+        port: usize,
+        timestamp: Time,
+    ) -> TransparentClockActionIterator<'_> {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == entry_id) else {
+            log::debug!("Transmit timestamp for a forgotten event message");
+            return TransparentClockActionIterator::empty();
+        };
+
+        let residence_time = timestamp - entry.ingress_time;
+        entry.residence_times[port] = Some(residence_time);
+        let correction = TimeInterval::from(entry.link_delay + residence_time);
+
+        let ResidenceKind::Sync {
+            one_step,
+            held_follow_up,
+        } = &entry.kind
+        else {
+            return TransparentClockActionIterator::empty();
+        };
+
+        let buffer = &mut self.buffers[port];
+        let length = if let Some((header, origin_timestamp)) = one_step {
+            let message = Message {
+                header: Header {
+                    two_step_flag: false,
+                    correction_field: correction,
+                    ..*header
+                },
+                body: MessageBody::FollowUp(FollowUpMessage {
+                    precise_origin_timestamp: *origin_timestamp,
+                }),
+                suffix: Default::default(),
+            };
+            message.serialize(buffer).unwrap()
+        } else if let Some(follow_up) = held_follow_up {
+            // The held Follow_Up was parsed successfully before
+            let mut message = Message::deserialize(follow_up).unwrap();
+            message.header.correction_field.0 += correction.0;
+            message.serialize(buffer).unwrap()
+        } else {
+            return TransparentClockActionIterator::empty();
+        };
+
+        TransparentClockActionIterator::single(TransparentClockAction::SendGeneral {
+            port,
+            data: &buffer[..length],
+            link_local: false,
+        })
+    }
+
+    fn respond_pdelay_req(
+        &mut self,
+        port: usize,
+        header: Header,
+        timestamp: Time,
+    ) -> TransparentClockActionIterator<'_> {
+        let message = Message::pdelay_resp(
+            &self.default_ds,
+            self.port_identity(port),
+            header,
+            timestamp,
+        );
+
+        let buffer = &mut self.buffers[port];
+        let length = message.serialize(buffer).unwrap();
+        TransparentClockActionIterator::single(TransparentClockAction::SendEvent {
+            port,
+            context: TransparentClockTimestampContext {
+                inner: TimestampContextInner::PDelayResp {
+                    port,
+                    id: header.sequence_id,
+                    requestor_identity: header.source_port_identity,
+                },
+            },
+            data: &buffer[..length],
+            link_local: true,
+        })
+    }
+
+    fn handle_link_delay_measurement(
+        &mut self,
+        port: usize,
+        measurement: Result<Option<PeerDelayMeasurement>, MultipleResponders>,
+    ) {
+        match measurement {
+            Ok(Some(PeerDelayMeasurement { peer_delay, .. })) => {
+                log::debug!("Link delay measurement on port {}: {:?}", port, peer_delay);
+
+                let link_delay = &mut self.link_delays[port];
+                *link_delay = Some(match *link_delay {
+                    // Average out the jitter of the individual measurements
+                    Some(link_delay) => {
+                        link_delay + (peer_delay - link_delay) / LINK_DELAY_SMOOTHING
+                    }
+                    None => peer_delay,
+                });
+            }
+            Ok(None) => {}
+            Err(MultipleResponders) => {
+                log::error!(
+                    "Responses from multiple devices to peer delay request on port {}, not \
+                     forwarding Sync messages received on it",
+                    port
+                );
+                self.link_delays[port] = None;
+            }
+        }
+    }
+
+    fn is_peer_to_peer(&self) -> bool {
+        matches!(self.config.delay_mechanism, DelayMechanism::P2P { .. })
+    }
+
+    fn port_identity(&self, port: usize) -> PortIdentity {
+        PortIdentity {
+            clock_identity: self.config.clock_identity,
+            port_number: port as u16 + 1,
+        }
+    }
+
+    fn parse<'a>(&self, port: usize, data: &'a [u8]) -> Option<Message<'a>> {
+        debug_assert!(port < self.config.number_ports);
+
+        let message = match Message::deserialize(data) {
+            Ok(message) => message,
+            Err(error) => {
+                log::warn!("Could not parse packet: {:?}", error);
+                return None;
+            }
+        };
+
+        if message.wire_size() > MAX_DATA_LEN {
+            log::warn!("Message too large to forward");
+            return None;
+        }
+
+        // Unicast messages are routed by the network, not by us
+        if message.header.unicast_flag {
+            return None;
+        }
+
+        Some(message)
+    }
+
+    fn find_entry(
+        &mut self,
+        sync: bool,
+        domain_number: u8,
+        source_port_identity: PortIdentity,
+        sequence_id: u16,
+    ) -> Option<&mut ResidenceEntry> {
+        self.entries.iter_mut().rev().find(|entry| {
+            matches!(entry.kind, ResidenceKind::Sync { .. }) == sync
+                && entry.domain_number == domain_number
+                && entry.source_port_identity == source_port_identity
+                && entry.sequence_id == sequence_id
+        })
+    }
+
+    fn forward_follow_up<'a>(
+        &'a mut self,
+        port: usize,
+        message: Message<'a>,
+        data: &'a [u8],
+    ) -> TransparentClockActionIterator<'a> {
+        let Some(entry) = self.find_entry(
+            true,
+            message.header.domain_number,
+            message.header.source_port_identity,
+            message.header.sequence_id,
+        ) else {
+            log::debug!("Dropping Follow_Up for an unknown Sync");
+            return TransparentClockActionIterator::empty();
+        };
+
+        if entry.ingress_port != port {
+            return TransparentClockActionIterator::empty();
+        }
+
+        let link_delay = entry.link_delay;
+        let residence_times = entry.residence_times;
+
+        // Hold on to the Follow_Up for the ports of which we don't know the
+        // transmit timestamp of the Sync yet
+        if let ResidenceKind::Sync { held_follow_up, .. } = &mut entry.kind {
+            match ArrayVec::try_from(data) {
+                Ok(follow_up) => *held_follow_up = Some(follow_up),
+                Err(_) => log::warn!("Follow_Up too large to hold until the Sync is sent"),
+            }
+        }
+
+        self.forward_corrected(port, message, |egress_port| {
+            residence_times[egress_port].map(|residence_time| link_delay + residence_time)
+        })
+    }
+
+    /// Forward a general message to all other ports, adding the correction
+    /// returned by `correction` for that port to its correction field. Ports
+    /// for which no correction is known are skipped.
+    fn forward_corrected<'a>(
+        &'a mut self,
+        port: usize,
+        mut message: Message<'_>,
+        correction: impl Fn(usize) -> Option<Duration>,
+    ) -> TransparentClockActionIterator<'a> {
+        let correction_field = message.header.correction_field;
+
+        let mut actions = ArrayVec::new();
+        for (egress_port, buffer) in self.buffers.iter_mut().enumerate() {
+            if egress_port >= self.config.number_ports || egress_port == port {
+                continue;
+            }
+
+            let Some(correction) = correction(egress_port) else {
+                continue;
+            };
+
+            message.header.correction_field.0 =
+                correction_field.0 + TimeInterval::from(correction).0;
+            let length = message.serialize(buffer).unwrap();
+            actions.push(TransparentClockAction::SendGeneral {
+                port: egress_port,
+                data: &buffer[..length],
+                link_local: false,
+            });
+        }
+
+        TransparentClockActionIterator::from(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{
+        datastructures::{
+            common::ClockIdentity,
+            datasets::InternalParentDS,
+            messages::{DelayRespMessage, PDelayRespFollowUpMessage, PDelayRespMessage},
+        },
+        ptp_instance::PtpInstanceState,
+        time::Interval,
+    };
+
+    const MASTER: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([1, 0, 0, 0, 0, 0, 0, 0]),
+        port_number: 1,
+    };
+    const SLAVE: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([2, 0, 0, 0, 0, 0, 0, 0]),
+        port_number: 1,
+    };
+
+    fn config(number_ports: usize, delay_mechanism: DelayMechanism) -> TransparentClockConfig {
+        TransparentClockConfig {
+            clock_identity: ClockIdentity([3, 0, 0, 0, 0, 0, 0, 0]),
+            number_ports,
+            domain_number: 0,
+            sdo_id: Default::default(),
+            delay_mechanism,
+        }
+    }
+
+    fn e2e() -> DelayMechanism {
+        DelayMechanism::E2E {
+            interval: Interval::ONE_SECOND,
+        }
+    }
+
+    fn p2p() -> DelayMechanism {
+        DelayMechanism::P2P {
+            interval: Interval::ONE_SECOND,
+        }
+    }
+
+    fn default_ds() -> InternalDefaultDS {
+        InternalDefaultDS::new(InstanceConfig {
+            clock_identity: ClockIdentity([0; 8]),
+            priority_1: 128,
+            priority_2: 128,
+            domain_number: 0,
+            slave_only: false,
+            sdo_id: Default::default(),
+            profile: Default::default(),
+            clock_quality: None,
+            holdover: None,
+        })
+    }
+
+    fn instance_state() -> PtpInstanceState {
+        let default_ds = default_ds();
+
+        PtpInstanceState {
+            default_ds,
+            current_ds: Default::default(),
+            parent_ds: InternalParentDS::new(default_ds),
+            time_properties_ds: Default::default(),
+            local_time_properties_ds: Default::default(),
+            local_clock_quality: default_ds.clock_quality,
+            holdover_config: None,
+            holdover: None,
+            tracking_clock_quality: None,
+            gm_rate_ratio: 1.0,
+        }
+    }
+
+    fn serialize(message: Message) -> Vec<u8> {
+        let mut buffer = [0; MAX_DATA_LEN];
+        let length = message.serialize(&mut buffer).unwrap();
+        buffer[..length].to_vec()
+    }
+
+    fn correction(data: &[u8]) -> Duration {
+        Duration::from(Message::deserialize(data).unwrap().header.correction_field)
+    }
+
+    fn event_sends(
+        actions: TransparentClockActionIterator,
+    ) -> Vec<(usize, TransparentClockTimestampContext, Vec<u8>)> {
+        actions
+            .map(|action| match action {
+                TransparentClockAction::SendEvent {
+                    port,
+                    context,
+                    data,
+                    link_local: false,
+                } => (port, context, data.to_vec()),
+                _ => panic!("Unexpected action"),
+            })
+            .collect()
+    }
+
+    fn general_sends(actions: TransparentClockActionIterator) -> Vec<(usize, Vec<u8>)> {
+        actions
+            .map(|action| match action {
+                TransparentClockAction::SendGeneral {
+                    port,
+                    data,
+                    link_local: false,
+                } => (port, data.to_vec()),
+                _ => panic!("Unexpected action"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_step_sync() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(3, e2e())).unwrap();
+
+        let sync = serialize(Message::sync(&default_ds, MASTER, 5));
+        let sends = event_sends(clock.handle_event_receive(0, &sync, Time::from_micros(100)));
+        assert_eq!(sends.len(), 2);
+
+        let mut contexts = Vec::new();
+        for (port, context, data) in sends {
+            assert_eq!(data, sync);
+            contexts.push((port, context));
+        }
+
+        // Timestamp of port 1 arrives before the Follow_Up, of port 2 after
+        let (port, context) = contexts.remove(0);
+        assert_eq!(port, 1);
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(150)));
+        assert!(sends.is_empty());
+
+        let mut follow_up = Message::follow_up(&default_ds, MASTER, 5, Time::from_micros(50));
+        follow_up.header.correction_field = TimeInterval::from(Duration::from_nanos(1000));
+        let follow_up = serialize(follow_up);
+
+        let sends = general_sends(clock.handle_general_receive(0, &follow_up));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 1);
+        assert_eq!(correction(&sends[0].1), Duration::from_nanos(51_000));
+
+        let (port, context) = contexts.remove(0);
+        assert_eq!(port, 2);
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(200)));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 2);
+        assert_eq!(correction(&sends[0].1), Duration::from_nanos(101_000));
+    }
+
+    #[test]
+    fn test_one_step_sync() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(2, e2e())).unwrap();
+
+        let mut sync = Message::sync(&default_ds, MASTER, 5);
+        sync.header.two_step_flag = false;
+        if let MessageBody::Sync(body) = &mut sync.body {
+            body.origin_timestamp = Time::from_micros(10).into();
+        }
+        let sync = serialize(sync);
+
+        let mut sends = event_sends(clock.handle_event_receive(1, &sync, Time::from_micros(100)));
+        assert_eq!(sends.len(), 1);
+        let (port, context, data) = sends.remove(0);
+        assert_eq!(port, 0);
+        assert!(Message::deserialize(&data).unwrap().header.two_step_flag);
+
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(130)));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 0);
+
+        let follow_up = Message::deserialize(&sends[0].1).unwrap();
+        assert_eq!(follow_up.header.source_port_identity, MASTER);
+        assert_eq!(follow_up.header.sequence_id, 5);
+        assert_eq!(
+            Duration::from(follow_up.header.correction_field),
+            Duration::from_micros(30)
+        );
+        let MessageBody::FollowUp(body) = follow_up.body else {
+            panic!("Unexpected message");
+        };
+        assert_eq!(
+            Time::from(body.precise_origin_timestamp),
+            Time::from_micros(10)
+        );
+    }
+
+    #[test]
+    fn test_delay_req() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(2, e2e())).unwrap();
+
+        let delay_req = serialize(Message::delay_req(&default_ds, SLAVE, 7));
+        let mut sends =
+            event_sends(clock.handle_event_receive(1, &delay_req, Time::from_micros(100)));
+        assert_eq!(sends.len(), 1);
+        let (port, context, data) = sends.remove(0);
+        assert_eq!(port, 0);
+        assert_eq!(data, delay_req);
+
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(120)));
+        assert!(sends.is_empty());
+
+        let delay_resp = Message {
+            header: Header {
+                source_port_identity: MASTER,
+                sequence_id: 7,
+                correction_field: TimeInterval::from(Duration::from_nanos(500)),
+                ..Default::default()
+            },
+            body: MessageBody::DelayResp(DelayRespMessage {
+                receive_timestamp: Time::from_micros(200).into(),
+                requesting_port_identity: SLAVE,
+            }),
+            suffix: Default::default(),
+        };
+        let delay_resp = serialize(delay_resp);
+
+        let sends = general_sends(clock.handle_general_receive(0, &delay_resp));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 1);
+        assert_eq!(correction(&sends[0].1), Duration::from_nanos(20_500));
+
+        // A Delay_Resp for a request we did not forward is dropped
+        let mut unknown = Message::deserialize(&delay_resp).unwrap();
+        unknown.header.sequence_id = 8;
+        let unknown = serialize(unknown);
+        assert!(general_sends(clock.handle_general_receive(0, &unknown)).is_empty());
+    }
+
+    #[test]
+    fn test_forwarding_rules() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(3, e2e())).unwrap();
+
+        // Announce messages are forwarded unchanged
+        let announce = serialize(Message::announce(&instance_state(), MASTER, 1));
+        let sends = general_sends(clock.handle_general_receive(2, &announce));
+        assert_eq!(
+            sends.iter().map(|(port, _)| *port).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert!(sends.iter().all(|(_, data)| *data == announce));
+
+        // Peer delay messages are not forwarded
+        let pdelay_req = serialize(Message::pdelay_req(&default_ds, SLAVE, 1));
+        assert!(clock
+            .handle_event_receive(0, &pdelay_req, Time::from_micros(1))
+            .next()
+            .is_none());
+
+        // Unicast messages are left to the network
+        let mut sync = Message::sync(&default_ds, MASTER, 2);
+        sync.header.unicast_flag = true;
+        let sync = serialize(sync);
+        assert!(clock
+            .handle_event_receive(0, &sync, Time::from_micros(1))
+            .next()
+            .is_none());
+    }
+
+    fn tc_port(port_number: u16) -> PortIdentity {
+        PortIdentity {
+            clock_identity: ClockIdentity([3, 0, 0, 0, 0, 0, 0, 0]),
+            port_number,
+        }
+    }
+
+    /// Run a peer delay measurement on `port` with a peer at `responder`,
+    /// measuring a link delay of `delay_us` microseconds
+    fn measure_link_delay(
+        clock: &mut TransparentClock,
+        port: usize,
+        responder: PortIdentity,
+        delay_us: u64,
+    ) {
+        let mut actions = clock.handle_delay_request_timer(port);
+        assert!(matches!(
+            actions.next(),
+            Some(TransparentClockAction::ResetDelayRequestTimer { port: p, .. }) if p == port
+        ));
+        let Some(TransparentClockAction::SendEvent {
+            port: p,
+            context,
+            data,
+            link_local: true,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert_eq!(p, port);
+        let request = Message::deserialize(data).unwrap();
+        assert!(matches!(request.body, MessageBody::PDelayReq(_)));
+        assert_eq!(
+            request.header.source_port_identity,
+            tc_port(port as u16 + 1)
+        );
+        let sequence_id = request.header.sequence_id;
+        drop(actions);
+
+        assert!(clock
+            .handle_send_timestamp(context, Time::from_micros(100))
+            .next()
+            .is_none());
+
+        let header = Header {
+            two_step_flag: true,
+            source_port_identity: responder,
+            sequence_id,
+            ..Default::default()
+        };
+        let response = serialize(Message {
+            header,
+            body: MessageBody::PDelayResp(PDelayRespMessage {
+                request_receive_timestamp: Time::from_micros(1000).into(),
+                requesting_port_identity: tc_port(port as u16 + 1),
+            }),
+            suffix: Default::default(),
+        });
+        let follow_up = serialize(Message {
+            header,
+            body: MessageBody::PDelayRespFollowUp(PDelayRespFollowUpMessage {
+                response_origin_timestamp: Time::from_micros(1010).into(),
+                requesting_port_identity: tc_port(port as u16 + 1),
+            }),
+            suffix: Default::default(),
+        });
+
+        let receive_time = Time::from_micros(110 + 2 * delay_us);
+        assert!(clock
+            .handle_event_receive(port, &response, receive_time)
+            .next()
+            .is_none());
+        assert!(clock
+            .handle_general_receive(port, &follow_up)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_p2p_pdelay_response() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(2, p2p())).unwrap();
+
+        let pdelay_req = serialize(Message::pdelay_req(&default_ds, SLAVE, 9));
+        let mut actions = clock.handle_event_receive(1, &pdelay_req, Time::from_micros(100));
+        let Some(TransparentClockAction::SendEvent {
+            port: 1,
+            context,
+            data,
+            link_local: true,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        assert!(actions.next().is_none());
+
+        let response = Message::deserialize(data).unwrap();
+        assert_eq!(response.header.source_port_identity, tc_port(2));
+        assert_eq!(response.header.sequence_id, 9);
+        let MessageBody::PDelayResp(body) = response.body else {
+            panic!("Unexpected message");
+        };
+        assert_eq!(body.requesting_port_identity, SLAVE);
+        assert_eq!(
+            Time::from(body.request_receive_timestamp),
+            Time::from_micros(100)
+        );
+        drop(actions);
+
+        let mut actions = clock.handle_send_timestamp(context, Time::from_micros(110));
+        let Some(TransparentClockAction::SendGeneral {
+            port: 1,
+            data,
+            link_local: true,
+        }) = actions.next()
+        else {
+            panic!("Unexpected action");
+        };
+        let follow_up = Message::deserialize(data).unwrap();
+        assert_eq!(follow_up.header.sequence_id, 9);
+        let MessageBody::PDelayRespFollowUp(body) = follow_up.body else {
+            panic!("Unexpected message");
+        };
+        assert_eq!(body.requesting_port_identity, SLAVE);
+        assert_eq!(
+            Time::from(body.response_origin_timestamp),
+            Time::from_micros(110)
+        );
+    }
+
+    #[test]
+    fn test_p2p_link_delay() {
+        let mut clock = TransparentClock::new(config(2, p2p())).unwrap();
+        assert_eq!(clock.link_delay(0), None);
+
+        measure_link_delay(&mut clock, 0, MASTER, 10);
+        assert_eq!(clock.link_delay(0), Some(Duration::from_micros(10)));
+        assert_eq!(clock.link_delay(1), None);
+
+        // Later measurements are averaged in
+        measure_link_delay(&mut clock, 0, MASTER, 18);
+        assert_eq!(clock.link_delay(0), Some(Duration::from_micros(11)));
+
+        // A response from a second device means this is not a point to point link
+        measure_link_delay(&mut clock, 0, MASTER, 10);
+        measure_link_delay(&mut clock, 0, MASTER, 10);
+        let mut actions = clock.handle_delay_request_timer(0);
+        actions.next();
+        let Some(TransparentClockAction::SendEvent { data, .. }) = actions.next() else {
+            panic!("Unexpected action");
+        };
+        let sequence_id = Message::deserialize(data).unwrap().header.sequence_id;
+        drop(actions);
+        for responder in [MASTER, SLAVE] {
+            let response = serialize(Message {
+                header: Header {
+                    source_port_identity: responder,
+                    sequence_id,
+                    ..Default::default()
+                },
+                body: MessageBody::PDelayResp(PDelayRespMessage {
+                    request_receive_timestamp: Time::from_micros(1000).into(),
+                    requesting_port_identity: tc_port(1),
+                }),
+                suffix: Default::default(),
+            });
+            assert!(clock
+                .handle_event_receive(0, &response, Time::from_micros(120))
+                .next()
+                .is_none());
+        }
+        assert_eq!(clock.link_delay(0), None);
+    }
+
+    #[test]
+    fn test_p2p_sync() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(3, p2p())).unwrap();
+
+        // Without a known link delay the Sync can't be corrected
+        let sync = serialize(Message::sync(&default_ds, MASTER, 5));
+        assert!(clock
+            .handle_event_receive(0, &sync, Time::from_micros(200))
+            .next()
+            .is_none());
+
+        measure_link_delay(&mut clock, 0, MASTER, 10);
+
+        let sends = event_sends(clock.handle_event_receive(0, &sync, Time::from_micros(200)));
+        assert_eq!(sends.len(), 2);
+
+        let mut contexts = Vec::new();
+        for (port, context, data) in sends {
+            assert_eq!(data, sync);
+            contexts.push((port, context));
+        }
+
+        let (port, context) = contexts.remove(0);
+        assert_eq!(port, 1);
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(250)));
+        assert!(sends.is_empty());
+
+        let mut follow_up = Message::follow_up(&default_ds, MASTER, 5, Time::from_micros(50));
+        follow_up.header.correction_field = TimeInterval::from(Duration::from_nanos(1000));
+        let follow_up = serialize(follow_up);
+
+        // The correction contains both the link delay and the residence time
+        let sends = general_sends(clock.handle_general_receive(0, &follow_up));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 1);
+        assert_eq!(correction(&sends[0].1), Duration::from_nanos(61_000));
+
+        let (port, context) = contexts.remove(0);
+        assert_eq!(port, 2);
+        let sends = general_sends(clock.handle_send_timestamp(context, Time::from_micros(300)));
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].0, 2);
+        assert_eq!(correction(&sends[0].1), Duration::from_nanos(111_000));
+    }
+
+    #[test]
+    fn test_p2p_forwarding_rules() {
+        let default_ds = default_ds();
+        let mut clock = TransparentClock::new(config(2, p2p())).unwrap();
+        measure_link_delay(&mut clock, 0, MASTER, 10);
+        measure_link_delay(&mut clock, 1, SLAVE, 10);
+
+        let announce = serialize(Message::announce(&instance_state(), MASTER, 1));
+        assert!(clock.handle_general_receive(0, &announce).next().is_none());
+
+        let delay_req = serialize(Message::delay_req(&default_ds, SLAVE, 7));
+        assert!(clock
+            .handle_event_receive(1, &delay_req, Time::from_micros(1))
+            .next()
+            .is_none());
+
+        let delay_resp = serialize(Message {
+            header: Header {
+                source_port_identity: MASTER,
+                sequence_id: 7,
+                ..Default::default()
+            },
+            body: MessageBody::DelayResp(DelayRespMessage {
+                receive_timestamp: Time::from_micros(2).into(),
+                requesting_port_identity: SLAVE,
+            }),
+            suffix: Default::default(),
+        });
+        assert!(clock
+            .handle_general_receive(0, &delay_resp)
+            .next()
+            .is_none());
+    }
+}